@@ -0,0 +1,489 @@
+//! # BinaryParametersDB
+//!
+//! Binary interaction parameters of pairs of species, keyed by the two
+//! species and the model they are fitted for: the 'k_ij' of the Peng-Robinson
+//! and Soave-Redlich-Kwong equations of state, and the parameters of the
+//! NRTL, UNIQUAC and Wilson activity models.
+//!
+//! Parameters are kept in the 'binary_parameters' table of a
+//! [`PropertyStore`]. A default dataset is bundled with the crate as
+//! 'data/binary_parameters.csv', and is used for the pairs that a store does
+//! not have. Species are named as in the pure species table and compared
+//! ignoring case.
+//!
+//! [`PropertyStore`]: crate::PropertyStore
+
+use crate::csv;
+use crate::PropertyStore;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+
+/// Parameters bundled with the crate
+static BUNDLED_PARAMETERS: Lazy<BinaryParameterTable> = Lazy::new(|| {
+    BinaryParameterTable::parse(include_str!("../data/binary_parameters.csv"))
+        .expect("the bundled binary parameters are valid")
+});
+
+/// The models binary parameters are fitted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BinaryModel {
+    /// The Peng-Robinson equation of state
+    PengRobinson,
+    /// The Soave-Redlich-Kwong equation of state
+    SoaveRedlichKwong,
+    /// The NRTL activity model
+    NRTL,
+    /// The UNIQUAC activity model
+    UNIQUAC,
+    /// The Wilson activity model
+    Wilson,
+}
+
+impl BinaryModel {
+    /// Name of the model as it is stored, such as 'peng-robinson'.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryModel::PengRobinson => "peng-robinson",
+            BinaryModel::SoaveRedlichKwong => "soave-redlich-kwong",
+            BinaryModel::NRTL => "nrtl",
+            BinaryModel::UNIQUAC => "uniquac",
+            BinaryModel::Wilson => "wilson",
+        }
+    }
+}
+
+impl fmt::Display for BinaryModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BinaryModel {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "peng-robinson" => Ok(BinaryModel::PengRobinson),
+            "soave-redlich-kwong" => Ok(BinaryModel::SoaveRedlichKwong),
+            "nrtl" => Ok(BinaryModel::NRTL),
+            "uniquac" => Ok(BinaryModel::UNIQUAC),
+            "wilson" => Ok(BinaryModel::Wilson),
+            _ => Err("unknown binary parameter model"),
+        }
+    }
+}
+
+/// # InteractionParameters
+///
+/// The parameters of one pair of species for one model, from the first
+/// species 'i' to the second 'j'. Temperature dependent parameters are
+/// 'a + b / T' with the temperature in K.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InteractionParameters {
+    /// 'k_ij' of the Peng-Robinson equation
+    PengRobinson {
+        /// Binary interaction parameter
+        kij: f64,
+    },
+    /// 'k_ij' of the Soave-Redlich-Kwong equation
+    SoaveRedlichKwong {
+        /// Binary interaction parameter
+        kij: f64,
+    },
+    /// NRTL parameters, 'tau_ij = a_ij + b_ij / T'
+    NRTL {
+        /// Constant term of 'tau_ij'
+        a_ij: f64,
+        /// Inverse temperature term of 'tau_ij', in K
+        b_ij: f64,
+        /// Constant term of 'tau_ji'
+        a_ji: f64,
+        /// Inverse temperature term of 'tau_ji', in K
+        b_ji: f64,
+        /// Non-randomness parameter
+        alpha: f64,
+    },
+    /// UNIQUAC parameters, 'ln(tau_ij) = a_ij + b_ij / T'
+    UNIQUAC {
+        /// Constant term of 'ln(tau_ij)'
+        a_ij: f64,
+        /// Inverse temperature term of 'ln(tau_ij)', in K
+        b_ij: f64,
+        /// Constant term of 'ln(tau_ji)'
+        a_ji: f64,
+        /// Inverse temperature term of 'ln(tau_ji)', in K
+        b_ji: f64,
+    },
+    /// Wilson parameters, 'ln(Lambda_ij) = a_ij + b_ij / T'
+    Wilson {
+        /// Constant term of 'ln(Lambda_ij)'
+        a_ij: f64,
+        /// Inverse temperature term of 'ln(Lambda_ij)', in K
+        b_ij: f64,
+        /// Constant term of 'ln(Lambda_ji)'
+        a_ji: f64,
+        /// Inverse temperature term of 'ln(Lambda_ji)', in K
+        b_ji: f64,
+    },
+}
+
+impl InteractionParameters {
+    /// Reads the parameters of a model from its coefficients, in the order
+    /// of the fields of each variant.
+    pub fn new(model: BinaryModel, coefficients: &[f64]) -> Result<Self, &'static str> {
+        let parameters = match (model, coefficients) {
+            (BinaryModel::PengRobinson, &[kij]) => InteractionParameters::PengRobinson { kij },
+            (BinaryModel::SoaveRedlichKwong, &[kij]) => {
+                InteractionParameters::SoaveRedlichKwong { kij }
+            }
+            (BinaryModel::NRTL, &[a_ij, b_ij, a_ji, b_ji, alpha]) => InteractionParameters::NRTL {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+                alpha,
+            },
+            (BinaryModel::UNIQUAC, &[a_ij, b_ij, a_ji, b_ji]) => InteractionParameters::UNIQUAC {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+            },
+            (BinaryModel::Wilson, &[a_ij, b_ij, a_ji, b_ji]) => InteractionParameters::Wilson {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+            },
+            _ => return Err("wrong number of binary parameter coefficients for the model"),
+        };
+        Ok(parameters)
+    }
+
+    /// The model the parameters are for.
+    pub fn model(&self) -> BinaryModel {
+        match self {
+            InteractionParameters::PengRobinson { .. } => BinaryModel::PengRobinson,
+            InteractionParameters::SoaveRedlichKwong { .. } => BinaryModel::SoaveRedlichKwong,
+            InteractionParameters::NRTL { .. } => BinaryModel::NRTL,
+            InteractionParameters::UNIQUAC { .. } => BinaryModel::UNIQUAC,
+            InteractionParameters::Wilson { .. } => BinaryModel::Wilson,
+        }
+    }
+
+    /// The coefficients, in the order read by [`InteractionParameters::new`].
+    pub fn coefficients(&self) -> Vec<f64> {
+        match *self {
+            InteractionParameters::PengRobinson { kij }
+            | InteractionParameters::SoaveRedlichKwong { kij } => vec![kij],
+            InteractionParameters::NRTL {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+                alpha,
+            } => vec![a_ij, b_ij, a_ji, b_ji, alpha],
+            InteractionParameters::UNIQUAC {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+            }
+            | InteractionParameters::Wilson {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+            } => vec![a_ij, b_ij, a_ji, b_ji],
+        }
+    }
+
+    /// The same parameters with the two species swapped.
+    pub fn reversed(&self) -> Self {
+        let coefficients = self.coefficients();
+        let reversed = match coefficients.as_slice() {
+            &[a_ij, b_ij, a_ji, b_ji, ref rest @ ..] => {
+                [&[a_ji, b_ji, a_ij, b_ij][..], rest].concat()
+            }
+            _ => coefficients,
+        };
+        InteractionParameters::new(self.model(), &reversed)
+            .expect("reversing keeps the number of coefficients")
+    }
+}
+
+/// # BinaryParameterRecord
+///
+/// A row of the 'binary_parameters' table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryParameterRecord {
+    /// Row id in the store, `None` for parameters that are not saved yet
+    pub id: Option<i64>,
+    /// Name of the first species, 'i'
+    pub first: String,
+    /// Name of the second species, 'j'
+    pub second: String,
+    /// The parameters, from the first species to the second
+    pub parameters: InteractionParameters,
+    /// Where the parameters were published
+    pub source: String,
+}
+
+impl BinaryParameterRecord {
+    /// The parameters from one species of the pair to the other, or `None`
+    /// when the record is for another pair.
+    pub fn between(&self, first: &str, second: &str) -> Option<InteractionParameters> {
+        let same = |a: &str, b: &str| a.trim().eq_ignore_ascii_case(b.trim());
+        if same(&self.first, first) && same(&self.second, second) {
+            Some(self.parameters)
+        } else if same(&self.first, second) && same(&self.second, first) {
+            Some(self.parameters.reversed())
+        } else {
+            None
+        }
+    }
+
+    /// The record with its species in lower case and in alphabetical
+    /// order, as it is stored.
+    pub(crate) fn normalized(&self) -> Self {
+        let (first, second) = normalized_pair(&self.first, &self.second);
+        let parameters = self
+            .between(&first, &second)
+            .expect("the normalized pair is the pair of the record");
+        BinaryParameterRecord {
+            first,
+            second,
+            parameters,
+            ..self.clone()
+        }
+    }
+}
+
+/// The names of a pair in lower case and in alphabetical order, as they are
+/// stored.
+pub(crate) fn normalized_pair(first: &str, second: &str) -> (String, String) {
+    let (first, second) = (first.trim().to_lowercase(), second.trim().to_lowercase());
+    if first <= second {
+        (first, second)
+    } else {
+        (second, first)
+    }
+}
+
+/// A row of the 'binary_parameters' table as it is stored.
+#[derive(FromRow)]
+pub(crate) struct BinaryParameterRow {
+    id: i64,
+    first: String,
+    second: String,
+    model: String,
+    #[sqlx(json)]
+    coefficients: Vec<f64>,
+    source: String,
+}
+
+impl TryFrom<BinaryParameterRow> for BinaryParameterRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(row: BinaryParameterRow) -> anyhow::Result<Self> {
+        let model = row.model.parse().map_err(anyhow::Error::msg)?;
+        Ok(BinaryParameterRecord {
+            id: Some(row.id),
+            first: row.first,
+            second: row.second,
+            parameters: InteractionParameters::new(model, &row.coefficients)
+                .map_err(anyhow::Error::msg)?,
+            source: row.source,
+        })
+    }
+}
+
+/// Statements creating the 'binary_parameters' table, given the types of
+/// the id and JSON columns of the backend.
+pub(crate) fn create_tables(id_column: &str, json_column: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS binary_parameters (
+            id {id_column} PRIMARY KEY,
+            first TEXT NOT NULL,
+            second TEXT NOT NULL,
+            model TEXT NOT NULL,
+            coefficients {json_column} NOT NULL,
+            source TEXT NOT NULL,
+            UNIQUE (first, second, model)
+        );"
+    )
+}
+
+/// Query for the parameters of a normalized pair, with the species as '$1'
+/// and '$2' and the model as '$3'.
+pub(crate) const SELECT_BINARY_PARAMETERS: &str = "SELECT id, first, second, model, \
+    coefficients, source FROM binary_parameters \
+    WHERE first = $1 AND second = $2 AND model = $3";
+
+/// Statement inserting the parameters of a normalized pair, or updating the
+/// stored parameters of the pair and model, returning their id.
+pub(crate) const SAVE_BINARY_PARAMETERS: &str = "INSERT INTO binary_parameters (first, second, \
+    model, coefficients, source) VALUES ($1, $2, $3, $4, $5) \
+    ON CONFLICT (first, second, model) DO UPDATE SET coefficients = excluded.coefficients, \
+    source = excluded.source RETURNING id";
+
+/// Binds the fields of a normalized [`BinaryParameterRecord`] to
+/// [`SAVE_BINARY_PARAMETERS`].
+macro_rules! bind_binary_parameters {
+    ($query:expr, $record:expr) => {
+        $query
+            .bind(&$record.first)
+            .bind(&$record.second)
+            .bind($record.parameters.model().name())
+            .bind(sqlx::types::Json($record.parameters.coefficients()))
+            .bind(&$record.source)
+    };
+}
+pub(crate) use bind_binary_parameters;
+
+/// # BinaryParameterTable
+///
+/// Binary parameters held in memory, such as the bundled defaults.
+#[derive(Debug, Clone, Default)]
+pub struct BinaryParameterTable {
+    records: Vec<BinaryParameterRecord>,
+}
+
+impl BinaryParameterTable {
+    /// Reads a table from CSV text, with records
+    /// 'first,second,model,source,coefficients...'. Blank lines and comments
+    /// starting with '#' are skipped, and fields holding commas, such as a
+    /// source, can be quoted.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut table = BinaryParameterTable::default();
+        for fields in csv::records(text) {
+            if fields.len() < 5 {
+                return Err("a binary parameter record needs two species, a model, a source and its coefficients");
+            }
+            let coefficients = fields[4..]
+                .iter()
+                .map(|field| csv::number(field))
+                .collect::<Result<Vec<f64>, _>>()?;
+            table.records.push(BinaryParameterRecord {
+                id: None,
+                first: String::from(fields[0]),
+                second: String::from(fields[1]),
+                parameters: InteractionParameters::new(fields[2].parse()?, &coefficients)?,
+                source: String::from(fields[3]),
+            });
+        }
+        Ok(table)
+    }
+
+    /// The default parameters bundled with the crate.
+    pub fn bundled() -> &'static BinaryParameterTable {
+        &BUNDLED_PARAMETERS
+    }
+
+    /// All records, in the order they were read.
+    pub fn records(&self) -> &[BinaryParameterRecord] {
+        &self.records
+    }
+
+    /// The parameters of a pair for a model, from the first species to the
+    /// second.
+    pub fn get(
+        &self,
+        first: &str,
+        second: &str,
+        model: BinaryModel,
+    ) -> Option<InteractionParameters> {
+        self.records
+            .iter()
+            .filter(|record| record.parameters.model() == model)
+            .find_map(|record| record.between(first, second))
+    }
+}
+
+/// # BinaryParameterSource
+///
+/// Where the property packages look binary parameters up.
+pub trait BinaryParameterSource {
+    /// The parameters of a pair for a model, from the first species to the
+    /// second, or `None` when the pair is not known.
+    fn interaction_parameters(
+        &self,
+        first: &str,
+        second: &str,
+        model: BinaryModel,
+    ) -> anyhow::Result<Option<InteractionParameters>>;
+}
+
+impl BinaryParameterSource for BinaryParameterTable {
+    fn interaction_parameters(
+        &self,
+        first: &str,
+        second: &str,
+        model: BinaryModel,
+    ) -> anyhow::Result<Option<InteractionParameters>> {
+        Ok(self.get(first, second, model))
+    }
+}
+
+/// Stores give their own parameters, and the bundled defaults for the pairs
+/// they do not have.
+impl<S: PropertyStore + ?Sized> BinaryParameterSource for S {
+    fn interaction_parameters(
+        &self,
+        first: &str,
+        second: &str,
+        model: BinaryModel,
+    ) -> anyhow::Result<Option<InteractionParameters>> {
+        match self.binary_parameters(first, second, model)? {
+            Some(record) => Ok(record.between(first, second)),
+            None => Ok(BinaryParameterTable::bundled().get(first, second, model)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod binary_parameters_db_tests {
+    use super::*;
+
+    #[test]
+    fn bundled_parameters_are_found_in_either_order() {
+        let table = BinaryParameterTable::bundled();
+        assert!(table.records().len() > 20);
+        assert_eq!(
+            table.get("Methane", "propane", BinaryModel::PengRobinson),
+            Some(InteractionParameters::PengRobinson { kij: 0.014 })
+        );
+        assert_eq!(
+            table.get("propane", "methane", BinaryModel::PengRobinson),
+            table.get("methane", "propane", BinaryModel::PengRobinson)
+        );
+        assert!(table
+            .get("methane", "propane", BinaryModel::SoaveRedlichKwong)
+            .is_none());
+
+        let forward = table.get("ethanol", "water", BinaryModel::NRTL).unwrap();
+        let backward = table.get("water", "ethanol", BinaryModel::NRTL).unwrap();
+        assert_eq!(backward, forward.reversed());
+        assert_eq!(backward.reversed(), forward);
+        match backward {
+            InteractionParameters::NRTL { a_ij, alpha, .. } => {
+                assert_eq!((a_ij, alpha), (3.4578, 0.3))
+            }
+            _ => panic!("the parameters are for NRTL"),
+        }
+    }
+
+    #[test]
+    fn coefficients_must_match_the_model() {
+        assert!(InteractionParameters::new(BinaryModel::Wilson, &[1.0, 2.0]).is_err());
+        assert!("van-laar".parse::<BinaryModel>().is_err());
+        assert!(BinaryParameterTable::parse("a,b,nrtl,source,1,2,3").is_err());
+        let table = BinaryParameterTable::parse("a,b,peng-robinson,\"Smith, 1990\",0.01").unwrap();
+        assert_eq!(table.records()[0].source, "Smith, 1990");
+        let parameters = InteractionParameters::new(BinaryModel::UNIQUAC, &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(parameters.unwrap().coefficients(), [1.0, 2.0, 3.0, 4.0]);
+    }
+}
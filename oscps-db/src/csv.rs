@@ -0,0 +1,65 @@
+//! # CSV
+//!
+//! Reading of the CSV tables bundled with the crates, such as the binary
+//! parameters, the component database and the UNIFAC tables.
+
+use std::str::FromStr;
+
+/// Fields of each record of a CSV table, skipping blank lines and comments
+/// starting with '#'.
+pub fn records(text: &str) -> impl Iterator<Item = Vec<&str>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(fields)
+}
+
+/// Splits a record at the commas outside double quotes, so that names such
+/// as "1,4-dioxane" can be quoted.
+fn fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(unquote(&line[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(unquote(&line[start..]));
+    fields
+}
+
+/// Trims a field and strips the double quotes around it.
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+/// Parses a numeric field of a record.
+pub fn number<T: FromStr>(field: &str) -> Result<T, &'static str> {
+    field
+        .parse()
+        .map_err(|_| "a CSV table has a field that is not a number")
+}
+
+#[cfg(test)]
+mod csv_tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields_keep_their_commas() {
+        let text = "# comment\n\n \"1,4-dioxane\", water ,\"Smith, 1990\",2.5\n";
+        let records: Vec<Vec<&str>> = records(text).collect();
+        assert_eq!(records, [["1,4-dioxane", "water", "Smith, 1990", "2.5"]]);
+        assert_eq!(number::<f64>(records[0][3]), Ok(2.5));
+        assert!(number::<f64>(records[0][2]).is_err());
+    }
+}
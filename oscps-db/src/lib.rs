@@ -1,13 +1,71 @@
-///#OSCPS-db
-///
-///Main library folder for the OSCPS-db library crate
-///
-///will hold the methods to pull information from the POSTGRES database and from relevant APIs
-///
-///Will hold property information for chemcial property calculation
-///Will hold information about the user's simulation
-
+//! #OSCPS-db
+//!
+//! Main library folder for the OSCPS-db library crate
+//!
+//! Holds the databases that property information for chemical property
+//! calculation is kept in. Every database implements the [`PropertyStore`]
+//! trait, so the simulation can use a local SQLite file or a shared Postgres
+//! server the same way. Postgres support is enabled with the "postgres"
+//! cargo feature.
+//!
+//! Will hold information about the user's simulation
 
+/// Binary interaction parameters and their bundled defaults
+pub mod binary_parameters_db;
+/// Reading of the bundled CSV tables
+pub mod csv;
+/// Connection to a Postgres server
+#[cfg(feature = "postgres")]
 pub mod postgres_db;
+/// Records and queries of the property tables
 pub mod properties_db;
+/// File-backed SQLite database
+pub mod sqlite_db;
+
+pub use binary_parameters_db::{
+    BinaryModel, BinaryParameterRecord, BinaryParameterSource, InteractionParameters,
+};
+pub use properties_db::{PropertySet, PureSpeciesRecord, SpeciesQuery};
+
+/// # PropertyStore
+///
+/// Common trait for the databases that properties are stored in. The
+/// methods block until the database answers, so they should not be called
+/// from inside an asynchronous runtime.
+pub trait PropertyStore {
+    /// Finds the species matching a query, ordered by name.
+    fn find_species(&self, query: &SpeciesQuery) -> anyhow::Result<Vec<PureSpeciesRecord>>;
+
+    /// Finds a pure species by name, returning `None` when it is not stored.
+    fn pure_species(&self, name: &str) -> anyhow::Result<Option<PureSpeciesRecord>> {
+        let species = self.find_species(&SpeciesQuery::Name(String::from(name)))?;
+        Ok(species.into_iter().next())
+    }
+
+    /// Saves a pure species, replacing the stored species of the same name,
+    /// and returns its row id. This is how user defined species are added
+    /// and updated.
+    fn save_pure_species(&self, species: &PureSpeciesRecord) -> anyhow::Result<i64>;
+
+    /// The property sets of a species for one property, such as
+    /// 'vapor_pressure', in the order they were added.
+    fn property_sets(&self, species_id: i64, property: &str) -> anyhow::Result<Vec<PropertySet>>;
+
+    /// Saves a property set and returns its row id. Sets without an id are
+    /// added, and the others replace the stored set with their id.
+    fn save_property_set(&self, set: &PropertySet) -> anyhow::Result<i64>;
+
+    /// The binary parameters stored for a pair and model, in either order of
+    /// the species. The bundled defaults are not searched; see
+    /// [`BinaryParameterSource`] for that.
+    fn binary_parameters(
+        &self,
+        first: &str,
+        second: &str,
+        model: BinaryModel,
+    ) -> anyhow::Result<Option<BinaryParameterRecord>>;
 
+    /// Saves binary parameters, replacing the stored parameters of the pair
+    /// and model, and returns their row id.
+    fn save_binary_parameters(&self, record: &BinaryParameterRecord) -> anyhow::Result<i64>;
+}
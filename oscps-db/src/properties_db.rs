@@ -1,4 +1,310 @@
-///# PropertiesDB
+//! # PropertiesDB
+//!
+//! Records and queries of the property tables, shared by every
+//! [`PropertyStore`]. Values are stored as plain numbers in SI units, so that
+//! the tables can be read and filled by other tools, and are converted to
+//! `uom` quantities by 'oscps-lib'.
+//!
+//! Species are kept in the 'pure_species' table and are found with a
+//! [`SpeciesQuery`]. Correlations of temperature dependent properties, such
+//! as vapor pressures or liquid densities, are kept in the 'property_sets'
+//! table as [`PropertySet`]s, each with the source of its coefficients and
+//! the temperatures it is valid for. Users add their own species, such as
+//! proprietary components, by saving records marked as user defined.
+//!
+//! [`PropertyStore`]: crate::PropertyStore
+
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+/// # PureSpeciesRecord
 ///
-///Will formulate the right queries required for 'DBConnector'
+/// A row of the 'pure_species' table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct PureSpeciesRecord {
+    /// Row id in the store, `None` for species that are not saved yet
+    pub id: Option<i64>,
+    /// Name of the species, unique in a store
+    pub name: String,
+    /// CAS registry number
+    pub cas: Option<String>,
+    /// Molecular formula
+    pub formula: Option<String>,
+    /// Canonical SMILES
+    pub smiles: Option<String>,
+    /// PubChem compound identifier
+    pub pubchem_cid: Option<i64>,
+    /// Whether the species was added by a user rather than taken from a
+    /// published source
+    pub user_defined: bool,
+    /// Molar mass in kg/mol
+    pub molar_mass: f64,
+    /// Boiling point at one atmosphere in K
+    pub normal_boiling_point: f64,
+    /// Critical temperature in K
+    pub critical_temperature: f64,
+    /// Critical pressure in Pa
+    pub critical_pressure: f64,
+    /// Critical molar volume in m^3/mol
+    pub critical_volume: f64,
+    /// Compressibility factor at the critical point
+    pub critical_compressibility: f64,
+    /// Acentric factor
+    pub acentric_factor: f64,
+    /// Antoine constant 'A' of 'ln(P / Pa) = A - B / (T / K + C)'
+    pub antoine_a: Option<f64>,
+    /// Antoine constant 'B', in K
+    pub antoine_b: Option<f64>,
+    /// Antoine constant 'C', in K
+    pub antoine_c: Option<f64>,
+}
+
+/// # SpeciesQuery
 ///
+/// The ways species are looked up in a store. Names are compared ignoring
+/// case, and the other identifiers exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpeciesQuery {
+    /// The species with a name
+    Name(String),
+    /// The species with a CAS registry number, such as '71-43-2'
+    Cas(String),
+    /// The species with a molecular formula, such as 'C6H6'. Isomers share
+    /// a formula, so several species may be found.
+    Formula(String),
+    /// The species with a canonical SMILES
+    Smiles(String),
+    /// The species with a PubChem compound identifier
+    PubchemCid(i64),
+    /// The species whose names contain a text, such as 'xylene'
+    NameContains(String),
+    /// The species added by users
+    UserDefined,
+}
+
+/// A value bound to a query.
+pub(crate) enum QueryParameter {
+    Text(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl SpeciesQuery {
+    /// The condition selecting the species, with one parameter '$1'.
+    fn condition(&self) -> &'static str {
+        match self {
+            SpeciesQuery::Name(_) => "LOWER(name) = LOWER($1)",
+            SpeciesQuery::Cas(_) => "cas = $1",
+            SpeciesQuery::Formula(_) => "formula = $1",
+            SpeciesQuery::Smiles(_) => "smiles = $1",
+            SpeciesQuery::PubchemCid(_) => "pubchem_cid = $1",
+            SpeciesQuery::NameContains(_) => "LOWER(name) LIKE $1 ESCAPE '\\'",
+            SpeciesQuery::UserDefined => "user_defined = $1",
+        }
+    }
+
+    /// The value of the parameter of the condition.
+    pub(crate) fn parameter(&self) -> QueryParameter {
+        match self {
+            SpeciesQuery::Name(text)
+            | SpeciesQuery::Cas(text)
+            | SpeciesQuery::Formula(text)
+            | SpeciesQuery::Smiles(text) => QueryParameter::Text(text.trim().to_string()),
+            SpeciesQuery::PubchemCid(cid) => QueryParameter::Integer(*cid),
+            SpeciesQuery::NameContains(text) => {
+                let escaped = text
+                    .trim()
+                    .to_lowercase()
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                QueryParameter::Text(format!("%{escaped}%"))
+            }
+            SpeciesQuery::UserDefined => QueryParameter::Boolean(true),
+        }
+    }
+
+    /// Query for the species, ordered by name.
+    pub(crate) fn sql(&self) -> String {
+        format!(
+            "SELECT {PURE_SPECIES_COLUMNS} FROM pure_species WHERE {} ORDER BY name",
+            self.condition()
+        )
+    }
+}
+
+/// # PropertySet
+///
+/// A row of the 'property_sets' table: the coefficients of a correlation of
+/// one property of a species, as published by one source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct PropertySet {
+    /// Row id in the store, `None` for sets that are not saved yet
+    pub id: Option<i64>,
+    /// Row id of the species in the 'pure_species' table
+    pub species_id: i64,
+    /// Property the correlation gives, such as 'vapor_pressure'
+    pub property: String,
+    /// Form of the correlation, such as 'antoine' or 'dippr101'
+    pub correlation: String,
+    /// Coefficients of the correlation, in the units of its source
+    #[sqlx(json)]
+    pub coefficients: Vec<f64>,
+    /// Where the coefficients were published
+    pub source: String,
+    /// Lowest temperature the correlation is valid for, in K
+    pub minimum_temperature: Option<f64>,
+    /// Highest temperature the correlation is valid for, in K
+    pub maximum_temperature: Option<f64>,
+}
+
+impl PropertySet {
+    /// Whether the correlation is valid at a temperature in K. Open ends of
+    /// the range are taken as valid.
+    pub fn covers(&self, temperature: f64) -> bool {
+        self.minimum_temperature
+            .is_none_or(|minimum| temperature >= minimum)
+            && self
+                .maximum_temperature
+                .is_none_or(|maximum| temperature <= maximum)
+    }
+}
+
+/// Columns of the 'pure_species' table, in the order of the record fields
+const PURE_SPECIES_COLUMNS: &str = "id, name, cas, formula, smiles, pubchem_cid, user_defined, \
+    molar_mass, normal_boiling_point, critical_temperature, critical_pressure, critical_volume, \
+    critical_compressibility, acentric_factor, antoine_a, antoine_b, antoine_c";
+
+/// Statements creating the property tables, given the types of the id and
+/// JSON columns of the backend.
+pub(crate) fn create_tables(id_column: &str, json_column: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS pure_species (
+            id {id_column} PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            cas TEXT,
+            formula TEXT,
+            smiles TEXT,
+            pubchem_cid BIGINT,
+            user_defined BOOLEAN NOT NULL DEFAULT FALSE,
+            molar_mass DOUBLE PRECISION NOT NULL,
+            normal_boiling_point DOUBLE PRECISION NOT NULL,
+            critical_temperature DOUBLE PRECISION NOT NULL,
+            critical_pressure DOUBLE PRECISION NOT NULL,
+            critical_volume DOUBLE PRECISION NOT NULL,
+            critical_compressibility DOUBLE PRECISION NOT NULL,
+            acentric_factor DOUBLE PRECISION NOT NULL,
+            antoine_a DOUBLE PRECISION,
+            antoine_b DOUBLE PRECISION,
+            antoine_c DOUBLE PRECISION
+        );
+        CREATE INDEX IF NOT EXISTS pure_species_cas ON pure_species (cas);
+        CREATE INDEX IF NOT EXISTS pure_species_formula ON pure_species (formula);
+        CREATE TABLE IF NOT EXISTS property_sets (
+            id {id_column} PRIMARY KEY,
+            species_id BIGINT NOT NULL REFERENCES pure_species (id) ON DELETE CASCADE,
+            property TEXT NOT NULL,
+            correlation TEXT NOT NULL,
+            coefficients {json_column} NOT NULL,
+            source TEXT NOT NULL,
+            minimum_temperature DOUBLE PRECISION,
+            maximum_temperature DOUBLE PRECISION
+        );
+        CREATE INDEX IF NOT EXISTS property_sets_species ON property_sets (species_id, property);"
+    )
+}
+
+/// Statement inserting a pure species, or updating the species of the same
+/// name, returning its id. The values are bound by [`bind_pure_species`].
+pub(crate) const SAVE_PURE_SPECIES: &str = "INSERT INTO pure_species (name, cas, formula, \
+    smiles, pubchem_cid, user_defined, molar_mass, normal_boiling_point, critical_temperature, \
+    critical_pressure, critical_volume, critical_compressibility, acentric_factor, antoine_a, \
+    antoine_b, antoine_c) \
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) \
+    ON CONFLICT (name) DO UPDATE SET cas = excluded.cas, formula = excluded.formula, \
+    smiles = excluded.smiles, pubchem_cid = excluded.pubchem_cid, \
+    user_defined = excluded.user_defined, molar_mass = excluded.molar_mass, \
+    normal_boiling_point = excluded.normal_boiling_point, \
+    critical_temperature = excluded.critical_temperature, \
+    critical_pressure = excluded.critical_pressure, critical_volume = excluded.critical_volume, \
+    critical_compressibility = excluded.critical_compressibility, \
+    acentric_factor = excluded.acentric_factor, antoine_a = excluded.antoine_a, \
+    antoine_b = excluded.antoine_b, antoine_c = excluded.antoine_c \
+    RETURNING id";
+
+/// Query for the property sets of a species for one property, with the
+/// species id as '$1' and the property as '$2'.
+pub(crate) const SELECT_PROPERTY_SETS: &str = "SELECT id, species_id, property, correlation, \
+    coefficients, source, minimum_temperature, maximum_temperature FROM property_sets \
+    WHERE species_id = $1 AND property = $2 ORDER BY id";
+
+/// Statement adding a property set, returning its id. The values are bound
+/// by [`bind_property_set`].
+pub(crate) const INSERT_PROPERTY_SET: &str = "INSERT INTO property_sets (species_id, property, \
+    correlation, coefficients, source, minimum_temperature, maximum_temperature) \
+    VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id";
+
+/// Statement replacing the property set with the id of '$1', returning its
+/// id. The other values are bound by [`bind_property_set`].
+pub(crate) const UPDATE_PROPERTY_SET: &str = "UPDATE property_sets SET species_id = $2, \
+    property = $3, correlation = $4, coefficients = $5, source = $6, \
+    minimum_temperature = $7, maximum_temperature = $8 WHERE id = $1 RETURNING id";
+
+/// Binds the fields of a [`PureSpeciesRecord`] to [`SAVE_PURE_SPECIES`]. A
+/// macro, so that it works with the query type of any backend.
+macro_rules! bind_pure_species {
+    ($query:expr, $species:expr) => {
+        $query
+            .bind(&$species.name)
+            .bind(&$species.cas)
+            .bind(&$species.formula)
+            .bind(&$species.smiles)
+            .bind($species.pubchem_cid)
+            .bind($species.user_defined)
+            .bind($species.molar_mass)
+            .bind($species.normal_boiling_point)
+            .bind($species.critical_temperature)
+            .bind($species.critical_pressure)
+            .bind($species.critical_volume)
+            .bind($species.critical_compressibility)
+            .bind($species.acentric_factor)
+            .bind($species.antoine_a)
+            .bind($species.antoine_b)
+            .bind($species.antoine_c)
+    };
+}
+pub(crate) use bind_pure_species;
+
+/// Binds the fields of a [`PropertySet`] other than its id to
+/// [`INSERT_PROPERTY_SET`] or [`UPDATE_PROPERTY_SET`].
+macro_rules! bind_property_set {
+    ($query:expr, $set:expr) => {
+        $query
+            .bind($set.species_id)
+            .bind(&$set.property)
+            .bind(&$set.correlation)
+            .bind($crate::properties_db::coefficients(&$set.coefficients))
+            .bind(&$set.source)
+            .bind($set.minimum_temperature)
+            .bind($set.maximum_temperature)
+    };
+}
+pub(crate) use bind_property_set;
+
+/// Coefficients as they are stored, in a JSON column.
+pub(crate) fn coefficients(coefficients: &[f64]) -> Json<&[f64]> {
+    Json(coefficients)
+}
+
+/// Binds the parameter of a [`SpeciesQuery`] to its query.
+macro_rules! bind_species_query {
+    ($query:expr, $species_query:expr) => {
+        match $species_query.parameter() {
+            $crate::properties_db::QueryParameter::Text(text) => $query.bind(text),
+            $crate::properties_db::QueryParameter::Integer(integer) => $query.bind(integer),
+            $crate::properties_db::QueryParameter::Boolean(boolean) => $query.bind(boolean),
+        }
+    };
+}
+pub(crate) use bind_species_query;
@@ -0,0 +1,338 @@
+//! # SqliteDB
+//!
+//! A property store kept in a single SQLite file, for properties of a user
+//! or a project that should be available without a database server. The
+//! file and its tables are created when it is first opened.
+
+use crate::binary_parameters_db::{
+    self, bind_binary_parameters, BinaryModel, BinaryParameterRecord, BinaryParameterRow,
+};
+use crate::properties_db::{
+    self, bind_property_set, bind_pure_species, bind_species_query, PropertySet, PureSpeciesRecord,
+    SpeciesQuery,
+};
+use crate::PropertyStore;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tokio::runtime::{Builder, Runtime};
+
+/// # SqliteDB
+///
+/// Connection to a SQLite property database.
+pub struct SqliteDB {
+    path: Option<PathBuf>,
+    runtime: Runtime,
+    pool: SqlitePool,
+}
+
+impl SqliteDB {
+    /// Opens the database in a file, creating the file if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path.as_ref())
+            .create_if_missing(true);
+        SqliteDB::connect(options, Some(path.as_ref().to_path_buf()))
+    }
+
+    /// Opens an empty database kept in memory, which is lost when it is
+    /// dropped.
+    pub fn in_memory() -> anyhow::Result<Self> {
+        SqliteDB::connect(SqliteConnectOptions::new().in_memory(true), None)
+    }
+
+    fn connect(options: SqliteConnectOptions, path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        // Every connection to an in-memory database sees a database of its
+        // own, so a single connection is kept.
+        let pool = runtime.block_on(
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect_with(options),
+        )?;
+        let database = SqliteDB {
+            path,
+            runtime,
+            pool,
+        };
+        let tables = format!(
+            "{}\n{}",
+            properties_db::create_tables("INTEGER", "TEXT"),
+            binary_parameters_db::create_tables("INTEGER", "TEXT")
+        );
+        database.block_on(sqlx::raw_sql(&tables).execute(&database.pool))?;
+        Ok(database)
+    }
+
+    /// The file the database is kept in, or `None` for an in-memory
+    /// database.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+impl PropertyStore for SqliteDB {
+    fn find_species(&self, query: &SpeciesQuery) -> anyhow::Result<Vec<PureSpeciesRecord>> {
+        let sql = query.sql();
+        let species = bind_species_query!(sqlx::query_as(&sql), query).fetch_all(&self.pool);
+        Ok(self.block_on(species)?)
+    }
+
+    fn save_pure_species(&self, species: &PureSpeciesRecord) -> anyhow::Result<i64> {
+        let query = bind_pure_species!(
+            sqlx::query_scalar(properties_db::SAVE_PURE_SPECIES),
+            species
+        );
+        Ok(self.block_on(query.fetch_one(&self.pool))?)
+    }
+
+    fn property_sets(&self, species_id: i64, property: &str) -> anyhow::Result<Vec<PropertySet>> {
+        let sets = sqlx::query_as(properties_db::SELECT_PROPERTY_SETS)
+            .bind(species_id)
+            .bind(property)
+            .fetch_all(&self.pool);
+        Ok(self.block_on(sets)?)
+    }
+
+    fn save_property_set(&self, set: &PropertySet) -> anyhow::Result<i64> {
+        let query = match set.id {
+            None => sqlx::query_scalar(properties_db::INSERT_PROPERTY_SET),
+            Some(id) => sqlx::query_scalar(properties_db::UPDATE_PROPERTY_SET).bind(id),
+        };
+        let id = self.block_on(bind_property_set!(query, set).fetch_optional(&self.pool))?;
+        id.ok_or_else(|| anyhow::anyhow!("no property set has the id {:?}", set.id))
+    }
+
+    fn binary_parameters(
+        &self,
+        first: &str,
+        second: &str,
+        model: BinaryModel,
+    ) -> anyhow::Result<Option<BinaryParameterRecord>> {
+        let (first, second) = binary_parameters_db::normalized_pair(first, second);
+        let row: Option<BinaryParameterRow> = self.block_on(
+            sqlx::query_as(binary_parameters_db::SELECT_BINARY_PARAMETERS)
+                .bind(&first)
+                .bind(&second)
+                .bind(model.name())
+                .fetch_optional(&self.pool),
+        )?;
+        row.map(BinaryParameterRecord::try_from).transpose()
+    }
+
+    fn save_binary_parameters(&self, record: &BinaryParameterRecord) -> anyhow::Result<i64> {
+        let record = record.normalized();
+        let query = bind_binary_parameters!(
+            sqlx::query_scalar(binary_parameters_db::SAVE_BINARY_PARAMETERS),
+            record
+        );
+        Ok(self.block_on(query.fetch_one(&self.pool))?)
+    }
+}
+
+#[cfg(test)]
+mod sqlite_db_tests {
+    use super::*;
+    use crate::{BinaryParameterSource, InteractionParameters};
+
+    fn benzene() -> PureSpeciesRecord {
+        PureSpeciesRecord {
+            id: None,
+            name: String::from("benzene"),
+            cas: Some(String::from("71-43-2")),
+            formula: Some(String::from("C6H6")),
+            smiles: Some(String::from("C1=CC=CC=C1")),
+            pubchem_cid: Some(241),
+            user_defined: false,
+            molar_mass: 0.078114,
+            normal_boiling_point: 353.24,
+            critical_temperature: 562.05,
+            critical_pressure: 4.895e6,
+            critical_volume: 256e-6,
+            critical_compressibility: 0.268,
+            acentric_factor: 0.210,
+            antoine_a: Some(20.7936),
+            antoine_b: Some(2788.51),
+            antoine_c: Some(-52.36),
+        }
+    }
+
+    #[test]
+    fn species_are_kept_in_the_file() {
+        let path = std::env::temp_dir().join(format!("oscps-db-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let database = SqliteDB::open(&path).unwrap();
+        assert_eq!(database.path(), Some(path.as_path()));
+        assert_eq!(database.pure_species("benzene").unwrap(), None);
+        let id = database.save_pure_species(&benzene()).unwrap();
+        drop(database);
+
+        let database = SqliteDB::open(&path).unwrap();
+        let stored = database.pure_species("benzene").unwrap().unwrap();
+        assert_eq!(
+            stored,
+            PureSpeciesRecord {
+                id: Some(id),
+                ..benzene()
+            }
+        );
+        drop(database);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saving_a_species_again_updates_it() {
+        let database = SqliteDB::in_memory().unwrap();
+        let id = database.save_pure_species(&benzene()).unwrap();
+        let updated = PureSpeciesRecord {
+            antoine_a: None,
+            antoine_b: None,
+            antoine_c: None,
+            ..benzene()
+        };
+        assert_eq!(database.save_pure_species(&updated).unwrap(), id);
+        let stored = database.pure_species("benzene").unwrap().unwrap();
+        assert_eq!(stored.antoine_a, None);
+        assert_eq!(stored.id, Some(id));
+    }
+
+    #[test]
+    fn species_are_found_by_their_identifiers() {
+        let database = SqliteDB::in_memory().unwrap();
+        database.save_pure_species(&benzene()).unwrap();
+        for (name, smiles) in [
+            ("o-xylene", "CC1=CC=CC=C1C"),
+            ("p-xylene", "CC1=CC=C(C)C=C1"),
+        ] {
+            database
+                .save_pure_species(&PureSpeciesRecord {
+                    name: String::from(name),
+                    cas: None,
+                    formula: Some(String::from("C8H10")),
+                    smiles: Some(String::from(smiles)),
+                    pubchem_cid: None,
+                    user_defined: true,
+                    ..benzene()
+                })
+                .unwrap();
+        }
+        let names = |query: SpeciesQuery| -> Vec<String> {
+            let species = database.find_species(&query).unwrap();
+            species.into_iter().map(|species| species.name).collect()
+        };
+
+        assert_eq!(
+            names(SpeciesQuery::Name(String::from("BENZENE"))),
+            ["benzene"]
+        );
+        assert_eq!(
+            names(SpeciesQuery::Cas(String::from("71-43-2"))),
+            ["benzene"]
+        );
+        assert_eq!(
+            names(SpeciesQuery::Smiles(String::from("CC1=CC=CC=C1C"))),
+            ["o-xylene"]
+        );
+        assert_eq!(names(SpeciesQuery::PubchemCid(241)), ["benzene"]);
+        assert_eq!(
+            names(SpeciesQuery::Formula(String::from("C8H10"))),
+            ["o-xylene", "p-xylene"]
+        );
+        assert_eq!(
+            names(SpeciesQuery::NameContains(String::from("Xyl"))),
+            ["o-xylene", "p-xylene"]
+        );
+        assert!(names(SpeciesQuery::NameContains(String::from("_"))).is_empty());
+        assert_eq!(names(SpeciesQuery::UserDefined), ["o-xylene", "p-xylene"]);
+        assert!(database.pure_species("toluene").unwrap().is_none());
+    }
+
+    #[test]
+    fn property_sets_keep_their_source_and_range() {
+        let database = SqliteDB::in_memory().unwrap();
+        let benzene = database.save_pure_species(&benzene()).unwrap();
+        let mut set = PropertySet {
+            id: None,
+            species_id: benzene,
+            property: String::from("vapor_pressure"),
+            correlation: String::from("antoine"),
+            coefficients: vec![20.7936, 2788.51, -52.36],
+            source: String::from("Reid, Prausnitz and Poling (1987)"),
+            minimum_temperature: Some(280.0),
+            maximum_temperature: Some(377.0),
+        };
+        set.id = Some(database.save_property_set(&set).unwrap());
+        assert_eq!(
+            database.property_sets(benzene, "vapor_pressure").unwrap(),
+            [set.clone()]
+        );
+        assert!(database
+            .property_sets(benzene, "liquid_density")
+            .unwrap()
+            .is_empty());
+
+        set.maximum_temperature = None;
+        assert_eq!(database.save_property_set(&set).unwrap(), set.id.unwrap());
+        let stored = &database.property_sets(benzene, "vapor_pressure").unwrap()[0];
+        assert!(stored.covers(500.0) && !stored.covers(250.0));
+
+        let missing = PropertySet {
+            id: Some(1000),
+            ..set
+        };
+        assert!(database.save_property_set(&missing).is_err());
+    }
+
+    #[test]
+    fn binary_parameters_override_the_bundled_defaults() {
+        let database = SqliteDB::in_memory().unwrap();
+        let bundled = database
+            .interaction_parameters("methane", "propane", BinaryModel::PengRobinson)
+            .unwrap();
+        assert_eq!(
+            bundled,
+            Some(InteractionParameters::PengRobinson { kij: 0.014 })
+        );
+        assert!(database
+            .binary_parameters("methane", "propane", BinaryModel::PengRobinson)
+            .unwrap()
+            .is_none());
+
+        let fitted = InteractionParameters::NRTL {
+            a_ij: 1.0,
+            b_ij: 100.0,
+            a_ji: 2.0,
+            b_ji: 200.0,
+            alpha: 0.2,
+        };
+        let record = BinaryParameterRecord {
+            id: None,
+            first: String::from("Water"),
+            second: String::from("ethanol"),
+            parameters: fitted,
+            source: String::from("plant data"),
+        };
+        let id = database.save_binary_parameters(&record).unwrap();
+        assert_eq!(database.save_binary_parameters(&record).unwrap(), id);
+
+        let stored = database
+            .binary_parameters("ethanol", "water", BinaryModel::NRTL)
+            .unwrap()
+            .unwrap();
+        assert_eq!((stored.first.as_str(), stored.id), ("ethanol", Some(id)));
+        assert_eq!(stored.parameters, fitted.reversed());
+        let found = database
+            .interaction_parameters("water", "ethanol", BinaryModel::NRTL)
+            .unwrap();
+        assert_eq!(found, Some(fitted));
+        assert!(database
+            .interaction_parameters("water", "benzene", BinaryModel::NRTL)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -3,13 +3,15 @@ use iced::widget::canvas::event::{self, Event};
 use iced::widget::canvas::path::Builder;
 use iced::widget::canvas::{self, Canvas, Frame, Geometry, Path, Stroke};
 use iced::{Element, Fill, Point, Rectangle, Renderer, Theme};
-use oscps_lib::simulation::Simulation;
 
 use std::time::{Duration, SystemTime};
 
 use log::{debug, info, warn};
 use strum_macros::Display;
 
+// Number of outlets a splitter is drawn with.
+pub const SPLITTER_OUTLETS: usize = 3;
+
 #[derive(Default)]
 pub struct State {
     cache: canvas::Cache,
@@ -17,16 +19,11 @@ pub struct State {
 }
 
 impl State {
-    pub fn view<'a>(
-        &'a self,
-        components: &'a [Component],
-        simulation: &'a Simulation,
-    ) -> Element<'a, Component> {
+    pub fn view<'a>(&'a self, components: &'a [Component]) -> Element<'a, Component> {
         Canvas::new(Flowsheet {
             state: self,
             components,
             left_click_time: SystemTime::now(),
-            simulation,
         })
         .width(Fill)
         .height(Fill)
@@ -57,6 +54,11 @@ pub enum Component {
         at: Option<Point>,
         input: Option<Point>,
     },
+    Splitter {
+        at: Option<Point>,
+        input: Option<Point>,
+        outputs: [Option<Point>; SPLITTER_OUTLETS],
+    },
 }
 
 impl Component {
@@ -86,6 +88,13 @@ impl Component {
             output: None,
         }
     }
+    pub fn splitter() -> Self {
+        Component::Splitter {
+            at: None,
+            input: None,
+            outputs: [None; SPLITTER_OUTLETS],
+        }
+    }
 
     // Determine if cursor is within 5 pixels of a given point.
     fn is_in_bounds(&self, cursor_position: Point, input: Point) -> bool {
@@ -117,29 +126,34 @@ impl Component {
 
     // Determine if the cursor is in bounds of the output
     fn get_input(&self) -> Option<Point> {
-        return match self {
+        match self {
             Component::Connector { from, .. } => *from,
             Component::Mixer { input, .. } => *input,
             Component::Sink { input, .. } => *input,
+            Component::Splitter { input, .. } => *input,
             Component::Source { .. } => None, // Source does not have an input
-        };
+        }
     }
 
     fn on_output(&self, cursor_position: Point) -> bool {
-        let output = self.get_output();
-        match output {
-            Some(point) => self.is_in_bounds(cursor_position, point),
-            None => false,
-        }
+        self.output_at(cursor_position).is_some()
     }
 
-    fn get_output(&self) -> Option<Point> {
-        return match self {
-            Component::Connector { to, .. } => *to,
-            Component::Mixer { output, .. } => *output,
-            Component::Source { output, .. } => *output,
-            Component::Sink { .. } => None, // Sink does not have an output
-        };
+    // Find the output the cursor is in bounds of, if any
+    fn output_at(&self, cursor_position: Point) -> Option<Point> {
+        self.get_outputs()
+            .into_iter()
+            .find(|output| self.is_in_bounds(cursor_position, *output))
+    }
+
+    fn get_outputs(&self) -> Vec<Point> {
+        match self {
+            Component::Connector { to, .. } => to.iter().copied().collect(),
+            Component::Mixer { output, .. } => output.iter().copied().collect(),
+            Component::Source { output, .. } => output.iter().copied().collect(),
+            Component::Splitter { outputs, .. } => outputs.iter().flatten().copied().collect(),
+            Component::Sink { .. } => Vec::new(), // Sink does not have an output
+        }
     }
 
     fn draw_all(components: &[Component], frame: &mut Frame, theme: &Theme) {
@@ -181,6 +195,13 @@ impl Component {
 
                         Component::draw_sink(p, at, input)
                     }
+                    Component::Splitter { at, input, outputs } => {
+                        let at = at.expect(&expect_string);
+                        let input = input.expect(&expect_string);
+                        let outputs = outputs.map(|output| output.expect(&expect_string));
+
+                        Component::draw_splitter(p, at, input, &outputs)
+                    }
                 }
             }
         });
@@ -228,6 +249,28 @@ impl Component {
         p.circle(output, 5.0);
     }
 
+    pub fn draw_splitter(p: &mut Builder, at: Point, input: Point, outputs: &[Point]) {
+        debug!("Drawing splitter.");
+
+        // Mirror image of the mixer, with the point facing the input.
+        let top_point = Point::new(at.x + 100.0, at.y);
+        let bottom_point = Point::new(at.x + 100.0, at.y + 100.0);
+        let middle_point = Point::new(at.x, at.y + 50.0);
+        p.move_to(middle_point);
+        p.line_to(top_point);
+        p.line_to(bottom_point);
+        p.line_to(middle_point);
+
+        // Draw a circle for input connectors
+        p.move_to(at);
+        p.circle(input, 5.0);
+        // One circle for each output connector
+        for output in outputs {
+            p.move_to(at);
+            p.circle(*output, 5.0);
+        }
+    }
+
     pub fn draw_source(p: &mut Builder, at: Point, output: Point) {
         debug!("Drawing source.");
 
@@ -264,7 +307,6 @@ struct Flowsheet<'a> {
     state: &'a State,
     components: &'a [Component],
     left_click_time: SystemTime,
-    simulation: &'a Simulation,
 }
 
 impl<'a> Flowsheet<'a> {
@@ -307,6 +349,28 @@ impl<'a> Flowsheet<'a> {
         })
     }
 
+    // Helper function to place a splitter block, with its outputs spread
+    // evenly down its right side
+    fn place_splitter(cursor_position: Point) -> Option<Component> {
+        let input = Point::new(cursor_position.x - 5.0, cursor_position.y + 50.0);
+        let spacing = 100.0 / (SPLITTER_OUTLETS + 1) as f32;
+        let outputs: [Option<Point>; SPLITTER_OUTLETS] = std::array::from_fn(|i| {
+            Some(Point::new(
+                cursor_position.x + 105.0,
+                cursor_position.y + spacing * (i + 1) as f32,
+            ))
+        });
+        info!(
+            "Creating splitter at ({}, {}) with input at ({}, {}) and {} outputs",
+            cursor_position.x, cursor_position.y, input.x, input.y, SPLITTER_OUTLETS
+        );
+        Some(Component::Splitter {
+            at: Some(cursor_position),
+            input: Some(input),
+            outputs,
+        })
+    }
+
     // Helper function to connect a connector to an input/output.
     fn place_connector(
         &self,
@@ -329,7 +393,7 @@ impl<'a> Flowsheet<'a> {
                         result = Some(Pending::One {
                             // NOTE: Should be safe. This must be Some(..) if
                             // on_output returned true.
-                            from: component.get_output().unwrap(),
+                            from: component.output_at(cursor_position).unwrap(),
                         });
                         *state = result;
                         return None;
@@ -405,14 +469,14 @@ impl<'a> canvas::Program<Component> for Flowsheet<'a> {
 
                         match self.state.placement_mode {
                             Component::Connector { .. } => {
-                                Flowsheet::place_connector(&self, state, cursor_position)
-                            }
-                            Component::Mixer { .. } => {
-                                self.simulation;
-                                Flowsheet::place_mixer(cursor_position)
+                                Flowsheet::place_connector(self, state, cursor_position)
                             }
+                            Component::Mixer { .. } => Flowsheet::place_mixer(cursor_position),
                             Component::Source { .. } => Flowsheet::place_source(cursor_position),
                             Component::Sink { .. } => Flowsheet::place_sink(cursor_position),
+                            Component::Splitter { .. } => {
+                                Flowsheet::place_splitter(cursor_position)
+                            }
                         }
                     }
                     // Right click should cancel placement.
@@ -9,15 +9,17 @@ use oscps_lib::simulation::{self, Settings, Simulation};
 
 use icon::Icon;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 
 pub fn main() -> iced::Result {
     // Start the GUI env_logger::init();
     info!("Starting application");
 
-    let mut settings = iced::window::Settings::default();
-    settings.size = (1920.0, 1080.0).into();
-    settings.min_size = Some((480.0, 720.0).into());
+    let settings = iced::window::Settings {
+        size: (1920.0, 1080.0).into(),
+        min_size: Some((480.0, 720.0).into()),
+        ..Default::default()
+    };
 
     let application = iced::application(
         "Open Source Chemical Process Simulator",
@@ -79,15 +81,23 @@ impl MainWindow {
                 self.components.push(component);
                 self.flowsheet.request_redraw();
                 match component {
-                    flowsheet::Component::Source{ ..}  => todo!(),
-                    flowsheet::Component::Sink{ ..} => todo!(),
-                    flowsheet::Component::Mixer{ ..} => {
+                    flowsheet::Component::Source { .. } => {
+                        self.simulation.add_block(simulation::BlockType::Source);
+                    }
+                    flowsheet::Component::Sink { .. } => {
+                        self.simulation.add_block(simulation::BlockType::Sink);
+                    }
+                    flowsheet::Component::Mixer { .. } => {
                         self.simulation.add_block(simulation::BlockType::Mixer);
-                    },
-                    flowsheet::Component::Connector{ .. } => { 
-                        // self.simulation.add_stream(simulation::BlockType::Mixer);
-                        todo!();
-                    },
+                    }
+                    flowsheet::Component::Splitter { .. } => {
+                        self.simulation.add_block(simulation::BlockType::Splitter);
+                    }
+                    flowsheet::Component::Connector { .. } => {
+                        // TODO: Track which blocks the connector ends are attached to,
+                        // then add the stream to the simulation.
+                        warn!("Connectors are not yet added to the simulation.");
+                    }
                 }
             }
             // TODO: Make the clear option more deliberate (2 clicks at least)
@@ -115,6 +125,10 @@ impl MainWindow {
                         info!("Setting to sink placement mode.");
                         self.flowsheet.placement_mode = flowsheet::Component::sink();
                     }
+                    flowsheet::Component::Splitter { .. } => {
+                        info!("Setting to splitter placement mode.");
+                        self.flowsheet.placement_mode = flowsheet::Component::splitter();
+                    }
                 }
             }
             Message::Clicked(pane) => {
@@ -153,14 +167,14 @@ impl MainWindow {
         )
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let focus = self.focus;
         let pane_grid = PaneGrid::new(&self.panes, |id, pane, _is_maximized| {
             let is_focused = focus == Some(id);
             match pane {
                 Pane::ComponentSelection => {
                     debug!("Found Selection!");
-                    return column![
+                    column![
                         container(text("Component Selection"))
                             .padding(5)
                             .width(Length::Fill)
@@ -174,9 +188,10 @@ impl MainWindow {
                         self.placement_button(flowsheet::Component::connector())
                             .into(),
                         self.placement_button(flowsheet::Component::mixer()).into(),
+                        self.placement_button(flowsheet::Component::splitter()).into(),
                     ]
                     .width(Length::Fill)
-                    .into();
+                    .into()
                 }
                 Pane::Canvas => {
                     debug!("Found canvas!");
@@ -192,7 +207,7 @@ impl MainWindow {
                     pane_grid::Content::new(responsive(move |_size| {
                         view_content(hover(
                             self.flowsheet
-                                .view(&self.components, &self.simulation)
+                                .view(&self.components)
                                 .map(Message::AddedComponent),
                             if self.components.is_empty() {
                                 container(horizontal_space())
@@ -6,73 +6,201 @@
 //! For example, if a block is a simple mixer, then it will implement the
 //! MassBalance trait but not the EnergyBalance.
 
-use crate::stream::Stream;
+use crate::simulation::equation_oriented::{self, EquationVariables};
+use crate::stream::{MolarFlowRate, StreamThermoState};
+use crate::thermodynamics::phase_stability::{self, MultiphaseResult, PhaseSplit};
+use crate::thermodynamics::{find_root, FlashResult, Phase, PhaseEquilibrium};
+use autodiff::F1;
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uom::si::catalytic_activity::katal;
 use uom::si::energy::joule;
-use uom::si::f64::Energy;
-use uom::si::f64::Mass;
+use uom::si::f64::{
+    Energy, Mass, MassRate, MolarEnergy, Power, Pressure, Ratio, ThermodynamicTemperature, Time,
+};
 use uom::si::mass::kilogram;
+use uom::si::mass_rate::kilogram_per_second;
+use uom::si::molar_energy::joule_per_mole;
+use uom::si::molar_mass::kilogram_per_mole;
+use uom::si::power::watt;
+use uom::si::pressure::pascal;
+use uom::si::ratio::ratio;
+use uom::si::thermodynamic_temperature::kelvin;
+use uom::si::time::second;
 
 use crate::simulation::StreamReference;
 
 /// # Block
 ///
 /// A trait that all blocks must implement.
-/// TODO: In ASPEN, streams can be used to specify process inputs and outputs.
-/// Instead, have special blocks that are 'source' and 'sink' blocks for
-/// material entering and exiting the simulation. To make it more user friendly,
-/// if a user attempts to run a simulation with stream that are not connected to
-/// inputs or outputs, offer to automatically insert sources/sinks where the loose
-/// ends are. While these special blocks will still have to implement this trait
-/// (and thus implement unnecessary functions, such as the "connect_input" function
-/// for a souce block, these functions can simply be dummy functions for this special case.
-/// For safety, they can throw errors if called, but they should never be used.
+///
+/// Material enters and leaves the simulation through special [`Source`] and
+/// [`Sink`] blocks rather than through unconnected streams. Blocks report
+/// ports left without a stream through [`Block::has_open_inlet`] and
+/// [`Block::open_outlets`], so that the simulation can offer to insert
+/// sources and sinks at these loose ends. Sources and sinks still implement
+/// the port functions they have no use for, such as "connect_input" for a
+/// source, but these return errors if called.
 pub trait Block {
-    /// Connect an input to a block. TODO: Have this function create the input stream and return a
-    /// reference to it. Then use that stream reference to connect an output.
-    fn connect_input(&mut self, stream: &mut Stream) -> Result<(), &str>;
+    /// Connect an input to a block. Streams are created by the simulation,
+    /// which then connects them to the blocks at either end.
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &str>;
     /// Disconnect an input to a block
-    fn disconnect_input(&mut self, stream: &mut Stream) -> Result<(), &str>;
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &str>;
     /// Connect an output to a block
-    fn connect_output(&mut self, stream: &mut Stream) -> Result<(), &str>;
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &str>;
     /// Disconnect an output to a block
-    fn disconnect_output(&mut self, stream: &mut Stream) -> Result<(), &str>;
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &str>;
+    /// Execute the block, calculating its outlet streams from its inlet
+    /// streams. Called by the simulation once all upstream blocks have been
+    /// executed.
+    fn execute(&mut self) -> Result<(), &str>;
+    /// Residual equations of the block for the equation-oriented solver,
+    /// written in terms of the variables of its inlet and outlet streams. A
+    /// block should contribute one equation per outlet stream variable. Blocks
+    /// without equations, such as sinks, can use the default.
+    fn residuals(&self, _variables: &EquationVariables) -> Result<Vec<F1>, &str> {
+        Ok(Vec::new())
+    }
+    /// Returns true if the block needs an inlet stream that is not connected.
+    fn has_open_inlet(&self) -> bool {
+        false
+    }
+    /// Returns the number of outlet streams the block needs that are not
+    /// connected.
+    fn open_outlets(&self) -> usize {
+        0
+    }
+    /// Specify the material fed to the flowsheet by the block. Only sources
+    /// take a feed.
+    fn set_feed(&mut self, _feed: StreamThermoState) -> Result<(), &str> {
+        Err("block does not take a feed")
+    }
+    /// Set the thermodynamic model of the block, which takes precedence over
+    /// any property package. Passing 'None' returns the block to its
+    /// property packages.
+    fn set_thermo_model(&mut self, _model: Option<Arc<dyn PhaseEquilibrium>>) -> Result<(), &str> {
+        Err("block does not use a thermodynamic model")
+    }
+    /// The models of the property packages the block falls back on, or
+    /// `None` for blocks without thermodynamic calculations.
+    fn package_models(&mut self) -> Option<&mut PackageModels> {
+        None
+    }
     // TODO: Add additional functions that all Blocks should implement
 }
 
-/// # Separator
+/// # PackageModels
 ///
-/// A Separator block that allows components of a stream to be separated.
-/// Allows for a single input and an arbitrary number of outputs.
-#[allow(dead_code)]
-struct Separator {
-    id: u64,
-    input: Option<Stream>, // An option is used in case there is no input stream
-    outputs: Vec<Stream>, // An empty vec can represent no outputs, no need for Option<Vec<Stream>>>
-                          // TODO: Add additional fields that controls how components are separated
+/// The models of the property packages a block falls back on when it is not
+/// given a thermodynamic model of its own. A package given to the block
+/// takes precedence over the package of the simulation.
+#[derive(Clone, Default)]
+pub struct PackageModels {
+    /// Model of the property package given to the block
+    pub block: Option<Arc<dyn PhaseEquilibrium>>,
+    /// Model of the property package of the simulation, passed on before
+    /// every solve
+    pub simulation: Option<Arc<dyn PhaseEquilibrium>>,
 }
 
-#[allow(dead_code)]
-impl Separator {
-    fn new(id: u64) -> Self {
-        Separator {
-            id,
-            input: None,
-            outputs: Vec::new(),
-        }
+impl PackageModels {
+    /// The model a block uses: its own model, or else that of its property
+    /// package, or else that of the simulation.
+    pub fn select<'a>(
+        &'a self,
+        own: &'a Option<Arc<dyn PhaseEquilibrium>>,
+    ) -> Option<&'a Arc<dyn PhaseEquilibrium>> {
+        own.as_ref()
+            .or(self.block.as_ref())
+            .or(self.simulation.as_ref())
+    }
+}
+
+/// Reads the material carried by a stream, if any.
+fn read_stream(stream: &StreamReference) -> Result<Option<StreamThermoState>, &'static str> {
+    let stream = stream.read().map_err(|_| "stream lock poisoned")?;
+    Ok(stream.thermo.clone())
+}
+
+/// Overwrites the material carried by a stream.
+fn write_stream(stream: &StreamReference, thermo: StreamThermoState) -> Result<(), &'static str> {
+    let mut stream = stream.write().map_err(|_| "stream lock poisoned")?;
+    stream.thermo = Some(thermo);
+    Ok(())
+}
+
+/// Returns the molar enthalpy of a stream, flashing it at its temperature and
+/// pressure if it has not been calculated yet.
+fn stream_enthalpy(
+    stream: &StreamThermoState,
+    model: &dyn PhaseEquilibrium,
+) -> Result<MolarEnergy, &'static str> {
+    match stream.enthalpy {
+        Some(enthalpy) => Ok(enthalpy),
+        None => Ok(model
+            .flash_tp(
+                stream.temperature,
+                stream.pressure,
+                &model.composition(stream)?,
+            )?
+            .enthalpy),
     }
 }
 
+/// Rate at which enthalpy is carried by a stream described by its equation
+/// variables (component flows, temperature and pressure).
+fn enthalpy_flow_residual(
+    components: &[String],
+    variables: &[F1],
+    model: &dyn PhaseEquilibrium,
+) -> Result<F1, &'static str> {
+    equation_oriented::lift(
+        |x| {
+            let n = components.len();
+            let total: f64 = x[..n].iter().sum();
+            if total <= 0.0 {
+                return Ok(0.0);
+            }
+            let flows = x[..n]
+                .iter()
+                .map(|flow| MolarFlowRate::new::<katal>(*flow))
+                .collect();
+            let stream = StreamThermoState::from_molar_flows(
+                components.to_vec(),
+                flows,
+                ThermodynamicTemperature::new::<kelvin>(x[n]),
+                Pressure::new::<pascal>(x[n + 1]),
+            )?;
+            let enthalpy = stream_enthalpy(&stream, model)?;
+            Ok(total * enthalpy.get::<joule_per_mole>())
+        },
+        variables,
+    )
+}
+
 #[allow(dead_code)]
 /// # Mixer
 ///
 /// A block used for simple stream mixing operations. Spacial information
 /// is not stored in the case that non-gui applications use this backend.
+///
+/// The mixer is adiabatic: the outlet enthalpy equals the combined enthalpy of
+/// the inlets, and the outlet temperature and phase split are found with a
+/// flash at that enthalpy.
 pub struct Mixer {
     /// Set of inlet streams for the mixer
     pub inputs: Option<Vec<StreamReference>>,
     /// Outlet stream for the mixer block
     pub output: Option<StreamReference>,
+    /// Outlet pressure. If not given, the lowest inlet pressure is used.
+    pub outlet_pressure: Option<Pressure>,
+    /// Thermodynamic model used for the energy balance
+    pub thermo_model: Option<Arc<dyn PhaseEquilibrium>>,
+    /// Models of the property packages, used when no thermodynamic model
+    /// is given
+    pub package_models: PackageModels,
 }
 
 #[allow(dead_code)]
@@ -83,85 +211,1736 @@ impl Mixer {
         Mixer {
             inputs: None,
             output: None,
+            outlet_pressure: None,
+            thermo_model: None,
+            package_models: PackageModels::default(),
+        }
+    }
+
+    /// Create a new mixer block that uses the given thermodynamic model.
+    pub fn with_model(thermo_model: Arc<dyn PhaseEquilibrium>) -> Mixer {
+        Mixer {
+            thermo_model: Some(thermo_model),
+            ..Mixer::new()
         }
     }
 
-    // TODO: Uncomment once desired base functionality is achieved
-    // /// Execute the mixer block (calculate balances, output streams, etc) /// This function still needs to be implemented pub fn execute_block(&mut self) {
-    //     self.outlet_stream = Some(connector::Stream {
-    //         s_id: String::from("Mass_Outlet"),
-    //         thermo: None,
-    //         from_block: String::from("M1"),
-    //         to_block: String::from("M2")
-    //         // m_flow_total: self.compute_total_outlet_mass_flow().unwrap(),
-    //     });
-    //     // self.outlet_stream_energy = Some(connector::Econnector {
-    //     //     e_conn_id: String::from("Energy Outlet"),
-    //     //     energy_flow_total: self.compute_outlet_energy_flows().unwrap(),
-    //     // });
-    // }
-
-    // /// This private method will compute the outlet mass flows for the mixer block
-    // ///
-    // /// # Returns
-    // ///
-    // /// A Mass quantity (uom object) that holds the outlet mass flow
-    // fn compute_total_outlet_mass_flow(&self) -> Option<f64> {
-    //     // TODO: steps to implement function:
-    //     // Need to loop through each of the connector structures and add up the mass flows
-    //     // During this process, need to make sure that all the mass flows are in the same units
-    //     // Use the UOM package to help with this part...
-    //     let mut mass_flow_sum: f64 = 0.0;
-
-    //     for s in self.inlet_streams.iter() {
-    //         mass_flow_sum += s.thermo.as_ref().unwrap().total_mass();
-    //     }
-    //     Some(mass_flow_sum)
-    // }
-
-    // /// Determines the total energy flowing through the block
-    // fn compute_outlet_energy_flows(&self) -> Option<f64> {
-    //     let mut energy_flow_sum: f64 = 0.0;
-
-    //     for s in self.inlet_streams.iter() {
-    //         energy_flow_sum += s.thermo.as_ref().unwrap().enthalpy();
-    //     }
-    //     Some(energy_flow_sum)
-    // }
-
-    // /// Determines the phase fractions of the output using thermodynamics.
-    // /// TODO: Implement this function
-    // fn compute_outlet_phase_fractions(&self) {}
-
-    // /// Computes the outlet temperature of the mixer (assumes no chemical
-    // /// reactions) TODO: Implement this function
-    // fn compute_outlet_temperature(&self) {}
-
-    // /// Computes the mixer outlet pressure.
-    // /// TODO: Implement this function
-    // fn compute_outlet_pressure(&self) {}
+    /// Collects the material of every inlet stream. Inlets that carry no
+    /// material yet, such as a tear stream before its first pass, are skipped.
+    fn inlet_states(&self) -> Result<Vec<StreamThermoState>, &'static str> {
+        let mut inlets = Vec::new();
+        for stream in self.inputs.iter().flatten() {
+            if let Some(thermo) = read_stream(stream)? {
+                inlets.push(thermo);
+            }
+        }
+        if inlets.is_empty() {
+            return Err("mixer has no inlet streams carrying material");
+        }
+        Ok(inlets)
+    }
+
+    /// Sums the flow of each component over all inlets. Components are listed
+    /// in the order they are first found in the inlets.
+    fn compute_outlet_flows(inlets: &[StreamThermoState]) -> (Vec<String>, Vec<MolarFlowRate>) {
+        let mut components: Vec<String> = Vec::new();
+        let mut flows: Vec<MolarFlowRate> = Vec::new();
+        for inlet in inlets {
+            for (name, flow) in inlet.components.iter().zip(&inlet.molar_flows) {
+                match components.iter().position(|component| component == name) {
+                    Some(index) => flows[index] += *flow,
+                    None => {
+                        components.push(name.clone());
+                        flows.push(*flow);
+                    }
+                }
+            }
+        }
+        (components, flows)
+    }
+
+    /// Total mass flow entering the mixer.
+    fn compute_total_outlet_mass_flow(
+        inlets: &[StreamThermoState],
+        model: &dyn PhaseEquilibrium,
+    ) -> Result<MassRate, &'static str> {
+        inlets.iter().try_fold(MassRate::default(), |total, inlet| {
+            Ok(total + model.mass_flow(inlet)?)
+        })
+    }
+
+    /// Total rate at which enthalpy enters the mixer.
+    fn compute_outlet_energy_flows(
+        inlets: &[StreamThermoState],
+        model: &dyn PhaseEquilibrium,
+    ) -> Result<Power, &'static str> {
+        inlets.iter().try_fold(Power::default(), |total, inlet| {
+            let enthalpy = stream_enthalpy(inlet, model)?;
+            Ok(total + inlet.total_molar_flow() * enthalpy)
+        })
+    }
+
+    /// Computes the mixer outlet pressure: the specified outlet pressure, or
+    /// the lowest inlet pressure.
+    fn compute_outlet_pressure(&self, inlets: &[StreamThermoState]) -> Pressure {
+        self.outlet_pressure.unwrap_or_else(|| {
+            inlets.iter().map(|inlet| inlet.pressure).fold(
+                inlets[0].pressure,
+                |lowest, pressure| {
+                    if pressure < lowest {
+                        pressure
+                    } else {
+                        lowest
+                    }
+                },
+            )
+        })
+    }
+
+    /// Computes the outlet temperature and phase fractions of the mixer
+    /// (assumes no chemical reactions) by flashing the outlet at the combined
+    /// inlet enthalpy. The flow-weighted inlet temperature is used as the
+    /// starting guess.
+    fn compute_outlet_temperature(
+        outlet: &StreamThermoState,
+        energy_flow: Power,
+        inlets: &[StreamThermoState],
+        model: &dyn PhaseEquilibrium,
+    ) -> Result<FlashResult, &'static str> {
+        let total_flow = outlet.total_molar_flow().get::<katal>();
+        let enthalpy = MolarEnergy::new::<joule_per_mole>(energy_flow.get::<watt>() / total_flow);
+        let guess = inlets
+            .iter()
+            .map(|inlet| {
+                inlet.total_molar_flow().get::<katal>() * inlet.temperature.get::<kelvin>()
+            })
+            .sum::<f64>()
+            / total_flow;
+        model.flash_ph(
+            outlet.pressure,
+            enthalpy,
+            &model.composition(outlet)?,
+            ThermodynamicTemperature::new::<kelvin>(guess),
+        )
+    }
+
+    /// Mixes the inlet streams into the outlet stream and checks that mass
+    /// and energy are conserved.
+    fn mix(
+        &self,
+        inlets: &[StreamThermoState],
+        model: &dyn PhaseEquilibrium,
+    ) -> Result<StreamThermoState, &'static str> {
+        let (components, flows) = Self::compute_outlet_flows(inlets);
+        let mut outlet = StreamThermoState::from_molar_flows(
+            components,
+            flows,
+            inlets[0].temperature,
+            self.compute_outlet_pressure(inlets),
+        )?;
+        if outlet.total_molar_flow().get::<katal>() <= 0.0 {
+            return Ok(outlet);
+        }
+
+        let energy_in = Self::compute_outlet_energy_flows(inlets, model)?;
+        let flash = Self::compute_outlet_temperature(&outlet, energy_in, inlets, model)?;
+        outlet.temperature = flash.temperature;
+        outlet.vapor_fraction = Some(flash.vapor_fraction);
+        outlet.enthalpy = Some(flash.enthalpy);
+
+        // Balances are checked over one second of operation.
+        let basis = Time::new::<second>(1.0);
+        let mass_in: Mass = Self::compute_total_outlet_mass_flow(inlets, model)? * basis;
+        let mass_out: Mass = model.mass_flow(&outlet)? * basis;
+        if !self.mass_balance_check(mass_in, mass_out) {
+            return Err("mixer mass balance is not satisfied");
+        }
+        let energy_out: Power = outlet.enthalpy_flow().ok_or("outlet enthalpy is unknown")?;
+        if !self.energy_balance_check(energy_in * basis, energy_out * basis) {
+            return Err("mixer energy balance is not satisfied");
+        }
+        Ok(outlet)
+    }
 }
 
 impl Block for Mixer {
-    fn connect_input<'a>(&mut self, _stream: &mut Stream) -> Result<(), &'static str> {
-        // TODO: Figure out how to store references to streams
-        // self.inputs.push(stream);
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        self.inputs.get_or_insert_with(Vec::new).push(stream);
+        Ok(())
+    }
+
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        let inputs = self
+            .inputs
+            .as_mut()
+            .ok_or("stream is not an inlet of the mixer")?;
+        let count = inputs.len();
+        inputs.retain(|input| !Arc::ptr_eq(input, stream));
+        if inputs.len() == count {
+            return Err("stream is not an inlet of the mixer");
+        }
+        Ok(())
+    }
+
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.output.is_some() {
+            return Err("mixer already has an outlet stream");
+        }
+        self.output = Some(stream);
+        Ok(())
+    }
+
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.output {
+            Some(output) if Arc::ptr_eq(output, stream) => {
+                self.output = None;
+                Ok(())
+            }
+            _ => Err("stream is not the outlet of the mixer"),
+        }
+    }
+
+    fn has_open_inlet(&self) -> bool {
+        self.inputs.as_ref().is_none_or(|inputs| inputs.is_empty())
+    }
+
+    fn open_outlets(&self) -> usize {
+        usize::from(self.output.is_none())
+    }
+
+    fn set_thermo_model(
+        &mut self,
+        model: Option<Arc<dyn PhaseEquilibrium>>,
+    ) -> Result<(), &'static str> {
+        self.thermo_model = model;
+        Ok(())
+    }
+
+    fn package_models(&mut self) -> Option<&mut PackageModels> {
+        Some(&mut self.package_models)
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let output = self.output.as_ref().ok_or("mixer has no outlet stream")?;
+        let model = self
+            .package_models
+            .select(&self.thermo_model)
+            .ok_or("mixer requires a thermodynamic model for its energy balance")?;
+        let inlets = self.inlet_states()?;
+        let outlet = self.mix(&inlets, model.as_ref())?;
+        write_stream(output, outlet)
+    }
+
+    /// Component balances, the outlet pressure specification and the
+    /// adiabatic energy balance.
+    fn residuals(&self, variables: &EquationVariables) -> Result<Vec<F1>, &'static str> {
+        let output = self.output.as_ref().ok_or("mixer has no outlet stream")?;
+        let model = self
+            .package_models
+            .select(&self.thermo_model)
+            .ok_or("mixer requires a thermodynamic model for its energy balance")?;
+        let outlet = read_stream(output)?.ok_or("mixer outlet carries no material")?;
+        let outlet_variables = variables
+            .get(output)
+            .ok_or("mixer outlet is not in the system")?;
+        let n = outlet.components.len();
+
+        let mut flow_balance: Vec<F1> = outlet_variables[..n].to_vec();
+        let mut energy_balance =
+            enthalpy_flow_residual(&outlet.components, outlet_variables, model.as_ref())?;
+        let mut lowest_pressure: Option<F1> = None;
+        for stream in self.inputs.iter().flatten() {
+            let Some(inlet) = read_stream(stream)? else {
+                continue;
+            };
+            let inlet_variables = variables
+                .get(stream)
+                .ok_or("mixer inlet is not in the system")?;
+            for (name, flow) in inlet.components.iter().zip(inlet_variables) {
+                let index = outlet
+                    .components
+                    .iter()
+                    .position(|component| component == name)
+                    .ok_or("mixer inlet component is missing from the outlet")?;
+                flow_balance[index] -= *flow;
+            }
+            let pressure = inlet_variables[inlet.components.len() + 1];
+            if lowest_pressure.is_none_or(|lowest| pressure < lowest) {
+                lowest_pressure = Some(pressure);
+            }
+            energy_balance -=
+                enthalpy_flow_residual(&inlet.components, inlet_variables, model.as_ref())?;
+        }
+
+        let pressure_balance = match self.outlet_pressure {
+            Some(pressure) => outlet_variables[n + 1] - pressure.get::<pascal>(),
+            None => {
+                outlet_variables[n + 1] - lowest_pressure.ok_or("mixer has no inlet streams")?
+            }
+        };
+        flow_balance.push(pressure_balance);
+        flow_balance.push(energy_balance);
+        Ok(flow_balance)
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer::new()
+    }
+}
+
+/// Optional temperature and pressure specifications for an outlet stream.
+/// Conditions that are not specified are taken from the feed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutletConditions {
+    /// Temperature of the outlet stream
+    pub temperature: Option<ThermodynamicTemperature>,
+    /// Pressure of the outlet stream
+    pub pressure: Option<Pressure>,
+}
+
+/// # Separator
+///
+/// A Separator block that allows components of a stream to be separated.
+/// Allows for a single input and an arbitrary number of outputs.
+///
+/// The fraction of each component sent to each outlet is specified by the
+/// user. Outlets are numbered in the order they were connected. For each
+/// component, either a fraction is given for every outlet, or one fewer and
+/// the last outlet receives the rest. Components without split fractions go
+/// entirely to the last outlet.
+pub struct Separator {
+    /// Inlet stream for the separator
+    pub input: Option<StreamReference>,
+    /// Outlet streams for the separator, in the order they were connected
+    pub outputs: Vec<StreamReference>,
+    /// Fraction of each component's feed sent to each outlet
+    pub split_fractions: BTreeMap<String, Vec<f64>>,
+    /// Conditions of each outlet, in the same order as `outputs`
+    pub outlet_conditions: Vec<OutletConditions>,
+    /// Thermodynamic model used to calculate the duty
+    pub thermo_model: Option<Arc<dyn PhaseEquilibrium>>,
+    /// Models of the property packages, used when no thermodynamic model
+    /// is given
+    pub package_models: PackageModels,
+    /// Heat added to the separator during the last execution. Only
+    /// calculated when a thermodynamic model is given.
+    pub duty: Option<Power>,
+}
+
+impl Separator {
+    /// Create a new separator block without split specifications.
+    pub fn new() -> Separator {
+        Separator {
+            input: None,
+            outputs: Vec::new(),
+            split_fractions: BTreeMap::new(),
+            outlet_conditions: Vec::new(),
+            thermo_model: None,
+            package_models: PackageModels::default(),
+            duty: None,
+        }
+    }
+
+    /// Sets the fraction of a component sent to each outlet.
+    pub fn set_split_fractions(&mut self, component: &str, fractions: Vec<f64>) {
+        self.split_fractions
+            .insert(component.to_string(), fractions);
+    }
+
+    /// Returns the fraction of a component sent to each of the outlets.
+    fn fractions(&self, component: &str) -> Result<Vec<f64>, &'static str> {
+        let count = self.outputs.len();
+        let given = self
+            .split_fractions
+            .get(component)
+            .map_or(&[][..], |f| &f[..]);
+        if given.iter().any(|fraction| !(0.0..=1.0).contains(fraction)) {
+            return Err("split fractions must be between zero and one");
+        }
+        let total: f64 = given.iter().sum();
+        let mut fractions = given.to_vec();
+        if given.is_empty() && count > 0 {
+            fractions = vec![0.0; count];
+            fractions[count - 1] = 1.0;
+        } else if given.len() + 1 == count {
+            if total > 1.0 + SPLIT_TOLERANCE {
+                return Err("split fractions of a component add up to more than one");
+            }
+            fractions.push((1.0 - total).max(0.0));
+        } else if given.len() == count {
+            if (total - 1.0).abs() > SPLIT_TOLERANCE {
+                return Err("split fractions to every outlet must add up to one");
+            }
+        } else {
+            return Err("split fractions do not match the number of outlets");
+        }
+        Ok(fractions)
+    }
+
+    /// Returns the temperature and pressure of an outlet.
+    fn conditions(
+        &self,
+        outlet: usize,
+        feed: &StreamThermoState,
+    ) -> (ThermodynamicTemperature, Pressure) {
+        let conditions = self
+            .outlet_conditions
+            .get(outlet)
+            .copied()
+            .unwrap_or_default();
+        (
+            conditions.temperature.unwrap_or(feed.temperature),
+            conditions.pressure.unwrap_or(feed.pressure),
+        )
+    }
+}
+
+impl Block for Separator {
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.input.is_some() {
+            return Err("separator already has an inlet stream");
+        }
+        self.input = Some(stream);
+        Ok(())
+    }
+
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.input {
+            Some(input) if Arc::ptr_eq(input, stream) => {
+                self.input = None;
+                Ok(())
+            }
+            _ => Err("stream is not the inlet of the separator"),
+        }
+    }
+
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        self.outputs.push(stream);
+        Ok(())
+    }
+
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        let count = self.outputs.len();
+        self.outputs.retain(|output| !Arc::ptr_eq(output, stream));
+        if self.outputs.len() == count {
+            return Err("stream is not an outlet of the separator");
+        }
+        Ok(())
+    }
+
+    fn set_thermo_model(
+        &mut self,
+        model: Option<Arc<dyn PhaseEquilibrium>>,
+    ) -> Result<(), &'static str> {
+        self.thermo_model = model;
+        Ok(())
+    }
+
+    fn package_models(&mut self) -> Option<&mut PackageModels> {
+        Some(&mut self.package_models)
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let input = self.input.as_ref().ok_or("separator has no inlet stream")?;
+        let feed = read_stream(input)?.ok_or("separator inlet carries no material")?;
+        if self.outputs.is_empty() {
+            return Err("separator has no outlet streams");
+        }
+        let fractions = feed
+            .components
+            .iter()
+            .map(|component| self.fractions(component))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut outlets = Vec::with_capacity(self.outputs.len());
+        for outlet in 0..self.outputs.len() {
+            let flows = feed
+                .molar_flows
+                .iter()
+                .zip(&fractions)
+                .map(|(flow, fractions)| *flow * fractions[outlet])
+                .collect();
+            let (temperature, pressure) = self.conditions(outlet, &feed);
+            let mut thermo = StreamThermoState::from_molar_flows(
+                feed.components.clone(),
+                flows,
+                temperature,
+                pressure,
+            )?;
+            if let Some(model) = self.package_models.select(&self.thermo_model) {
+                if thermo.total_molar_flow().get::<katal>() > 0.0 {
+                    let flash =
+                        model.flash_tp(temperature, pressure, &model.composition(&thermo)?)?;
+                    thermo.vapor_fraction = Some(flash.vapor_fraction);
+                    thermo.enthalpy = Some(flash.enthalpy);
+                } else {
+                    thermo.enthalpy = Some(MolarEnergy::default());
+                }
+            }
+            outlets.push(thermo);
+        }
+
+        self.duty = match self.package_models.select(&self.thermo_model) {
+            Some(model) => {
+                let energy_in = feed.total_molar_flow() * stream_enthalpy(&feed, model.as_ref())?;
+                let energy_out = outlets.iter().try_fold(Power::default(), |total, outlet| {
+                    Ok::<_, &'static str>(
+                        total + outlet.enthalpy_flow().ok_or("outlet enthalpy is unknown")?,
+                    )
+                })?;
+                Some(energy_out - energy_in)
+            }
+            None => None,
+        };
+        for (output, thermo) in self.outputs.iter().zip(outlets) {
+            write_stream(output, thermo)?;
+        }
+        Ok(())
+    }
+
+    /// Component splits and outlet conditions for every outlet.
+    fn residuals(&self, variables: &EquationVariables) -> Result<Vec<F1>, &'static str> {
+        let input = self.input.as_ref().ok_or("separator has no inlet stream")?;
+        let feed = read_stream(input)?.ok_or("separator inlet carries no material")?;
+        let inlet_variables = variables
+            .get(input)
+            .ok_or("separator inlet is not in the system")?;
+        let n = feed.components.len();
+        let fractions = feed
+            .components
+            .iter()
+            .map(|component| self.fractions(component))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut residuals = Vec::new();
+        for (outlet, output) in self.outputs.iter().enumerate() {
+            let outlet_variables = variables
+                .get(output)
+                .ok_or("separator outlet is not in the system")?;
+            if outlet_variables.len() != n + 2 {
+                return Err("separator outlet does not match the feed");
+            }
+            for j in 0..n {
+                residuals.push(outlet_variables[j] - inlet_variables[j] * fractions[j][outlet]);
+            }
+            let conditions = self
+                .outlet_conditions
+                .get(outlet)
+                .copied()
+                .unwrap_or_default();
+            residuals.push(match conditions.temperature {
+                Some(temperature) => outlet_variables[n] - temperature.get::<kelvin>(),
+                None => outlet_variables[n] - inlet_variables[n],
+            });
+            residuals.push(match conditions.pressure {
+                Some(pressure) => outlet_variables[n + 1] - pressure.get::<pascal>(),
+                None => outlet_variables[n + 1] - inlet_variables[n + 1],
+            });
+        }
+        Ok(residuals)
+    }
+
+    fn has_open_inlet(&self) -> bool {
+        self.input.is_none()
+    }
+
+    fn open_outlets(&self) -> usize {
+        usize::from(self.outputs.is_empty())
+    }
+}
+
+impl Default for Separator {
+    fn default() -> Self {
+        Separator::new()
+    }
+}
+
+/// How much of the feed a splitter sends to one of its outlets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitSpec {
+    /// Fraction of the feed sent to the outlet
+    Fraction(f64),
+    /// Total molar flow of the outlet
+    MoleFlow(MolarFlowRate),
+    /// Total mass flow of the outlet. Requires a thermodynamic model for the
+    /// molar masses of the components.
+    MassFlow(MassRate),
+    /// Whatever is left once the other outlets are satisfied. At most one
+    /// outlet may use this specification.
+    Remaining,
+}
+
+/// # Splitter
+///
+/// A block that divides one stream into several streams with the same
+/// composition and conditions as the feed. Each outlet is given a
+/// [`SplitSpec`], in the order the outlets were connected.
+pub struct Splitter {
+    /// Inlet stream for the splitter
+    pub input: Option<StreamReference>,
+    /// Outlet streams for the splitter, in the order they were connected
+    pub outputs: Vec<StreamReference>,
+    /// Specification of each outlet, in the same order as `outputs`
+    pub specs: Vec<SplitSpec>,
+    /// Thermodynamic model, used for the molar masses of mass flow specs
+    pub thermo_model: Option<Arc<dyn PhaseEquilibrium>>,
+    /// Models of the property packages, used when no thermodynamic model
+    /// is given
+    pub package_models: PackageModels,
+}
+
+impl Splitter {
+    /// Create a new splitter block without outlet specifications.
+    pub fn new() -> Splitter {
+        Splitter {
+            input: None,
+            outputs: Vec::new(),
+            specs: Vec::new(),
+            thermo_model: None,
+            package_models: PackageModels::default(),
+        }
+    }
+
+    /// Molar mass of each feed component in kg/mol, if any outlet needs them.
+    fn molar_masses(&self, feed: &StreamThermoState) -> Result<Vec<f64>, &'static str> {
+        if !self
+            .specs
+            .iter()
+            .any(|spec| matches!(spec, SplitSpec::MassFlow(_)))
+        {
+            return Ok(Vec::new());
+        }
+        let model = self
+            .package_models
+            .select(&self.thermo_model)
+            .ok_or("mass flow splits require a thermodynamic model")?;
+        let molar_masses = model.molar_masses();
+        feed.components
+            .iter()
+            .map(|name| {
+                model
+                    .components()
+                    .iter()
+                    .position(|component| component == name)
+                    .map(|index| molar_masses[index].get::<kilogram_per_mole>())
+                    .ok_or("feed component is not in the thermodynamic model")
+            })
+            .collect()
+    }
+
+    /// Fraction of the feed sent to each outlet, as a function of the feed
+    /// component flows in mol/s.
+    fn fractions(&self, flows: &[F1], molar_masses: &[f64]) -> Result<Vec<F1>, &'static str> {
+        if self.specs.len() != self.outputs.len() {
+            return Err("a split specification must be given for every outlet");
+        }
+        let total_flow = flows.iter().fold(F1::cst(0.0), |total, flow| total + *flow);
+        let total_mass = flows
+            .iter()
+            .zip(molar_masses)
+            .fold(F1::cst(0.0), |total, (flow, mass)| total + *flow * *mass);
+        let share = |specified: f64, total: F1| {
+            if total.value() > 0.0 {
+                F1::cst(specified) / total
+            } else {
+                F1::cst(0.0)
+            }
+        };
+
+        let mut remaining = None;
+        let mut fractions = Vec::with_capacity(self.specs.len());
+        for (outlet, spec) in self.specs.iter().enumerate() {
+            fractions.push(match spec {
+                SplitSpec::Fraction(fraction) => {
+                    if !(0.0..=1.0).contains(fraction) {
+                        return Err("split fractions must be between zero and one");
+                    }
+                    F1::cst(*fraction)
+                }
+                SplitSpec::MoleFlow(flow) => share(flow.get::<katal>(), total_flow),
+                SplitSpec::MassFlow(flow) => share(flow.get::<kilogram_per_second>(), total_mass),
+                SplitSpec::Remaining => {
+                    if remaining.replace(outlet).is_some() {
+                        return Err("only one outlet may take the remaining flow");
+                    }
+                    F1::cst(0.0)
+                }
+            });
+        }
+        let total = fractions
+            .iter()
+            .fold(F1::cst(0.0), |total, fraction| total + *fraction);
+        match remaining {
+            Some(outlet) => fractions[outlet] = F1::cst(1.0) - total,
+            None if (total.value() - 1.0).abs() > SPLIT_TOLERANCE => {
+                return Err("splits must add up to the feed when no outlet takes the remainder");
+            }
+            None => {}
+        }
+        if fractions
+            .iter()
+            .any(|fraction| fraction.value() < -SPLIT_TOLERANCE)
+        {
+            return Err("outlet specifications exceed the feed");
+        }
+        Ok(fractions)
+    }
+}
+
+impl Block for Splitter {
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.input.is_some() {
+            return Err("splitter already has an inlet stream");
+        }
+        self.input = Some(stream);
+        Ok(())
+    }
+
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.input {
+            Some(input) if Arc::ptr_eq(input, stream) => {
+                self.input = None;
+                Ok(())
+            }
+            _ => Err("stream is not the inlet of the splitter"),
+        }
+    }
+
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        self.outputs.push(stream);
+        Ok(())
+    }
+
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        let count = self.outputs.len();
+        self.outputs.retain(|output| !Arc::ptr_eq(output, stream));
+        if self.outputs.len() == count {
+            return Err("stream is not an outlet of the splitter");
+        }
+        Ok(())
+    }
+
+    fn set_thermo_model(
+        &mut self,
+        model: Option<Arc<dyn PhaseEquilibrium>>,
+    ) -> Result<(), &'static str> {
+        self.thermo_model = model;
+        Ok(())
+    }
+
+    fn package_models(&mut self) -> Option<&mut PackageModels> {
+        Some(&mut self.package_models)
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let input = self.input.as_ref().ok_or("splitter has no inlet stream")?;
+        let feed = read_stream(input)?.ok_or("splitter inlet carries no material")?;
+        let flows: Vec<F1> = feed
+            .molar_flows
+            .iter()
+            .map(|flow| F1::cst(flow.get::<katal>()))
+            .collect();
+        let fractions = self.fractions(&flows, &self.molar_masses(&feed)?)?;
+        for (output, fraction) in self.outputs.iter().zip(fractions) {
+            let fraction = fraction.value().max(0.0);
+            let mut thermo = feed.clone();
+            for flow in thermo.molar_flows.iter_mut() {
+                *flow *= fraction;
+            }
+            write_stream(output, thermo)?;
+        }
+        Ok(())
+    }
+
+    /// Component flows of each outlet, with the temperature and pressure of
+    /// every outlet equal to those of the feed.
+    fn residuals(&self, variables: &EquationVariables) -> Result<Vec<F1>, &'static str> {
+        let input = self.input.as_ref().ok_or("splitter has no inlet stream")?;
+        let feed = read_stream(input)?.ok_or("splitter inlet carries no material")?;
+        let inlet_variables = variables
+            .get(input)
+            .ok_or("splitter inlet is not in the system")?;
+        let n = feed.components.len();
+        let fractions = self.fractions(&inlet_variables[..n], &self.molar_masses(&feed)?)?;
+
+        let mut residuals = Vec::new();
+        for (output, fraction) in self.outputs.iter().zip(fractions) {
+            let outlet_variables = variables
+                .get(output)
+                .ok_or("splitter outlet is not in the system")?;
+            if outlet_variables.len() != n + 2 {
+                return Err("splitter outlet does not match the feed");
+            }
+            for j in 0..n {
+                residuals.push(outlet_variables[j] - inlet_variables[j] * fraction);
+            }
+            residuals.push(outlet_variables[n] - inlet_variables[n]);
+            residuals.push(outlet_variables[n + 1] - inlet_variables[n + 1]);
+        }
+        Ok(residuals)
+    }
+
+    fn has_open_inlet(&self) -> bool {
+        self.input.is_none()
+    }
+
+    fn open_outlets(&self) -> usize {
+        usize::from(self.outputs.is_empty())
+    }
+}
+
+impl Default for Splitter {
+    fn default() -> Self {
+        Splitter::new()
+    }
+}
+
+/// The pair of conditions that fix the state of a flash drum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlashSpec {
+    /// Temperature and pressure of the drum
+    TemperaturePressure(ThermodynamicTemperature, Pressure),
+    /// Pressure of the drum and heat added to it
+    PressureDuty(Pressure, Power),
+    /// Temperature of the drum and heat added to it
+    TemperatureDuty(ThermodynamicTemperature, Power),
+    /// Pressure of the drum and molar fraction of the feed leaving as vapor
+    PressureVaporFraction(Pressure, Ratio),
+    /// Temperature of the drum and molar fraction of the feed leaving as vapor
+    TemperatureVaporFraction(ThermodynamicTemperature, Ratio),
+}
+
+/// Bounds on the natural logarithm of the pressure in Pa, used when a flash
+/// has to search for the drum pressure.
+const LOG_PRESSURE_BOUNDS: (f64, f64) = (0.0, 23.0);
+
+/// Bounds on the temperature in K, used when a flash has to search for the
+/// drum temperature.
+const TEMPERATURE_BOUNDS: (f64, f64) = (1.0, 10_000.0);
+
+/// Builds the outlet stream carrying one phase of a flashed feed. `amount`
+/// is the molar fraction of the feed in the phase and `composition` its mole
+/// fractions in the component order of the model.
+fn phase_outlet(
+    feed: &StreamThermoState,
+    model: &dyn PhaseEquilibrium,
+    (temperature, pressure): (ThermodynamicTemperature, Pressure),
+    (phase, amount): (Phase, f64),
+    composition: &[f64],
+    enthalpy: MolarEnergy,
+) -> Result<StreamThermoState, &'static str> {
+    let flow = feed.total_molar_flow();
+    let flows = feed
+        .components
+        .iter()
+        .map(|name| {
+            let index = model
+                .components()
+                .iter()
+                .position(|component| component == name)
+                .ok_or("feed component is not in the thermodynamic model")?;
+            Ok(flow * (amount * composition[index]).max(0.0))
+        })
+        .collect::<Result<Vec<_>, &'static str>>()?;
+    let mut thermo =
+        StreamThermoState::from_molar_flows(feed.components.clone(), flows, temperature, pressure)?;
+    thermo.enthalpy = Some(enthalpy);
+    thermo.vapor_fraction = Some(Ratio::new::<ratio>(match phase {
+        Phase::Vapor => 1.0,
+        Phase::Liquid => 0.0,
+    }));
+    Ok(thermo)
+}
+
+/// Ratio of the mole fraction of each component in one phase to its mole
+/// fraction in another. Components absent from the second phase are left out.
+fn distribution_ratios(
+    components: &[String],
+    phase: &[f64],
+    other: &[f64],
+) -> BTreeMap<String, f64> {
+    components
+        .iter()
+        .zip(phase.iter().zip(other))
+        .filter(|(_, (_, x))| **x > 0.0)
+        .map(|(name, (y, x))| (name.clone(), y / x))
+        .collect()
+}
+
+/// Flash outlet variables in the layout of
+/// [`crate::stream::Stream::variables`], calculated from the variables of
+/// the feed. `flash` splits the feed into its outlets.
+fn flashed_variables(
+    components: &[String],
+    feed_variables: &[f64],
+    flash: impl Fn(&StreamThermoState) -> Result<Vec<StreamThermoState>, &'static str>,
+) -> Result<Vec<f64>, &'static str> {
+    let n = components.len();
+    let flows = feed_variables[..n]
+        .iter()
+        .map(|flow| MolarFlowRate::new::<katal>(flow.max(0.0)))
+        .collect();
+    let feed = StreamThermoState::from_molar_flows(
+        components.to_vec(),
+        flows,
+        ThermodynamicTemperature::new::<kelvin>(feed_variables[n]),
+        Pressure::new::<pascal>(feed_variables[n + 1]),
+    )?;
+    let mut variables = Vec::new();
+    for outlet in flash(&feed)? {
+        variables.extend(outlet.molar_flows.iter().map(|flow| flow.get::<katal>()));
+        variables.push(outlet.temperature.get::<kelvin>());
+        variables.push(outlet.pressure.get::<pascal>());
+    }
+    Ok(variables)
+}
+
+/// Residuals of a flash block: each outlet variable equals the value found
+/// by flashing the feed described by the inlet variables.
+fn flash_residuals(
+    input: &StreamReference,
+    outputs: &[&StreamReference],
+    variables: &EquationVariables,
+    flash: impl Fn(&StreamThermoState) -> Result<Vec<StreamThermoState>, &'static str>,
+) -> Result<Vec<F1>, &'static str> {
+    let feed = read_stream(input)?.ok_or("flash inlet carries no material")?;
+    let inlet_variables = variables
+        .get(input)
+        .ok_or("flash inlet is not in the system")?;
+    let mut outlet_variables = Vec::new();
+    for output in outputs {
+        let values = variables
+            .get(output)
+            .ok_or("flash outlet is not in the system")?;
+        if values.len() != feed.components.len() + 2 {
+            return Err("flash outlet does not match the feed");
+        }
+        outlet_variables.extend_from_slice(values);
+    }
+    let calculated = equation_oriented::lift_all(
+        |x| flashed_variables(&feed.components, x, &flash),
+        inlet_variables,
+    )?;
+    if calculated.len() != outlet_variables.len() {
+        return Err("flash outlets do not match the flashed phases");
+    }
+    Ok(outlet_variables
+        .iter()
+        .zip(calculated)
+        .map(|(variable, calculated)| *variable - calculated)
+        .collect())
+}
+
+/// Molar enthalpy of the outlet of a drum given the heat added to it.
+fn target_enthalpy(
+    feed: &StreamThermoState,
+    energy_in: Power,
+    duty: Power,
+) -> Result<MolarEnergy, &'static str> {
+    let flow = feed.total_molar_flow();
+    if flow.get::<katal>() <= 0.0 {
+        return Err("duty specifications require a feed with flow");
+    }
+    Ok((energy_in + duty) / flow)
+}
+
+/// Solves a flash specification by searching for the temperature or pressure
+/// that meets it. `flash` brings the feed to equilibrium at a given
+/// temperature and pressure, and `state` reads the molar enthalpy and vapor
+/// fraction of its result. Returns the result meeting the specification and
+/// the heat added to reach it from `energy_in`.
+fn solve_flash_spec<R>(
+    spec: FlashSpec,
+    feed: &StreamThermoState,
+    energy_in: Power,
+    flash: impl Fn(ThermodynamicTemperature, Pressure) -> Result<R, &'static str>,
+    state: impl Fn(&R) -> (MolarEnergy, f64),
+) -> Result<(R, Power), &'static str> {
+    let temperature_of = |t: f64| ThermodynamicTemperature::new::<kelvin>(t);
+    let pressure_of = |log_p: f64| Pressure::new::<pascal>(log_p.exp());
+    let enthalpy_of = |result: &R| state(result).0.get::<joule_per_mole>();
+    let vapor_target = |fraction: Ratio| -> Result<f64, &'static str> {
+        let fraction = fraction.get::<ratio>();
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err("vapor fraction must be between zero and one");
+        }
+        // Bubble and dew points sit at the edge of the two-phase region,
+        // so search for a point just inside it.
+        Ok(fraction.clamp(1e-9, 1.0 - 1e-9))
+    };
+    let search_temperature = |pressure: Pressure, f: &dyn Fn(&R) -> f64| {
+        let t = find_root(
+            |t| Ok(f(&flash(temperature_of(t), pressure)?)),
+            feed.temperature.get::<kelvin>(),
+            10.0,
+            TEMPERATURE_BOUNDS,
+        )?;
+        flash(temperature_of(t), pressure)
+    };
+    let search_pressure = |temperature: ThermodynamicTemperature, f: &dyn Fn(&R) -> f64| {
+        let log_p = find_root(
+            |log_p| Ok(f(&flash(temperature, pressure_of(log_p))?)),
+            feed.pressure.get::<pascal>().ln(),
+            0.1,
+            LOG_PRESSURE_BOUNDS,
+        )?;
+        flash(temperature, pressure_of(log_p))
+    };
+
+    let result = match spec {
+        FlashSpec::TemperaturePressure(temperature, pressure) => flash(temperature, pressure)?,
+        FlashSpec::PressureDuty(pressure, duty) => {
+            let target = target_enthalpy(feed, energy_in, duty)?.get::<joule_per_mole>();
+            search_temperature(pressure, &|result| enthalpy_of(result) - target)?
+        }
+        FlashSpec::TemperatureDuty(temperature, duty) => {
+            let target = target_enthalpy(feed, energy_in, duty)?.get::<joule_per_mole>();
+            search_pressure(temperature, &|result| enthalpy_of(result) - target)?
+        }
+        FlashSpec::PressureVaporFraction(pressure, fraction) => {
+            let target = vapor_target(fraction)?;
+            search_temperature(pressure, &|result| state(result).1 - target)?
+        }
+        FlashSpec::TemperatureVaporFraction(temperature, fraction) => {
+            let target = vapor_target(fraction)?;
+            search_pressure(temperature, &|result| state(result).1 - target)?
+        }
+    };
+    let energy_out: Power = feed.total_molar_flow() * state(&result).0;
+    Ok((result, energy_out - energy_in))
+}
+
+/// # Flash
+///
+/// A two-phase flash drum. The feed is brought to equilibrium at the
+/// conditions given by the [`FlashSpec`] and leaves as a vapor stream and a
+/// liquid stream. The first outlet connected is the vapor outlet and the
+/// second is the liquid outlet.
+pub struct Flash {
+    /// Inlet stream for the flash drum
+    pub input: Option<StreamReference>,
+    /// Vapor outlet stream
+    pub vapor_output: Option<StreamReference>,
+    /// Liquid outlet stream
+    pub liquid_output: Option<StreamReference>,
+    /// Conditions of the drum
+    pub spec: Option<FlashSpec>,
+    /// Thermodynamic model used for the phase equilibrium
+    pub thermo_model: Option<Arc<dyn PhaseEquilibrium>>,
+    /// Models of the property packages, used when no thermodynamic model
+    /// is given
+    pub package_models: PackageModels,
+    /// Heat added to the drum during the last execution
+    pub duty: Option<Power>,
+    /// Ratio of the vapor to the liquid mole fraction of each component found
+    /// by the last execution. Components absent from the liquid are left out.
+    pub k_values: BTreeMap<String, f64>,
+}
+
+impl Flash {
+    /// Create a new flash drum without a specification.
+    pub fn new() -> Flash {
+        Flash {
+            input: None,
+            vapor_output: None,
+            liquid_output: None,
+            spec: None,
+            thermo_model: None,
+            package_models: PackageModels::default(),
+            duty: None,
+            k_values: BTreeMap::new(),
+        }
+    }
+
+    /// Create a new flash drum with the given specification and model.
+    pub fn with_spec(spec: FlashSpec, thermo_model: Arc<dyn PhaseEquilibrium>) -> Flash {
+        Flash {
+            spec: Some(spec),
+            thermo_model: Some(thermo_model),
+            ..Flash::new()
+        }
+    }
+
+    fn model(&self) -> Result<&dyn PhaseEquilibrium, &'static str> {
+        self.package_models
+            .select(&self.thermo_model)
+            .map(Arc::as_ref)
+            .ok_or("flash requires a thermodynamic model")
+    }
+
+    /// Brings the feed to equilibrium at the specified conditions. Returns the
+    /// flash result along with the heat added to the drum.
+    fn equilibrate(&self, feed: &StreamThermoState) -> Result<(FlashResult, Power), &'static str> {
+        let model = self.model()?;
+        let spec = self.spec.ok_or("flash has no specification")?;
+        let z = model.composition(feed)?;
+        let energy_in: Power = feed.total_molar_flow() * stream_enthalpy(feed, model)?;
+        if let FlashSpec::PressureDuty(pressure, duty) = spec {
+            let enthalpy = target_enthalpy(feed, energy_in, duty)?;
+            let result = model.flash_ph(pressure, enthalpy, &z, feed.temperature)?;
+            return Ok((result, duty));
+        }
+        solve_flash_spec(
+            spec,
+            feed,
+            energy_in,
+            |temperature, pressure| model.flash_tp(temperature, pressure, &z),
+            |result| (result.enthalpy, result.vapor_fraction.get::<ratio>()),
+        )
+    }
+
+    /// Splits the feed into its vapor and liquid outlets.
+    fn outlets(
+        &self,
+        feed: &StreamThermoState,
+        result: &FlashResult,
+    ) -> Result<(StreamThermoState, StreamThermoState), &'static str> {
+        let model = self.model()?;
+        let (temperature, pressure) = (result.temperature, result.pressure);
+        let vapor_fraction = result.vapor_fraction.get::<ratio>();
+        let vapor = phase_outlet(
+            feed,
+            model,
+            (temperature, pressure),
+            (Phase::Vapor, vapor_fraction),
+            &result.vapor_composition,
+            result.vapor_enthalpy,
+        )?;
+        let liquid = phase_outlet(
+            feed,
+            model,
+            (temperature, pressure),
+            (Phase::Liquid, 1.0 - vapor_fraction),
+            &result.liquid_composition,
+            result.liquid_enthalpy,
+        )?;
+        Ok((vapor, liquid))
+    }
+}
+
+impl Block for Flash {
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.input.is_some() {
+            return Err("flash already has an inlet stream");
+        }
+        self.input = Some(stream);
+        Ok(())
+    }
+
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.input {
+            Some(input) if Arc::ptr_eq(input, stream) => {
+                self.input = None;
+                Ok(())
+            }
+            _ => Err("stream is not the inlet of the flash"),
+        }
+    }
+
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.vapor_output.is_none() {
+            self.vapor_output = Some(stream);
+        } else if self.liquid_output.is_none() {
+            self.liquid_output = Some(stream);
+        } else {
+            return Err("flash already has vapor and liquid outlets");
+        }
+        Ok(())
+    }
+
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        for output in [&mut self.vapor_output, &mut self.liquid_output] {
+            if output
+                .as_ref()
+                .is_some_and(|output| Arc::ptr_eq(output, stream))
+            {
+                *output = None;
+                return Ok(());
+            }
+        }
+        Err("stream is not an outlet of the flash")
+    }
+
+    fn set_thermo_model(
+        &mut self,
+        model: Option<Arc<dyn PhaseEquilibrium>>,
+    ) -> Result<(), &'static str> {
+        self.thermo_model = model;
+        Ok(())
+    }
+
+    fn package_models(&mut self) -> Option<&mut PackageModels> {
+        Some(&mut self.package_models)
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let input = self.input.as_ref().ok_or("flash has no inlet stream")?;
+        let vapor_output = self
+            .vapor_output
+            .as_ref()
+            .ok_or("flash has no vapor outlet")?;
+        let liquid_output = self
+            .liquid_output
+            .as_ref()
+            .ok_or("flash has no liquid outlet")?;
+        let feed = read_stream(input)?.ok_or("flash inlet carries no material")?;
+        let (result, duty) = self.equilibrate(&feed)?;
+        let (vapor, liquid) = self.outlets(&feed, &result)?;
+        let (vapor_output, liquid_output) = (vapor_output.clone(), liquid_output.clone());
+
+        let model = self.model()?;
+        self.k_values = distribution_ratios(
+            model.components(),
+            &result.vapor_composition,
+            &result.liquid_composition,
+        );
+        self.duty = Some(duty);
+        write_stream(&vapor_output, vapor)?;
+        write_stream(&liquid_output, liquid)
+    }
+
+    /// Each outlet variable equals the value found by flashing the feed
+    /// described by the inlet variables.
+    fn residuals(&self, variables: &EquationVariables) -> Result<Vec<F1>, &'static str> {
+        let input = self.input.as_ref().ok_or("flash has no inlet stream")?;
+        let vapor_output = self
+            .vapor_output
+            .as_ref()
+            .ok_or("flash has no vapor outlet")?;
+        let liquid_output = self
+            .liquid_output
+            .as_ref()
+            .ok_or("flash has no liquid outlet")?;
+        flash_residuals(input, &[vapor_output, liquid_output], variables, |feed| {
+            let (result, _) = self.equilibrate(feed)?;
+            let (vapor, liquid) = self.outlets(feed, &result)?;
+            Ok(vec![vapor, liquid])
+        })
+    }
+
+    fn has_open_inlet(&self) -> bool {
+        self.input.is_none()
+    }
+
+    fn open_outlets(&self) -> usize {
+        [&self.vapor_output, &self.liquid_output]
+            .iter()
+            .filter(|output| output.is_none())
+            .count()
+    }
+}
+
+impl Default for Flash {
+    fn default() -> Self {
+        Flash::new()
+    }
+}
+
+/// # Flash3
+///
+/// A three-phase flash drum for systems that can split into two liquid
+/// phases, such as hydrocarbons and water. The number of phases present is
+/// decided by tangent plane distance stability testing, so the model must
+/// provide fugacity coefficients for each phase. The feed leaves as a vapor
+/// stream and two liquid streams, connected in that order. Phases that are
+/// not present leave with no flow.
+pub struct Flash3 {
+    /// Inlet stream for the flash drum
+    pub input: Option<StreamReference>,
+    /// Vapor outlet stream
+    pub vapor_output: Option<StreamReference>,
+    /// First liquid outlet stream
+    pub liquid1_output: Option<StreamReference>,
+    /// Second liquid outlet stream
+    pub liquid2_output: Option<StreamReference>,
+    /// Conditions of the drum
+    pub spec: Option<FlashSpec>,
+    /// Thermodynamic model used for the phase equilibrium
+    pub thermo_model: Option<Arc<dyn PhaseEquilibrium>>,
+    /// Models of the property packages, used when no thermodynamic model
+    /// is given
+    pub package_models: PackageModels,
+    /// Component used to tell the liquids apart: the second liquid is the
+    /// one richer in it. Without a key component, the first liquid is the
+    /// larger of the two.
+    pub key_component: Option<String>,
+    /// Heat added to the drum during the last execution
+    pub duty: Option<Power>,
+    /// Number of phases found by the last execution
+    pub phase_count: usize,
+    /// Ratio of the vapor to the first liquid mole fraction of each component
+    /// found by the last execution
+    pub k_values: BTreeMap<String, f64>,
+    /// Ratio of the second liquid to the first liquid mole fraction of each
+    /// component found by the last execution
+    pub liquid_k_values: BTreeMap<String, f64>,
+}
+
+/// A phase of a three-phase flash, sorted into its outlet.
+#[derive(Clone)]
+struct OutletPhase {
+    fraction: f64,
+    composition: Vec<f64>,
+    enthalpy: MolarEnergy,
+}
+
+impl Flash3 {
+    /// Create a new three-phase flash drum without a specification.
+    pub fn new() -> Flash3 {
+        Flash3 {
+            input: None,
+            vapor_output: None,
+            liquid1_output: None,
+            liquid2_output: None,
+            spec: None,
+            thermo_model: None,
+            package_models: PackageModels::default(),
+            key_component: None,
+            duty: None,
+            phase_count: 0,
+            k_values: BTreeMap::new(),
+            liquid_k_values: BTreeMap::new(),
+        }
+    }
+
+    /// Create a new three-phase flash drum with the given specification and
+    /// model.
+    pub fn with_spec(spec: FlashSpec, thermo_model: Arc<dyn PhaseEquilibrium>) -> Flash3 {
+        Flash3 {
+            spec: Some(spec),
+            thermo_model: Some(thermo_model),
+            ..Flash3::new()
+        }
+    }
+
+    fn model(&self) -> Result<&dyn PhaseEquilibrium, &'static str> {
+        self.package_models
+            .select(&self.thermo_model)
+            .map(Arc::as_ref)
+            .ok_or("flash requires a thermodynamic model")
+    }
+
+    fn outputs(&self) -> Result<[&StreamReference; 3], &'static str> {
+        match (
+            &self.vapor_output,
+            &self.liquid1_output,
+            &self.liquid2_output,
+        ) {
+            (Some(vapor), Some(liquid1), Some(liquid2)) => Ok([vapor, liquid1, liquid2]),
+            _ => Err("flash outlets are not connected"),
+        }
+    }
+
+    /// Brings the feed to equilibrium at the specified conditions. Returns the
+    /// flash result along with the heat added to the drum.
+    fn equilibrate(
+        &self,
+        feed: &StreamThermoState,
+    ) -> Result<(MultiphaseResult, Power), &'static str> {
+        let model = self.model()?;
+        let spec = self.spec.ok_or("flash has no specification")?;
+        let z = model.composition(feed)?;
+        let flash = |temperature, pressure| {
+            phase_stability::flash_multiphase(model, temperature, pressure, &z, 3)
+        };
+        let feed_enthalpy = match feed.enthalpy {
+            Some(enthalpy) => enthalpy,
+            None => flash(feed.temperature, feed.pressure)?.enthalpy(),
+        };
+        let energy_in: Power = feed.total_molar_flow() * feed_enthalpy;
+        solve_flash_spec(spec, feed, energy_in, flash, |result| {
+            (result.enthalpy(), result.vapor_fraction())
+        })
+    }
+
+    /// Sorts the phases of a flash result into the vapor, first liquid and
+    /// second liquid outlets. Phases that are not present are given the feed
+    /// composition and no amount.
+    fn sort_phases(
+        &self,
+        z: &[f64],
+        result: &MultiphaseResult,
+    ) -> Result<[OutletPhase; 3], &'static str> {
+        let model = self.model()?;
+        let (temperature, pressure) = (result.temperature, result.pressure);
+        let absent = |phase: Phase| -> Result<OutletPhase, &'static str> {
+            Ok(OutletPhase {
+                fraction: 0.0,
+                composition: z.to_vec(),
+                enthalpy: model.phase_enthalpy(temperature, pressure, z, phase)?,
+            })
+        };
+        let present = |split: &PhaseSplit| OutletPhase {
+            fraction: split.fraction,
+            composition: split.composition.clone(),
+            enthalpy: split.enthalpy,
+        };
+
+        let vapor = match result
+            .phases
+            .iter()
+            .find(|split| split.phase == Phase::Vapor)
+        {
+            Some(split) => present(split),
+            None => absent(Phase::Vapor)?,
+        };
+        let key = match &self.key_component {
+            Some(key) => Some(
+                model
+                    .components()
+                    .iter()
+                    .position(|component| component == key)
+                    .ok_or("key component is not in the thermodynamic model")?,
+            ),
+            None => None,
+        };
+        let mut liquids: Vec<&PhaseSplit> = result
+            .phases
+            .iter()
+            .filter(|split| split.phase == Phase::Liquid)
+            .collect();
+        liquids.sort_by(|a, b| match key {
+            Some(key) => a.composition[key].total_cmp(&b.composition[key]),
+            None => b.fraction.total_cmp(&a.fraction),
+        });
+        let liquid1 = match liquids.first() {
+            Some(split) => present(split),
+            None => absent(Phase::Liquid)?,
+        };
+        let liquid2 = match liquids.get(1) {
+            Some(split) => present(split),
+            None => OutletPhase {
+                fraction: 0.0,
+                ..liquid1.clone()
+            },
+        };
+        Ok([vapor, liquid1, liquid2])
+    }
+
+    /// Splits the feed into its vapor and liquid outlets.
+    fn outlets(
+        &self,
+        feed: &StreamThermoState,
+        phases: &[OutletPhase; 3],
+        result: &MultiphaseResult,
+    ) -> Result<Vec<StreamThermoState>, &'static str> {
+        let model = self.model()?;
+        phases
+            .iter()
+            .zip([Phase::Vapor, Phase::Liquid, Phase::Liquid])
+            .map(|(split, phase)| {
+                phase_outlet(
+                    feed,
+                    model,
+                    (result.temperature, result.pressure),
+                    (phase, split.fraction),
+                    &split.composition,
+                    split.enthalpy,
+                )
+            })
+            .collect()
+    }
+
+    /// Flashes the feed and splits it into its outlets.
+    fn flash(
+        &self,
+        feed: &StreamThermoState,
+    ) -> Result<(MultiphaseResult, [OutletPhase; 3], Power), &'static str> {
+        let (result, duty) = self.equilibrate(feed)?;
+        let phases = self.sort_phases(&self.model()?.composition(feed)?, &result)?;
+        Ok((result, phases, duty))
+    }
+}
+
+impl Block for Flash3 {
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.input.is_some() {
+            return Err("flash already has an inlet stream");
+        }
+        self.input = Some(stream);
+        Ok(())
+    }
+
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.input {
+            Some(input) if Arc::ptr_eq(input, stream) => {
+                self.input = None;
+                Ok(())
+            }
+            _ => Err("stream is not the inlet of the flash"),
+        }
+    }
+
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        let output = [
+            &mut self.vapor_output,
+            &mut self.liquid1_output,
+            &mut self.liquid2_output,
+        ]
+        .into_iter()
+        .find(|output| output.is_none())
+        .ok_or("flash already has vapor and liquid outlets")?;
+        *output = Some(stream);
         Ok(())
     }
 
-    fn disconnect_input(&mut self, _stream: &mut Stream) -> Result<(), &'static str> {
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        for output in [
+            &mut self.vapor_output,
+            &mut self.liquid1_output,
+            &mut self.liquid2_output,
+        ] {
+            if output
+                .as_ref()
+                .is_some_and(|output| Arc::ptr_eq(output, stream))
+            {
+                *output = None;
+                return Ok(());
+            }
+        }
+        Err("stream is not an outlet of the flash")
+    }
+
+    fn set_thermo_model(
+        &mut self,
+        model: Option<Arc<dyn PhaseEquilibrium>>,
+    ) -> Result<(), &'static str> {
+        self.thermo_model = model;
         Ok(())
     }
 
-    fn connect_output(&mut self, _stream: &mut Stream) -> Result<(), &'static str> {
+    fn package_models(&mut self) -> Option<&mut PackageModels> {
+        Some(&mut self.package_models)
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let input = self.input.as_ref().ok_or("flash has no inlet stream")?;
+        let outputs: Vec<StreamReference> = self.outputs()?.into_iter().cloned().collect();
+        let feed = read_stream(input)?.ok_or("flash inlet carries no material")?;
+        let (result, phases, duty) = self.flash(&feed)?;
+        let outlets = self.outlets(&feed, &phases, &result)?;
+
+        let components = self.model()?.components();
+        let k_values =
+            distribution_ratios(components, &phases[0].composition, &phases[1].composition);
+        let liquid_k_values =
+            distribution_ratios(components, &phases[2].composition, &phases[1].composition);
+        self.k_values = k_values;
+        self.liquid_k_values = liquid_k_values;
+        self.phase_count = result.phases.len();
+        self.duty = Some(duty);
+        for (output, outlet) in outputs.iter().zip(outlets) {
+            write_stream(output, outlet)?;
+        }
+        Ok(())
+    }
+
+    /// Each outlet variable equals the value found by flashing the feed
+    /// described by the inlet variables.
+    fn residuals(&self, variables: &EquationVariables) -> Result<Vec<F1>, &'static str> {
+        let input = self.input.as_ref().ok_or("flash has no inlet stream")?;
+        flash_residuals(input, &self.outputs()?, variables, |feed| {
+            let (result, phases, _) = self.flash(feed)?;
+            self.outlets(feed, &phases, &result)
+        })
+    }
+
+    fn has_open_inlet(&self) -> bool {
+        self.input.is_none()
+    }
+
+    fn open_outlets(&self) -> usize {
+        [
+            &self.vapor_output,
+            &self.liquid1_output,
+            &self.liquid2_output,
+        ]
+        .iter()
+        .filter(|output| output.is_none())
+        .count()
+    }
+}
+
+impl Default for Flash3 {
+    fn default() -> Self {
+        Flash3::new()
+    }
+}
+
+/// # Source
+///
+/// A block that feeds material into the simulation. The feed conditions and
+/// composition are specified by the user and copied to the single outlet
+/// stream.
+pub struct Source {
+    /// Material fed to the simulation
+    pub feed: Option<StreamThermoState>,
+    /// Outlet stream for the source block
+    pub output: Option<StreamReference>,
+}
+
+impl Source {
+    /// Create a new source block without a feed specification.
+    pub fn new() -> Source {
+        Source {
+            feed: None,
+            output: None,
+        }
+    }
+
+    /// Create a new source block feeding the given material.
+    pub fn with_feed(feed: StreamThermoState) -> Source {
+        Source {
+            feed: Some(feed),
+            output: None,
+        }
+    }
+}
+
+impl Block for Source {
+    fn connect_input(&mut self, _stream: StreamReference) -> Result<(), &'static str> {
+        Err("a source has no inlets")
+    }
+
+    fn disconnect_input(&mut self, _stream: &StreamReference) -> Result<(), &'static str> {
+        Err("a source has no inlets")
+    }
+
+    fn connect_output(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.output.is_some() {
+            return Err("source already has an outlet stream");
+        }
+        self.output = Some(stream);
         Ok(())
     }
 
-    fn disconnect_output(&mut self, _stream: &mut Stream) -> Result<(), &'static str> {
+    fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.output {
+            Some(output) if Arc::ptr_eq(output, stream) => {
+                self.output = None;
+                Ok(())
+            }
+            _ => Err("stream is not the outlet of the source"),
+        }
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let output = self.output.as_ref().ok_or("source has no outlet stream")?;
+        let feed = self.feed.clone().ok_or("source has no feed specified")?;
+        write_stream(output, feed)
+    }
+
+    /// Fixes every outlet variable to the feed specification.
+    fn residuals(&self, variables: &EquationVariables) -> Result<Vec<F1>, &'static str> {
+        let output = self.output.as_ref().ok_or("source has no outlet stream")?;
+        let feed = self.feed.as_ref().ok_or("source has no feed specified")?;
+        let outlet_variables = variables
+            .get(output)
+            .ok_or("source outlet is not in the system")?;
+        let specified = feed
+            .molar_flows
+            .iter()
+            .map(|flow| flow.get::<katal>())
+            .chain([
+                feed.temperature.get::<kelvin>(),
+                feed.pressure.get::<pascal>(),
+            ]);
+        if outlet_variables.len() != feed.molar_flows.len() + 2 {
+            return Err("source outlet does not match the feed");
+        }
+        Ok(outlet_variables
+            .iter()
+            .zip(specified)
+            .map(|(variable, value)| *variable - value)
+            .collect())
+    }
+
+    fn open_outlets(&self) -> usize {
+        usize::from(self.output.is_none())
+    }
+
+    fn set_feed(&mut self, feed: StreamThermoState) -> Result<(), &'static str> {
+        self.feed = Some(feed);
         Ok(())
     }
 }
 
+impl Default for Source {
+    fn default() -> Self {
+        Source::new()
+    }
+}
+
+/// # Sink
+///
+/// A block that collects material leaving the simulation. The material of its
+/// single inlet stream is kept as the product after each execution.
+pub struct Sink {
+    /// Inlet stream for the sink block
+    pub input: Option<StreamReference>,
+    /// Material collected by the sink during the last execution
+    pub product: Option<StreamThermoState>,
+}
+
+impl Sink {
+    /// Create a new sink block.
+    pub fn new() -> Sink {
+        Sink {
+            input: None,
+            product: None,
+        }
+    }
+}
+
+impl Block for Sink {
+    fn connect_input(&mut self, stream: StreamReference) -> Result<(), &'static str> {
+        if self.input.is_some() {
+            return Err("sink already has an inlet stream");
+        }
+        self.input = Some(stream);
+        Ok(())
+    }
+
+    fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &'static str> {
+        match &self.input {
+            Some(input) if Arc::ptr_eq(input, stream) => {
+                self.input = None;
+                Ok(())
+            }
+            _ => Err("stream is not the inlet of the sink"),
+        }
+    }
+
+    fn connect_output(&mut self, _stream: StreamReference) -> Result<(), &'static str> {
+        Err("a sink has no outlets")
+    }
+
+    fn disconnect_output(&mut self, _stream: &StreamReference) -> Result<(), &'static str> {
+        Err("a sink has no outlets")
+    }
+
+    fn execute(&mut self) -> Result<(), &'static str> {
+        let input = self.input.as_ref().ok_or("sink has no inlet stream")?;
+        self.product = read_stream(input)?;
+        Ok(())
+    }
+
+    fn has_open_inlet(&self) -> bool {
+        self.input.is_none()
+    }
+}
+
+impl Default for Sink {
+    fn default() -> Self {
+        Sink::new()
+    }
+}
+
+/// Allowed deviation of split fractions from adding up to one.
+const SPLIT_TOLERANCE: f64 = 1e-9;
+
 #[allow(dead_code)]
 /// Minimum error allowed for energy difference.
 /// TODO: Change this to a relative scale instead of an absolute scale.
@@ -230,78 +2009,721 @@ impl Clone for Box<dyn Block> {
 /// The following module holds all the unit test cases for the blocks module
 #[cfg(test)]
 mod block_tests {
-    // use crate::connector::Stream;
-
-    // use super::*;
-    // use uom::si::energy::kilojoule;
-    // use uom::si::f64::Energy;
-    // use uom::si::mass::pound;
-
-    // #[test]
-    // /// checks whether the mass balance check function was implemented properly
-    // fn test_mass_balance_check_steady_state_for_mixer() {
-    //     // here you will need to check that the mass into the mixer = mass out of mixer
-
-    //     let mixer_test_obj = Mixer {
-    //         block_id: String::from("Test Mixer"),
-    //         y_cord: 0,
-    //         inlet_streams: Vec::new(),
-    //         outlet_stream: None,
-    //     };
-    //     let mass_in = Mass::new::<pound>(100.0);
-    //     let mass_out = Mass::new::<pound>(95.0);
-    //     assert!(mixer_test_obj.mass_balance_check(mass_in, mass_out));
-    // }
-
-    // #[test]
-    // /// checks if the 'energy_balance_check' function was implemented properly
-    // fn test_energy_balance_check_steady_state_for_mixer() {
-    //     // energy into mixer = energy out of mixer
-    //     let mixer_test_obj = Mixer {
-    //         block_id: String::from("Test Mixer"),
-    //         x_cord: 0,
-    //         y_cord: 0,
-    //         inlet_streams: Vec::new(),
-    //         outlet_stream: None,
-    //     };
-    //     let energy_in = Energy::new::<kilojoule>(10.0);
-    //     let energy_out = Energy::new::<kilojoule>(95.0);
-    //     assert!(mixer_test_obj.energy_balance_check(energy_in, energy_out));
-    // }
-
-    // #[test]
-    // checking functionality of 'compute_total_outlet_mass_flow'
-    // fn test_compute_total_outlet_mass_flow() {
-    //     let in_streams_mass = vec![
-    //         Mconnector {
-    //             m_conn_id: String::from("Mass1"),
-    //             m_flow_total: 3.0,
-    //         },
-    //         Mconnector {
-    //             m_conn_id: String::from("Mass2"),
-    //             m_flow_total: 7.0,
-    //         },
-    //     ];
-    //     let mixer = Mixer::new(String::from("Mixer3"), 0, 0, in_streams_mass, vec![]);
-
-    //     assert_eq!(mixer.compute_total_outlet_mass_flow(), Some(10.0));
-    // }
-
-    // #[test]
-    // /// checking functionality of 'compute_outlet_energy_flows'
-    // fn test_compute_outlet_energy_flows() {
-    //     let in_streams_energy = vec![
-    //         Econnector {
-    //             e_conn_id: String::from("Energy1"),
-    //             energy_flow_total: 100.0,
-    //         },
-    //         Econnector {
-    //             e_conn_id: String::from("Energy2"),
-    //             energy_flow_total: 200.0,
-    //         },
-    //     ];
-    //     let mixer = Mixer::new(String::from("Mixer5"), 0, 0, vec![], in_streams_energy);
-
-    //     assert_eq!(mixer.compute_outlet_energy_flows(), Some(300.0));
-    // }
+    use super::*;
+    use crate::simulation::BlockReference;
+    use crate::stream::Stream;
+    use std::sync::RwLock;
+    use uom::si::energy::kilojoule;
+    use uom::si::f64::MolarMass;
+    use uom::si::mass::pound;
+    use uom::si::molar_mass::gram_per_mole;
+
+    /// An ideal gas with a constant heat capacity for each component, which
+    /// never condenses. Enthalpies are relative to 298.15 K.
+    struct ConstantHeatCapacity {
+        components: Vec<String>,
+        heat_capacities: Vec<f64>,
+    }
+
+    impl PhaseEquilibrium for ConstantHeatCapacity {
+        fn components(&self) -> &[String] {
+            &self.components
+        }
+
+        fn molar_masses(&self) -> Vec<MolarMass> {
+            vec![MolarMass::new::<gram_per_mole>(20.0); self.components.len()]
+        }
+
+        fn flash_tp(
+            &self,
+            temperature: ThermodynamicTemperature,
+            pressure: Pressure,
+            composition: &[f64],
+        ) -> Result<FlashResult, &'static str> {
+            let heat_capacity: f64 = composition
+                .iter()
+                .zip(&self.heat_capacities)
+                .map(|(x, cp)| x * cp)
+                .sum();
+            let enthalpy = MolarEnergy::new::<joule_per_mole>(
+                heat_capacity * (temperature.get::<kelvin>() - 298.15),
+            );
+            Ok(FlashResult {
+                temperature,
+                pressure,
+                vapor_fraction: Ratio::new::<ratio>(1.0),
+                liquid_composition: composition.to_vec(),
+                vapor_composition: composition.to_vec(),
+                enthalpy,
+                liquid_enthalpy: enthalpy,
+                vapor_enthalpy: enthalpy,
+            })
+        }
+    }
+
+    fn test_model() -> Arc<dyn PhaseEquilibrium> {
+        Arc::new(ConstantHeatCapacity {
+            components: vec![String::from("A"), String::from("B")],
+            heat_capacities: vec![30.0, 60.0],
+        })
+    }
+
+    /// An ideal two-phase mixture following Raoult's law, with vapor
+    /// pressures given by the Clausius-Clapeyron equation. Liquid enthalpies
+    /// use a constant heat capacity relative to 298.15 K, and the vapor adds
+    /// a constant heat of vaporization.
+    struct IdealSolution {
+        components: Vec<String>,
+        boiling_points: Vec<f64>,
+        heats_of_vaporization: Vec<f64>,
+        heat_capacities: Vec<f64>,
+    }
+
+    impl IdealSolution {
+        fn vapor_pressures(&self, temperature: f64) -> Vec<f64> {
+            self.boiling_points
+                .iter()
+                .zip(&self.heats_of_vaporization)
+                .map(|(tb, dh)| 101_325.0 * (dh / 8.314 * (1.0 / tb - 1.0 / temperature)).exp())
+                .collect()
+        }
+    }
+
+    impl PhaseEquilibrium for IdealSolution {
+        fn components(&self) -> &[String] {
+            &self.components
+        }
+
+        fn molar_masses(&self) -> Vec<MolarMass> {
+            vec![MolarMass::new::<gram_per_mole>(50.0); self.components.len()]
+        }
+
+        fn flash_tp(
+            &self,
+            temperature: ThermodynamicTemperature,
+            pressure: Pressure,
+            composition: &[f64],
+        ) -> Result<FlashResult, &'static str> {
+            let t = temperature.get::<kelvin>();
+            let k: Vec<f64> = self
+                .vapor_pressures(t)
+                .iter()
+                .map(|psat| psat / pressure.get::<pascal>())
+                .collect();
+            // Rachford-Rice, solved by bisection.
+            let rachford_rice = |v: f64| -> f64 {
+                composition
+                    .iter()
+                    .zip(&k)
+                    .map(|(z, k)| z * (k - 1.0) / (1.0 + v * (k - 1.0)))
+                    .sum()
+            };
+            let v = if rachford_rice(0.0) <= 0.0 {
+                0.0
+            } else if rachford_rice(1.0) >= 0.0 {
+                1.0
+            } else {
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..100 {
+                    let middle = 0.5 * (low + high);
+                    if rachford_rice(middle) > 0.0 {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                0.5 * (low + high)
+            };
+            let x: Vec<f64> = composition
+                .iter()
+                .zip(&k)
+                .map(|(z, k)| z / (1.0 + v * (k - 1.0)))
+                .collect();
+            let y: Vec<f64> = x.iter().zip(&k).map(|(x, k)| x * k).collect();
+            let phase_enthalpy = |fractions: &[f64], vapor: bool| {
+                fractions
+                    .iter()
+                    .zip(self.heat_capacities.iter().zip(&self.heats_of_vaporization))
+                    .map(|(x, (cp, dh))| x * (cp * (t - 298.15) + if vapor { *dh } else { 0.0 }))
+                    .sum::<f64>()
+            };
+            let (hl, hv) = (phase_enthalpy(&x, false), phase_enthalpy(&y, true));
+            Ok(FlashResult {
+                temperature,
+                pressure,
+                vapor_fraction: Ratio::new::<ratio>(v),
+                liquid_composition: x,
+                vapor_composition: y,
+                enthalpy: MolarEnergy::new::<joule_per_mole>((1.0 - v) * hl + v * hv),
+                liquid_enthalpy: MolarEnergy::new::<joule_per_mole>(hl),
+                vapor_enthalpy: MolarEnergy::new::<joule_per_mole>(hv),
+            })
+        }
+    }
+
+    fn two_phase_model() -> Arc<dyn PhaseEquilibrium> {
+        Arc::new(IdealSolution {
+            components: vec![String::from("A"), String::from("B")],
+            boiling_points: vec![330.0, 380.0],
+            heats_of_vaporization: vec![30_000.0, 35_000.0],
+            heat_capacities: vec![120.0, 150.0],
+        })
+    }
+
+    /// A regular solution liquid in equilibrium with an ideal gas. Strong
+    /// interactions between components can split the liquid in two. Vapor
+    /// pressures and enthalpies follow [`IdealSolution`].
+    struct RegularSolution {
+        ideal: IdealSolution,
+        interactions: Vec<Vec<f64>>,
+    }
+
+    impl PhaseEquilibrium for RegularSolution {
+        fn components(&self) -> &[String] {
+            &self.ideal.components
+        }
+
+        fn molar_masses(&self) -> Vec<MolarMass> {
+            self.ideal.molar_masses()
+        }
+
+        fn flash_tp(
+            &self,
+            temperature: ThermodynamicTemperature,
+            pressure: Pressure,
+            composition: &[f64],
+        ) -> Result<FlashResult, &'static str> {
+            phase_stability::flash_vapor_liquid(self, temperature, pressure, composition)
+        }
+
+        fn ln_fugacity_coefficients(
+            &self,
+            temperature: ThermodynamicTemperature,
+            pressure: Pressure,
+            composition: &[f64],
+            phase: Phase,
+        ) -> Result<Vec<f64>, &'static str> {
+            if phase == Phase::Vapor {
+                return Ok(vec![0.0; composition.len()]);
+            }
+            let excess: f64 = (0..composition.len())
+                .flat_map(|j| (0..composition.len()).map(move |k| (j, k)))
+                .map(|(j, k)| 0.5 * self.interactions[j][k] * composition[j] * composition[k])
+                .sum();
+            Ok(self
+                .ideal
+                .vapor_pressures(temperature.get::<kelvin>())
+                .iter()
+                .enumerate()
+                .map(|(i, psat)| {
+                    let ln_gamma = self.interactions[i]
+                        .iter()
+                        .zip(composition)
+                        .map(|(a, x)| a * x)
+                        .sum::<f64>()
+                        - excess;
+                    ln_gamma + (psat / pressure.get::<pascal>()).ln()
+                })
+                .collect())
+        }
+
+        fn phase_enthalpy(
+            &self,
+            temperature: ThermodynamicTemperature,
+            _pressure: Pressure,
+            composition: &[f64],
+            phase: Phase,
+        ) -> Result<MolarEnergy, &'static str> {
+            let t = temperature.get::<kelvin>();
+            Ok(MolarEnergy::new::<joule_per_mole>(
+                composition
+                    .iter()
+                    .zip(
+                        self.ideal
+                            .heat_capacities
+                            .iter()
+                            .zip(&self.ideal.heats_of_vaporization),
+                    )
+                    .map(|(x, (cp, dh))| {
+                        x * (cp * (t - 298.15) + if phase == Phase::Vapor { *dh } else { 0.0 })
+                    })
+                    .sum(),
+            ))
+        }
+    }
+
+    fn test_stream(flows: [f64; 2], temperature: f64, pressure: f64) -> StreamReference {
+        let block: BlockReference = Arc::new(RwLock::new(Box::new(Mixer::new())));
+        let mut stream = Stream::new(block.clone(), block);
+        stream.thermo = Some(
+            StreamThermoState::from_molar_flows(
+                vec![String::from("A"), String::from("B")],
+                flows
+                    .iter()
+                    .map(|flow| MolarFlowRate::new::<katal>(*flow))
+                    .collect(),
+                ThermodynamicTemperature::new::<kelvin>(temperature),
+                Pressure::new::<pascal>(pressure),
+            )
+            .unwrap(),
+        );
+        Arc::new(RwLock::new(Box::new(stream)))
+    }
+
+    #[test]
+    /// checks whether the mass balance check function was implemented properly
+    fn test_mass_balance_check_steady_state_for_mixer() {
+        // here you will need to check that the mass into the mixer = mass out of mixer
+        let mixer_test_obj = Mixer::new();
+        let mass_in = Mass::new::<pound>(100.0);
+        let mass_out = Mass::new::<pound>(95.0);
+        assert!(mixer_test_obj.mass_balance_check(mass_in, mass_out));
+    }
+
+    #[test]
+    /// checks if the 'energy_balance_check' function was implemented properly
+    fn test_energy_balance_check_steady_state_for_mixer() {
+        // energy into mixer = energy out of mixer
+        let mixer_test_obj = Mixer::new();
+        let energy_in = Energy::new::<kilojoule>(10.0);
+        let energy_out = Energy::new::<kilojoule>(95.0);
+        assert!(mixer_test_obj.energy_balance_check(energy_in, energy_out));
+    }
+
+    #[test]
+    /// checks that the mixer sums the flows and solves the energy balance
+    fn test_mixer_execute() {
+        let mut mixer = Mixer::with_model(test_model());
+        mixer
+            .connect_input(test_stream([1.0, 0.0], 300.0, 2.0e5))
+            .unwrap();
+        mixer
+            .connect_input(test_stream([0.0, 1.0], 400.0, 1.5e5))
+            .unwrap();
+        let outlet = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        mixer.connect_output(outlet.clone()).unwrap();
+        assert!(mixer.connect_output(outlet.clone()).is_err());
+
+        mixer.execute().unwrap();
+        let thermo = outlet.read().unwrap().thermo.clone().unwrap();
+        assert_eq!(thermo.total_molar_flow().get::<katal>(), 2.0);
+        assert_eq!(thermo.pressure.get::<pascal>(), 1.5e5);
+        // 30 * (T - 300) + 60 * (T - 400) = 0
+        assert!((thermo.temperature.get::<kelvin>() - 1100.0 / 3.0).abs() < 1e-6);
+
+        mixer.outlet_pressure = Some(Pressure::new::<pascal>(1.0e5));
+        mixer.execute().unwrap();
+        let thermo = outlet.read().unwrap().thermo.clone().unwrap();
+        assert_eq!(thermo.pressure.get::<pascal>(), 1.0e5);
+    }
+
+    #[test]
+    /// checks that the mixer residuals vanish at the mixed outlet
+    fn test_mixer_residuals() {
+        let mut mixer = Mixer::with_model(test_model());
+        let inlets = [
+            test_stream([1.0, 0.5], 300.0, 2.0e5),
+            test_stream([0.0, 1.0], 400.0, 1.5e5),
+        ];
+        for inlet in &inlets {
+            mixer.connect_input(inlet.clone()).unwrap();
+        }
+        let outlet = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        mixer.connect_output(outlet.clone()).unwrap();
+        mixer.execute().unwrap();
+
+        let mut variables = EquationVariables::new();
+        for stream in inlets.iter().chain([&outlet]) {
+            let values = stream
+                .read()
+                .unwrap()
+                .variables()
+                .into_iter()
+                .map(F1::cst)
+                .collect();
+            variables.insert(stream.clone(), values);
+        }
+        let residuals = mixer.residuals(&variables).unwrap();
+        assert_eq!(residuals.len(), 4);
+        for residual in residuals {
+            assert!(residual.value().abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    /// checks that material passes from a source to a sink
+    fn test_source_and_sink() {
+        let feed = test_stream([1.0, 2.0], 320.0, 1.0e5);
+        let feed = feed.read().unwrap().thermo.clone().unwrap();
+        let mut source = Source::new();
+        let mut sink = Sink::new();
+        assert!(source.open_outlets() == 1 && sink.has_open_inlet());
+
+        let stream = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        assert!(source.connect_input(stream.clone()).is_err());
+        assert!(sink.connect_output(stream.clone()).is_err());
+        source.connect_output(stream.clone()).unwrap();
+        sink.connect_input(stream.clone()).unwrap();
+        assert!(source.open_outlets() == 0 && !sink.has_open_inlet());
+
+        assert!(source.execute().is_err());
+        source.set_feed(feed.clone()).unwrap();
+        source.execute().unwrap();
+        sink.execute().unwrap();
+        assert_eq!(sink.product, Some(feed));
+    }
+
+    #[test]
+    /// checks that the separator splits each component and reports its duty
+    fn test_separator() {
+        let mut separator = Separator::new();
+        separator.thermo_model = Some(test_model());
+        separator.set_split_fractions("A", vec![0.9]);
+        separator.set_split_fractions("B", vec![0.25, 0.75]);
+        separator.outlet_conditions = vec![OutletConditions {
+            temperature: Some(ThermodynamicTemperature::new::<kelvin>(310.0)),
+            pressure: None,
+        }];
+        let feed = test_stream([1.0, 2.0], 300.0, 1.0e5);
+        let outlets = [
+            test_stream([0.0, 0.0], 298.15, 1.0e5),
+            test_stream([0.0, 0.0], 298.15, 1.0e5),
+        ];
+        separator.connect_input(feed.clone()).unwrap();
+        for outlet in &outlets {
+            separator.connect_output(outlet.clone()).unwrap();
+        }
+        separator.execute().unwrap();
+
+        let top = outlets[0].read().unwrap().thermo.clone().unwrap();
+        let bottom = outlets[1].read().unwrap().thermo.clone().unwrap();
+        assert!((top.molar_flows[0].get::<katal>() - 0.9).abs() < 1e-12);
+        assert!((top.molar_flows[1].get::<katal>() - 0.5).abs() < 1e-12);
+        assert!((bottom.molar_flows[0].get::<katal>() - 0.1).abs() < 1e-12);
+        assert!((bottom.molar_flows[1].get::<katal>() - 1.5).abs() < 1e-12);
+        assert_eq!(top.temperature.get::<kelvin>(), 310.0);
+        assert_eq!(bottom.temperature.get::<kelvin>(), 300.0);
+        // Only the first outlet is heated: (0.9 * 30 + 0.5 * 60) * 10 K.
+        let duty = separator.duty.unwrap().get::<watt>();
+        assert!((duty - 570.0).abs() < 1e-6);
+
+        let mut variables = EquationVariables::new();
+        for stream in outlets.iter().chain([&feed]) {
+            let values = stream
+                .read()
+                .unwrap()
+                .variables()
+                .into_iter()
+                .map(F1::cst)
+                .collect();
+            variables.insert(stream.clone(), values);
+        }
+        let residuals = separator.residuals(&variables).unwrap();
+        assert_eq!(residuals.len(), 8);
+        assert!(residuals
+            .iter()
+            .all(|residual| residual.value().abs() < 1e-9));
+
+        separator.set_split_fractions("B", vec![0.5, 0.6]);
+        assert!(separator.execute().is_err());
+
+        // Components without split fractions go to the last outlet
+        separator.split_fractions.remove("B");
+        separator.execute().unwrap();
+        let top = outlets[0].read().unwrap().thermo.clone().unwrap();
+        let bottom = outlets[1].read().unwrap().thermo.clone().unwrap();
+        assert_eq!(top.molar_flows[1].get::<katal>(), 0.0);
+        assert!((bottom.molar_flows[1].get::<katal>() - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    /// checks the splitter specifications and residuals
+    fn test_splitter() {
+        let mut splitter = Splitter::new();
+        splitter.thermo_model = Some(test_model());
+        splitter.specs = vec![
+            SplitSpec::Fraction(0.25),
+            SplitSpec::MoleFlow(MolarFlowRate::new::<katal>(1.0)),
+            SplitSpec::Remaining,
+            SplitSpec::MassFlow(MassRate::new::<kilogram_per_second>(0.02)),
+        ];
+        let feed = test_stream([1.0, 3.0], 300.0, 1.0e5);
+        let outlets: Vec<StreamReference> = (0..4)
+            .map(|_| test_stream([0.0, 0.0], 298.15, 1.0e5))
+            .collect();
+        splitter.connect_input(feed.clone()).unwrap();
+        for outlet in &outlets {
+            splitter.connect_output(outlet.clone()).unwrap();
+        }
+        splitter.execute().unwrap();
+
+        // The mass flow spec is a quarter of the 0.08 kg/s feed.
+        for (outlet, fraction) in outlets.iter().zip([0.25, 0.25, 0.25, 0.25]) {
+            let thermo = outlet.read().unwrap().thermo.clone().unwrap();
+            assert!((thermo.molar_flows[0].get::<katal>() - fraction).abs() < 1e-12);
+            assert!((thermo.molar_flows[1].get::<katal>() - 3.0 * fraction).abs() < 1e-12);
+            assert_eq!(thermo.temperature.get::<kelvin>(), 300.0);
+        }
+
+        let mut variables = EquationVariables::new();
+        for stream in outlets.iter().chain([&feed]) {
+            let values = stream
+                .read()
+                .unwrap()
+                .variables()
+                .into_iter()
+                .map(F1::cst)
+                .collect();
+            variables.insert(stream.clone(), values);
+        }
+        let residuals = splitter.residuals(&variables).unwrap();
+        assert_eq!(residuals.len(), 16);
+        assert!(residuals
+            .iter()
+            .all(|residual| residual.value().abs() < 1e-9));
+
+        splitter.specs[0] = SplitSpec::Remaining;
+        assert!(splitter.execute().is_err());
+        splitter.specs[0] = SplitSpec::Fraction(0.9);
+        assert!(splitter.execute().is_err());
+    }
+
+    #[test]
+    /// checks that every flash specification finds the same drum state
+    fn test_flash_specifications() {
+        let model = two_phase_model();
+        let feed = test_stream([1.0, 1.0], 300.0, 1.0e5);
+        let temperature = ThermodynamicTemperature::new::<kelvin>(355.0);
+        let pressure = Pressure::new::<pascal>(1.0e5);
+        let mut flash = Flash::with_spec(
+            FlashSpec::TemperaturePressure(temperature, pressure),
+            model.clone(),
+        );
+        let vapor = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        let liquid = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        flash.connect_input(feed.clone()).unwrap();
+        flash.connect_output(vapor.clone()).unwrap();
+        flash.connect_output(liquid.clone()).unwrap();
+        assert!(flash.connect_output(liquid.clone()).is_err());
+        flash.execute().unwrap();
+
+        let v = vapor.read().unwrap().thermo.clone().unwrap();
+        let l = liquid.read().unwrap().thermo.clone().unwrap();
+        let vapor_flow = v.total_molar_flow().get::<katal>();
+        assert!(vapor_flow > 0.0 && vapor_flow < 2.0);
+        for j in 0..2 {
+            let total = v.molar_flows[j].get::<katal>() + l.molar_flows[j].get::<katal>();
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+        let k_a = flash.k_values["A"];
+        let y_a = v.molar_flows[0].get::<katal>() / vapor_flow;
+        let x_a = l.molar_flows[0].get::<katal>() / l.total_molar_flow().get::<katal>();
+        assert!((k_a - y_a / x_a).abs() < 1e-9);
+        assert!(k_a > 1.0 && flash.k_values["B"] < 1.0);
+        let duty = flash.duty.unwrap();
+        let fraction = Ratio::new::<ratio>(vapor_flow / 2.0);
+
+        for spec in [
+            FlashSpec::PressureDuty(pressure, duty),
+            FlashSpec::TemperatureDuty(temperature, duty),
+            FlashSpec::PressureVaporFraction(pressure, fraction),
+            FlashSpec::TemperatureVaporFraction(temperature, fraction),
+        ] {
+            flash.spec = Some(spec);
+            flash.execute().unwrap();
+            let state = vapor.read().unwrap().thermo.clone().unwrap();
+            assert!((state.temperature.get::<kelvin>() - 355.0).abs() < 1e-4);
+            assert!((state.pressure.get::<pascal>() - 1.0e5).abs() < 1e-1);
+            assert!((state.total_molar_flow().get::<katal>() - vapor_flow).abs() < 1e-6);
+            assert!((flash.duty.unwrap() - duty).get::<watt>().abs() < 1e-2);
+        }
+
+        let mut variables = EquationVariables::new();
+        for stream in [&feed, &vapor, &liquid] {
+            let values = stream
+                .read()
+                .unwrap()
+                .variables()
+                .into_iter()
+                .map(F1::cst)
+                .collect();
+            variables.insert(stream.clone(), values);
+        }
+        let residuals = flash.residuals(&variables).unwrap();
+        assert_eq!(residuals.len(), 8);
+        assert!(residuals
+            .iter()
+            .all(|residual| residual.value().abs() < 1e-3));
+    }
+
+    #[test]
+    /// checks the lifted flash derivatives against central differences
+    fn test_flash_jacobian() {
+        let feed = test_stream([1.0, 1.0], 300.0, 1.0e5);
+        let vapor = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        let liquid = test_stream([0.0, 0.0], 298.15, 1.0e5);
+        let pressure = Pressure::new::<pascal>(1.0e5);
+        let mut flash = Flash::with_spec(
+            FlashSpec::TemperaturePressure(
+                ThermodynamicTemperature::new::<kelvin>(355.0),
+                pressure,
+            ),
+            two_phase_model(),
+        );
+        flash.connect_input(feed.clone()).unwrap();
+        flash.connect_output(vapor.clone()).unwrap();
+        flash.connect_output(liquid.clone()).unwrap();
+        flash.execute().unwrap();
+        let duty = flash.duty.unwrap();
+
+        let values: Vec<Vec<f64>> = [&feed, &vapor, &liquid]
+            .iter()
+            .map(|stream| stream.read().unwrap().variables())
+            .collect();
+        let residuals = |flash: &Flash, column: usize, shift: f64, seeded: bool| {
+            let mut variables = EquationVariables::new();
+            for (k, stream) in [&feed, &vapor, &liquid].iter().enumerate() {
+                let stream_values = values[k]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| match (k, i == column) {
+                        (0, true) if seeded => F1::var(*value + shift),
+                        (0, true) => F1::cst(*value + shift),
+                        _ => F1::cst(*value),
+                    })
+                    .collect();
+                variables.insert((*stream).clone(), stream_values);
+            }
+            flash.residuals(&variables).unwrap()
+        };
+
+        // Each column of the feed variables, for a flash at fixed conditions
+        // and one that searches for the temperature meeting a duty.
+        for spec in [flash.spec.unwrap(), FlashSpec::PressureDuty(pressure, duty)] {
+            flash.spec = Some(spec);
+            for (column, value) in values[0].iter().enumerate() {
+                let step = 1e-4 * value.abs().max(1.0);
+                let lifted = residuals(&flash, column, 0.0, true);
+                let more = residuals(&flash, column, step, false);
+                let less = residuals(&flash, column, -step, false);
+                for (row, lifted) in lifted.iter().enumerate() {
+                    let central = (more[row].value() - less[row].value()) / (2.0 * step);
+                    let scale = central.abs().max(1e-3);
+                    assert!(
+                        (lifted.deriv() - central).abs() < 1e-3 * scale,
+                        "{spec:?} column {column} row {row}: {} != {central}",
+                        lifted.deriv()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    /// checks that the three-phase flash finds the phases present
+    fn test_flash3() {
+        let model = Arc::new(RegularSolution {
+            ideal: IdealSolution {
+                components: vec![String::from("A"), String::from("B"), String::from("C")],
+                boiling_points: vec![350.0, 373.0, 300.0],
+                heats_of_vaporization: vec![30_000.0, 35_000.0, 25_000.0],
+                heat_capacities: vec![120.0, 75.0, 100.0],
+            },
+            interactions: vec![
+                vec![0.0, 3.5, 0.5],
+                vec![3.5, 0.0, 0.5],
+                vec![0.5, 0.5, 0.0],
+            ],
+        });
+        let block: BlockReference = Arc::new(RwLock::new(Box::new(Mixer::new())));
+        let stream = |flows: [f64; 3], temperature: f64| -> StreamReference {
+            let mut stream = Stream::new(block.clone(), block.clone());
+            stream.thermo = Some(
+                StreamThermoState::from_molar_flows(
+                    vec![String::from("A"), String::from("B"), String::from("C")],
+                    flows
+                        .iter()
+                        .map(|flow| MolarFlowRate::new::<katal>(*flow))
+                        .collect(),
+                    ThermodynamicTemperature::new::<kelvin>(temperature),
+                    Pressure::new::<pascal>(1.0e5),
+                )
+                .unwrap(),
+            );
+            Arc::new(RwLock::new(Box::new(stream)))
+        };
+        let pressure = Pressure::new::<pascal>(1.0e5);
+        let feed = stream([0.4, 0.4, 0.2], 300.0);
+        let outlets = [
+            stream([0.0; 3], 300.0),
+            stream([0.0; 3], 300.0),
+            stream([0.0; 3], 300.0),
+        ];
+        let mut flash = Flash3::new();
+        flash.thermo_model = Some(model.clone());
+        flash.key_component = Some(String::from("B"));
+        flash.connect_input(feed.clone()).unwrap();
+        for outlet in &outlets {
+            flash.connect_output(outlet.clone()).unwrap();
+        }
+
+        let read = |outlet: &StreamReference| outlet.read().unwrap().thermo.clone().unwrap();
+        let at = ThermodynamicTemperature::new::<kelvin>;
+
+        // Below the bubble point the liquid splits in two, with the second
+        // liquid richer in the key component.
+        flash.spec = Some(FlashSpec::TemperaturePressure(at(310.0), pressure));
+        flash.execute().unwrap();
+        assert_eq!(flash.phase_count, 2);
+        assert_eq!(read(&outlets[0]).total_molar_flow().get::<katal>(), 0.0);
+        let (liquid1, liquid2) = (read(&outlets[1]), read(&outlets[2]));
+        assert!(liquid2.mole_fractions()[1] > liquid1.mole_fractions()[1]);
+        assert!(flash.liquid_k_values["B"] > 1.0);
+
+        // Vapor and both liquids at 330 K, with equal fugacities in each.
+        flash.spec = Some(FlashSpec::TemperaturePressure(at(330.0), pressure));
+        flash.execute().unwrap();
+        assert_eq!(flash.phase_count, 3);
+        let phases: Vec<StreamThermoState> = outlets.iter().map(read).collect();
+        for (j, flow) in [0.4, 0.4, 0.2].iter().enumerate() {
+            let total: f64 = phases
+                .iter()
+                .map(|phase| phase.molar_flows[j].get::<katal>())
+                .sum();
+            assert!((total - flow).abs() < 1e-9);
+        }
+        let fugacities: Vec<Vec<f64>> = phases
+            .iter()
+            .zip([Phase::Vapor, Phase::Liquid, Phase::Liquid])
+            .map(|(phase, kind)| {
+                let x = phase.mole_fractions();
+                model
+                    .ln_fugacity_coefficients(at(330.0), pressure, &x, kind)
+                    .unwrap()
+                    .iter()
+                    .zip(&x)
+                    .map(|(ln_phi, x)| x * ln_phi.exp())
+                    .collect()
+            })
+            .collect();
+        for liquid in &fugacities[1..] {
+            for (vapor, liquid) in fugacities[0].iter().zip(liquid) {
+                assert!((vapor - liquid).abs() < 1e-6);
+            }
+        }
+
+        // The same state is found from the duty.
+        let vapor_flow = phases[0].total_molar_flow().get::<katal>();
+        flash.spec = Some(FlashSpec::PressureDuty(pressure, flash.duty.unwrap()));
+        flash.execute().unwrap();
+        let vapor = read(&outlets[0]);
+        assert!((vapor.temperature.get::<kelvin>() - 330.0).abs() < 1e-4);
+        assert!((vapor.total_molar_flow().get::<katal>() - vapor_flow).abs() < 1e-6);
+
+        // Above the dew point only vapor remains.
+        flash.spec = Some(FlashSpec::TemperaturePressure(at(400.0), pressure));
+        flash.execute().unwrap();
+        assert_eq!(flash.phase_count, 1);
+        assert!((read(&outlets[0]).total_molar_flow().get::<katal>() - 1.0).abs() < 1e-9);
+    }
 }
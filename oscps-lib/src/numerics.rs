@@ -0,0 +1,121 @@
+//! # Numerics
+//!
+//! Numerical methods shared by the simulation and the thermodynamic
+//! calculations.
+
+use std::collections::BTreeMap;
+
+/// # SparseMatrix
+///
+/// A square sparse matrix stored by rows, with a direct solver based on
+/// Gaussian elimination with partial pivoting.
+#[derive(Debug, Clone)]
+pub struct SparseMatrix {
+    rows: Vec<BTreeMap<usize, f64>>,
+}
+
+impl SparseMatrix {
+    /// Create an empty matrix with the given number of rows and columns.
+    pub fn new(size: usize) -> Self {
+        SparseMatrix {
+            rows: vec![BTreeMap::new(); size],
+        }
+    }
+
+    /// Number of rows (and columns) of the matrix.
+    pub fn size(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Adds a value to an entry of the matrix.
+    pub fn add(&mut self, row: usize, column: usize, value: f64) {
+        if value != 0.0 {
+            *self.rows[row].entry(column).or_insert(0.0) += value;
+        }
+    }
+
+    /// Returns an entry of the matrix.
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        self.rows[row].get(&column).copied().unwrap_or(0.0)
+    }
+
+    /// Largest absolute value in each row.
+    pub fn row_norms(&self) -> Vec<f64> {
+        self.rows
+            .iter()
+            .map(|row| row.values().fold(0.0, |norm, value| value.abs().max(norm)))
+            .collect()
+    }
+
+    /// Solves `A x = b`. Returns `None` if the matrix is singular.
+    pub fn solve(&self, rhs: &[f64]) -> Option<Vec<f64>> {
+        let n = self.size();
+        let mut rows = self.rows.clone();
+        let mut rhs = rhs.to_vec();
+        let scale = self
+            .row_norms()
+            .into_iter()
+            .fold(0.0, f64::max)
+            .max(f64::MIN_POSITIVE);
+
+        for k in 0..n {
+            let pivot = (k..n)
+                .filter_map(|i| rows[i].get(&k).map(|value| (i, value.abs())))
+                .max_by(|a, b| a.1.total_cmp(&b.1))?;
+            if pivot.1 <= 1e-14 * scale {
+                return None;
+            }
+            rows.swap(k, pivot.0);
+            rhs.swap(k, pivot.0);
+
+            let pivot_row = rows[k].clone();
+            let pivot_value = pivot_row[&k];
+            for i in k + 1..n {
+                let Some(factor) = rows[i].remove(&k).map(|value| value / pivot_value) else {
+                    continue;
+                };
+                for (column, value) in pivot_row.range(k + 1..) {
+                    *rows[i].entry(*column).or_insert(0.0) -= factor * value;
+                }
+                rhs[i] -= factor * rhs[k];
+            }
+        }
+
+        let mut solution = vec![0.0; n];
+        for k in (0..n).rev() {
+            let known: f64 = rows[k]
+                .range(k + 1..)
+                .map(|(column, value)| value * solution[*column])
+                .sum();
+            solution[k] = (rhs[k] - known) / rows[k][&k];
+        }
+        Some(solution)
+    }
+}
+
+#[cfg(test)]
+mod numerics_tests {
+    use super::*;
+
+    #[test]
+    fn sparse_solve_pivots() {
+        // [0 2 0] [x]   [4]
+        // [1 0 1] [y] = [4]
+        // [0 1 3] [z]   [11]
+        let mut matrix = SparseMatrix::new(3);
+        matrix.add(0, 1, 2.0);
+        matrix.add(1, 0, 1.0);
+        matrix.add(1, 2, 1.0);
+        matrix.add(2, 1, 1.0);
+        matrix.add(2, 2, 3.0);
+        let solution = matrix.solve(&[4.0, 4.0, 11.0]).unwrap();
+        for (value, expected) in solution.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((value - expected).abs() < 1e-12);
+        }
+
+        let mut singular = SparseMatrix::new(2);
+        singular.add(0, 0, 1.0);
+        singular.add(1, 0, 2.0);
+        assert!(singular.solve(&[1.0, 2.0]).is_none());
+    }
+}
@@ -3,33 +3,58 @@
 //! Contains chemical properties for species in the simulation.
 
 extern crate uom;
+#[cfg(feature = "pubchem")]
 extern crate pubchem;
+///Bundled database of pure component constants
+pub mod component_database;
 ///Importing pure species properties
 pub mod pure_species_properties;
 
 use anyhow::Result;
+use crate::thermodynamics::heat_capacity::{CorrelationRange, HeatCapacityCoefficients};
+use component_database::{ComponentDatabase, ComponentRecord};
+use oscps_db::{PropertySet, PropertyStore, PureSpeciesRecord};
+use uom::si::dynamic_viscosity::pascal_second;
 use uom::si::f64::*;
+use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+use uom::si::molar_volume::cubic_meter_per_mole;
+use uom::si::pressure::pascal;
+use uom::si::ratio::ratio;
+use uom::si::thermal_conductivity::watt_per_meter_kelvin;
+use uom::si::thermodynamic_temperature::kelvin;
+#[cfg(feature = "pubchem")]
 use std::{thread,time::Duration};
 use serde::{Serialize, Deserialize};
 
+/// Number of attempts made to reach PubChem before giving up
+#[cfg(feature = "pubchem")]
+const PUBCHEM_ATTEMPTS: u32 = 3;
+
 #[allow(dead_code)]
-/// Used by the "Chemical" struct to create the pubchem::Compound obj based on
-/// either the chemical name or the pubchem id of the chemical
+/// Used by the "Chemical" struct to find a chemical, either in the bundled
+/// component database or on PubChem, from its name or its PubChem ID
 pub enum ChemicalIdentifier {
     /// The PubChem ID of the component.
     PubchemID(u32),
-    /// The actual name of the component.
+    /// The actual name of the component, or its CAS number.
     CompoundName(String),
 }
 
 
 #[allow(dead_code)]
 /// A struct to store information regarding the chemical properties of a 
-/// particular substance. The "Chemical" struct is a wrapper for the 
-/// pubchem::Compound object
+/// particular substance. Chemicals are taken from the bundled component
+/// database when they are in it, so no network access is needed, and are
+/// otherwise looked up on PubChem when the "pubchem" feature is enabled.
 pub struct Chemical {
-    /// The (PubChem)[<https://pubchem.ncbi.nlm.nih.gov/>] CID of a compound.
-    pub pubchem_obj: pubchem::Compound,
+    /// Name of the compound
+    pub name: String,
+    /// Record of the compound in the bundled component database, if it is there.
+    pub record: Option<ComponentRecord>,
+    /// The (PubChem)[<https://pubchem.ncbi.nlm.nih.gov/>] compound, for
+    /// compounds that were looked up online.
+    #[cfg(feature = "pubchem")]
+    pub pubchem_obj: Option<pubchem::Compound>,
     /// Physical properties of a compound.
     pub properties: ChemicalProperties,
 }
@@ -37,38 +62,89 @@ pub struct Chemical {
 #[allow(dead_code)]
 /// Implementation of the chemical of interest.
 impl Chemical {
-    /// Constructs a new chemical.
+    /// Constructs a new chemical. The bundled component database is searched
+    /// first, and PubChem is only asked for compounds that are not in it.
+    /// Returns an error instead of panicking when the compound is unknown or
+    /// PubChem cannot be reached.
     pub fn new(identifier: ChemicalIdentifier) -> Result<Self> {
-        let pubchem_chemical_object = match identifier {
-            ChemicalIdentifier::PubchemID(id) => pubchem::Compound::new(id),
-            ChemicalIdentifier::CompoundName(name) => pubchem::Compound::with_name(name.as_str()),
+        let database = ComponentDatabase::bundled();
+        let record = match &identifier {
+            ChemicalIdentifier::PubchemID(id) => database.by_pubchem_cid(*id),
+            ChemicalIdentifier::CompoundName(name) => database.by_name(name),
+        };
+        match record {
+            Some(record) => Ok(Chemical::from_record(record.clone())),
+            None => Chemical::from_pubchem(identifier),
+        }
+    }
+
+    /// Constructs a chemical from a record of a component database.
+    pub fn from_record(record: ComponentRecord) -> Self {
+        Chemical {
+            name: record.name.clone(),
+            properties: ChemicalProperties {
+                critical: Some(CriticalProperties::from_record(&record)),
+                heat_capacity: record.heat_capacity.clone(),
+                transport: None,
+                other_properties: None,
+            },
+            record: Some(record),
+            #[cfg(feature = "pubchem")]
+            pubchem_obj: None,
+        }
+    }
+
+    /// Looks a chemical up on PubChem, retrying a few times with a short
+    /// delay when the request fails.
+    #[cfg(feature = "pubchem")]
+    fn from_pubchem(identifier: ChemicalIdentifier) -> Result<Self> {
+        let (pubchem_chemical_object, description) = match identifier {
+            ChemicalIdentifier::PubchemID(id) => (pubchem::Compound::new(id), format!("CID {}", id)),
+            ChemicalIdentifier::CompoundName(name) => (pubchem::Compound::with_name(name.as_str()), name),
         };
-        let mut request_counter = 0;
-        let mut cid_vec = None;
-        while request_counter <= 10 {
-            match pubchem_chemical_object.cids(){
+        let mut cids = None;
+        for attempt in 1..=PUBCHEM_ATTEMPTS {
+            match pubchem_chemical_object.cids() {
                 Ok(cid_list) => {
-                    cid_vec = Some(cid_list);
+                    cids = Some(cid_list);
                     break;
                 },
-                _ => {
-                    request_counter += 1;
-                    thread::sleep(Duration::from_secs(10));
-                }
+                Err(_) if attempt < PUBCHEM_ATTEMPTS => thread::sleep(Duration::from_secs(1)),
+                Err(_) => {},
             };
         }
-
-        // let cid_vec = pubchem_chemical_object.cids().unwrap();
-        let cid: i32 = cid_vec.unwrap()[0];
-        let prop = ChemicalProperties::new(cid).unwrap();
+        let cids = cids.ok_or_else(|| anyhow::anyhow!(
+            "{} is not in the component database and PubChem could not be reached", description))?;
+        if cids.is_empty() {
+            anyhow::bail!("{} was not found on PubChem", description);
+        }
+        let name = pubchem_chemical_object.title().unwrap_or(description);
         Ok(Chemical {
-            pubchem_obj: pubchem_chemical_object,
-            properties: prop,
+            name,
+            record: None,
+            pubchem_obj: Some(pubchem_chemical_object),
+            properties: ChemicalProperties {
+                critical: None,
+                heat_capacity: None,
+                transport: None,
+                other_properties: None,
+            },
         })
     }
-    /// Returns the pubchem object for the compound.
-    pub fn get_pubchem_obj(&self) -> &pubchem::Compound {
-        &self.pubchem_obj
+
+    /// Without the "pubchem" feature, only the bundled database is searched.
+    #[cfg(not(feature = "pubchem"))]
+    fn from_pubchem(identifier: ChemicalIdentifier) -> Result<Self> {
+        match identifier {
+            ChemicalIdentifier::PubchemID(id) => anyhow::bail!("CID {} is not in the component database", id),
+            ChemicalIdentifier::CompoundName(name) => anyhow::bail!("{} is not in the component database", name),
+        }
+    }
+
+    /// Returns the pubchem object for the compound, if it was looked up online.
+    #[cfg(feature = "pubchem")]
+    pub fn get_pubchem_obj(&self) -> Option<&pubchem::Compound> {
+        self.pubchem_obj.as_ref()
     }
 
     /// Returns the "ChemicalProperties" object for the "Chemical" object.
@@ -79,6 +155,7 @@ impl Chemical {
 
 #[allow(dead_code)]
 /// Struct containing properties of a chemical
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChemicalProperties {
     /// Pure species properties
     pub critical: Option<CriticalProperties>,
@@ -94,14 +171,456 @@ pub struct ChemicalProperties {
     pub other_properties: Option<Vec<OtherProperty>>,
 }
 
+/// Properties that are looked up in a property store besides the critical
+/// constants and transport properties, when a species has them
+const OTHER_PROPERTIES: [&str; 4] = ["vapor_pressure", "liquid_density", "heat_of_vaporization", "surface_tension"];
+
+#[allow(dead_code)]
+impl ChemicalProperties {
+    /// Fetches the properties of a species from a property store, returning
+    /// `None` when the store does not have the species. Ideal gas heat
+    /// capacities are read from the 'ideal_gas_heat_capacity' property sets.
+    pub fn fetch(store: &dyn PropertyStore, name: &str) -> Result<Option<Self>> {
+        let record = match store.pure_species(name)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let id = record.id.ok_or_else(|| anyhow::anyhow!("{} has no row id in the property store", name))?;
+        let heat_capacity = fetch_heat_capacity(store, id)?;
+        let mut other_properties = Vec::new();
+        for property in OTHER_PROPERTIES {
+            other_properties.extend(OtherProperty::fetch(store, id, property)?);
+        }
+        Ok(Some(ChemicalProperties {
+            critical: Some(CriticalProperties::from(&record)),
+            heat_capacity,
+            transport: Some(TransportProperties::fetch(store, id)?),
+            other_properties: if other_properties.is_empty() { None } else { Some(other_properties) },
+        }))
+    }
+}
+
+/// Ideal gas heat capacity of a species from its 'ideal_gas_heat_capacity'
+/// property sets. The form of the first set is used, and the NASA and
+/// Shomate forms take one set for each temperature range they are fitted
+/// over. The forms are named as in the property store:
+///
+/// - 'constant': '[Cp]' in J/(mol*K)
+/// - 'polynomial': 'Cp = c_0 + c_1 T + c_2 T^2 + ...' in J/(mol*K)
+/// - 'dippr107': DIPPR equation 107 in J/(kmol*K)
+/// - 'nasa7' and 'nasa9': NASA polynomials in 'Cp / R'
+/// - 'shomate': the Shomate equation in J/(mol*K)
+///
+/// Returns an error for a form that is not known, or a set with the wrong
+/// number of coefficients for its form.
+pub(crate) fn fetch_heat_capacity(store: &dyn PropertyStore, species_id: i64) -> Result<Option<HeatCapacityCoefficients>> {
+    let sets = store.property_sets(species_id, "ideal_gas_heat_capacity")?;
+    let form = match sets.first() {
+        Some(set) => set.correlation.clone(),
+        None => return Ok(None),
+    };
+    let mut sets: Vec<PropertySet> = sets.into_iter().filter(|set| set.correlation == form).collect();
+    sets.sort_by(|a, b| a.minimum_temperature.unwrap_or(0.0).total_cmp(&b.minimum_temperature.unwrap_or(0.0)));
+    let heat_capacity = match form.as_str() {
+        "constant" => match sets[0].coefficients[..] {
+            [heat_capacity] => HeatCapacityCoefficients::Constant(MolarHeatCapacity::new::<joule_per_kelvin_mole>(heat_capacity)),
+            _ => anyhow::bail!("a constant heat capacity has one coefficient"),
+        },
+        "polynomial" => HeatCapacityCoefficients::Polynomial(sets[0].coefficients.clone()),
+        "dippr107" => HeatCapacityCoefficients::Dippr107(coefficients(&sets[0])?),
+        "nasa7" => HeatCapacityCoefficients::Nasa7(ranges(&sets)?),
+        "nasa9" => HeatCapacityCoefficients::Nasa9(ranges(&sets)?),
+        "shomate" => HeatCapacityCoefficients::Shomate(ranges(&sets)?),
+        _ => anyhow::bail!("{} is not a known form of the ideal gas heat capacity", form),
+    };
+    Ok(Some(heat_capacity))
+}
+
+/// Coefficients of a property set, checking that it has as many as its
+/// correlation takes
+fn coefficients<const N: usize>(set: &PropertySet) -> Result<[f64; N]> {
+    set.coefficients.as_slice().try_into().map_err(|_| {
+        anyhow::anyhow!("a {} correlation has {} coefficients, not {}", set.correlation, N, set.coefficients.len())
+    })
+}
+
+/// Temperature ranges of a correlation fitted over several ranges, one for
+/// each property set. Open ends of the ranges are taken as unbounded.
+fn ranges<const N: usize>(sets: &[PropertySet]) -> Result<Vec<CorrelationRange<N>>> {
+    sets.iter()
+        .map(|set| {
+            Ok(CorrelationRange {
+                minimum: ThermodynamicTemperature::new::<kelvin>(set.minimum_temperature.unwrap_or(0.0)),
+                maximum: ThermodynamicTemperature::new::<kelvin>(set.maximum_temperature.unwrap_or(f64::INFINITY)),
+                coefficients: coefficients(set)?,
+            })
+        })
+        .collect()
+}
+
+/// Constants of a pure species at its critical point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CriticalProperties {
+    /// Critical temperature
+    pub temperature: ThermodynamicTemperature,
+    /// Critical pressure
+    pub pressure: Pressure,
+    /// Critical molar volume, when it is known
+    pub molar_volume: Option<MolarVolume>,
+    /// Compressibility factor at the critical point, when it is known
+    pub compressibility: Option<Ratio>,
+    /// Acentric factor
+    pub acentric_factor: Ratio,
+}
+
+impl CriticalProperties {
+    /// Critical constants of a record of a component database.
+    pub fn from_record(record: &ComponentRecord) -> Self {
+        CriticalProperties {
+            temperature: record.critical_temperature,
+            pressure: record.critical_pressure,
+            molar_volume: record.critical_volume,
+            compressibility: record.critical_compressibility.map(Ratio::new::<ratio>),
+            acentric_factor: Ratio::new::<ratio>(record.acentric_factor),
+        }
+    }
+
+    /// Fetches the critical constants of a species from a property store,
+    /// returning `None` when the store does not have the species.
+    pub fn fetch(store: &dyn PropertyStore, name: &str) -> Result<Option<Self>> {
+        Ok(store.pure_species(name)?.as_ref().map(CriticalProperties::from))
+    }
+}
+
+impl From<&PureSpeciesRecord> for CriticalProperties {
+    fn from(record: &PureSpeciesRecord) -> Self {
+        CriticalProperties {
+            temperature: ThermodynamicTemperature::new::<kelvin>(record.critical_temperature),
+            pressure: Pressure::new::<pascal>(record.critical_pressure),
+            molar_volume: Some(MolarVolume::new::<cubic_meter_per_mole>(record.critical_volume)),
+            compressibility: Some(Ratio::new::<ratio>(record.critical_compressibility)),
+            acentric_factor: Ratio::new::<ratio>(record.acentric_factor),
+        }
+    }
+}
+
+/// # PropertyCorrelation
+///
+/// A temperature dependent property fitted to one of the DIPPR equations or
+/// the Antoine equation, with values in SI units. The correlation is named
+/// as in the property store:
+///
+/// - 'dippr100': 'Y = A + B T + C T^2 + D T^3 + E T^4'
+/// - 'dippr101': 'Y = exp(A + B / T + C ln(T) + D T^E)'
+/// - 'dippr102': 'Y = A T^B / (1 + C / T + D / T^2)'
+/// - 'dippr105': 'Y = A / B^(1 + (1 - T / C)^D)'
+/// - 'antoine': 'Y = exp(A - B / (T + C))'
+///
+/// Missing coefficients are zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertyCorrelation {
+    /// Name of the correlation
+    pub correlation: String,
+    /// Coefficients of the correlation, in the order they are published
+    pub coefficients: Vec<f64>,
+    /// Where the coefficients were taken from
+    pub source: String,
+    /// Lowest temperature the correlation was fitted to, when it is known
+    pub minimum_temperature: Option<ThermodynamicTemperature>,
+    /// Highest temperature the correlation was fitted to, when it is known
+    pub maximum_temperature: Option<ThermodynamicTemperature>,
+}
+
+impl PropertyCorrelation {
+    /// Whether a temperature is within the range of the correlation
+    pub fn covers(&self, temperature: ThermodynamicTemperature) -> bool {
+        self.minimum_temperature.is_none_or(|minimum| temperature >= minimum)
+            && self.maximum_temperature.is_none_or(|maximum| temperature <= maximum)
+    }
+
+    /// Value of the property in SI units at a temperature. Temperatures
+    /// outside of the range of the correlation are extrapolated.
+    pub fn evaluate(&self, temperature: ThermodynamicTemperature) -> std::result::Result<f64, &'static str> {
+        let t = temperature.get::<kelvin>();
+        let c = |i: usize| self.coefficients.get(i).copied().unwrap_or(0.0);
+        match self.correlation.as_str() {
+            "dippr100" => Ok(self.coefficients.iter().rev().fold(0.0, |sum, coefficient| sum * t + coefficient)),
+            "dippr101" => Ok((c(0) + c(1) / t + c(2) * t.ln() + c(3) * t.powf(c(4))).exp()),
+            "dippr102" => Ok(c(0) * t.powf(c(1)) / (1.0 + c(2) / t + c(3) / (t * t))),
+            "dippr105" => Ok(c(0) / c(1).powf(1.0 + (1.0 - t / c(2)).powf(c(3)))),
+            "antoine" => Ok((c(0) - c(1) / (t + c(2))).exp()),
+            _ => Err("the correlation is not known"),
+        }
+    }
+}
+
+impl From<PropertySet> for PropertyCorrelation {
+    fn from(set: PropertySet) -> Self {
+        PropertyCorrelation {
+            correlation: set.correlation,
+            coefficients: set.coefficients,
+            source: set.source,
+            minimum_temperature: set.minimum_temperature.map(ThermodynamicTemperature::new::<kelvin>),
+            maximum_temperature: set.maximum_temperature.map(ThermodynamicTemperature::new::<kelvin>),
+        }
+    }
+}
+
+/// Picks the correlation covering a temperature, or the first one to
+/// extrapolate when none of them does, and evaluates it.
+fn evaluate(correlations: &[PropertyCorrelation], temperature: ThermodynamicTemperature) -> Option<f64> {
+    let correlation = correlations
+        .iter()
+        .find(|correlation| correlation.covers(temperature))
+        .or_else(|| correlations.first())?;
+    correlation.evaluate(temperature).ok()
+}
+
+/// Viscosities and thermal conductivities of a pure species, each as the
+/// correlations stored for it. A property may be fitted over several
+/// temperature ranges.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransportProperties {
+    /// Viscosity of the liquid, in Pa*s
+    pub liquid_viscosity: Vec<PropertyCorrelation>,
+    /// Viscosity of the vapor at low pressure, in Pa*s
+    pub vapor_viscosity: Vec<PropertyCorrelation>,
+    /// Thermal conductivity of the liquid, in W/(m*K)
+    pub liquid_thermal_conductivity: Vec<PropertyCorrelation>,
+    /// Thermal conductivity of the vapor at low pressure, in W/(m*K)
+    pub vapor_thermal_conductivity: Vec<PropertyCorrelation>,
+}
+
+impl TransportProperties {
+    /// Fetches the transport properties of a species from a property store,
+    /// from its 'liquid_viscosity', 'vapor_viscosity',
+    /// 'liquid_thermal_conductivity' and 'vapor_thermal_conductivity'
+    /// property sets.
+    pub fn fetch(store: &dyn PropertyStore, species_id: i64) -> Result<Self> {
+        let correlations = |property: &str| -> Result<Vec<PropertyCorrelation>> {
+            let sets = store.property_sets(species_id, property)?;
+            Ok(sets.into_iter().map(PropertyCorrelation::from).collect())
+        };
+        Ok(TransportProperties {
+            liquid_viscosity: correlations("liquid_viscosity")?,
+            vapor_viscosity: correlations("vapor_viscosity")?,
+            liquid_thermal_conductivity: correlations("liquid_thermal_conductivity")?,
+            vapor_thermal_conductivity: correlations("vapor_thermal_conductivity")?,
+        })
+    }
+
+    /// Viscosity of the liquid, when it has a correlation
+    pub fn liquid_viscosity(&self, temperature: ThermodynamicTemperature) -> Option<DynamicViscosity> {
+        evaluate(&self.liquid_viscosity, temperature).map(DynamicViscosity::new::<pascal_second>)
+    }
+
+    /// Viscosity of the vapor at low pressure, when it has a correlation
+    pub fn vapor_viscosity(&self, temperature: ThermodynamicTemperature) -> Option<DynamicViscosity> {
+        evaluate(&self.vapor_viscosity, temperature).map(DynamicViscosity::new::<pascal_second>)
+    }
+
+    /// Thermal conductivity of the liquid, when it has a correlation
+    pub fn liquid_thermal_conductivity(&self, temperature: ThermodynamicTemperature) -> Option<ThermalConductivity> {
+        evaluate(&self.liquid_thermal_conductivity, temperature).map(ThermalConductivity::new::<watt_per_meter_kelvin>)
+    }
+
+    /// Thermal conductivity of the vapor at low pressure, when it has a
+    /// correlation
+    pub fn vapor_thermal_conductivity(&self, temperature: ThermodynamicTemperature) -> Option<ThermalConductivity> {
+        evaluate(&self.vapor_thermal_conductivity, temperature).map(ThermalConductivity::new::<watt_per_meter_kelvin>)
+    }
+}
+
+/// Any other temperature dependent property of a pure species, such as its
+/// vapor pressure or liquid density
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtherProperty {
+    /// Name of the property, as in the property store
+    pub property: String,
+    /// Correlation of the property
+    pub correlation: PropertyCorrelation,
+}
+
+impl OtherProperty {
+    /// Fetches the correlations of one property of a species from a
+    /// property store.
+    pub fn fetch(store: &dyn PropertyStore, species_id: i64, property: &str) -> Result<Vec<Self>> {
+        let sets = store.property_sets(species_id, property)?;
+        Ok(sets.into_iter().map(OtherProperty::from).collect())
+    }
+
+    /// Value of the property in SI units at a temperature
+    pub fn evaluate(&self, temperature: ThermodynamicTemperature) -> std::result::Result<f64, &'static str> {
+        self.correlation.evaluate(temperature)
+    }
+}
+
+impl From<PropertySet> for OtherProperty {
+    fn from(set: PropertySet) -> Self {
+        OtherProperty {
+            property: set.property.clone(),
+            correlation: PropertyCorrelation::from(set),
+        }
+    }
+}
+
 /// Trait to group all property libraries
-trait PropertyLibrary {
+pub trait PropertyLibrary {
     /// default function for connecting the database to pull relevant property information
-    fn oscps_db_connection(&self) -> &db_connection;
+    fn oscps_db_connection(&self) -> &dyn PropertyStore;
 }
 
 
 
 #[cfg(test)]
 mod chemical_species_tests {
+    use super::*;
+    use oscps_db::sqlite_db::SqliteDB;
+
+    #[test]
+    fn chemicals_are_found_offline() {
+        let methane = Chemical::new(ChemicalIdentifier::CompoundName(String::from("Methane"))).unwrap();
+        assert_eq!(methane.name, "methane");
+        assert!(methane.get_properties().heat_capacity.is_some());
+
+        let benzene = Chemical::new(ChemicalIdentifier::PubchemID(241)).unwrap();
+        assert_eq!(benzene.record.unwrap().cas, "71-43-2");
+        let critical = benzene.properties.critical.unwrap();
+        assert!((critical.temperature.get::<kelvin>() - 562.05).abs() < 0.1);
+    }
+
+    fn water() -> PureSpeciesRecord {
+        PureSpeciesRecord {
+            id: None,
+            name: String::from("water"),
+            cas: Some(String::from("7732-18-5")),
+            formula: Some(String::from("H2O")),
+            smiles: Some(String::from("O")),
+            pubchem_cid: Some(962),
+            user_defined: false,
+            molar_mass: 0.018015,
+            normal_boiling_point: 373.15,
+            critical_temperature: 647.14,
+            critical_pressure: 220.64e5,
+            critical_volume: 55.95e-6,
+            critical_compressibility: 0.229,
+            acentric_factor: 0.344,
+            antoine_a: None,
+            antoine_b: None,
+            antoine_c: None,
+        }
+    }
+
+    fn property_set(species_id: i64, property: &str, correlation: &str, coefficients: Vec<f64>, range: (f64, f64)) -> PropertySet {
+        PropertySet {
+            id: None,
+            species_id,
+            property: String::from(property),
+            correlation: String::from(correlation),
+            coefficients,
+            source: String::from("DIPPR 801"),
+            minimum_temperature: Some(range.0),
+            maximum_temperature: Some(range.1),
+        }
+    }
+
+    #[test]
+    fn properties_are_fetched_from_a_store() {
+        let store = SqliteDB::in_memory().unwrap();
+        let id = store.save_pure_species(&water()).unwrap();
+        for set in [
+            property_set(id, "liquid_viscosity", "dippr101", vec![-52.843, 3703.6, 5.866, -5.879e-29, 10.0], (273.16, 646.15)),
+            property_set(id, "vapor_viscosity", "dippr102", vec![1.7096e-8, 1.1146], (273.16, 1073.15)),
+            property_set(id, "liquid_thermal_conductivity", "dippr100", vec![-0.432, 5.7255e-3, -8.078e-6, 1.861e-9], (273.16, 633.15)),
+            property_set(id, "vapor_pressure", "antoine", vec![23.1964, 3816.44, -46.13], (284.0, 441.0)),
+            property_set(id, "ideal_gas_heat_capacity", "dippr107", vec![0.33363e5, 0.2679e5, 2.6105e3, 0.08896e5, 1169.0], (100.0, 2273.15)),
+        ] {
+            store.save_property_set(&set).unwrap();
+        }
+
+        let properties = ChemicalProperties::fetch(&store, "water").unwrap().unwrap();
+        let critical = properties.critical.unwrap();
+        assert_eq!(critical.pressure.get::<pascal>(), 220.64e5);
+        assert_eq!(critical.compressibility, Some(Ratio::new::<ratio>(0.229)));
+        let room = ThermodynamicTemperature::new::<kelvin>(298.15);
+        let heat_capacity = properties.heat_capacity.unwrap().heat_capacity(room);
+        assert!((heat_capacity.get::<joule_per_kelvin_mole>() - 33.6).abs() < 0.2);
+
+        let transport = properties.transport.unwrap();
+        let liquid = transport.liquid_viscosity(room).unwrap().get::<pascal_second>();
+        assert!((liquid - 0.89e-3).abs() < 0.03e-3);
+        let conductivity = transport.liquid_thermal_conductivity(room).unwrap();
+        assert!((conductivity.get::<watt_per_meter_kelvin>() - 0.607).abs() < 0.01);
+        let vapor = transport.vapor_viscosity(ThermodynamicTemperature::new::<kelvin>(400.0)).unwrap();
+        assert!((vapor.get::<pascal_second>() - 13.3e-6).abs() < 0.5e-6);
+        assert!(transport.vapor_thermal_conductivity(room).is_none());
+
+        let other = properties.other_properties.unwrap();
+        assert_eq!(other.len(), 1);
+        let boiling = other[0].evaluate(ThermodynamicTemperature::new::<kelvin>(373.15)).unwrap();
+        assert!((boiling / 101325.0 - 1.0).abs() < 0.01);
+
+        assert!(ChemicalProperties::fetch(&store, "steam").unwrap().is_none());
+        assert!(CriticalProperties::fetch(&store, "steam").unwrap().is_none());
+    }
+
+    #[test]
+    fn heat_capacity_forms_are_fetched() {
+        let store = SqliteDB::in_memory().unwrap();
+        let id = store.save_pure_species(&water()).unwrap();
+        // GRI-Mech 3.0, saved with the high temperature range first
+        for (coefficients, range) in [
+            (vec![3.03399249, 2.17691804e-3, -1.64072518e-7, -9.70419870e-11, 1.68200992e-14, -3.00042971e4, 4.96677010], (1000.0, 3500.0)),
+            (vec![4.19864056, -2.03643410e-3, 6.52040211e-6, -5.48797062e-9, 1.77197817e-12, -3.02937267e4, -8.49032208e-1], (200.0, 1000.0)),
+        ] {
+            store.save_property_set(&property_set(id, "ideal_gas_heat_capacity", "nasa7", coefficients, range)).unwrap();
+        }
+        let heat_capacity = fetch_heat_capacity(&store, id).unwrap().unwrap();
+        let cp = |t: f64| heat_capacity.heat_capacity(ThermodynamicTemperature::new::<kelvin>(t)).get::<joule_per_kelvin_mole>();
+        assert!((cp(298.15) - 33.6).abs() < 0.2);
+        assert!((cp(1500.0) - 47.3).abs() < 0.3);
+
+        let mut water = water();
+        water.name = String::from("steam");
+        water.cas = None;
+        water.pubchem_cid = None;
+        let id = store.save_pure_species(&water).unwrap();
+        let shomate = vec![30.09200, 6.832514, 6.793435, -2.534480, 0.082139, -250.8810, 223.3967, -241.8264];
+        store.save_property_set(&property_set(id, "ideal_gas_heat_capacity", "shomate", shomate, (500.0, 1700.0))).unwrap();
+        let heat_capacity = fetch_heat_capacity(&store, id).unwrap().unwrap();
+        let room = ThermodynamicTemperature::new::<kelvin>(298.15);
+        assert!((heat_capacity.heat_capacity(room).get::<joule_per_kelvin_mole>() - 33.6).abs() < 0.2);
+
+        water.name = String::from("ice");
+        let id = store.save_pure_species(&water).unwrap();
+        store.save_property_set(&property_set(id, "ideal_gas_heat_capacity", "wilhoit", vec![1.0], (200.0, 1000.0))).unwrap();
+        assert!(fetch_heat_capacity(&store, id).is_err());
+        water.name = String::from("vapor");
+        let id = store.save_pure_species(&water).unwrap();
+        store.save_property_set(&property_set(id, "ideal_gas_heat_capacity", "nasa9", vec![1.0; 7], (200.0, 1000.0))).unwrap();
+        assert!(fetch_heat_capacity(&store, id).is_err());
+    }
+
+    #[test]
+    fn properties_are_serialized() {
+        let store = SqliteDB::in_memory().unwrap();
+        store.save_pure_species(&water()).unwrap();
+        let critical = CriticalProperties::fetch(&store, "water").unwrap().unwrap();
+        let json = serde_json::to_string(&critical).unwrap();
+        assert_eq!(serde_json::from_str::<CriticalProperties>(&json).unwrap(), critical);
+
+        let transport = TransportProperties {
+            vapor_viscosity: vec![PropertyCorrelation::from(property_set(1, "vapor_viscosity", "dippr102", vec![1.7096e-8, 1.1146], (273.16, 1073.15)))],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&transport).unwrap();
+        assert_eq!(serde_json::from_str::<TransportProperties>(&json).unwrap(), transport);
+
+        let id = store.pure_species("water").unwrap().unwrap().id.unwrap();
+        let nasa7 = vec![4.19864056, -2.03643410e-3, 6.52040211e-6, -5.48797062e-9, 1.77197817e-12, -3.02937267e4, -8.49032208e-1];
+        store.save_property_set(&property_set(id, "ideal_gas_heat_capacity", "nasa7", nasa7, (200.0, 1000.0))).unwrap();
+        let properties = ChemicalProperties::fetch(&store, "water").unwrap().unwrap();
+        let json = serde_json::to_string(&properties).unwrap();
+        assert_eq!(serde_json::from_str::<ChemicalProperties>(&json).unwrap(), properties);
+    }
 }
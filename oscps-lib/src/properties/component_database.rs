@@ -0,0 +1,294 @@
+//! # Component Database
+//!
+//! Constants of common pure components, bundled with the crate so that
+//! chemicals can be set up without network access. The table is the CSV file
+//! 'data/components.csv', with one record per component holding its
+//! identifiers, critical constants, acentric factor, ideal gas heat capacity
+//! and vapor pressure correlation. Components are found by name, CAS number
+//! or PubChem CID, and their records give the parameters of the
+//! thermodynamic models directly.
+
+use crate::thermodynamics::activity_models::{AntoineCoefficients, LiquidParameters};
+use crate::thermodynamics::eos_models::EOSParameters;
+use crate::thermodynamics::heat_capacity::HeatCapacityCoefficients;
+use once_cell::sync::Lazy;
+use oscps_db::csv::{number, records};
+use std::str::FromStr;
+use uom::si::f64::*;
+use uom::si::molar_mass::gram_per_mole;
+use uom::si::molar_volume::cubic_centimeter_per_mole;
+use uom::si::pressure::bar;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// Components bundled with the crate
+static BUNDLED_COMPONENTS: Lazy<ComponentDatabase> = Lazy::new(|| {
+    ComponentDatabase::parse(include_str!("../../data/components.csv"))
+        .expect("the bundled component database is valid")
+});
+
+/// # ComponentRecord
+///
+/// The constants of one pure component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentRecord {
+    /// Common name, such as 'n-butane'
+    pub name: String,
+    /// Molecular formula in Hill notation
+    pub formula: String,
+    /// CAS registry number
+    pub cas: String,
+    /// PubChem compound identifier
+    pub pubchem_cid: u32,
+    /// Molar mass
+    pub molar_mass: MolarMass,
+    /// Boiling point at one atmosphere
+    pub normal_boiling_point: ThermodynamicTemperature,
+    /// Critical temperature
+    pub critical_temperature: ThermodynamicTemperature,
+    /// Critical pressure
+    pub critical_pressure: Pressure,
+    /// Critical molar volume, if known
+    pub critical_volume: Option<MolarVolume>,
+    /// Compressibility factor at the critical point, if known
+    pub critical_compressibility: Option<f64>,
+    /// Acentric factor
+    pub acentric_factor: f64,
+    /// Ideal gas heat capacity, if known
+    pub heat_capacity: Option<HeatCapacityCoefficients>,
+    /// Vapor pressure correlation, if known
+    pub antoine: Option<AntoineCoefficients>,
+}
+
+impl ComponentRecord {
+    /// Parameters of the component for a cubic equation of state or
+    /// PC-SAFT's ideal gas part.
+    pub fn eos_parameters(&self) -> Result<EOSParameters, &'static str> {
+        Ok(EOSParameters {
+            critical_temperature: self.critical_temperature,
+            critical_pressure: self.critical_pressure,
+            acentric_factor: self.acentric_factor,
+            molar_mass: self.molar_mass,
+            heat_capacity: self
+                .heat_capacity
+                .clone()
+                .ok_or("the component has no heat capacity in the database")?,
+        })
+    }
+
+    /// Parameters of the component for a gamma-phi model or Raoult's law.
+    pub fn liquid_parameters(&self) -> Result<LiquidParameters, &'static str> {
+        Ok(LiquidParameters {
+            antoine: self
+                .antoine
+                .ok_or("the component has no vapor pressure correlation in the database")?,
+            molar_mass: self.molar_mass,
+            heat_capacity: self
+                .heat_capacity
+                .clone()
+                .ok_or("the component has no heat capacity in the database")?,
+        })
+    }
+}
+
+/// # ComponentDatabase
+///
+/// A table of pure component constants.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentDatabase {
+    records: Vec<ComponentRecord>,
+}
+
+/// A number that may be left empty when it is not known.
+fn optional<T: FromStr>(field: &str) -> Result<Option<T>, &'static str> {
+    if field.is_empty() {
+        Ok(None)
+    } else {
+        number(field).map(Some)
+    }
+}
+
+/// Optional coefficients that are given either all together or not at all.
+fn coefficients(fields: &[&str]) -> Result<Option<Vec<f64>>, &'static str> {
+    if fields.iter().all(|field| field.is_empty()) {
+        return Ok(None);
+    }
+    fields
+        .iter()
+        .map(|field| number(field))
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+impl ComponentDatabase {
+    /// Reads a database from CSV text. Each record is
+    /// 'name,formula,cas,pubchem_cid,molar_mass,normal_boiling_point,
+    /// critical_temperature,critical_pressure,critical_volume,
+    /// critical_compressibility,acentric_factor,cp_a,cp_b,cp_c,cp_d,
+    /// antoine_a,antoine_b,antoine_c', in g/mol, K, bar and cm^3/mol. The
+    /// heat capacity is 'cp_a + cp_b T + cp_c T^2 + cp_d T^3' in J/(mol K),
+    /// and the Antoine equation gives ln(P / Pa). The critical volume and
+    /// compressibility, the heat capacity and the Antoine coefficients may
+    /// be left empty.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut database = ComponentDatabase::default();
+        for record in records(text) {
+            if record.len() != 18 {
+                return Err("a component record needs eighteen fields");
+            }
+            let antoine = coefficients(&record[15..18])?.map(|c| AntoineCoefficients {
+                a: c[0],
+                b: c[1],
+                c: c[2],
+            });
+            database.records.push(ComponentRecord {
+                name: String::from(record[0]),
+                formula: String::from(record[1]),
+                cas: String::from(record[2]),
+                pubchem_cid: number(record[3])?,
+                molar_mass: MolarMass::new::<gram_per_mole>(number(record[4])?),
+                normal_boiling_point: ThermodynamicTemperature::new::<kelvin>(number(record[5])?),
+                critical_temperature: ThermodynamicTemperature::new::<kelvin>(number(record[6])?),
+                critical_pressure: Pressure::new::<bar>(number(record[7])?),
+                critical_volume: optional(record[8])?
+                    .map(MolarVolume::new::<cubic_centimeter_per_mole>),
+                critical_compressibility: optional(record[9])?,
+                acentric_factor: number(record[10])?,
+                heat_capacity: coefficients(&record[11..15])?
+                    .map(HeatCapacityCoefficients::Polynomial),
+                antoine,
+            });
+        }
+        Ok(database)
+    }
+
+    /// The database bundled with the crate.
+    pub fn bundled() -> &'static ComponentDatabase {
+        &BUNDLED_COMPONENTS
+    }
+
+    /// All records, in the order they were read.
+    pub fn records(&self) -> &[ComponentRecord] {
+        &self.records
+    }
+
+    /// Finds a component by its name or CAS number, ignoring case.
+    pub fn by_name(&self, name: &str) -> Option<&ComponentRecord> {
+        let name = name.trim();
+        self.records
+            .iter()
+            .find(|record| record.name.eq_ignore_ascii_case(name) || record.cas == name)
+    }
+
+    /// Finds a component by its PubChem compound identifier.
+    pub fn by_pubchem_cid(&self, cid: u32) -> Option<&ComponentRecord> {
+        self.records.iter().find(|record| record.pubchem_cid == cid)
+    }
+}
+
+#[cfg(test)]
+mod component_database_tests {
+    use super::*;
+    use crate::thermodynamics::constants::UNIVERSAL_GAS_CONSTANT;
+    use crate::thermodynamics::eos_models::{CubicEquation, CubicModel};
+    use crate::thermodynamics::{Phase, PhaseEquilibrium};
+    use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+    use uom::si::pressure::pascal;
+
+    #[test]
+    fn bundled_records_are_consistent() {
+        let database = ComponentDatabase::bundled();
+        assert!(database.records().len() >= 300);
+        for record in database.records() {
+            let tc = record.critical_temperature.get::<kelvin>();
+            assert!(
+                record.normal_boiling_point.get::<kelvin>() < tc,
+                "{}",
+                record.name
+            );
+
+            // Zc = Pc Vc / (R Tc)
+            if let (Some(volume), Some(zc)) =
+                (record.critical_volume, record.critical_compressibility)
+            {
+                let computed = (record.critical_pressure * volume
+                    / (UNIVERSAL_GAS_CONSTANT * record.critical_temperature))
+                    .value;
+                assert!(
+                    (computed - zc).abs() < 0.01,
+                    "{}: Zc {computed}",
+                    record.name
+                );
+            }
+
+            // The vapor pressure at the normal boiling point is one atmosphere
+            if let Some(antoine) = record.antoine {
+                let pressure = antoine
+                    .vapor_pressure(record.normal_boiling_point)
+                    .get::<pascal>();
+                assert!(
+                    (pressure / 101325.0 - 1.0).abs() < 0.1,
+                    "{}: {pressure} Pa",
+                    record.name
+                );
+            }
+
+            // Ideal gas heat capacities at 298 K are above (3/2) R
+            if let Some(cp) = &record.heat_capacity {
+                let cp = cp
+                    .heat_capacity(ThermodynamicTemperature::new::<kelvin>(298.15))
+                    .get::<joule_per_kelvin_mole>();
+                assert!(cp > 20.0 && cp < 400.0, "{}: Cp {cp}", record.name);
+            }
+        }
+    }
+
+    #[test]
+    fn components_are_found_by_name_cas_and_cid() {
+        let database = ComponentDatabase::bundled();
+        let benzene = database.by_name("Benzene").unwrap();
+        assert_eq!(benzene.formula, "C6H6");
+        assert_eq!(database.by_name("71-43-2"), Some(benzene));
+        assert_eq!(database.by_pubchem_cid(241), Some(benzene));
+        assert!(database.by_name("unobtainium").is_none());
+        assert!(database.by_pubchem_cid(0).is_none());
+        let dioxane = database.by_name("1,4-dioxane").unwrap();
+        assert_eq!(dioxane.formula, "C4H8O2");
+        assert!(ComponentDatabase::parse("methane,CH4,74-82-8").is_err());
+    }
+
+    #[test]
+    fn records_give_model_parameters() {
+        let database = ComponentDatabase::bundled();
+        let parameters = ["methane", "propane"]
+            .iter()
+            .map(|name| database.by_name(name).unwrap().eos_parameters().unwrap())
+            .collect();
+        let model = CubicEquation::new(
+            CubicModel::PengRobinson,
+            vec![String::from("methane"), String::from("propane")],
+            parameters,
+        )
+        .unwrap();
+        let ln_phi = model
+            .ln_fugacity_coefficients(
+                ThermodynamicTemperature::new::<kelvin>(300.0),
+                Pressure::new::<bar>(10.0),
+                &[0.5, 0.5],
+                Phase::Vapor,
+            )
+            .unwrap();
+        assert!(ln_phi.iter().all(|value| *value < 0.0));
+
+        let water = database
+            .by_name("water")
+            .unwrap()
+            .liquid_parameters()
+            .unwrap();
+        assert!(water.antoine.a > 20.0);
+        assert!(database
+            .by_name("methyl tert-butyl ether")
+            .unwrap()
+            .liquid_parameters()
+            .is_err());
+    }
+}
@@ -1,32 +1,331 @@
-///# Pure Species Properties
-/// This sub-module will contain the information regarding the cheracteristic properties of pure
-/// species. This would include the molar mass (molecular weight), accentric factor, critical
-/// temperature, critical pressure, critical compressibility, critical molar volume, and normal
-/// boiling point.
-
-#[warn(unused_imports)]
-use crate::properties::*;
+//!# Pure Species Properties
+//! This sub-module will contain the information regarding the cheracteristic properties of pure
+//! species. This would include the molar mass (molecular weight), accentric factor, critical
+//! temperature, critical pressure, critical compressibility, critical molar volume, and normal
+//! boiling point. They are fetched from any 'oscps_db::PropertyStore'.
+
+use crate::thermodynamics::activity_models::unifac::{UNIFACGroups, UNIFACVariant};
+use crate::thermodynamics::activity_models::AntoineCoefficients;
+use crate::thermodynamics::eos_models::pc_saft::PCSAFTParameters;
+use crate::thermodynamics::heat_capacity::HeatCapacityCoefficients;
+use super::fetch_heat_capacity;
+use oscps_db::{PropertySet, PropertyStore, PureSpeciesRecord};
 use uom::si::f64;
-use std::sync::Arc;
-// use oscps_db;
+use uom::si::length::angstrom;
+use uom::si::molar_mass::kilogram_per_mole;
+use uom::si::molar_volume::cubic_meter_per_mole;
+use uom::si::pressure::pascal;
+use uom::si::ratio::ratio;
+use uom::si::thermodynamic_temperature::kelvin;
 
 ///#PureSpeciesProperties
 ///
 ///This will contain all the important properties for pure species
 pub struct PureSpeciesProperties {
-    pub species_obj_id: Arc<f64>,
+    /// Row id of the species in the property store
+    pub species_obj_id: i64,
+    /// Name of the species
+    pub name: String,
+    /// Antoine equation constants '[A, B, C]', empty when they are unknown
     pub antoine_equation_constants: Vec<f64::Ratio>,
+    /// Critical temperature
     pub critical_temperature: f64::ThermodynamicTemperature,
+    /// Critical pressure
+    pub critical_pressure: f64::Pressure,
+    /// Molar mass
     pub molar_mass: f64::MolarMass,
+    /// Boiling point at one atmosphere
     pub normal_boiling_point: f64::ThermodynamicTemperature,
-    pub critical_molar_volume: f64::Volume,
+    /// Critical molar volume
+    pub critical_molar_volume: f64::MolarVolume,
+    /// Acentric factor
     pub accentric_factor: f64::Ratio,
-    pub compressibility_factor: f64::Ratio
+    /// Compressibility factor at the critical point
+    pub compressibility_factor: f64::Ratio,
+    /// Segment number, segment diameter and energy parameters for PC-SAFT,
+    /// for species that have them
+    pub pc_saft_parameters: Option<PCSAFTParameters>,
+    /// Decomposition into UNIFAC subgroups, for species that have one
+    pub unifac_groups: Option<UNIFACGroups>,
 }
 
 ///Functions to pull pure species properties from the database
     // Database will need to handle API calls to external sources for information currently in the
     // database.
 impl PureSpeciesProperties {
-    
-} 
+    /// Fetches the properties of a species from a property store, returning
+    /// `None` when the store does not have the species. PC-SAFT parameters
+    /// are read from the 'pc_saft' property set of the species, whose
+    /// coefficients are '[m, sigma, epsilon / k]' with sigma in angstrom and
+    /// epsilon / k in K, followed by '[epsilon_AB / k, kappa_AB, donors,
+    /// acceptors]' for associating species. UNIFAC groups are read from the
+    /// 'unifac_groups' property sets, saved by [`save_unifac_groups`].
+    ///
+    /// [`save_unifac_groups`]: PureSpeciesProperties::save_unifac_groups
+    pub fn fetch(store: &dyn PropertyStore, name: &str) -> anyhow::Result<Option<Self>> {
+        let record = match store.pure_species(name)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let id = record.id;
+        let mut properties = PureSpeciesProperties::from(record);
+        if let Some(id) = id {
+            if let Some(set) = store.property_sets(id, "pc_saft")?.into_iter().next() {
+                let heat_capacity = fetch_heat_capacity(store, id)?.ok_or_else(|| {
+                    anyhow::anyhow!("{name} has PC-SAFT parameters but no ideal gas heat capacity")
+                })?;
+                properties.pc_saft_parameters =
+                    Some(pc_saft_parameters(&set, properties.molar_mass, heat_capacity)?);
+            }
+            properties.unifac_groups = unifac_groups(store.property_sets(id, "unifac_groups")?)?;
+        }
+        Ok(Some(properties))
+    }
+
+    /// Saves the UNIFAC group decompositions of a species as its
+    /// 'unifac_groups' property sets, one for each variant, with the
+    /// correlation 'original' or 'dortmund' and the coefficients
+    /// '[subgroup, count, subgroup, count, ...]'. Decompositions saved
+    /// before are replaced.
+    pub fn save_unifac_groups(
+        store: &dyn PropertyStore,
+        species_id: i64,
+        groups: &UNIFACGroups,
+        source: &str,
+    ) -> anyhow::Result<()> {
+        let saved = store.property_sets(species_id, "unifac_groups")?;
+        for (variant, correlation) in UNIFAC_VARIANTS {
+            let id = saved
+                .iter()
+                .find(|set| set.correlation == correlation)
+                .and_then(|set| set.id);
+            let coefficients = groups
+                .decomposition(variant)
+                .iter()
+                .flat_map(|(subgroup, count)| [*subgroup as f64, *count as f64])
+                .collect();
+            store.save_property_set(&PropertySet {
+                id,
+                species_id,
+                property: String::from("unifac_groups"),
+                correlation: String::from(correlation),
+                coefficients,
+                source: String::from(source),
+                minimum_temperature: None,
+                maximum_temperature: None,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Antoine coefficients of the species, stored as '[A, B, C]' for
+    /// 'ln(P / Pa) = A - B / (T / K + C)'
+    pub fn antoine_coefficients(&self) -> Result<AntoineCoefficients, &'static str> {
+        match self.antoine_equation_constants.as_slice() {
+            [a, b, c] => Ok(AntoineCoefficients {
+                a: a.value,
+                b: b.value,
+                c: c.value,
+            }),
+            _ => Err("the species needs three Antoine equation constants"),
+        }
+    }
+}
+
+/// Names of the UNIFAC variants in the 'unifac_groups' property sets
+const UNIFAC_VARIANTS: [(UNIFACVariant, &str); 2] = [
+    (UNIFACVariant::Original, "original"),
+    (UNIFACVariant::Dortmund, "dortmund"),
+];
+
+/// UNIFAC groups from the 'unifac_groups' property sets of a species, or
+/// `None` when it has none.
+fn unifac_groups(sets: Vec<PropertySet>) -> anyhow::Result<Option<UNIFACGroups>> {
+    if sets.is_empty() {
+        return Ok(None);
+    }
+    let mut groups = UNIFACGroups::default();
+    for set in sets {
+        if set.coefficients.len() % 2 != 0 {
+            anyhow::bail!("UNIFAC group sets have pairs of a subgroup and a count");
+        }
+        let decomposition = set
+            .coefficients
+            .chunks(2)
+            .map(|pair| (pair[0] as usize, pair[1] as usize))
+            .collect();
+        match UNIFAC_VARIANTS.iter().find(|(_, name)| *name == set.correlation) {
+            Some((UNIFACVariant::Original, _)) => groups.original = decomposition,
+            Some((UNIFACVariant::Dortmund, _)) => groups.dortmund = decomposition,
+            None => anyhow::bail!("{} is not a UNIFAC variant", set.correlation),
+        }
+    }
+    Ok(Some(groups))
+}
+
+/// PC-SAFT parameters from the coefficients of a 'pc_saft' property set.
+fn pc_saft_parameters(
+    set: &PropertySet,
+    molar_mass: f64::MolarMass,
+    heat_capacity: HeatCapacityCoefficients,
+) -> anyhow::Result<PCSAFTParameters> {
+    let (segments, association) = match set.coefficients.as_slice() {
+        [m, sigma, epsilon] => ([*m, *sigma, *epsilon], [0.0; 4]),
+        [m, sigma, epsilon, energy, volume, donors, acceptors] => {
+            ([*m, *sigma, *epsilon], [*energy, *volume, *donors, *acceptors])
+        }
+        _ => anyhow::bail!("PC-SAFT parameter sets have three or seven coefficients"),
+    };
+    Ok(PCSAFTParameters {
+        segment_number: segments[0],
+        segment_diameter: f64::Length::new::<angstrom>(segments[1]),
+        dispersion_energy: f64::ThermodynamicTemperature::new::<kelvin>(segments[2]),
+        association_energy: f64::ThermodynamicTemperature::new::<kelvin>(association[0]),
+        association_volume: association[1],
+        association_sites: (association[2] as usize, association[3] as usize),
+        molar_mass,
+        heat_capacity,
+    })
+}
+
+impl From<PureSpeciesRecord> for PureSpeciesProperties {
+    fn from(record: PureSpeciesRecord) -> Self {
+        let antoine = match (record.antoine_a, record.antoine_b, record.antoine_c) {
+            (Some(a), Some(b), Some(c)) => vec![a, b, c],
+            _ => Vec::new(),
+        };
+        PureSpeciesProperties {
+            species_obj_id: record.id.unwrap_or_default(),
+            name: record.name,
+            antoine_equation_constants: antoine.into_iter().map(f64::Ratio::new::<ratio>).collect(),
+            critical_temperature: f64::ThermodynamicTemperature::new::<kelvin>(record.critical_temperature),
+            critical_pressure: f64::Pressure::new::<pascal>(record.critical_pressure),
+            molar_mass: f64::MolarMass::new::<kilogram_per_mole>(record.molar_mass),
+            normal_boiling_point: f64::ThermodynamicTemperature::new::<kelvin>(record.normal_boiling_point),
+            critical_molar_volume: f64::MolarVolume::new::<cubic_meter_per_mole>(record.critical_volume),
+            accentric_factor: f64::Ratio::new::<ratio>(record.acentric_factor),
+            compressibility_factor: f64::Ratio::new::<ratio>(record.critical_compressibility),
+            pc_saft_parameters: None,
+            unifac_groups: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod pure_species_properties_tests {
+    use super::*;
+    use crate::thermodynamics::eos_models::pc_saft::PCSAFT;
+    use oscps_db::sqlite_db::SqliteDB;
+    use uom::si::pressure::bar;
+
+    fn water() -> PureSpeciesRecord {
+        PureSpeciesRecord {
+            id: None,
+            name: String::from("water"),
+            cas: Some(String::from("7732-18-5")),
+            formula: Some(String::from("H2O")),
+            smiles: Some(String::from("O")),
+            pubchem_cid: Some(962),
+            user_defined: false,
+            molar_mass: 0.018015,
+            normal_boiling_point: 373.15,
+            critical_temperature: 647.14,
+            critical_pressure: 220.64e5,
+            critical_volume: 55.95e-6,
+            critical_compressibility: 0.229,
+            acentric_factor: 0.344,
+            antoine_a: Some(23.1964),
+            antoine_b: Some(3816.44),
+            antoine_c: Some(-46.13),
+        }
+    }
+
+    fn property_set(
+        species_id: i64,
+        property: &str,
+        correlation: &str,
+        coefficients: Vec<f64>,
+    ) -> PropertySet {
+        PropertySet {
+            id: None,
+            species_id,
+            property: String::from(property),
+            correlation: String::from(correlation),
+            coefficients,
+            source: String::from("Gross and Sadowski (2002)"),
+            minimum_temperature: None,
+            maximum_temperature: None,
+        }
+    }
+
+    #[test]
+    fn properties_are_fetched_from_a_store() {
+        let store = SqliteDB::in_memory().unwrap();
+        let id = store.save_pure_species(&water()).unwrap();
+
+        let water = PureSpeciesProperties::fetch(&store, "water").unwrap().unwrap();
+        assert_eq!(water.species_obj_id, id);
+        assert!((water.critical_pressure.get::<bar>() - 220.64).abs() < 1e-9);
+        let boiling = water
+            .antoine_coefficients()
+            .unwrap()
+            .vapor_pressure(water.normal_boiling_point);
+        assert!((boiling.get::<pascal>() / 101325.0 - 1.0).abs() < 0.01);
+        assert!(water.pc_saft_parameters.is_none());
+        assert!(PureSpeciesProperties::fetch(&store, "steam").unwrap().is_none());
+    }
+
+    #[test]
+    fn pc_saft_parameters_are_fetched_from_a_store() {
+        let store = SqliteDB::in_memory().unwrap();
+        let id = store.save_pure_species(&water()).unwrap();
+        let parameters = vec![1.0656, 3.0007, 366.51, 2500.7, 0.034868, 1.0, 1.0];
+        store
+            .save_property_set(&property_set(id, "pc_saft", "gross_sadowski", parameters))
+            .unwrap();
+        assert!(PureSpeciesProperties::fetch(&store, "water").is_err());
+
+        let heat_capacity = property_set(id, "ideal_gas_heat_capacity", "polynomial", vec![33.6]);
+        store.save_property_set(&heat_capacity).unwrap();
+        let water = PureSpeciesProperties::fetch(&store, "water").unwrap().unwrap();
+        let parameters = water.pc_saft_parameters.as_ref().unwrap();
+        assert_eq!(parameters.segment_number, 1.0656);
+        assert_eq!(parameters.segment_diameter.get::<angstrom>(), 3.0007);
+        assert_eq!(parameters.association_energy.get::<kelvin>(), 2500.7);
+        assert_eq!(parameters.association_sites, (1, 1));
+        assert_eq!(parameters.molar_mass, water.molar_mass);
+
+        let equation = PCSAFT::from_properties(std::slice::from_ref(&water)).unwrap();
+        assert_eq!(equation.parameters(), std::slice::from_ref(parameters));
+        let propane = PureSpeciesProperties::from(PureSpeciesRecord {
+            name: String::from("propane"),
+            ..self::water()
+        });
+        assert!(PCSAFT::from_properties(&[water, propane]).is_err());
+    }
+
+    #[test]
+    fn unifac_groups_round_trip() {
+        let store = SqliteDB::in_memory().unwrap();
+        let id = store.save_pure_species(&water()).unwrap();
+        let mut groups = UNIFACGroups {
+            original: vec![(16, 1)],
+            dortmund: vec![(16, 1)],
+        };
+        let source = "Hansen et al. (1991)";
+        PureSpeciesProperties::save_unifac_groups(&store, id, &groups, source).unwrap();
+        let water = PureSpeciesProperties::fetch(&store, "water").unwrap().unwrap();
+        assert_eq!(water.unifac_groups, Some(groups.clone()));
+
+        // Saving again replaces the decompositions
+        groups.original = vec![(1, 1), (2, 1), (14, 1)];
+        PureSpeciesProperties::save_unifac_groups(&store, id, &groups, source).unwrap();
+        assert_eq!(store.property_sets(id, "unifac_groups").unwrap().len(), 2);
+        let water = PureSpeciesProperties::fetch(&store, "water").unwrap().unwrap();
+        assert_eq!(water.unifac_groups, Some(groups));
+
+        let unknown = property_set(id, "unifac_groups", "lyngby", vec![16.0, 1.0]);
+        store.save_property_set(&unknown).unwrap();
+        assert!(PureSpeciesProperties::fetch(&store, "water").is_err());
+    }
+}
@@ -1,9 +1,26 @@
 //! # Simulation
 //!
-//! Allows for the construction of a simulation object. TODO: Implement this.
+//! Allows for the construction of a simulation object, and for solving the
+//! flowsheet it describes with the sequential-modular approach: blocks are
+//! executed one at a time, in an order where every block runs after all of
+//! the blocks feeding it. Recycle loops are broken by tear streams, which are
+//! iterated on until they converge. Alternatively, the flowsheet can be solved
+//! in equation-oriented mode, where the equations of every block are solved
+//! simultaneously.
 
-use crate::blocks::{Block, Mixer};
-use crate::stream::Stream;
+/// Convergence methods for tear streams
+pub mod convergence;
+/// Simultaneous solution of the flowsheet equations
+pub mod equation_oriented;
+/// Block ordering and tear stream selection
+pub mod tearing;
+
+use crate::blocks::{Block, Flash, Flash3, Mixer, Separator, Sink, Source, Splitter};
+use crate::stream::{Stream, StreamThermoState};
+use crate::thermodynamics::property_package::PropertyPackage;
+use convergence::{Accelerator, ConvergenceMethod};
+use equation_oriented::{BlockEquations, FlowsheetSystem, StreamSlot};
+use tearing::StreamEdge;
 // use std::collections::HashMap;
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
@@ -11,6 +28,9 @@ use std::sync::{Arc, RwLock};
 pub type BlockReference = Arc<RwLock<Box<dyn Block + Send + Sync>>>;
 /// An Arc, RwLock, Box reference for threadsafe Stream interactions.
 pub type StreamReference = Arc<RwLock<Box<Stream>>>;
+/// The equations of a flowsheet, the initial values of its variables, and
+/// the offset and length of each stream's variables by stream id.
+type EquationSystem = (FlowsheetSystem, Vec<f64>, BTreeMap<u64, (usize, usize)>);
 
 /// Used to tell functions what type of block to add.
 pub enum BlockType {
@@ -20,6 +40,14 @@ pub enum BlockType {
     Source,
     /// Sink for streams.
     Sink,
+    /// Separate the components of a stream into several output streams.
+    Separator,
+    /// Divide a stream into several streams of the same composition.
+    Splitter,
+    /// Separate a stream into vapor and liquid at equilibrium.
+    Flash,
+    /// Separate a stream into vapor and two liquids at equilibrium.
+    Flash3,
 }
 
 // fn compute_outlet_phase_fractions(&self) {
@@ -34,25 +62,108 @@ pub enum BlockType {
 
 // }
 
+/// Smallest fraction of its calculated value that a tear stream temperature
+/// or pressure may fall to in one accelerated step.
+const MIN_CONDITION_FRACTION: f64 = 0.5;
+
+/// The approaches available for solving a flowsheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolverMode {
+    /// Execute the blocks one at a time, iterating on tear streams to
+    /// converge recycle loops.
+    #[default]
+    SequentialModular,
+    /// Solve the equations of every block simultaneously with Newton's method.
+    EquationOriented,
+}
+
 /// A struct for storing settings of the simulation
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Settings {
-    // Add fields as needed
+    /// Approach used to solve the flowsheet
+    pub solver_mode: SolverMode,
+    /// Method used to converge the tear streams of recycle loops
+    pub convergence_method: ConvergenceMethod,
+    /// Maximum number of passes through the flowsheet when converging
+    /// recycles, or of Newton iterations in equation-oriented mode
+    pub max_iterations: usize,
+    /// Relative tolerance on the tear stream variables, or on the Newton step
+    /// in equation-oriented mode
+    pub tolerance: f64,
+    /// Lower and upper bounds on the Wegstein acceleration factor
+    pub wegstein_bounds: (f64, f64),
+    /// Property package used by every block that is not given its own
+    pub property_package: Option<Arc<dyn PropertyPackage>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            solver_mode: SolverMode::default(),
+            convergence_method: ConvergenceMethod::default(),
+            max_iterations: 100,
+            tolerance: 1e-6,
+            wegstein_bounds: (-5.0, 0.0),
+            property_package: None,
+        }
+    }
+}
+
+/// The convergence status of a single block after a solve.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum BlockStatus {
+    /// The block has not been executed since the last solve began.
+    #[default]
+    NotSolved,
+    /// The block executed successfully.
+    Converged,
+    /// The block failed to execute, with the reason given by the block.
+    Failed(String),
 }
 
 /// A struct for storing the current state of the simulation
 #[derive(Debug, Clone, Default)]
 pub struct SimulationState {
-    // Add fields as needed
+    /// Convergence status of each block, keyed by block ID.
+    pub block_status: BTreeMap<u64, BlockStatus>,
+    /// The order in which blocks were executed during the last solve.
+    pub calculation_order: Vec<u64>,
+    /// The streams torn to break recycle loops during the last solve.
+    pub tear_streams: Vec<u64>,
+    /// Number of passes made through the flowsheet, or of Newton iterations,
+    /// during the last solve.
+    pub iterations: usize,
+    /// Whether the tear streams converged during the last solve.
+    pub tears_converged: bool,
 }
 
 impl SimulationState {
     /// Create a new SimulationState.
     pub fn new() -> Self {
-        return SimulationState {};
+        SimulationState::default()
+    }
+
+    /// Returns true if every block and every tear stream converged during
+    /// the last solve.
+    pub fn is_converged(&self) -> bool {
+        self.tears_converged
+            && !self.block_status.is_empty()
+            && self
+                .block_status
+                .values()
+                .all(|status| *status == BlockStatus::Converged)
     }
 }
 
+/// A block port that needs a stream but has none connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LooseEnd {
+    /// The block with the given ID is missing an inlet stream
+    Inlet(u64),
+    /// The block with the given ID is missing an outlet stream
+    Outlet(u64),
+}
+
 /// An enum used to represent errors.
 #[derive(Debug)]
 pub enum Err {
@@ -64,13 +175,20 @@ pub enum Err {
     BlockExists,
     /// Error when a connector with a matching ID is already in the simulation
     ConnectorExists,
+    /// Error when a block fails to execute during a solve
+    BlockFailed(u64),
+    /// Error when the tear streams do not converge within the iteration limit
+    NotConverged,
+    /// Error when blocks are missing streams. Sources and sinks can be
+    /// inserted at these loose ends with
+    /// [`Simulation::insert_sources_and_sinks`].
+    LooseEnds(Vec<LooseEnd>),
     /// Any other error
     Other(String),
 }
 
 /// The Simulation struct stores information pertaining to blocks and streams
 #[derive(Default)]
-#[allow(dead_code)]
 pub struct Simulation {
     /// Stores all the blocks in the simulation
     blocks: BTreeMap<u64, BlockReference>,
@@ -95,42 +213,438 @@ impl Simulation {
 
     /// Adds a block to the simulation and returns the ID of
     /// the block.
-    #[allow(dead_code)]
     pub fn add_block(&mut self, block: BlockType) -> u64 {
+        match block {
+            BlockType::Mixer => self.insert_block(Box::new(Mixer::new())),
+            BlockType::Source => self.insert_block(Box::new(Source::new())),
+            BlockType::Sink => self.insert_block(Box::new(Sink::new())),
+            BlockType::Separator => self.insert_block(Box::new(Separator::new())),
+            BlockType::Splitter => self.insert_block(Box::new(Splitter::new())),
+            BlockType::Flash => self.insert_block(Box::new(Flash::new())),
+            BlockType::Flash3 => self.insert_block(Box::new(Flash3::new())),
+        }
+    }
+
+    /// Adds an already configured block to the simulation and returns the ID
+    /// of the block.
+    pub fn insert_block(&mut self, block: Box<dyn Block + Send + Sync>) -> u64 {
         // Start with a block id of 1.
         let mut id = 1;
         while self.blocks.contains_key(&id) {
             id += 1;
         }
+        self.blocks.insert(id, Arc::new(RwLock::new(block)));
+        id
+    }
 
-        match block {
-            BlockType::Mixer => {
-                self.blocks
-                    .insert(id, Arc::new(RwLock::new(Box::new(Mixer::new()))));
+    /// Adds a stream to the simulation, connects it to the outlet of `from`
+    /// and the inlet of `to`, and returns the ID of the stream.
+    pub fn add_stream(&mut self, from: BlockReference, to: BlockReference) -> Result<u64, Err> {
+        // Start with a stream ID of 1.
+        let mut id = 1;
+        while self.streams.contains_key(&id) {
+            id += 1;
+        }
+        let stream: StreamReference =
+            Arc::new(RwLock::new(Box::new(Stream::new(from.clone(), to.clone()))));
+
+        connect(&from, |block| block.connect_output(stream.clone()))?;
+        if let Err(error) = connect(&to, |block| block.connect_input(stream.clone())) {
+            let _ = connect(&from, |block| block.disconnect_output(&stream));
+            return Err(error);
+        }
+        self.streams.insert(id, stream);
+        Ok(id)
+    }
+
+    /// Returns a reference to the block with the given ID.
+    pub fn get_block(&self, id: u64) -> Result<BlockReference, Err> {
+        self.blocks.get(&id).cloned().ok_or(Err::BlockNotFound)
+    }
+
+    /// Returns a reference to the stream with the given ID.
+    pub fn get_stream(&self, id: u64) -> Result<StreamReference, Err> {
+        self.streams.get(&id).cloned().ok_or(Err::ConnectorNotFound)
+    }
+
+    /// Specifies the material fed by the source block with the given ID.
+    pub fn set_feed(&mut self, id: u64, feed: StreamThermoState) -> Result<(), Err> {
+        connect(&self.get_block(id)?, |block| block.set_feed(feed))
+    }
+
+    /// Lists the block ports that need a stream but have none connected, with
+    /// one entry for each open port.
+    pub fn loose_ends(&self) -> Result<Vec<LooseEnd>, Err> {
+        let mut loose_ends = Vec::new();
+        for (id, block) in &self.blocks {
+            let block = block
+                .read()
+                .map_err(|_| Err::Other(String::from("block lock poisoned")))?;
+            if block.has_open_inlet() {
+                loose_ends.push(LooseEnd::Inlet(*id));
+            }
+            for _ in 0..block.open_outlets() {
+                loose_ends.push(LooseEnd::Outlet(*id));
+            }
+        }
+        Ok(loose_ends)
+    }
+
+    /// Closes every loose end of the flowsheet: a source is connected to each
+    /// open inlet and a sink to each open outlet. Returns the IDs of the
+    /// inserted blocks. The feeds of the new sources still need to be
+    /// specified with [`Simulation::set_feed`].
+    pub fn insert_sources_and_sinks(&mut self) -> Result<Vec<u64>, Err> {
+        let mut inserted = Vec::new();
+        for loose_end in self.loose_ends()? {
+            let (id, from, to) = match loose_end {
+                LooseEnd::Inlet(id) => {
+                    let source = self.add_block(BlockType::Source);
+                    (source, self.get_block(source)?, self.get_block(id)?)
+                }
+                LooseEnd::Outlet(id) => {
+                    let sink = self.add_block(BlockType::Sink);
+                    (sink, self.get_block(id)?, self.get_block(sink)?)
+                }
+            };
+            self.add_stream(from, to)?;
+            inserted.push(id);
+        }
+        Ok(inserted)
+    }
+
+    /// Returns the state of the simulation after the last solve.
+    pub fn state(&self) -> &SimulationState {
+        &self.state
+    }
+
+    /// Returns the property package used by blocks without their own.
+    pub fn property_package(&self) -> Option<&Arc<dyn PropertyPackage>> {
+        self.settings.property_package.as_ref()
+    }
+
+    /// Sets the property package used by blocks without their own.
+    pub fn set_property_package(&mut self, package: Option<Arc<dyn PropertyPackage>>) {
+        self.settings.property_package = package;
+    }
+
+    /// Gives the block with the given ID its own property package, in place
+    /// of the default of the simulation. Passing 'None' returns the block to
+    /// the default. A model given to the block directly still takes
+    /// precedence over either package.
+    pub fn set_block_property_package(
+        &mut self,
+        id: u64,
+        package: Option<Arc<dyn PropertyPackage>>,
+    ) -> Result<(), Err> {
+        let model = package.map(|package| package.model());
+        connect(&self.get_block(id)?, |block| {
+            let models = block
+                .package_models()
+                .ok_or("block does not use a thermodynamic model")?;
+            models.block = model;
+            Ok(())
+        })
+    }
+
+    /// Passes the model of the default property package on to every block.
+    fn apply_property_package(&self) -> Result<(), Err> {
+        let model = self
+            .settings
+            .property_package
+            .as_ref()
+            .map(|package| package.model());
+        for block in self.blocks.values() {
+            let mut block = block
+                .write()
+                .map_err(|_| Err::Other(String::from("block lock poisoned")))?;
+            if let Some(models) = block.package_models() {
+                models.simulation = model.clone();
             }
-            BlockType::Source => {
-                todo!()
+        }
+        Ok(())
+    }
+
+    /// Finds the ID of a block from a reference to it.
+    fn block_id(&self, block: &BlockReference) -> Option<u64> {
+        self.blocks
+            .iter()
+            .find(|(_, candidate)| Arc::ptr_eq(candidate, block))
+            .map(|(id, _)| *id)
+    }
+
+    /// Describes every stream as an edge between block IDs.
+    fn stream_edges(&self) -> Result<Vec<StreamEdge>, Err> {
+        let mut edges = Vec::with_capacity(self.streams.len());
+        for (id, stream) in &self.streams {
+            let stream = stream
+                .read()
+                .map_err(|_| Err::Other(String::from("stream lock poisoned")))?;
+            edges.push(StreamEdge {
+                stream: *id,
+                from: self.block_id(&stream.from).ok_or(Err::BlockNotFound)?,
+                to: self.block_id(&stream.to).ok_or(Err::BlockNotFound)?,
+            });
+        }
+        Ok(edges)
+    }
+
+    /// Selects the streams to tear so that the flowsheet has no recycle loops.
+    /// Returns an empty vector for flowsheets without recycles.
+    pub fn tear_streams(&self) -> Result<Vec<u64>, Err> {
+        let blocks: Vec<u64> = self.blocks.keys().copied().collect();
+        Ok(tearing::select_tear_streams(&blocks, &self.stream_edges()?))
+    }
+
+    /// Determines the order in which the blocks must be executed, so that
+    /// every block runs after the blocks feeding it, ignoring tear streams.
+    /// Ties are broken by block ID so the order is repeatable.
+    pub fn calculation_order(&self) -> Result<Vec<u64>, Err> {
+        let tears = self.tear_streams()?;
+        let blocks: Vec<u64> = self.blocks.keys().copied().collect();
+        let edges: Vec<StreamEdge> = self
+            .stream_edges()?
+            .into_iter()
+            .filter(|edge| !tears.contains(&edge.stream))
+            .collect();
+        tearing::topological_order(&blocks, &edges).ok_or_else(|| {
+            Err::Other(String::from(
+                "tear streams did not break every recycle loop",
+            ))
+        })
+    }
+
+    /// Solves the flowsheet with the approach selected in the settings.
+    /// Fails without solving if any block is missing a stream. Blocks
+    /// without a thermodynamic model of their own use the property package
+    /// of the settings.
+    pub fn solve(&mut self) -> Result<(), Err> {
+        let loose_ends = self.loose_ends()?;
+        if !loose_ends.is_empty() {
+            return Err(Err::LooseEnds(loose_ends));
+        }
+        self.apply_property_package()?;
+        match self.settings.solver_mode {
+            SolverMode::SequentialModular => self.solve_sequential_modular(),
+            SolverMode::EquationOriented => self.solve_equation_oriented(),
+        }
+    }
+
+    /// Solves the flowsheet with the sequential-modular approach. The blocks
+    /// are executed in calculation order and their convergence status is
+    /// recorded in the simulation state. For flowsheets with recycle loops,
+    /// passes are repeated until the tear streams converge, using the method
+    /// and limits given in the settings. Execution stops at the first block
+    /// that fails.
+    fn solve_sequential_modular(&mut self) -> Result<(), Err> {
+        let tears = self.tear_streams()?;
+        let order = self.calculation_order()?;
+        self.state = SimulationState {
+            block_status: order
+                .iter()
+                .map(|id| (*id, BlockStatus::NotSolved))
+                .collect(),
+            calculation_order: order.clone(),
+            tear_streams: tears.clone(),
+            iterations: 0,
+            tears_converged: false,
+        };
+
+        let mut accelerator = Accelerator::new(&self.settings);
+        let mut guess = self.tear_variables(&tears)?;
+        while self.state.iterations < self.settings.max_iterations.max(1) {
+            self.state.iterations += 1;
+            self.execute_pass(&order)?;
+
+            let calculated = self.tear_variables(&tears)?;
+            let calculated_flat: Vec<f64> = calculated.iter().flatten().copied().collect();
+            let guess_flat: Vec<f64> = guess.iter().flatten().copied().collect();
+            if convergence::is_converged(&guess_flat, &calculated_flat, self.settings.tolerance) {
+                self.state.tears_converged = true;
+                return Ok(());
             }
-            BlockType::Sink => {
-                todo!()
+
+            // A pass may change the number of variables on a tear stream, for
+            // example the first time material reaches it. Restart the
+            // acceleration from the calculated values in that case.
+            let next_flat = if guess_flat.len() == calculated_flat.len() {
+                accelerator.next(&guess_flat, &calculated_flat)
+            } else {
+                accelerator.reset();
+                calculated_flat
+            };
+            let mut next_flat = next_flat.into_iter();
+            guess = calculated
+                .iter()
+                .map(|stream| next_flat.by_ref().take(stream.len()).collect())
+                .collect();
+            bound_step(&calculated, &mut guess);
+            self.set_tear_variables(&tears, &guess)?;
+        }
+        Err(Err::NotConverged)
+    }
+
+    /// Solves the flowsheet in equation-oriented mode. Sequential passes
+    /// provide the initial values of the stream variables, then the
+    /// residual equations of all blocks are solved together with Newton's
+    /// method. A final pass through the blocks updates the remaining stream
+    /// properties and records the status of each block.
+    fn solve_equation_oriented(&mut self) -> Result<(), Err> {
+        let order = self.calculation_order()?;
+        self.state = SimulationState {
+            block_status: order
+                .iter()
+                .map(|id| (*id, BlockStatus::NotSolved))
+                .collect(),
+            calculation_order: order.clone(),
+            ..SimulationState::default()
+        };
+
+        self.initialize_streams(&order)?;
+        let (mut system, initial, offsets) = self.equation_system()?;
+        let (solution, iterations) =
+            equation_oriented::newton(&mut system, initial, &self.settings)?;
+        self.state.iterations = iterations;
+        for (id, (offset, len)) in offsets {
+            self.streams[&id]
+                .write()
+                .map_err(|_| Err::Other(String::from("stream lock poisoned")))?
+                .set_variables(&solution[offset..offset + len])
+                .map_err(|reason| Err::Other(format!("stream {}: {}", id, reason)))?;
+        }
+
+        self.execute_pass(&order)?;
+        self.state.tears_converged = true;
+        Ok(())
+    }
+
+    /// Assembles the residual equations of every block into one system,
+    /// with the current stream variables as its initial values.
+    fn equation_system(&self) -> Result<EquationSystem, Err> {
+        let mut offsets = BTreeMap::new();
+        let mut initial = Vec::new();
+        for (id, stream) in &self.streams {
+            let stream = stream
+                .read()
+                .map_err(|_| Err::Other(String::from("stream lock poisoned")))?;
+            let variables = stream.variables();
+            offsets.insert(*id, (initial.len(), variables.len()));
+            initial.extend(variables);
+        }
+
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        let edges = self.stream_edges()?;
+        for (id, block) in &self.blocks {
+            let streams = edges
+                .iter()
+                .filter(|edge| edge.from == *id || edge.to == *id)
+                .map(|edge| {
+                    let (offset, len) = offsets[&edge.stream];
+                    StreamSlot {
+                        stream: self.streams[&edge.stream].clone(),
+                        offset,
+                        len,
+                    }
+                })
+                .collect();
+            blocks.push(BlockEquations {
+                id: *id,
+                block: block.clone(),
+                streams,
+            });
+        }
+
+        Ok((FlowsheetSystem::new(blocks), initial, offsets))
+    }
+
+    /// Executes every block once, in the given order.
+    fn execute_pass(&mut self, order: &[u64]) -> Result<(), Err> {
+        for id in order {
+            let block = self.get_block(*id)?;
+            let mut block = block
+                .write()
+                .map_err(|_| Err::Other(String::from("block lock poisoned")))?;
+            let status = match block.execute() {
+                Ok(()) => BlockStatus::Converged,
+                Err(reason) => BlockStatus::Failed(reason.to_string()),
+            };
+            let failed = status != BlockStatus::Converged;
+            self.state.block_status.insert(*id, status);
+            if failed {
+                return Err(Err::BlockFailed(*id));
             }
         }
+        Ok(())
+    }
 
-        return id;
+    /// Executes the blocks in calculation order until every stream carries
+    /// material, giving the initial values of an equation-oriented solve.
+    /// Blocks that fail, such as those waiting on a recycle stream, do not
+    /// stop the pass and are retried on the next one. Fails with the first
+    /// block that failed on the last pass if streams are still left without
+    /// values.
+    fn initialize_streams(&mut self, order: &[u64]) -> Result<(), Err> {
+        let mut failed = None;
+        for _ in 0..order.len().max(1) {
+            failed = None;
+            for id in order {
+                if self.execute_pass(&[*id]).is_err() && failed.is_none() {
+                    failed = Some(*id);
+                }
+            }
+            let missing = self.streams_without_values()?;
+            if missing.is_empty() {
+                return Ok(());
+            }
+            if failed.is_none() {
+                return Err(Err::Other(format!(
+                    "streams {:?} have no initial values",
+                    missing
+                )));
+            }
+        }
+        Err(Err::BlockFailed(failed.unwrap_or_default()))
     }
 
-    /// Adds a stream to the simulation and returns the ID of
-    /// the stream.
-    #[allow(dead_code)]
-    pub fn add_stream(&mut self, from: BlockReference, to: BlockReference) -> u64 {
-        // Start with a stream ID of 1.
-        let mut id = 1;
-        while self.streams.contains_key(&id) {
-            id += 1;
+    /// Ids of the streams that carry no material.
+    fn streams_without_values(&self) -> Result<Vec<u64>, Err> {
+        let mut missing = Vec::new();
+        for (id, stream) in &self.streams {
+            let stream = stream
+                .read()
+                .map_err(|_| Err::Other(String::from("stream lock poisoned")))?;
+            if stream.thermo.is_none() {
+                missing.push(*id);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Reads the variables of each tear stream.
+    fn tear_variables(&self, tears: &[u64]) -> Result<Vec<Vec<f64>>, Err> {
+        tears
+            .iter()
+            .map(|id| {
+                let stream = self.streams.get(id).ok_or(Err::ConnectorNotFound)?;
+                let stream = stream
+                    .read()
+                    .map_err(|_| Err::Other(String::from("stream lock poisoned")))?;
+                Ok(stream.variables())
+            })
+            .collect()
+    }
+
+    /// Overwrites the variables of each tear stream.
+    fn set_tear_variables(&self, tears: &[u64], variables: &[Vec<f64>]) -> Result<(), Err> {
+        for (id, variables) in tears.iter().zip(variables) {
+            let stream = self.streams.get(id).ok_or(Err::ConnectorNotFound)?;
+            stream
+                .write()
+                .map_err(|_| Err::Other(String::from("stream lock poisoned")))?
+                .set_variables(variables)
+                .map_err(|reason| Err::Other(format!("stream {}: {}", id, reason)))?;
         }
-        self.streams
-            .insert(id, Arc::new(RwLock::new(Box::new(Stream::new(from, to)))));
-        return id;
+        Ok(())
     }
 
     // /// Add a block to the simulation.
@@ -168,3 +682,596 @@ impl Simulation {
     //         Ok(())
     //     }
 }
+
+/// Shortens the accelerated step from the tear stream variables calculated by
+/// a pass to the next guess, so that no molar flow becomes negative and no
+/// temperature or pressure falls below [`MIN_CONDITION_FRACTION`] of its
+/// calculated value. The whole step is scaled, keeping its direction. The
+/// variables of each stream follow the layout of
+/// [`crate::stream::Stream::variables`].
+fn bound_step(calculated: &[Vec<f64>], guess: &mut [Vec<f64>]) {
+    let lower_bound = |stream: &[f64], index: usize| {
+        if index + 2 < stream.len() {
+            0.0
+        } else {
+            MIN_CONDITION_FRACTION * stream[index]
+        }
+    };
+    let mut length: f64 = 1.0;
+    for (calculated, guess) in calculated.iter().zip(guess.iter()) {
+        for (index, (g, x)) in calculated.iter().zip(guess).enumerate() {
+            let lower = lower_bound(calculated, index);
+            if *x < lower {
+                length = length.min((g - lower) / (g - x));
+            }
+        }
+    }
+    if length >= 1.0 {
+        return;
+    }
+    for (calculated, guess) in calculated.iter().zip(guess.iter_mut()) {
+        for (index, (g, x)) in calculated.iter().zip(guess.iter_mut()).enumerate() {
+            *x = (g + length * (*x - g)).max(lower_bound(calculated, index));
+        }
+    }
+}
+
+/// Runs a port operation on a block, converting its error for the simulation.
+fn connect(
+    block: &BlockReference,
+    operation: impl FnOnce(&mut Box<dyn Block + Send + Sync>) -> Result<(), &str>,
+) -> Result<(), Err> {
+    let mut block = block
+        .write()
+        .map_err(|_| Err::Other(String::from("block lock poisoned")))?;
+    operation(&mut block).map_err(|error| Err::Other(error.to_string()))
+}
+
+#[cfg(test)]
+mod simulation_tests {
+    use super::*;
+
+    /// A block that passes the material of its first inlet on to all of its
+    /// outlets, used to exercise the solver without a thermodynamic model.
+    #[derive(Default)]
+    struct Relay {
+        inputs: Vec<StreamReference>,
+        outputs: Vec<StreamReference>,
+    }
+
+    impl Block for Relay {
+        fn connect_input(&mut self, stream: StreamReference) -> Result<(), &str> {
+            self.inputs.push(stream);
+            Ok(())
+        }
+
+        fn disconnect_input(&mut self, stream: &StreamReference) -> Result<(), &str> {
+            self.inputs.retain(|input| !Arc::ptr_eq(input, stream));
+            Ok(())
+        }
+
+        fn connect_output(&mut self, stream: StreamReference) -> Result<(), &str> {
+            self.outputs.push(stream);
+            Ok(())
+        }
+
+        fn disconnect_output(&mut self, stream: &StreamReference) -> Result<(), &str> {
+            self.outputs.retain(|output| !Arc::ptr_eq(output, stream));
+            Ok(())
+        }
+
+        fn execute(&mut self) -> Result<(), &str> {
+            let thermo = match self.inputs.first() {
+                Some(input) => input.read().unwrap().thermo.clone(),
+                None => None,
+            };
+            for output in &self.outputs {
+                output.write().unwrap().thermo = thermo.clone();
+            }
+            Ok(())
+        }
+    }
+
+    /// Builds a simulation of relay blocks connected by the given (from, to)
+    /// pairs.
+    fn relay_flowsheet(block_count: u64, links: &[(u64, u64)]) -> Simulation {
+        let mut simulation = Simulation::new(Settings::default());
+        for id in 1..=block_count {
+            simulation
+                .blocks
+                .insert(id, Arc::new(RwLock::new(Box::new(Relay::default()))));
+        }
+        for (from, to) in links {
+            let from = simulation.get_block(*from).unwrap();
+            let to = simulation.get_block(*to).unwrap();
+            simulation.add_stream(from, to).unwrap();
+        }
+        simulation
+    }
+
+    #[test]
+    fn streams_connect_block_ports() {
+        let mut simulation = Simulation::new(Settings::default());
+        let first = simulation.add_block(BlockType::Mixer);
+        let second = simulation.add_block(BlockType::Mixer);
+        let from = simulation.get_block(first).unwrap();
+        let to = simulation.get_block(second).unwrap();
+        assert_eq!(simulation.add_stream(from.clone(), to.clone()).unwrap(), 1);
+        // A mixer has a single outlet.
+        assert!(simulation.add_stream(from, to).is_err());
+        assert_eq!(simulation.streams.len(), 1);
+    }
+
+    #[test]
+    fn calculation_order_follows_streams() {
+        let simulation = relay_flowsheet(4, &[(3, 1), (1, 4), (2, 4)]);
+        assert_eq!(simulation.calculation_order().unwrap(), vec![2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn solve_reports_block_status() {
+        let mut simulation = relay_flowsheet(3, &[(2, 3), (3, 1)]);
+        assert!(!simulation.state().is_converged());
+
+        simulation.solve().unwrap();
+        assert_eq!(simulation.state().calculation_order, vec![2, 3, 1]);
+        assert!(simulation.state().is_converged());
+        assert_eq!(
+            simulation.state().block_status.get(&1),
+            Some(&BlockStatus::Converged)
+        );
+        assert!(simulation.state().tear_streams.is_empty());
+    }
+
+    #[test]
+    fn recycle_loops_are_torn() {
+        // Streams: 1 (3 -> 1), 2 (1 -> 4), 3 (2 -> 4), 4 (4 -> 2)
+        let mut simulation = relay_flowsheet(4, &[(3, 1), (1, 4), (2, 4), (4, 2)]);
+        assert_eq!(simulation.tear_streams().unwrap(), vec![3]);
+        assert_eq!(simulation.calculation_order().unwrap(), vec![3, 1, 4, 2]);
+
+        simulation.solve().unwrap();
+        assert!(simulation.state().is_converged());
+        assert_eq!(simulation.state().tear_streams, vec![3]);
+    }
+
+    #[test]
+    fn accelerated_steps_keep_flows_positive() {
+        use crate::stream::MolarFlowRate;
+        use uom::si::catalytic_activity::katal;
+        use uom::si::f64::{Pressure, ThermodynamicTemperature};
+        use uom::si::pressure::pascal;
+        use uom::si::thermodynamic_temperature::kelvin;
+
+        /// Adds a feed of 1 mol/s to its inlet and recycles 't^2 / (t + 4)'
+        /// of the total 't', which converges to a recycle of 1/3 mol/s.
+        /// Fails on negative flows, as property models do.
+        #[derive(Default)]
+        struct Recycler {
+            input: Option<StreamReference>,
+            output: Option<StreamReference>,
+        }
+
+        impl Block for Recycler {
+            fn connect_input(&mut self, stream: StreamReference) -> Result<(), &str> {
+                self.input = Some(stream);
+                Ok(())
+            }
+
+            fn disconnect_input(&mut self, _stream: &StreamReference) -> Result<(), &str> {
+                self.input = None;
+                Ok(())
+            }
+
+            fn connect_output(&mut self, stream: StreamReference) -> Result<(), &str> {
+                self.output = Some(stream);
+                Ok(())
+            }
+
+            fn disconnect_output(&mut self, _stream: &StreamReference) -> Result<(), &str> {
+                self.output = None;
+                Ok(())
+            }
+
+            fn execute(&mut self) -> Result<(), &str> {
+                let inlet = self.input.as_ref().unwrap().read().unwrap().thermo.clone();
+                let recycled = inlet.map_or(0.0, |thermo| thermo.molar_flows[0].get::<katal>());
+                if recycled < 0.0 {
+                    return Err("negative molar flow");
+                }
+                let total = 1.0 + recycled;
+                self.output.as_ref().unwrap().write().unwrap().thermo =
+                    Some(StreamThermoState::from_molar_flows(
+                        vec![String::from("water")],
+                        vec![MolarFlowRate::new::<katal>(total * total / (total + 4.0))],
+                        ThermodynamicTemperature::new::<kelvin>(300.0),
+                        Pressure::new::<pascal>(101_325.0),
+                    )?);
+                Ok(())
+            }
+        }
+
+        // Starting from a recycle of 10 mol/s, the first Wegstein step
+        // extrapolates to a negative flow.
+        let mut simulation = relay_flowsheet(1, &[]);
+        let recycler = simulation.insert_block(Box::new(Recycler::default()));
+        for (from, to) in [(recycler, 1), (1, recycler)] {
+            let from = simulation.get_block(from).unwrap();
+            let to = simulation.get_block(to).unwrap();
+            let stream = simulation.add_stream(from, to).unwrap();
+            simulation
+                .get_stream(stream)
+                .unwrap()
+                .write()
+                .unwrap()
+                .thermo = Some(
+                StreamThermoState::from_molar_flows(
+                    vec![String::from("water")],
+                    vec![MolarFlowRate::new::<katal>(10.0)],
+                    ThermodynamicTemperature::new::<kelvin>(300.0),
+                    Pressure::new::<pascal>(101_325.0),
+                )
+                .unwrap(),
+            );
+        }
+        simulation.solve().unwrap();
+        assert!(simulation.state().is_converged());
+        for stream in simulation.streams.values() {
+            let thermo = stream.read().unwrap().thermo.clone().unwrap();
+            assert!((thermo.molar_flows[0].get::<katal>() - 1.0 / 3.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn equation_oriented_mode_solves() {
+        use crate::blocks::SplitSpec;
+        use crate::stream::MolarFlowRate;
+        use crate::thermodynamics::activity_models::{AntoineCoefficients, LiquidParameters};
+        use crate::thermodynamics::ideal_models::RaoultsLaw;
+        use crate::thermodynamics::property_package::FluidPackage;
+        use uom::si::catalytic_activity::katal;
+        use uom::si::f64::{MolarHeatCapacity, MolarMass, Pressure, ThermodynamicTemperature};
+        use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+        use uom::si::molar_mass::gram_per_mole;
+        use uom::si::pressure::pascal;
+        use uom::si::thermodynamic_temperature::kelvin;
+
+        let feed = StreamThermoState::from_molar_flows(
+            vec![String::from("water")],
+            vec![MolarFlowRate::new::<katal>(2.0)],
+            ThermodynamicTemperature::new::<kelvin>(300.0),
+            Pressure::new::<pascal>(101_325.0),
+        )
+        .unwrap();
+        // Half of the mixed stream leaves as product and the rest is
+        // recycled to the mixer
+        let mut simulation = Simulation::new(Settings {
+            solver_mode: SolverMode::EquationOriented,
+            ..Settings::default()
+        });
+        let source = simulation.add_block(BlockType::Source);
+        let mixer = simulation.add_block(BlockType::Mixer);
+        let splitter = simulation.insert_block(Box::new(Splitter {
+            specs: vec![SplitSpec::Fraction(0.5), SplitSpec::Remaining],
+            ..Splitter::new()
+        }));
+        let sink = simulation.add_block(BlockType::Sink);
+        let connect = |simulation: &mut Simulation, from: u64, to: u64| {
+            let from = simulation.get_block(from).unwrap();
+            let to = simulation.get_block(to).unwrap();
+            simulation.add_stream(from, to).unwrap()
+        };
+        connect(&mut simulation, source, mixer);
+        connect(&mut simulation, mixer, splitter);
+        let product = connect(&mut simulation, splitter, sink);
+        let recycle = connect(&mut simulation, splitter, mixer);
+        simulation.set_feed(source, feed).unwrap();
+        let flow = |simulation: &Simulation, stream: u64| {
+            let stream = simulation.get_stream(stream).unwrap();
+            let thermo = stream.read().unwrap().thermo.clone().unwrap();
+            thermo.total_molar_flow().get::<katal>()
+        };
+
+        // The mixer has no model for its energy balance, so the streams
+        // after it are left without initial values
+        assert!(matches!(simulation.solve(), Err(Err::BlockFailed(_))));
+        assert!(matches!(
+            simulation.state().block_status[&mixer],
+            BlockStatus::Failed(_)
+        ));
+
+        let water = LiquidParameters {
+            antoine: AntoineCoefficients {
+                a: 23.1964,
+                b: 3816.44,
+                c: -46.13,
+            },
+            molar_mass: MolarMass::new::<gram_per_mole>(18.015),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(75.3).into(),
+        };
+        let model = RaoultsLaw::new(vec![String::from("water")], vec![water]).unwrap();
+        simulation.set_property_package(Some(Arc::new(FluidPackage::new(
+            "Raoult's law",
+            Arc::new(model),
+        ))));
+        // The initial values recycle half of the feed, and Newton's method
+        // has to move the recycle to the whole feed
+        simulation.solve().unwrap();
+        assert!(simulation.state().is_converged());
+        assert!(simulation.state().iterations > 1);
+        assert!((flow(&simulation, recycle) - 2.0).abs() < 1e-9);
+        assert!((flow(&simulation, product) - 2.0).abs() < 1e-9);
+
+        // Streams that never carry material cannot be solved for
+        let mut simulation = relay_flowsheet(4, &[(3, 1), (1, 4), (2, 4), (4, 2)]);
+        simulation.settings.solver_mode = SolverMode::EquationOriented;
+        assert!(matches!(simulation.solve(), Err(Err::Other(_))));
+    }
+
+    #[test]
+    fn equation_oriented_jacobian_matches_central_differences() {
+        use crate::blocks::{FlashSpec, SplitSpec};
+        use crate::stream::MolarFlowRate;
+        use crate::thermodynamics::activity_models::{AntoineCoefficients, LiquidParameters};
+        use crate::thermodynamics::ideal_models::RaoultsLaw;
+        use crate::thermodynamics::property_package::FluidPackage;
+        use equation_oriented::NonlinearSystem;
+        use uom::si::catalytic_activity::katal;
+        use uom::si::f64::{MolarHeatCapacity, MolarMass, Pressure, ThermodynamicTemperature};
+        use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+        use uom::si::molar_mass::gram_per_mole;
+        use uom::si::pressure::pascal;
+        use uom::si::thermodynamic_temperature::kelvin;
+
+        let parameters = |a: f64, b: f64, c: f64| LiquidParameters {
+            antoine: AntoineCoefficients { a, b, c },
+            molar_mass: MolarMass::new::<gram_per_mole>(85.0),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(90.0).into(),
+        };
+        let components = vec![String::from("benzene"), String::from("toluene")];
+        let model = RaoultsLaw::new(
+            components.clone(),
+            vec![
+                parameters(20.7936, 2788.51, -52.36),
+                parameters(20.9065, 3096.52, -53.67),
+            ],
+        )
+        .unwrap();
+        let temperature = ThermodynamicTemperature::new::<kelvin>(370.0);
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let feed = StreamThermoState::from_molar_flows(
+            components,
+            vec![MolarFlowRate::new::<katal>(1.0); 2],
+            temperature,
+            pressure,
+        )
+        .unwrap();
+
+        // The liquid of a flash is split, and part of it is recycled to
+        // the mixer ahead of the flash
+        let mut simulation = Simulation::new(Settings {
+            solver_mode: SolverMode::EquationOriented,
+            ..Settings::default()
+        });
+        let source = simulation.add_block(BlockType::Source);
+        let mixer = simulation.add_block(BlockType::Mixer);
+        let flash = simulation.insert_block(Box::new(Flash {
+            spec: Some(FlashSpec::TemperaturePressure(temperature, pressure)),
+            ..Flash::new()
+        }));
+        let splitter = simulation.insert_block(Box::new(Splitter {
+            specs: vec![SplitSpec::Fraction(0.4), SplitSpec::Remaining],
+            ..Splitter::new()
+        }));
+        let vapor_sink = simulation.add_block(BlockType::Sink);
+        let liquid_sink = simulation.add_block(BlockType::Sink);
+        for (from, to) in [
+            (source, mixer),
+            (mixer, flash),
+            (flash, vapor_sink),
+            (flash, splitter),
+            (splitter, liquid_sink),
+            (splitter, mixer),
+        ] {
+            let from = simulation.get_block(from).unwrap();
+            let to = simulation.get_block(to).unwrap();
+            simulation.add_stream(from, to).unwrap();
+        }
+        simulation.set_property_package(Some(Arc::new(FluidPackage::new(
+            "Raoult's law",
+            Arc::new(model),
+        ))));
+        simulation.set_feed(source, feed).unwrap();
+        simulation.solve().unwrap();
+
+        // Away from the solution, so that no residual is at a special point
+        let (mut system, mut x, _) = simulation.equation_system().unwrap();
+        for (i, value) in x.iter_mut().enumerate() {
+            *value *= 1.0 + 0.01 * (i % 3) as f64;
+        }
+        let (residuals, jacobian) = system.jacobian(&x).unwrap();
+        for column in 0..x.len() {
+            let step = 1e-6 * x[column].abs().max(1.0);
+            let shifted = |system: &mut FlowsheetSystem, step: f64| {
+                let mut x = x.clone();
+                x[column] += step;
+                system.residuals(&x).unwrap()
+            };
+            let more = shifted(&mut system, step);
+            let less = shifted(&mut system, -step);
+            for row in 0..residuals.len() {
+                let central = (more[row] - less[row]) / (2.0 * step);
+                let entry = jacobian.get(row, column);
+                assert!(
+                    (entry - central).abs() < 1e-6 * central.abs().max(1.0),
+                    "row {row} column {column}: {entry} != {central}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn loose_ends_are_closed_with_sources_and_sinks() {
+        let mut simulation = Simulation::new(Settings::default());
+        let mixer = simulation.add_block(BlockType::Mixer);
+        assert_eq!(
+            simulation.loose_ends().unwrap(),
+            vec![LooseEnd::Inlet(mixer), LooseEnd::Outlet(mixer)]
+        );
+        assert!(matches!(simulation.solve(), Err(Err::LooseEnds(_))));
+
+        assert_eq!(simulation.insert_sources_and_sinks().unwrap(), vec![2, 3]);
+        assert!(simulation.loose_ends().unwrap().is_empty());
+        assert_eq!(simulation.calculation_order().unwrap(), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn sources_feed_sinks() {
+        use crate::stream::MolarFlowRate;
+        use uom::si::catalytic_activity::katal;
+        use uom::si::f64::{Pressure, ThermodynamicTemperature};
+        use uom::si::pressure::pascal;
+        use uom::si::thermodynamic_temperature::kelvin;
+
+        let feed = StreamThermoState::from_molar_flows(
+            vec![String::from("water")],
+            vec![MolarFlowRate::new::<katal>(2.0)],
+            ThermodynamicTemperature::new::<kelvin>(300.0),
+            Pressure::new::<pascal>(101_325.0),
+        )
+        .unwrap();
+        for mode in [SolverMode::SequentialModular, SolverMode::EquationOriented] {
+            let mut simulation = Simulation::new(Settings {
+                solver_mode: mode,
+                ..Settings::default()
+            });
+            let source = simulation.add_block(BlockType::Source);
+            let sink = simulation.add_block(BlockType::Sink);
+            let stream = simulation
+                .add_stream(
+                    simulation.get_block(source).unwrap(),
+                    simulation.get_block(sink).unwrap(),
+                )
+                .unwrap();
+            assert!(simulation.set_feed(sink, feed.clone()).is_err());
+            simulation.set_feed(source, feed.clone()).unwrap();
+
+            simulation.solve().unwrap();
+            assert!(simulation.state().is_converged());
+            let stream = simulation.get_stream(stream).unwrap();
+            assert_eq!(stream.read().unwrap().thermo, Some(feed.clone()));
+        }
+    }
+
+    #[test]
+    fn blocks_use_the_property_package() {
+        use crate::blocks::FlashSpec;
+        use crate::stream::MolarFlowRate;
+        use crate::thermodynamics::activity_models::{AntoineCoefficients, LiquidParameters};
+        use crate::thermodynamics::ideal_models::RaoultsLaw;
+        use crate::thermodynamics::property_package::FluidPackage;
+        use uom::si::catalytic_activity::katal;
+        use uom::si::f64::{MolarHeatCapacity, MolarMass, Pressure, ThermodynamicTemperature};
+        use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+        use uom::si::molar_mass::gram_per_mole;
+        use uom::si::pressure::pascal;
+        use uom::si::ratio::ratio;
+        use uom::si::thermodynamic_temperature::kelvin;
+
+        // Benzene and toluene, with the vapor pressures of the second
+        // package raised so that the feed boils off completely
+        let package = |name: &str, shift: f64| -> Arc<dyn PropertyPackage> {
+            let parameters = |a: f64, b: f64, c: f64| LiquidParameters {
+                antoine: AntoineCoefficients { a: a + shift, b, c },
+                molar_mass: MolarMass::new::<gram_per_mole>(85.0),
+                heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(90.0).into(),
+            };
+            let model = RaoultsLaw::new(
+                vec![String::from("benzene"), String::from("toluene")],
+                vec![
+                    parameters(20.7936, 2788.51, -52.36),
+                    parameters(20.9065, 3096.52, -53.67),
+                ],
+            )
+            .unwrap();
+            Arc::new(FluidPackage::new(name, Arc::new(model)))
+        };
+        let temperature = ThermodynamicTemperature::new::<kelvin>(370.0);
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let feed = StreamThermoState::from_molar_flows(
+            vec![String::from("benzene"), String::from("toluene")],
+            vec![MolarFlowRate::new::<katal>(1.0); 2],
+            temperature,
+            pressure,
+        )
+        .unwrap();
+
+        let mut simulation = Simulation::new(Settings::default());
+        let flash = simulation.insert_block(Box::new(Flash {
+            spec: Some(FlashSpec::TemperaturePressure(temperature, pressure)),
+            ..Flash::new()
+        }));
+        let inserted = simulation.insert_sources_and_sinks().unwrap();
+        assert_eq!(inserted.len(), 3);
+        assert!(simulation.loose_ends().unwrap().is_empty());
+        simulation.set_feed(inserted[0], feed).unwrap();
+        // Flow of the flash outlets leaving as vapor and as liquid
+        let outlet_flows = |simulation: &Simulation| {
+            let (mut vapor, mut liquid) = (0.0, 0.0);
+            for stream in simulation.streams.values() {
+                let stream = stream.read().unwrap();
+                if simulation.block_id(&stream.from) != Some(flash) {
+                    continue;
+                }
+                let thermo = stream.thermo.clone().unwrap();
+                let flow = thermo.total_molar_flow().get::<katal>();
+                if thermo.vapor_fraction.unwrap().get::<ratio>() == 1.0 {
+                    vapor += flow;
+                } else {
+                    liquid += flow;
+                }
+            }
+            (vapor, liquid)
+        };
+
+        assert!(matches!(simulation.solve(), Err(Err::BlockFailed(id)) if id == flash));
+
+        simulation.set_property_package(Some(package("Raoult's law", 0.0)));
+        assert_eq!(
+            simulation.property_package().unwrap().name(),
+            "Raoult's law"
+        );
+        simulation.solve().unwrap();
+        assert!(simulation.state().is_converged());
+        let (vapor, liquid) = outlet_flows(&simulation);
+        assert!(vapor > 0.0 && liquid > 0.0);
+        assert!((vapor + liquid - 2.0).abs() < 1e-9);
+
+        simulation
+            .set_block_property_package(flash, Some(package("Volatile", 2.0)))
+            .unwrap();
+        simulation.solve().unwrap();
+        assert!(outlet_flows(&simulation).1.abs() < 1e-12);
+
+        simulation.set_block_property_package(flash, None).unwrap();
+        simulation.solve().unwrap();
+        assert!(outlet_flows(&simulation).1 > 0.0);
+
+        // A model given to the block directly outlasts its property package
+        connect(&simulation.get_block(flash).unwrap(), |block| {
+            block.set_thermo_model(Some(package("Volatile", 2.0).model()))
+        })
+        .unwrap();
+        simulation
+            .set_block_property_package(flash, Some(package("Raoult's law", 0.0)))
+            .unwrap();
+        simulation.solve().unwrap();
+        assert!(outlet_flows(&simulation).1.abs() < 1e-12);
+        simulation.set_block_property_package(flash, None).unwrap();
+        simulation.solve().unwrap();
+        assert!(outlet_flows(&simulation).1.abs() < 1e-12);
+        assert!(simulation
+            .set_block_property_package(inserted[0], Some(package("Source", 0.0)))
+            .is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! # Convergence
+//!
+//! Methods for converging the tear streams of recycle loops. Each pass
+//! through the flowsheet maps the tear stream variables `x` to new values
+//! `g(x)`; the recycle is converged once `x` is a fixed point of `g`.
+
+use crate::simulation::Settings;
+
+/// The methods available for converging tear streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConvergenceMethod {
+    /// Use the values calculated by the last pass as the next guess.
+    DirectSubstitution,
+    /// Bounded Wegstein acceleration, applied to each variable separately.
+    #[default]
+    Wegstein,
+    /// Broyden's quasi-Newton method on the residual `g(x) - x`.
+    Broyden,
+}
+
+/// Returns true if every variable calculated by a pass is within the relative
+/// tolerance of its guessed value. Variables smaller than one are compared on
+/// an absolute basis.
+pub fn is_converged(guess: &[f64], calculated: &[f64], tolerance: f64) -> bool {
+    guess.len() == calculated.len()
+        && guess
+            .iter()
+            .zip(calculated)
+            .all(|(x, g)| (g - x).abs() <= tolerance * x.abs().max(1.0))
+}
+
+/// # Accelerator
+///
+/// Produces the next guess for the tear stream variables from the last guess
+/// and the values calculated from it. Keeps whatever history the selected
+/// method needs between passes.
+#[derive(Debug, Clone)]
+pub struct Accelerator {
+    method: ConvergenceMethod,
+    wegstein_bounds: (f64, f64),
+    /// The guess and calculated values from the previous pass
+    previous: Option<(Vec<f64>, Vec<f64>)>,
+    /// Broyden's approximation of the inverse Jacobian of `g(x) - x`
+    inverse_jacobian: Vec<Vec<f64>>,
+}
+
+impl Accelerator {
+    /// Create a new accelerator using the method and bounds in the settings.
+    pub fn new(settings: &Settings) -> Self {
+        Accelerator {
+            method: settings.convergence_method,
+            wegstein_bounds: settings.wegstein_bounds,
+            previous: None,
+            inverse_jacobian: Vec::new(),
+        }
+    }
+
+    /// Forget the history of previous passes. The next step will be a direct
+    /// substitution step.
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.inverse_jacobian.clear();
+    }
+
+    /// Calculates the next guess from the current guess `x` and the values
+    /// `g` calculated from it.
+    pub fn next(&mut self, x: &[f64], g: &[f64]) -> Vec<f64> {
+        if self
+            .previous
+            .as_ref()
+            .is_some_and(|(previous_x, _)| previous_x.len() != x.len())
+        {
+            self.reset();
+        }
+        let next = match (self.method, &self.previous) {
+            (ConvergenceMethod::DirectSubstitution, _) | (_, None) => g.to_vec(),
+            (ConvergenceMethod::Wegstein, Some((previous_x, previous_g))) => {
+                self.wegstein_step(x, g, previous_x, previous_g)
+            }
+            (ConvergenceMethod::Broyden, Some((previous_x, previous_g))) => {
+                let (previous_x, previous_g) = (previous_x.clone(), previous_g.clone());
+                self.broyden_step(x, g, &previous_x, &previous_g)
+            }
+        };
+        self.previous = Some((x.to_vec(), g.to_vec()));
+        next
+    }
+
+    /// Wegstein's method estimates the slope of `g` for each variable from
+    /// the last two passes and extrapolates along it. The acceleration factor
+    /// is bounded to keep the method stable.
+    fn wegstein_step(
+        &self,
+        x: &[f64],
+        g: &[f64],
+        previous_x: &[f64],
+        previous_g: &[f64],
+    ) -> Vec<f64> {
+        let (q_min, q_max) = self.wegstein_bounds;
+        x.iter()
+            .zip(g)
+            .zip(previous_x.iter().zip(previous_g))
+            .map(|((x, g), (previous_x, previous_g))| {
+                let dx = x - previous_x;
+                let q = if dx.abs() > f64::EPSILON * x.abs().max(1.0) {
+                    let slope = (g - previous_g) / dx;
+                    (slope / (slope - 1.0)).clamp(q_min, q_max)
+                } else {
+                    0.0
+                };
+                q * x + (1.0 - q) * g
+            })
+            .collect()
+    }
+
+    /// Broyden's method solves `f(x) = g(x) - x = 0`, starting from an
+    /// inverse Jacobian of `-I` (a direct substitution step) and refining it
+    /// with a rank-one update after each pass.
+    fn broyden_step(
+        &mut self,
+        x: &[f64],
+        g: &[f64],
+        previous_x: &[f64],
+        previous_g: &[f64],
+    ) -> Vec<f64> {
+        let n = x.len();
+        if self.inverse_jacobian.len() != n {
+            self.inverse_jacobian = (0..n)
+                .map(|i| (0..n).map(|j| if i == j { -1.0 } else { 0.0 }).collect())
+                .collect();
+        }
+        let f: Vec<f64> = g.iter().zip(x).map(|(g, x)| g - x).collect();
+        let dx: Vec<f64> = x.iter().zip(previous_x).map(|(x, p)| x - p).collect();
+        let df: Vec<f64> = f
+            .iter()
+            .zip(previous_g.iter().zip(previous_x))
+            .map(|(f, (g, x))| f - (g - x))
+            .collect();
+
+        let h = &self.inverse_jacobian;
+        let h_df: Vec<f64> = h.iter().map(|row| dot(row, &df)).collect();
+        let dx_h: Vec<f64> = (0..n)
+            .map(|j| (0..n).map(|i| dx[i] * h[i][j]).sum())
+            .collect();
+        let denominator = dot(&dx_h, &df);
+        if denominator.abs() > f64::EPSILON {
+            for (row, (dx, h_df)) in self.inverse_jacobian.iter_mut().zip(dx.iter().zip(&h_df)) {
+                let factor = (dx - h_df) / denominator;
+                for (h, dx_h) in row.iter_mut().zip(&dx_h) {
+                    *h += factor * dx_h;
+                }
+            }
+        }
+
+        x.iter()
+            .zip(&self.inverse_jacobian)
+            .map(|(x, row)| x - dot(row, &f))
+            .collect()
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(test)]
+mod convergence_tests {
+    use super::*;
+
+    /// A coupled linear fixed-point problem with solution (2, 3).
+    fn g(x: &[f64]) -> Vec<f64> {
+        vec![0.5 * x[0] + 0.2 * x[1] + 0.4, 0.3 * x[0] + 0.6 * x[1] + 0.6]
+    }
+
+    fn iterations_to_converge(method: ConvergenceMethod) -> usize {
+        let settings = Settings {
+            convergence_method: method,
+            ..Settings::default()
+        };
+        let mut accelerator = Accelerator::new(&settings);
+        let mut x = vec![0.0, 0.0];
+        for iteration in 1..=settings.max_iterations {
+            let calculated = g(&x);
+            if is_converged(&x, &calculated, settings.tolerance) {
+                assert!((x[0] - 2.0).abs() < 1e-4 && (x[1] - 3.0).abs() < 1e-4);
+                return iteration;
+            }
+            x = accelerator.next(&x, &calculated);
+        }
+        panic!("{:?} did not converge", method);
+    }
+
+    #[test]
+    fn all_methods_converge() {
+        let direct = iterations_to_converge(ConvergenceMethod::DirectSubstitution);
+        let wegstein = iterations_to_converge(ConvergenceMethod::Wegstein);
+        let broyden = iterations_to_converge(ConvergenceMethod::Broyden);
+        assert!(wegstein < direct);
+        assert!(broyden < direct);
+    }
+
+    #[test]
+    fn convergence_check_is_relative() {
+        assert!(is_converged(&[1.0e5, 0.1], &[1.00005e5, 0.1000001], 1e-3));
+        assert!(!is_converged(&[1.0e5], &[1.01e5], 1e-6));
+        assert!(!is_converged(&[1.0], &[1.0, 2.0], 1e-6));
+    }
+}
@@ -0,0 +1,359 @@
+//! # Equation Oriented
+//!
+//! Solves the whole flowsheet as a single nonlinear system. Every block
+//! contributes residual equations written in terms of the variables of its
+//! inlet and outlet streams, and the combined system is solved with Newton's
+//! method and a backtracking line search. Residuals are evaluated with
+//! forward-mode dual numbers, so derivatives of the residual algebra are
+//! exact.
+//!
+//! The Jacobian is only exact where the residuals are written in dual
+//! numbers. Property models work with `f64` and are not differentiated by
+//! `autodiff`: derivatives of property model calls, such as the enthalpies
+//! in the Mixer energy balance and the flash results in the Flash
+//! residuals, are estimated by central differences through [`lift`]. Those
+//! Jacobian entries carry the truncation error of the difference and the
+//! tolerance of any iterative model. Carrying dual numbers through the
+//! property models is left for later.
+
+use crate::numerics::SparseMatrix;
+use crate::simulation::convergence;
+use crate::simulation::{BlockReference, Err, Settings, StreamReference};
+use autodiff::F1;
+use std::sync::Arc;
+
+/// Smallest step fraction tried by the line search before the step is taken
+/// regardless.
+const MIN_STEP_FRACTION: f64 = 1.0 / 1024.0;
+
+/// Sufficient decrease constant for the line search (Armijo condition).
+const SUFFICIENT_DECREASE: f64 = 1e-4;
+
+/// # EquationVariables
+///
+/// The stream variables a block sees when evaluating its residuals. Values
+/// are dual numbers; a block should build its residuals with ordinary
+/// arithmetic on them so that derivatives are carried along.
+#[derive(Default)]
+pub struct EquationVariables {
+    streams: Vec<(StreamReference, Vec<F1>)>,
+}
+
+impl EquationVariables {
+    /// Create an empty set of variables.
+    pub fn new() -> Self {
+        EquationVariables::default()
+    }
+
+    /// Adds the variables of a stream.
+    pub fn insert(&mut self, stream: StreamReference, values: Vec<F1>) {
+        self.streams.push((stream, values));
+    }
+
+    /// Returns the variables of a stream, in the layout given by
+    /// [`crate::stream::Stream::variables`].
+    pub fn get(&self, stream: &StreamReference) -> Option<&[F1]> {
+        self.streams
+            .iter()
+            .find(|(candidate, _)| Arc::ptr_eq(candidate, stream))
+            .map(|(_, values)| values.as_slice())
+    }
+}
+
+/// A square system of nonlinear equations `r(x) = 0`.
+pub trait NonlinearSystem {
+    /// Evaluates the residuals.
+    fn residuals(&mut self, x: &[f64]) -> Result<Vec<f64>, Err>;
+    /// Evaluates the residuals along with their Jacobian.
+    fn jacobian(&mut self, x: &[f64]) -> Result<(Vec<f64>, SparseMatrix), Err>;
+}
+
+/// Solves a nonlinear system with Newton's method, starting from `x`. Each
+/// step is shortened by a backtracking line search until the scaled residual
+/// norm decreases sufficiently. Converges when the Newton step is within the
+/// relative tolerance of the current point. Returns the solution and the
+/// number of iterations taken.
+pub fn newton(
+    system: &mut impl NonlinearSystem,
+    mut x: Vec<f64>,
+    settings: &Settings,
+) -> Result<(Vec<f64>, usize), Err> {
+    for iteration in 1..=settings.max_iterations.max(1) {
+        let (residuals, jacobian) = system.jacobian(&x)?;
+        if residuals.len() != x.len() {
+            return Err(Err::Other(format!(
+                "equation-oriented system is not square: {} equations in {} variables",
+                residuals.len(),
+                x.len()
+            )));
+        }
+        if x.is_empty() {
+            return Ok((x, iteration));
+        }
+
+        // Scale each equation by its largest derivative so residuals with
+        // different units carry comparable weight in the line search.
+        let scale: Vec<f64> = jacobian
+            .row_norms()
+            .into_iter()
+            .map(|norm| if norm > 0.0 { 1.0 / norm } else { 1.0 })
+            .collect();
+        let merit = |residuals: &[f64]| -> f64 {
+            residuals
+                .iter()
+                .zip(&scale)
+                .map(|(r, s)| (r * s).powi(2))
+                .sum::<f64>()
+                / 2.0
+        };
+
+        let negative_residuals: Vec<f64> = residuals.iter().map(|r| -r).collect();
+        let step = jacobian
+            .solve(&negative_residuals)
+            .ok_or_else(|| Err::Other(String::from("singular jacobian")))?;
+        let full_step: Vec<f64> = x.iter().zip(&step).map(|(x, dx)| x + dx).collect();
+        if convergence::is_converged(&x, &full_step, settings.tolerance) {
+            return Ok((full_step, iteration));
+        }
+
+        let initial_merit = merit(&residuals);
+        let mut fraction = 1.0;
+        let mut trial = full_step;
+        loop {
+            let accepted = match system.residuals(&trial) {
+                Ok(trial_residuals) => {
+                    merit(&trial_residuals)
+                        <= (1.0 - 2.0 * SUFFICIENT_DECREASE * fraction) * initial_merit
+                }
+                Err(_) => false,
+            };
+            if accepted || fraction <= MIN_STEP_FRACTION {
+                break;
+            }
+            fraction /= 2.0;
+            trial = x
+                .iter()
+                .zip(&step)
+                .map(|(x, dx)| x + fraction * dx)
+                .collect();
+        }
+        x = trial;
+    }
+    Err(Err::NotConverged)
+}
+
+/// Evaluates a function of plain numbers on dual numbers. The value is exact,
+/// while the derivative along the seeded direction is found by a central
+/// difference. This lets residual equations call property models that only
+/// work with `f64`, such as flash calculations.
+///
+/// The residual algebra itself is differentiated exactly with `autodiff`.
+/// Property models are not, because they solve for their results with
+/// iterative searches over `f64` (flashes, cubic roots, temperature
+/// searches), which would all have to be made generic over the number type
+/// to carry dual numbers. A central difference with a step scaled to the
+/// arguments gives derivatives to about ten digits for two extra model
+/// calls, which is enough for the Newton steps of the flowsheet solver.
+pub fn lift<E>(f: impl Fn(&[f64]) -> Result<f64, E>, arguments: &[F1]) -> Result<F1, E> {
+    let values = lift_all(|x| Ok(vec![f(x)?]), arguments)?;
+    Ok(values[0])
+}
+
+/// Evaluates a function returning several plain numbers on dual numbers, as
+/// [`lift`] does for one. The function is called once for the values and
+/// twice more for the derivatives of all of them, so expensive models such
+/// as flashes are only evaluated three times per seeded direction.
+pub fn lift_all<E>(
+    f: impl Fn(&[f64]) -> Result<Vec<f64>, E>,
+    arguments: &[F1],
+) -> Result<Vec<F1>, E> {
+    let x: Vec<f64> = arguments.iter().map(F1::value).collect();
+    let values = f(&x)?;
+    let scale = arguments
+        .iter()
+        .filter(|argument| argument.deriv() != 0.0)
+        .map(|argument| argument.value().abs().max(1.0))
+        .fold(0.0, f64::max);
+    if scale == 0.0 {
+        return Ok(values.into_iter().map(F1::cst).collect());
+    }
+    let step = 1e-5 * scale;
+    let shifted = |step: f64| -> Vec<f64> {
+        arguments
+            .iter()
+            .map(|argument| argument.value() + step * argument.deriv())
+            .collect()
+    };
+    let more = f(&shifted(step))?;
+    let less = f(&shifted(-step))?;
+    Ok(values
+        .into_iter()
+        .zip(more.into_iter().zip(less))
+        .map(|(value, (more, less))| F1::new(value, (more - less) / (2.0 * step)))
+        .collect())
+}
+
+/// A stream connected to a block, with the position of its variables in the
+/// flowsheet variable vector.
+pub struct StreamSlot {
+    /// The stream
+    pub stream: StreamReference,
+    /// Index of the first variable of the stream
+    pub offset: usize,
+    /// Number of variables of the stream
+    pub len: usize,
+}
+
+/// A block along with the streams it is connected to.
+pub struct BlockEquations {
+    /// ID of the block
+    pub id: u64,
+    /// The block
+    pub block: BlockReference,
+    /// Inlet and outlet streams of the block
+    pub streams: Vec<StreamSlot>,
+}
+
+/// # FlowsheetSystem
+///
+/// The nonlinear system formed by the residual equations of every block in
+/// a flowsheet. Each block only depends on the variables of its own streams,
+/// so its rows of the Jacobian are found by seeding those variables one at a
+/// time.
+pub struct FlowsheetSystem {
+    blocks: Vec<BlockEquations>,
+}
+
+impl FlowsheetSystem {
+    /// Create a new system from the blocks of a flowsheet.
+    pub fn new(blocks: Vec<BlockEquations>) -> Self {
+        FlowsheetSystem { blocks }
+    }
+
+    /// Evaluates the residuals of one block, seeding the derivative of the
+    /// variable at index `seed` if one is given.
+    fn evaluate(block: &BlockEquations, x: &[f64], seed: Option<usize>) -> Result<Vec<F1>, Err> {
+        let mut variables = EquationVariables::new();
+        for slot in &block.streams {
+            let values = (slot.offset..slot.offset + slot.len)
+                .map(|index| match seed {
+                    Some(seed) if seed == index => F1::var(x[index]),
+                    _ => F1::cst(x[index]),
+                })
+                .collect();
+            variables.insert(slot.stream.clone(), values);
+        }
+        let block_lock = block
+            .block
+            .read()
+            .map_err(|_| Err::Other(String::from("block lock poisoned")))?;
+        block_lock
+            .residuals(&variables)
+            .map_err(|reason| Err::Other(format!("block {}: {}", block.id, reason)))
+    }
+}
+
+impl NonlinearSystem for FlowsheetSystem {
+    fn residuals(&mut self, x: &[f64]) -> Result<Vec<f64>, Err> {
+        let mut residuals = Vec::with_capacity(x.len());
+        for block in &self.blocks {
+            residuals.extend(Self::evaluate(block, x, None)?.iter().map(F1::value));
+        }
+        Ok(residuals)
+    }
+
+    fn jacobian(&mut self, x: &[f64]) -> Result<(Vec<f64>, SparseMatrix), Err> {
+        let residuals = self.residuals(x)?;
+        let mut jacobian = SparseMatrix::new(x.len().max(residuals.len()));
+        let mut row_offset = 0;
+        for block in &self.blocks {
+            let mut rows = 0;
+            for slot in &block.streams {
+                for column in slot.offset..slot.offset + slot.len {
+                    let seeded = Self::evaluate(block, x, Some(column))?;
+                    rows = seeded.len();
+                    for (row, value) in seeded.iter().enumerate() {
+                        jacobian.add(row_offset + row, column, value.deriv());
+                    }
+                }
+            }
+            if block.streams.iter().all(|slot| slot.len == 0) {
+                rows = Self::evaluate(block, x, None)?.len();
+            }
+            row_offset += rows;
+        }
+        Ok((residuals, jacobian))
+    }
+}
+
+#[cfg(test)]
+mod equation_oriented_tests {
+    use super::*;
+
+    /// Intersection of the circle x^2 + y^2 = 4 with the line y = x.
+    struct Circle;
+
+    impl Circle {
+        fn equations(x: &[F1]) -> Vec<F1> {
+            vec![x[0] * x[0] + x[1] * x[1] - 4.0, x[1] - x[0]]
+        }
+    }
+
+    impl NonlinearSystem for Circle {
+        fn residuals(&mut self, x: &[f64]) -> Result<Vec<f64>, Err> {
+            let x: Vec<F1> = x.iter().map(|x| F1::cst(*x)).collect();
+            Ok(Circle::equations(&x).iter().map(F1::value).collect())
+        }
+
+        fn jacobian(&mut self, x: &[f64]) -> Result<(Vec<f64>, SparseMatrix), Err> {
+            let mut jacobian = SparseMatrix::new(2);
+            for column in 0..2 {
+                let seeded: Vec<F1> = (0..2)
+                    .map(|i| {
+                        if i == column {
+                            F1::var(x[i])
+                        } else {
+                            F1::cst(x[i])
+                        }
+                    })
+                    .collect();
+                for (row, value) in Circle::equations(&seeded).iter().enumerate() {
+                    jacobian.add(row, column, value.deriv());
+                }
+            }
+            Ok((self.residuals(x)?, jacobian))
+        }
+    }
+
+    #[test]
+    fn lift_matches_dual_arithmetic() {
+        let arguments = [F1::var(2.0), F1::cst(3.0)];
+        let lifted = lift(|x| Ok::<f64, ()>(x[0] * x[0] * x[1]), &arguments).unwrap();
+        let exact = arguments[0] * arguments[0] * arguments[1];
+        assert_eq!(lifted.value(), exact.value());
+        assert!((lifted.deriv() - exact.deriv()).abs() < 1e-8);
+
+        let calls = std::cell::Cell::new(0);
+        let lifted = lift_all(
+            |x| {
+                calls.set(calls.get() + 1);
+                Ok::<_, ()>(vec![x[0] * x[1], x[0] + x[1]])
+            },
+            &arguments,
+        )
+        .unwrap();
+        assert_eq!(calls.get(), 3);
+        assert_eq!(lifted[0].value(), 6.0);
+        assert!((lifted[0].deriv() - 3.0).abs() < 1e-8);
+        assert!((lifted[1].deriv() - 1.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn newton_solves_nonlinear_system() {
+        let (solution, iterations) =
+            newton(&mut Circle, vec![10.0, 0.5], &Settings::default()).unwrap();
+        assert!((solution[0] - 2f64.sqrt()).abs() < 1e-8);
+        assert!((solution[1] - 2f64.sqrt()).abs() < 1e-8);
+        assert!(iterations < 20);
+    }
+}
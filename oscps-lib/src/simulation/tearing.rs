@@ -0,0 +1,275 @@
+//! # Tearing
+//!
+//! Graph algorithms used to order the blocks of a flowsheet and to pick the
+//! tear streams that break its recycle loops. The flowsheet is treated as a
+//! directed graph where blocks are nodes and streams are edges.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Upper bound on the number of stream combinations examined when searching
+/// for a minimum tear set. Larger recycle networks fall back to a heuristic.
+const MAX_TEAR_COMBINATIONS: usize = 100_000;
+
+/// A stream viewed as a directed edge between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamEdge {
+    /// ID of the stream
+    pub stream: u64,
+    /// ID of the block the stream leaves
+    pub from: u64,
+    /// ID of the block the stream enters
+    pub to: u64,
+}
+
+/// Orders the blocks so that every block comes after the blocks feeding it.
+/// Ties are broken by block ID so the order is repeatable. Returns `None` if
+/// the edges contain a cycle.
+pub fn topological_order(blocks: &[u64], edges: &[StreamEdge]) -> Option<Vec<u64>> {
+    let mut downstream: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    let mut inlet_count: BTreeMap<u64, usize> = blocks.iter().map(|id| (*id, 0)).collect();
+    for edge in edges {
+        downstream.entry(edge.from).or_default().push(edge.to);
+        *inlet_count.entry(edge.to).or_default() += 1;
+    }
+
+    let mut ready: BTreeSet<u64> = inlet_count
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(id, _)| *id)
+        .collect();
+    let mut order = Vec::with_capacity(inlet_count.len());
+
+    while let Some(id) = ready.pop_first() {
+        order.push(id);
+        for next in downstream.get(&id).into_iter().flatten() {
+            let count = inlet_count.get_mut(next).expect("block was counted above");
+            *count -= 1;
+            if *count == 0 {
+                ready.insert(*next);
+            }
+        }
+    }
+
+    (order.len() == inlet_count.len()).then_some(order)
+}
+
+/// Splits the blocks into strongly connected components (Tarjan's
+/// algorithm). Blocks within a component are all part of a common recycle
+/// loop; a component containing a single block is only a loop if that block
+/// feeds itself.
+pub fn strongly_connected_components(blocks: &[u64], edges: &[StreamEdge]) -> Vec<Vec<u64>> {
+    struct Tarjan<'a> {
+        downstream: &'a BTreeMap<u64, Vec<u64>>,
+        index: BTreeMap<u64, usize>,
+        low_link: BTreeMap<u64, usize>,
+        stack: Vec<u64>,
+        on_stack: BTreeSet<u64>,
+        components: Vec<Vec<u64>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, id: u64) {
+            let index = self.index.len();
+            self.index.insert(id, index);
+            self.low_link.insert(id, index);
+            self.stack.push(id);
+            self.on_stack.insert(id);
+
+            for next in self.downstream.get(&id).into_iter().flatten() {
+                if !self.index.contains_key(next) {
+                    self.visit(*next);
+                    let low = self.low_link[&id].min(self.low_link[next]);
+                    self.low_link.insert(id, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.low_link[&id].min(self.index[next]);
+                    self.low_link.insert(id, low);
+                }
+            }
+
+            if self.low_link[&id] == self.index[&id] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(&member);
+                    component.push(member);
+                    if member == id {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut downstream: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for edge in edges {
+        downstream.entry(edge.from).or_default().push(edge.to);
+    }
+    let mut tarjan = Tarjan {
+        downstream: &downstream,
+        index: BTreeMap::new(),
+        low_link: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for id in blocks {
+        if !tarjan.index.contains_key(id) {
+            tarjan.visit(*id);
+        }
+    }
+    tarjan.components
+}
+
+/// Selects the tear streams for a flowsheet: the smallest set of streams
+/// whose removal leaves no recycle loops. Each strongly connected component
+/// is searched exhaustively, preferring the lowest stream IDs among equally
+/// small sets. Components too large to search exhaustively fall back to
+/// tearing the back edges of a depth-first search, which breaks every loop
+/// but is not guaranteed to be minimal.
+pub fn select_tear_streams(blocks: &[u64], edges: &[StreamEdge]) -> Vec<u64> {
+    let mut tears = Vec::new();
+    for component in strongly_connected_components(blocks, edges) {
+        let members: BTreeSet<u64> = component.iter().copied().collect();
+        let internal: Vec<StreamEdge> = edges
+            .iter()
+            .filter(|edge| members.contains(&edge.from) && members.contains(&edge.to))
+            .copied()
+            .collect();
+        if internal.is_empty() {
+            continue;
+        }
+        let selected = minimum_tear_set(&component, &internal)
+            .unwrap_or_else(|| back_edges(&component, &internal));
+        tears.extend(selected);
+    }
+    tears.sort_unstable();
+    tears
+}
+
+/// Searches the stream combinations of a single strongly connected component
+/// in order of increasing size. Returns `None` if the search budget runs out.
+fn minimum_tear_set(blocks: &[u64], edges: &[StreamEdge]) -> Option<Vec<u64>> {
+    let mut examined = 0;
+    for size in 1..=edges.len() {
+        let mut indices: Vec<usize> = (0..size).collect();
+        loop {
+            examined += 1;
+            if examined > MAX_TEAR_COMBINATIONS {
+                return None;
+            }
+
+            let remaining: Vec<StreamEdge> = edges
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !indices.contains(i))
+                .map(|(_, edge)| *edge)
+                .collect();
+            if topological_order(blocks, &remaining).is_some() {
+                return Some(indices.iter().map(|i| edges[*i].stream).collect());
+            }
+
+            // Advance to the next combination in lexicographic order.
+            let Some(position) = (0..size)
+                .rev()
+                .find(|p| indices[*p] < edges.len() - size + p)
+            else {
+                break;
+            };
+            indices[position] += 1;
+            for p in position + 1..size {
+                indices[p] = indices[p - 1] + 1;
+            }
+        }
+    }
+    None
+}
+
+/// Returns the streams that point back to a block still being visited during
+/// a depth-first search. Removing them always leaves an acyclic graph.
+fn back_edges(blocks: &[u64], edges: &[StreamEdge]) -> Vec<u64> {
+    fn visit(
+        id: u64,
+        outlets: &BTreeMap<u64, Vec<StreamEdge>>,
+        visited: &mut BTreeSet<u64>,
+        active: &mut BTreeSet<u64>,
+        tears: &mut Vec<u64>,
+    ) {
+        visited.insert(id);
+        active.insert(id);
+        for edge in outlets.get(&id).into_iter().flatten() {
+            if active.contains(&edge.to) {
+                tears.push(edge.stream);
+            } else if !visited.contains(&edge.to) {
+                visit(edge.to, outlets, visited, active, tears);
+            }
+        }
+        active.remove(&id);
+    }
+
+    let mut outlets: BTreeMap<u64, Vec<StreamEdge>> = BTreeMap::new();
+    for edge in edges {
+        outlets.entry(edge.from).or_default().push(*edge);
+    }
+    let mut visited = BTreeSet::new();
+    let mut active = BTreeSet::new();
+    let mut tears = Vec::new();
+    for id in blocks {
+        if !visited.contains(id) {
+            visit(*id, &outlets, &mut visited, &mut active, &mut tears);
+        }
+    }
+    tears
+}
+
+#[cfg(test)]
+mod tearing_tests {
+    use super::*;
+
+    fn edges(links: &[(u64, u64, u64)]) -> Vec<StreamEdge> {
+        links
+            .iter()
+            .map(|(stream, from, to)| StreamEdge {
+                stream: *stream,
+                from: *from,
+                to: *to,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn acyclic_flowsheet_needs_no_tears() {
+        let edges = edges(&[(1, 1, 2), (2, 2, 3), (3, 1, 3)]);
+        assert!(select_tear_streams(&[1, 2, 3], &edges).is_empty());
+        assert_eq!(topological_order(&[1, 2, 3], &edges), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn nested_loops_share_a_single_tear() {
+        // Two recycle loops, 2 -> 3 -> 2 and 2 -> 3 -> 4 -> 2, both pass
+        // through stream 2 so tearing it alone breaks both.
+        let edges = edges(&[(1, 1, 2), (2, 2, 3), (3, 3, 2), (4, 3, 4), (5, 4, 2)]);
+        let blocks = [1, 2, 3, 4];
+        assert_eq!(topological_order(&blocks, &edges), None);
+        assert_eq!(select_tear_streams(&blocks, &edges), vec![2]);
+    }
+
+    #[test]
+    fn independent_loops_are_torn_separately() {
+        let edges = edges(&[(1, 1, 2), (2, 2, 1), (3, 2, 3), (4, 3, 3)]);
+        let components = strongly_connected_components(&[1, 2, 3], &edges);
+        assert_eq!(components.len(), 2);
+        assert_eq!(select_tear_streams(&[1, 2, 3], &edges), vec![1, 4]);
+    }
+
+    #[test]
+    fn back_edges_break_every_loop() {
+        let edges = edges(&[(1, 1, 2), (2, 2, 3), (3, 3, 1), (4, 3, 2)]);
+        let tears = back_edges(&[1, 2, 3], &edges);
+        let remaining: Vec<StreamEdge> = edges
+            .into_iter()
+            .filter(|edge| !tears.contains(&edge.stream))
+            .collect();
+        assert!(topological_order(&[1, 2, 3], &remaining).is_some());
+    }
+}
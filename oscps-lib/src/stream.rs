@@ -1,15 +1,159 @@
 //! # Stream
 
-// NOTE: Temporarily disabled until the thermodynamics crate is thread-safe.
-// use crate::thermodynamics::ThermoState;
 use crate::simulation::BlockReference;
+use uom::si::catalytic_activity::katal;
+use uom::si::f64::*;
+use uom::si::pressure::pascal;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// Molar flow rate of material. uom does not provide a dedicated quantity for
+/// molar flow, so the dimensionally identical catalytic activity is used
+/// (one katal is one mole per second).
+pub type MolarFlowRate = CatalyticActivity;
+
+/// # StreamThermoState
+///
+/// The material carried by a stream: the flow of each component along with
+/// the conditions of the stream. The vapor fraction and enthalpy depend on the
+/// thermodynamic model used and are `None` until a block calculates them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamThermoState {
+    /// Names of the components in the stream
+    pub components: Vec<String>,
+    /// Molar flow of each component, in the same order as `components`
+    pub molar_flows: Vec<MolarFlowRate>,
+    /// Temperature of the stream
+    pub temperature: ThermodynamicTemperature,
+    /// Pressure of the stream
+    pub pressure: Pressure,
+    /// Molar fraction of the stream in the vapor phase
+    pub vapor_fraction: Option<Ratio>,
+    /// Molar enthalpy of the stream
+    pub enthalpy: Option<MolarEnergy>,
+}
+
+impl StreamThermoState {
+    /// Specify a stream by the molar flow of each component.
+    pub fn from_molar_flows(
+        components: Vec<String>,
+        molar_flows: Vec<MolarFlowRate>,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+    ) -> Result<Self, &'static str> {
+        if components.len() != molar_flows.len() {
+            return Err("a flow must be given for every component");
+        }
+        if molar_flows.iter().any(|flow| flow.get::<katal>() < 0.0) {
+            return Err("component flows cannot be negative");
+        }
+        if temperature.get::<kelvin>() <= 0.0 || pressure.get::<pascal>() <= 0.0 {
+            return Err("temperature and pressure must be positive");
+        }
+        Ok(StreamThermoState {
+            components,
+            molar_flows,
+            temperature,
+            pressure,
+            vapor_fraction: None,
+            enthalpy: None,
+        })
+    }
+
+    /// Specify a stream by the mass flow of each component, converting to a
+    /// molar basis with the molar mass of each component.
+    pub fn from_mass_flows(
+        components: Vec<String>,
+        mass_flows: Vec<MassRate>,
+        molar_masses: &[MolarMass],
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+    ) -> Result<Self, &'static str> {
+        if mass_flows.len() != molar_masses.len() {
+            return Err("a molar mass must be given for every component");
+        }
+        let molar_flows = mass_flows
+            .iter()
+            .zip(molar_masses)
+            .map(|(mass_flow, molar_mass)| *mass_flow / *molar_mass)
+            .collect();
+        Self::from_molar_flows(components, molar_flows, temperature, pressure)
+    }
+
+    /// Specify a stream by the volumetric flow of each component, converting
+    /// to a molar basis with the molar volume of each pure component at the
+    /// stream conditions. Assumes ideal mixing, so the volumes are additive.
+    pub fn from_volume_flows(
+        components: Vec<String>,
+        volume_flows: Vec<VolumeRate>,
+        molar_volumes: &[MolarVolume],
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+    ) -> Result<Self, &'static str> {
+        if volume_flows.len() != molar_volumes.len() {
+            return Err("a molar volume must be given for every component");
+        }
+        let molar_flows = volume_flows
+            .iter()
+            .zip(molar_volumes)
+            .map(|(volume_flow, molar_volume)| *volume_flow / *molar_volume)
+            .collect();
+        Self::from_molar_flows(components, molar_flows, temperature, pressure)
+    }
+
+    /// Total molar flow of the stream.
+    pub fn total_molar_flow(&self) -> MolarFlowRate {
+        self.molar_flows
+            .iter()
+            .fold(MolarFlowRate::new::<katal>(0.0), |total, flow| {
+                total + *flow
+            })
+    }
+
+    /// Mole fraction of each component. All zero for a stream with no flow.
+    pub fn mole_fractions(&self) -> Vec<f64> {
+        let total = self.total_molar_flow().get::<katal>();
+        self.molar_flows
+            .iter()
+            .map(|flow| {
+                if total > 0.0 {
+                    flow.get::<katal>() / total
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Mass flow of each component, given the molar mass of each component.
+    pub fn mass_flows(&self, molar_masses: &[MolarMass]) -> Vec<MassRate> {
+        self.molar_flows
+            .iter()
+            .zip(molar_masses)
+            .map(|(flow, molar_mass)| *flow * *molar_mass)
+            .collect()
+    }
+
+    /// Total mass flow of the stream, given the molar mass of each component.
+    pub fn total_mass_flow(&self, molar_masses: &[MolarMass]) -> MassRate {
+        self.mass_flows(molar_masses)
+            .into_iter()
+            .fold(MassRate::default(), |total, flow| total + flow)
+    }
+
+    /// Rate at which enthalpy is carried by the stream, if the enthalpy is
+    /// known.
+    pub fn enthalpy_flow(&self) -> Option<Power> {
+        self.enthalpy
+            .map(|enthalpy| self.total_molar_flow() * enthalpy)
+    }
+}
 
 /// # Stream
 ///
 /// Struct to hold stream information
 pub struct Stream {
-    /// Instance of ThermoState struct that holds thermodynamic information.
-    // pub thermo: Option<ThermoState>, // HACK: Temporarily disable to enable thread-safety.
+    /// Material carried by the stream, if it has been specified or calculated.
+    pub thermo: Option<StreamThermoState>,
     /// ID of source block
     pub from: BlockReference,
     /// ID of destination block
@@ -19,6 +163,157 @@ pub struct Stream {
 impl Stream {
     /// Constructor for 'Stream' struct
     pub fn new(from: BlockReference, to: BlockReference) -> Stream {
-        Stream { from, to }
+        Stream {
+            thermo: None,
+            from,
+            to,
+        }
+    }
+
+    /// Returns the variables describing the stream, as used by the recycle
+    /// convergence methods and the equation-oriented solver: the molar flow
+    /// of each component in mol/s, followed by the temperature in K and the
+    /// pressure in Pa. Empty if the stream carries no material yet.
+    pub fn variables(&self) -> Vec<f64> {
+        match &self.thermo {
+            Some(thermo) => thermo
+                .molar_flows
+                .iter()
+                .map(|flow| flow.get::<katal>())
+                .chain([
+                    thermo.temperature.get::<kelvin>(),
+                    thermo.pressure.get::<pascal>(),
+                ])
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Overwrites the variables describing the stream. See
+    /// [`Stream::variables`] for their layout. The vapor fraction and
+    /// enthalpy are cleared, since they no longer match the new conditions.
+    /// Fails if the variables do not match the layout of the stream, which
+    /// has no variables while it carries no material.
+    pub fn set_variables(&mut self, variables: &[f64]) -> Result<(), &'static str> {
+        let Some(thermo) = self.thermo.as_mut() else {
+            return if variables.is_empty() {
+                Ok(())
+            } else {
+                Err("stream carries no material to set variables on")
+            };
+        };
+        let n = thermo.molar_flows.len();
+        if variables.len() != n + 2 {
+            return Err("variables do not match the components of the stream");
+        }
+        for (flow, value) in thermo.molar_flows.iter_mut().zip(variables) {
+            *flow = MolarFlowRate::new::<katal>(*value);
+        }
+        thermo.temperature = ThermodynamicTemperature::new::<kelvin>(variables[n]);
+        thermo.pressure = Pressure::new::<pascal>(variables[n + 1]);
+        thermo.vapor_fraction = None;
+        thermo.enthalpy = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+    use uom::si::mass_rate::kilogram_per_second;
+    use uom::si::molar_energy::joule_per_mole;
+    use uom::si::molar_mass::gram_per_mole;
+    use uom::si::molar_volume::cubic_centimeter_per_mole;
+    use uom::si::volume_rate::liter_per_second;
+
+    fn components() -> Vec<String> {
+        vec![String::from("water"), String::from("ethanol")]
+    }
+
+    #[test]
+    fn feed_bases_are_converted_to_molar_flows() {
+        let temperature = ThermodynamicTemperature::new::<kelvin>(298.15);
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let molar_masses = [
+            MolarMass::new::<gram_per_mole>(18.015),
+            MolarMass::new::<gram_per_mole>(46.07),
+        ];
+
+        let by_mass = StreamThermoState::from_mass_flows(
+            components(),
+            vec![
+                MassRate::new::<kilogram_per_second>(0.018015),
+                MassRate::new::<kilogram_per_second>(0.09214),
+            ],
+            &molar_masses,
+            temperature,
+            pressure,
+        )
+        .unwrap();
+        assert!((by_mass.total_molar_flow().get::<katal>() - 3.0).abs() < 1e-9);
+        let fractions = by_mass.mole_fractions();
+        assert!((fractions[0] - 1.0 / 3.0).abs() < 1e-9);
+        let total_mass = by_mass.total_mass_flow(&molar_masses);
+        assert!((total_mass.get::<kilogram_per_second>() - 0.110155).abs() < 1e-9);
+
+        let by_volume = StreamThermoState::from_volume_flows(
+            components(),
+            vec![
+                VolumeRate::new::<liter_per_second>(0.018),
+                VolumeRate::new::<liter_per_second>(0.0),
+            ],
+            &[
+                MolarVolume::new::<cubic_centimeter_per_mole>(18.0),
+                MolarVolume::new::<cubic_centimeter_per_mole>(58.4),
+            ],
+            temperature,
+            pressure,
+        )
+        .unwrap();
+        assert!((by_volume.molar_flows[0].get::<katal>() - 1.0).abs() < 1e-9);
+
+        assert!(StreamThermoState::from_molar_flows(
+            components(),
+            vec![MolarFlowRate::new::<katal>(-1.0); 2],
+            temperature,
+            pressure,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn stream_variables_round_trip() {
+        use crate::blocks::Sink;
+        use std::sync::{Arc, RwLock};
+
+        let block: BlockReference = Arc::new(RwLock::new(Box::new(Sink::new())));
+        let mut stream = Stream::new(block.clone(), block);
+        assert!(stream.variables().is_empty());
+        assert!(stream.set_variables(&[1.0, 2.0, 300.0, 101_325.0]).is_err());
+        stream.set_variables(&[]).unwrap();
+        assert!(stream.thermo.is_none());
+
+        let mut thermo = StreamThermoState::from_molar_flows(
+            components(),
+            vec![MolarFlowRate::new::<katal>(1.0); 2],
+            ThermodynamicTemperature::new::<kelvin>(298.15),
+            Pressure::new::<pascal>(101_325.0),
+        )
+        .unwrap();
+        thermo.enthalpy = Some(MolarEnergy::new::<joule_per_mole>(-1000.0));
+        stream.thermo = Some(thermo);
+        assert_eq!(stream.variables(), [1.0, 1.0, 298.15, 101_325.0]);
+
+        let variables = [0.5, 2.5, 350.0, 2e5];
+        stream.set_variables(&variables).unwrap();
+        assert_eq!(stream.variables(), variables);
+        let thermo = stream.thermo.as_ref().unwrap();
+        assert!((thermo.total_molar_flow().get::<katal>() - 3.0).abs() < 1e-12);
+        assert_eq!(thermo.molar_flows[1].get::<katal>(), 2.5);
+        assert_eq!(thermo.enthalpy, None);
+
+        // Variables that do not match the components are rejected
+        assert!(stream.set_variables(&[1.0, 350.0, 2e5]).is_err());
+        assert_eq!(stream.variables(), variables);
     }
 }
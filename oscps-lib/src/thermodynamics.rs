@@ -1,59 +1,31 @@
 //! # Thermodynamics
 //!
-//! This module will hold all the functions related to calculating 
+//! This module will hold all the functions related to calculating
 //! themrodynamic properties for the blocks and chemical species.
 
+///Activity coefficient models and gamma-phi phase equilibrium
+pub mod activity_models;
+///Physical constants and reference states
+pub mod constants;
 ///Importing EOSModels
 pub mod eos_models;
+///Ideal gas heat capacity correlations
+pub mod heat_capacity;
+///Ideal gas and Raoult's law property package
+pub mod ideal_models;
+///Bubble and dew points and phase envelopes
+pub mod phase_envelope;
+///Phase stability testing and multiphase flash
+pub mod phase_stability;
+///Selectable property packages for flowsheets
+pub mod property_package;
 
-/// Importing chemical properties
-use crate::properties::Chemical;
+use crate::stream::StreamThermoState;
 
 ///Importing External Packages
 use uom::si::f64::*;
-use uom::si::mass;
-use uom::si::pressure;
+use uom::si::molar_energy;
 use uom::si::thermodynamic_temperature;
-use uom::si::energy;
-use uom::si::amount_of_substance;
-
-#[allow(dead_code)]
-/// #ThermodynamicConstants
-///
-/// Struct for storing physical constants for thermodynamics.
-/// TODO: Reimplement the use of uom for dimensional analysis.
-pub enum ThermodynamicConstants {
-    /// The Universal gas constant in J/(mol*K)
-    UniversalGasConstant, // J/(mol*K)
-    /// Standard temperature in K
-    StandardTemperature,  // T_0
-    /// Standard pressure in Pa
-    StandardPressure,     // P_0
-    /// Avogadro's number in mol^-1
-    AvogadroNumber,       // N_A
-}
-
-#[allow(dead_code)] 
-/// Implements values of thermodynamic constants.
-impl ThermodynamicConstants {
-    /// Returns the value of the thermodynamic constant with its appropriate type.
-    pub fn value(&self) -> Box<dyn std::any::Any> {
-        match self {
-            ThermodynamicConstants::UniversalGasConstant => {
-                let r = 8.314462618;
-                let constant = Energy::new::<energy::joule>(r) / (ThermodynamicTemperature::new::<thermodynamic_temperature::kelvin>(1.0)* AmountOfSubstance::new::<amount_of_substance::mole>(1.0));
-                Box::new(constant)
-            },
-            ThermodynamicConstants::StandardTemperature => {
-                Box::new(ThermodynamicTemperature::new::<thermodynamic_temperature::kelvin>(273.15))
-            }
-            ThermodynamicConstants::StandardPressure => {
-                Box::new(Pressure::new::<pressure::pascal>(101_325.0))
-            },
-            ThermodynamicConstants::AvogadroNumber => Box::new(6.02214076e23), //Units: particles/mole
-        }
-    }
-}
 
 ///Thermodynamic Packages.
 ///
@@ -70,7 +42,7 @@ impl ThermodynamicConstants {
 ///TODO: Currently the rust std::autodiff is still experimental. Need to wait for this release. In
 ///the meantime, we will either manually write out the derivatives or use a third party autdiff
 ///package (the third party is: https://crates.io/crates/autodiff)
-pub trait MaxwellRelations{
+pub trait MaxwellRelations {
     ///Calculating the Enthalpy
     fn enthalpy(&self) -> MolarEnergy;
     ///Calculating the Entropy
@@ -91,5 +63,233 @@ pub trait MaxwellRelations{
     fn gibbs_free_energy(&self) -> Energy;
 }
 
+/// The kind of phase a composition is evaluated in. Equations of state use
+/// this to pick the vapor-like or liquid-like volume root, and activity
+/// models to choose between the vapor and liquid descriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// A vapor phase
+    Vapor,
+    /// A liquid phase
+    Liquid,
+}
+
+/// # FlashResult
+///
+/// The outcome of a flash calculation: the conditions of the mixture along
+/// with the amount and composition of each phase. Compositions are mole
+/// fractions in the component order of the model that performed the flash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlashResult {
+    /// Temperature of the mixture
+    pub temperature: ThermodynamicTemperature,
+    /// Pressure of the mixture
+    pub pressure: Pressure,
+    /// Molar fraction of the mixture in the vapor phase
+    pub vapor_fraction: Ratio,
+    /// Mole fractions of the liquid phase
+    pub liquid_composition: Vec<f64>,
+    /// Mole fractions of the vapor phase
+    pub vapor_composition: Vec<f64>,
+    /// Molar enthalpy of the mixture
+    pub enthalpy: MolarEnergy,
+    /// Molar enthalpy of the liquid phase
+    pub liquid_enthalpy: MolarEnergy,
+    /// Molar enthalpy of the vapor phase
+    pub vapor_enthalpy: MolarEnergy,
+}
+
+///#PhaseEquilibrium
+///
+///Common trait for thermodynamic models that can perform flash calculations.
+///This is what the blocks use to find the phase split and the temperature of
+///their outlet streams.
+///
+///Compositions are always given in the component order returned by
+///'components'. Streams name their components, so 'composition' can be used to
+///put the mole fractions of a stream in the right order.
+pub trait PhaseEquilibrium: Send + Sync {
+    ///Names of the components the model has parameters for
+    fn components(&self) -> &[String];
+    ///Molar mass of each component
+    fn molar_masses(&self) -> Vec<MolarMass>;
+    ///Flash a mixture at a given temperature and pressure
+    fn flash_tp(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+    ) -> Result<FlashResult, &'static str>;
+
+    ///Flash a mixture at a given pressure and molar enthalpy. By default, the
+    ///temperature is found by searching for the temperature flash that matches
+    ///the enthalpy, starting from 'initial_temperature'. Models whose enthalpy
+    ///jumps at a phase change (such as pure components) should override this.
+    fn flash_ph(
+        &self,
+        pressure: Pressure,
+        enthalpy: MolarEnergy,
+        composition: &[f64],
+        initial_temperature: ThermodynamicTemperature,
+    ) -> Result<FlashResult, &'static str> {
+        let target = enthalpy.get::<molar_energy::joule_per_mole>();
+        let temperature = find_root(
+            |t| {
+                let flash = self.flash_tp(
+                    ThermodynamicTemperature::new::<thermodynamic_temperature::kelvin>(t),
+                    pressure,
+                    composition,
+                )?;
+                Ok(flash.enthalpy.get::<molar_energy::joule_per_mole>() - target)
+            },
+            initial_temperature.get::<thermodynamic_temperature::kelvin>(),
+            10.0,
+            (1.0, 10_000.0),
+        )?;
+        self.flash_tp(
+            ThermodynamicTemperature::new::<thermodynamic_temperature::kelvin>(temperature),
+            pressure,
+            composition,
+        )
+    }
+
+    ///Natural logarithm of the fugacity coefficient of each component in a
+    ///phase of the given composition. Needed for phase stability testing and
+    ///for flashes with more than two phases. Models that cannot evaluate a
+    ///single phase on its own return an error.
+    fn ln_fugacity_coefficients(
+        &self,
+        _temperature: ThermodynamicTemperature,
+        _pressure: Pressure,
+        _composition: &[f64],
+        _phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        Err("thermodynamic model does not provide fugacity coefficients")
+    }
+
+    ///Molar enthalpy of a phase of the given composition
+    fn phase_enthalpy(
+        &self,
+        _temperature: ThermodynamicTemperature,
+        _pressure: Pressure,
+        _composition: &[f64],
+        _phase: Phase,
+    ) -> Result<MolarEnergy, &'static str> {
+        Err("thermodynamic model does not provide phase enthalpies")
+    }
+
+    ///Mole fractions of a stream in the component order of the model
+    fn composition(&self, stream: &StreamThermoState) -> Result<Vec<f64>, &'static str> {
+        let mut composition = vec![0.0; self.components().len()];
+        for (name, fraction) in stream.components.iter().zip(stream.mole_fractions()) {
+            let index = self
+                .components()
+                .iter()
+                .position(|component| component == name)
+                .ok_or("stream contains a component unknown to the thermodynamic model")?;
+            composition[index] += fraction;
+        }
+        Ok(composition)
+    }
+
+    ///Total mass flow of a stream, using the molar masses of the model
+    fn mass_flow(&self, stream: &StreamThermoState) -> Result<MassRate, &'static str> {
+        let molar_masses = self.molar_masses();
+        let mut mass_flow = MassRate::default();
+        for (name, flow) in stream.components.iter().zip(&stream.molar_flows) {
+            let index = self
+                .components()
+                .iter()
+                .position(|component| component == name)
+                .ok_or("stream contains a component unknown to the thermodynamic model")?;
+            mass_flow += *flow * molar_masses[index];
+        }
+        Ok(mass_flow)
+    }
+}
+
+/// Finds a root of a monotonic function of one variable. The root is first
+/// bracketed by stepping outward from 'initial' in both directions with a
+/// growing step, then refined with the Illinois variant of the false position
+/// method. The search never leaves 'bounds'.
+pub(crate) fn find_root(
+    mut f: impl FnMut(f64) -> Result<f64, &'static str>,
+    initial: f64,
+    initial_step: f64,
+    bounds: (f64, f64),
+) -> Result<f64, &'static str> {
+    let (lower, upper) = bounds;
+    let x0 = initial.clamp(lower, upper);
+    let f0 = f(x0)?;
+    if f0 == 0.0 {
+        return Ok(x0);
+    }
+
+    let mut step = initial_step;
+    let (mut low, mut high) = ((x0, f0), (x0, f0));
+    let bracket = loop {
+        if low.0 <= lower && high.0 >= upper {
+            return Err("no solution found within bounds");
+        }
+        if high.0 < upper {
+            let x = (x0 + step).min(upper);
+            let fx = f(x)?;
+            if fx.signum() != high.1.signum() {
+                break (high, (x, fx));
+            }
+            high = (x, fx);
+        }
+        if low.0 > lower {
+            let x = (x0 - step).max(lower);
+            let fx = f(x)?;
+            if fx.signum() != low.1.signum() {
+                break ((x, fx), low);
+            }
+            low = (x, fx);
+        }
+        step *= 2.0;
+    };
+
+    let ((mut a, mut fa), (mut b, mut fb)) = bracket;
+    let mut side = 0;
+    for _ in 0..200 {
+        let x = (a * fb - b * fa) / (fb - fa);
+        let fx = f(x)?;
+        if fx == 0.0 || (b - a).abs() <= 1e-10 * x.abs().max(1.0) {
+            return Ok(x);
+        }
+        if fx.signum() == fb.signum() {
+            (b, fb) = (x, fx);
+            if side == 1 {
+                fa /= 2.0;
+            }
+            side = 1;
+        } else {
+            (a, fa) = (x, fx);
+            if side == -1 {
+                fb /= 2.0;
+            }
+            side = -1;
+        }
+        if (b - a).abs() <= 1e-10 * x.abs().max(1.0) {
+            return Ok(x);
+        }
+    }
+    Err("root search did not converge")
+}
+
 #[cfg(test)]
-mod thermo_tests {}
+mod thermo_tests {
+    use super::*;
+
+    #[test]
+    fn find_root_brackets_and_converges() {
+        let root = find_root(|x| Ok(x * x - 2.0), 10.0, 1.0, (0.0, 100.0)).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-8);
+
+        let decreasing = find_root(|x| Ok(500.0 - x), 1.0, 1.0, (0.0, 1000.0)).unwrap();
+        assert!((decreasing - 500.0).abs() < 1e-8);
+
+        assert!(find_root(|x| Ok(x + 1.0), 1.0, 1.0, (0.0, 10.0)).is_err());
+    }
+}
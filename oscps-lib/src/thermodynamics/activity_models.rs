@@ -0,0 +1,864 @@
+//! # Activity Models
+//!
+//! Activity coefficient models for non-ideal liquid mixtures, and the
+//! gamma-phi approach that combines them with a vapor phase model for phase
+//! equilibrium calculations.
+//!
+//! The NRTL, Wilson and UNIQUAC models are available. Their binary
+//! parameters may depend on temperature through [`BinaryParameter`]. Each
+//! model is written in terms of a temperature given as a dual number, so the
+//! excess enthalpy follows exactly from the temperature derivative of the
+//! activity coefficients.
+
+///UNIFAC group contribution models
+pub mod unifac;
+
+use crate::thermodynamics::constants::ReferenceState;
+use crate::thermodynamics::eos_models::{ideal_gas_enthalpy, CubicEquation, GAS_CONSTANT};
+use crate::thermodynamics::heat_capacity::HeatCapacityCoefficients;
+use crate::thermodynamics::phase_stability;
+use crate::thermodynamics::{FlashResult, Phase, PhaseEquilibrium};
+use autodiff::{Float, F1};
+use std::sync::Arc;
+use uom::si::f64::*;
+use uom::si::molar_energy::joule_per_mole;
+use uom::si::pressure::pascal;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// Coordination number of the UNIQUAC model
+const COORDINATION_NUMBER: f64 = 10.0;
+
+/// # ActivityModel
+///
+/// Common trait for activity coefficient models. Compositions are mole
+/// fractions in the component order returned by 'components'.
+pub trait ActivityModel: Send + Sync {
+    ///Names of the components the model has parameters for
+    fn components(&self) -> &[String];
+
+    ///Natural logarithm of the activity coefficient of each component, with
+    ///the temperature in K given as a dual number so the temperature
+    ///derivatives are carried along
+    fn ln_activity_coefficients_dual(&self, temperature: F1, composition: &[f64]) -> Vec<F1>;
+
+    ///Natural logarithm of the activity coefficient of each component
+    fn ln_activity_coefficients(
+        &self,
+        temperature: ThermodynamicTemperature,
+        composition: &[f64],
+    ) -> Vec<f64> {
+        self.ln_activity_coefficients_dual(F1::cst(temperature.get::<kelvin>()), composition)
+            .iter()
+            .map(F1::value)
+            .collect()
+    }
+
+    ///Molar excess Gibbs energy of the mixture
+    fn excess_gibbs_energy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        composition: &[f64],
+    ) -> MolarEnergy {
+        let t = temperature.get::<kelvin>();
+        let ln_gamma = self.ln_activity_coefficients(temperature, composition);
+        MolarEnergy::new::<joule_per_mole>(
+            GAS_CONSTANT
+                * t
+                * composition
+                    .iter()
+                    .zip(ln_gamma)
+                    .map(|(x, ln_gamma)| x * ln_gamma)
+                    .sum::<f64>(),
+        )
+    }
+
+    ///Molar excess enthalpy of the mixture, from the Gibbs-Helmholtz
+    ///equation 'h_E = -R T^2 d(g_E / RT)/dT'
+    fn excess_enthalpy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        composition: &[f64],
+    ) -> MolarEnergy {
+        let t = temperature.get::<kelvin>();
+        let derivative: f64 = self
+            .ln_activity_coefficients_dual(F1::var(t), composition)
+            .iter()
+            .zip(composition)
+            .map(|(ln_gamma, x)| x * ln_gamma.deriv())
+            .sum();
+        MolarEnergy::new::<joule_per_mole>(-GAS_CONSTANT * t * t * derivative)
+    }
+}
+
+/// # BinaryParameter
+///
+/// A temperature dependent binary parameter, 'a + b / T + c ln(T) + d T'
+/// with the temperature in K.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BinaryParameter {
+    /// Constant term
+    pub a: f64,
+    /// Coefficient of the inverse temperature, in K
+    pub b: f64,
+    /// Coefficient of the logarithm of temperature
+    pub c: f64,
+    /// Coefficient of temperature, in 1/K
+    pub d: f64,
+}
+
+impl BinaryParameter {
+    /// A parameter that does not depend on temperature.
+    pub fn constant(a: f64) -> Self {
+        BinaryParameter {
+            a,
+            ..Default::default()
+        }
+    }
+
+    /// A parameter inversely proportional to temperature, 'b / T'.
+    pub fn inverse_temperature(b: f64) -> Self {
+        BinaryParameter {
+            b,
+            ..Default::default()
+        }
+    }
+
+    /// Value of the parameter at a temperature in K.
+    pub fn value(&self, temperature: F1) -> F1 {
+        self.a + self.b / temperature + self.c * temperature.ln() + self.d * temperature
+    }
+}
+
+/// Position of a component in a list of component names.
+fn position(components: &[String], component: &str) -> Result<usize, &'static str> {
+    components
+        .iter()
+        .position(|name| name == component)
+        .ok_or("component is unknown to the activity model")
+}
+
+/// Positions of two different components in a list of component names.
+fn pair(components: &[String], first: &str, second: &str) -> Result<(usize, usize), &'static str> {
+    let (i, j) = (position(components, first)?, position(components, second)?);
+    if i == j {
+        return Err("a component has no binary parameters with itself");
+    }
+    Ok((i, j))
+}
+
+/// # NRTL
+///
+/// The Non-Random Two-Liquid model of Renon and Prausnitz (1968), with
+/// 'G_ij = exp(-alpha_ij tau_ij)'. Binary parameters default to zero, an
+/// ideal solution.
+#[derive(Debug, Clone)]
+pub struct NRTL {
+    components: Vec<String>,
+    tau: Vec<Vec<BinaryParameter>>,
+    alpha: Vec<Vec<f64>>,
+}
+
+impl NRTL {
+    /// Creates an NRTL model with every binary parameter set to zero.
+    pub fn new(components: Vec<String>) -> Self {
+        let n = components.len();
+        NRTL {
+            components,
+            tau: vec![vec![BinaryParameter::default(); n]; n],
+            alpha: vec![vec![0.0; n]; n],
+        }
+    }
+
+    /// Sets the parameters of a binary pair: 'tau_ij', 'tau_ji' and the
+    /// non-randomness 'alpha_ij = alpha_ji'.
+    pub fn set_binary_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        tau_ij: BinaryParameter,
+        tau_ji: BinaryParameter,
+        alpha: f64,
+    ) -> Result<(), &'static str> {
+        let (i, j) = pair(&self.components, first, second)?;
+        self.tau[i][j] = tau_ij;
+        self.tau[j][i] = tau_ji;
+        self.alpha[i][j] = alpha;
+        self.alpha[j][i] = alpha;
+        Ok(())
+    }
+}
+
+impl ActivityModel for NRTL {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn ln_activity_coefficients_dual(&self, temperature: F1, x: &[f64]) -> Vec<F1> {
+        let n = x.len();
+        let tau: Vec<Vec<F1>> = (0..n)
+            .map(|i| (0..n).map(|j| self.tau[i][j].value(temperature)).collect())
+            .collect();
+        let g: Vec<Vec<F1>> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| (-self.alpha[i][j] * tau[i][j]).exp())
+                    .collect()
+            })
+            .collect();
+        // 'sum_k x_k G_ki' and 'sum_k x_k tau_ki G_ki' for each component
+        let denominator: Vec<F1> = (0..n)
+            .map(|i| (0..n).map(|k| x[k] * g[k][i]).sum())
+            .collect();
+        let numerator: Vec<F1> = (0..n)
+            .map(|i| (0..n).map(|k| x[k] * tau[k][i] * g[k][i]).sum())
+            .collect();
+        (0..n)
+            .map(|i| {
+                numerator[i] / denominator[i]
+                    + (0..n)
+                        .map(|j| {
+                            x[j] * g[i][j] / denominator[j]
+                                * (tau[i][j] - numerator[j] / denominator[j])
+                        })
+                        .sum::<F1>()
+            })
+            .collect()
+    }
+}
+
+/// # Wilson
+///
+/// The Wilson (1964) model. The parameters are 'ln(Lambda_ij)', which
+/// default to zero, an ideal solution. The model cannot predict a liquid
+/// phase split.
+#[derive(Debug, Clone)]
+pub struct Wilson {
+    components: Vec<String>,
+    ln_lambda: Vec<Vec<BinaryParameter>>,
+}
+
+impl Wilson {
+    /// Creates a Wilson model with every binary parameter set to zero.
+    pub fn new(components: Vec<String>) -> Self {
+        let n = components.len();
+        Wilson {
+            components,
+            ln_lambda: vec![vec![BinaryParameter::default(); n]; n],
+        }
+    }
+
+    /// Sets 'ln(Lambda_ij)' and 'ln(Lambda_ji)' of a binary pair. For the
+    /// usual form 'Lambda_ij = (V_j / V_i) exp(-lambda_ij / RT)', these are
+    /// 'a = ln(V_j / V_i)' and 'b = -lambda_ij / R'.
+    pub fn set_binary_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        ln_lambda_ij: BinaryParameter,
+        ln_lambda_ji: BinaryParameter,
+    ) -> Result<(), &'static str> {
+        let (i, j) = pair(&self.components, first, second)?;
+        self.ln_lambda[i][j] = ln_lambda_ij;
+        self.ln_lambda[j][i] = ln_lambda_ji;
+        Ok(())
+    }
+}
+
+impl ActivityModel for Wilson {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn ln_activity_coefficients_dual(&self, temperature: F1, x: &[f64]) -> Vec<F1> {
+        let n = x.len();
+        let lambda: Vec<Vec<F1>> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| self.ln_lambda[i][j].value(temperature).exp())
+                    .collect()
+            })
+            .collect();
+        // 'sum_j x_j Lambda_ij' for each component
+        let sums: Vec<F1> = (0..n)
+            .map(|i| (0..n).map(|j| x[j] * lambda[i][j]).sum())
+            .collect();
+        (0..n)
+            .map(|i| 1.0 - sums[i].ln() - (0..n).map(|k| x[k] * lambda[k][i] / sums[k]).sum::<F1>())
+            .collect()
+    }
+}
+
+/// # UNIQUAC
+///
+/// The UNIQUAC model of Abrams and Prausnitz (1975), with a coordination
+/// number of 10. The binary parameters are 'ln(tau_ij)', which default to
+/// zero, leaving only the combinatorial part.
+#[derive(Debug, Clone)]
+pub struct UNIQUAC {
+    components: Vec<String>,
+    /// Volume parameter 'r' of each component
+    volumes: Vec<f64>,
+    /// Surface area parameter 'q' of each component
+    areas: Vec<f64>,
+    ln_tau: Vec<Vec<BinaryParameter>>,
+}
+
+impl UNIQUAC {
+    /// Creates a UNIQUAC model from the volume ('r') and surface area ('q')
+    /// parameters of each component, with every binary parameter set to
+    /// zero.
+    pub fn new(
+        components: Vec<String>,
+        volumes: Vec<f64>,
+        areas: Vec<f64>,
+    ) -> Result<Self, &'static str> {
+        if volumes.len() != components.len() || areas.len() != components.len() {
+            return Err("every component needs a volume and a surface area parameter");
+        }
+        let n = components.len();
+        Ok(UNIQUAC {
+            components,
+            volumes,
+            areas,
+            ln_tau: vec![vec![BinaryParameter::default(); n]; n],
+        })
+    }
+
+    /// Sets 'ln(tau_ij)' and 'ln(tau_ji)' of a binary pair. For the usual
+    /// form 'tau_ij = exp(-u_ij / RT)', these are 'b = -u_ij / R'.
+    pub fn set_binary_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        ln_tau_ij: BinaryParameter,
+        ln_tau_ji: BinaryParameter,
+    ) -> Result<(), &'static str> {
+        let (i, j) = pair(&self.components, first, second)?;
+        self.ln_tau[i][j] = ln_tau_ij;
+        self.ln_tau[j][i] = ln_tau_ji;
+        Ok(())
+    }
+}
+
+impl ActivityModel for UNIQUAC {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn ln_activity_coefficients_dual(&self, temperature: F1, x: &[f64]) -> Vec<F1> {
+        let n = x.len();
+        let (r, q) = (&self.volumes, &self.areas);
+        let mean_volume: f64 = x.iter().zip(r).map(|(x, r)| x * r).sum();
+        let mean_area: f64 = x.iter().zip(q).map(|(x, q)| x * q).sum();
+        let theta: Vec<f64> = (0..n).map(|i| x[i] * q[i] / mean_area).collect();
+        let z = COORDINATION_NUMBER;
+        let l: Vec<f64> = (0..n)
+            .map(|i| z / 2.0 * (r[i] - q[i]) - (r[i] - 1.0))
+            .collect();
+        let mean_l: f64 = x.iter().zip(&l).map(|(x, l)| x * l).sum();
+
+        let tau: Vec<Vec<F1>> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| self.ln_tau[i][j].value(temperature).exp())
+                    .collect()
+            })
+            .collect();
+        // 'sum_k theta_k tau_ki' for each component
+        let sums: Vec<F1> = (0..n)
+            .map(|i| (0..n).map(|k| theta[k] * tau[k][i]).sum())
+            .collect();
+        (0..n)
+            .map(|i| {
+                // Ratios of the volume and area fractions to the mole
+                // fraction, which stay finite as the mole fraction vanishes
+                let phi = r[i] / mean_volume;
+                let area = q[i] / mean_area;
+                let combinatorial =
+                    phi.ln() + z / 2.0 * q[i] * (area / phi).ln() + l[i] - phi * mean_l;
+                let residual = q[i]
+                    * (1.0
+                        - sums[i].ln()
+                        - (0..n).map(|j| theta[j] * tau[i][j] / sums[j]).sum::<F1>());
+                combinatorial + residual
+            })
+            .collect()
+    }
+}
+
+/// # AntoineCoefficients
+///
+/// Coefficients of the Antoine equation for the vapor pressure of a pure
+/// component, 'ln(P / Pa) = A - B / (T / K + C)'.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AntoineCoefficients {
+    /// Constant term
+    pub a: f64,
+    /// Numerator of the temperature term, in K
+    pub b: f64,
+    /// Offset of the temperature, in K
+    pub c: f64,
+}
+
+impl AntoineCoefficients {
+    /// Vapor pressure at a given temperature.
+    pub fn vapor_pressure(&self, temperature: ThermodynamicTemperature) -> Pressure {
+        Pressure::new::<pascal>((self.a - self.b / (temperature.get::<kelvin>() + self.c)).exp())
+    }
+
+    /// Heat of vaporization from the Clausius-Clapeyron equation,
+    /// 'R T^2 d(ln P)/dT', which assumes an ideal gas vapor and a liquid of
+    /// negligible volume.
+    pub fn heat_of_vaporization(&self, temperature: ThermodynamicTemperature) -> MolarEnergy {
+        let t = temperature.get::<kelvin>();
+        MolarEnergy::new::<joule_per_mole>(GAS_CONSTANT * t * t * self.b / (t + self.c).powi(2))
+    }
+}
+
+/// # LiquidParameters
+///
+/// Pure component data a gamma-phi model needs besides the activity model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidParameters {
+    /// Vapor pressure correlation
+    pub antoine: AntoineCoefficients,
+    /// Molar mass
+    pub molar_mass: MolarMass,
+    /// Ideal gas heat capacity correlation
+    pub heat_capacity: HeatCapacityCoefficients,
+}
+
+/// The model used for the vapor phase of a gamma-phi model.
+#[derive(Debug, Clone)]
+pub enum VaporModel {
+    /// An ideal gas
+    IdealGas,
+    /// A cubic equation of state with the same components, in the same
+    /// order, as the activity model
+    Cubic(Arc<CubicEquation>),
+}
+
+/// # GammaPhi
+///
+/// Phase equilibrium from an activity model for the liquid and an ideal gas
+/// or cubic equation of state for the vapor. The fugacity of a component in
+/// the liquid is 'x_i gamma_i f_i', where 'f_i' is the fugacity of the pure
+/// saturated liquid: its vapor pressure, corrected by the fugacity
+/// coefficient of the saturated vapor when the vapor is not ideal. The
+/// Poynting correction is neglected.
+///
+/// Vapor enthalpies are ideal gas enthalpies, plus the departure of the
+/// cubic equation if there is one. Liquid enthalpies are the ideal gas
+/// enthalpy, less the heat of vaporization of each component, plus the
+/// excess enthalpy.
+#[derive(Clone)]
+pub struct GammaPhi {
+    /// Activity model of the liquid
+    pub liquid: Arc<dyn ActivityModel>,
+    /// Model of the vapor
+    pub vapor: VaporModel,
+    /// State where the enthalpy of each ideal gas is zero
+    pub reference_state: ReferenceState,
+    parameters: Vec<LiquidParameters>,
+}
+
+impl GammaPhi {
+    /// Combines an activity model with a vapor model. Pure component
+    /// parameters are given in the component order of the activity model.
+    pub fn new(
+        liquid: Arc<dyn ActivityModel>,
+        vapor: VaporModel,
+        parameters: Vec<LiquidParameters>,
+    ) -> Result<Self, &'static str> {
+        if parameters.len() != liquid.components().len() {
+            return Err("every component needs one set of parameters");
+        }
+        if let VaporModel::Cubic(equation) = &vapor {
+            if equation.components() != liquid.components() {
+                return Err("the vapor and liquid models must have the same components");
+            }
+        }
+        Ok(GammaPhi {
+            liquid,
+            vapor,
+            reference_state: ReferenceState::default(),
+            parameters,
+        })
+    }
+
+    /// Measures enthalpies from another reference state.
+    pub fn with_reference_state(mut self, reference_state: ReferenceState) -> Self {
+        self.reference_state = reference_state;
+        self
+    }
+
+    /// Pure component parameters, in the component order of the model
+    pub fn parameters(&self) -> &[LiquidParameters] {
+        &self.parameters
+    }
+
+    fn heat_capacities(&self) -> Vec<&HeatCapacityCoefficients> {
+        self.parameters
+            .iter()
+            .map(|parameters| &parameters.heat_capacity)
+            .collect()
+    }
+
+    /// Natural logarithm of the fugacity of each pure saturated liquid in Pa.
+    fn ln_pure_fugacities(
+        &self,
+        temperature: ThermodynamicTemperature,
+    ) -> Result<Vec<f64>, &'static str> {
+        let n = self.parameters.len();
+        self.parameters
+            .iter()
+            .enumerate()
+            .map(|(i, parameters)| {
+                let vapor_pressure = parameters.antoine.vapor_pressure(temperature);
+                let correction = match &self.vapor {
+                    VaporModel::IdealGas => 0.0,
+                    VaporModel::Cubic(equation) => {
+                        let mut pure = vec![0.0; n];
+                        pure[i] = 1.0;
+                        equation.ln_fugacity_coefficients(
+                            temperature,
+                            vapor_pressure,
+                            &pure,
+                            Phase::Vapor,
+                        )?[i]
+                    }
+                };
+                Ok(vapor_pressure.get::<pascal>().ln() + correction)
+            })
+            .collect()
+    }
+}
+
+impl PhaseEquilibrium for GammaPhi {
+    fn components(&self) -> &[String] {
+        self.liquid.components()
+    }
+
+    fn molar_masses(&self) -> Vec<MolarMass> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.molar_mass)
+            .collect()
+    }
+
+    fn flash_tp(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+    ) -> Result<FlashResult, &'static str> {
+        phase_stability::flash_vapor_liquid(self, temperature, pressure, composition)
+    }
+
+    fn ln_fugacity_coefficients(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        match (phase, &self.vapor) {
+            (Phase::Vapor, VaporModel::IdealGas) => Ok(vec![0.0; composition.len()]),
+            (Phase::Vapor, VaporModel::Cubic(equation)) => {
+                equation.ln_fugacity_coefficients(temperature, pressure, composition, phase)
+            }
+            (Phase::Liquid, _) => {
+                let ln_p = pressure.get::<pascal>().ln();
+                Ok(self
+                    .liquid
+                    .ln_activity_coefficients(temperature, composition)
+                    .iter()
+                    .zip(self.ln_pure_fugacities(temperature)?)
+                    .map(|(ln_gamma, ln_f)| ln_gamma + ln_f - ln_p)
+                    .collect())
+            }
+        }
+    }
+
+    fn phase_enthalpy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarEnergy, &'static str> {
+        let ideal = MolarEnergy::new::<joule_per_mole>(ideal_gas_enthalpy(
+            &self.heat_capacities(),
+            temperature.get::<kelvin>(),
+            composition,
+            &self.reference_state,
+        ));
+        match (phase, &self.vapor) {
+            (Phase::Vapor, VaporModel::IdealGas) => Ok(ideal),
+            (Phase::Vapor, VaporModel::Cubic(equation)) => {
+                Ok(ideal
+                    + equation.departure_enthalpy(temperature, pressure, composition, phase)?)
+            }
+            (Phase::Liquid, _) => {
+                Ok(self
+                    .parameters
+                    .iter()
+                    .zip(composition)
+                    .fold(ideal, |total, (parameters, x)| {
+                        total - parameters.antoine.heat_of_vaporization(temperature) * *x
+                    })
+                    + self.liquid.excess_enthalpy(temperature, composition))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod activity_tests {
+    use super::*;
+    use crate::thermodynamics::eos_models::{CubicModel, EOSParameters};
+    use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+    use uom::si::molar_mass::gram_per_mole;
+    use uom::si::ratio::ratio;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    /// Three models of a ternary mixture with temperature dependent
+    /// parameters.
+    fn ternary_models() -> Vec<Box<dyn ActivityModel>> {
+        let components = names(&["A", "B", "C"]);
+        let parameter = |a: f64, b: f64| BinaryParameter {
+            a,
+            b,
+            c: 0.01,
+            d: -1e-4,
+        };
+        let mut nrtl = NRTL::new(components.clone());
+        nrtl.set_binary_parameters("A", "B", parameter(0.3, 150.0), parameter(-0.2, 80.0), 0.3)
+            .unwrap();
+        nrtl.set_binary_parameters("A", "C", parameter(0.1, 50.0), parameter(0.4, -20.0), 0.2)
+            .unwrap();
+        nrtl.set_binary_parameters("B", "C", parameter(-0.5, 200.0), parameter(0.2, 10.0), 0.47)
+            .unwrap();
+        let mut wilson = Wilson::new(components.clone());
+        wilson
+            .set_binary_parameters("A", "B", parameter(0.2, -100.0), parameter(-0.3, -50.0))
+            .unwrap();
+        wilson
+            .set_binary_parameters("B", "C", parameter(0.1, -30.0), parameter(0.3, -120.0))
+            .unwrap();
+        let mut uniquac =
+            UNIQUAC::new(components, vec![1.5, 2.1, 3.2], vec![1.4, 1.9, 2.8]).unwrap();
+        uniquac
+            .set_binary_parameters("A", "B", parameter(0.1, -60.0), parameter(0.2, -90.0))
+            .unwrap();
+        uniquac
+            .set_binary_parameters("A", "C", parameter(-0.1, 40.0), parameter(0.0, -70.0))
+            .unwrap();
+        vec![Box::new(nrtl), Box::new(wilson), Box::new(uniquac)]
+    }
+
+    #[test]
+    fn activity_coefficients_are_partial_molar() {
+        // ln(gamma_i) is the derivative of 'n g_E / RT' by the amount of each
+        // component, and the excess enthalpy is the temperature derivative
+        // of 'g_E / RT'.
+        let temperature = ThermodynamicTemperature::new::<kelvin>(330.0);
+        let x = [0.2, 0.5, 0.3];
+        let total_excess = |model: &dyn ActivityModel, t: f64, n: &[f64]| {
+            let total: f64 = n.iter().sum();
+            let x: Vec<f64> = n.iter().map(|n| n / total).collect();
+            let excess = model.excess_gibbs_energy(ThermodynamicTemperature::new::<kelvin>(t), &x);
+            total * excess.get::<joule_per_mole>() / (GAS_CONSTANT * t)
+        };
+        for model in ternary_models() {
+            let ln_gamma = model.ln_activity_coefficients(temperature, &x);
+            for i in 0..3 {
+                let step = 1e-6;
+                let mut more = x.to_vec();
+                let mut less = x.to_vec();
+                more[i] += step;
+                less[i] -= step;
+                let derivative = (total_excess(model.as_ref(), 330.0, &more)
+                    - total_excess(model.as_ref(), 330.0, &less))
+                    / (2.0 * step);
+                assert!((derivative - ln_gamma[i]).abs() < 1e-7);
+            }
+
+            let step = 1e-3;
+            let derivative = (total_excess(model.as_ref(), 330.0 + step, &x)
+                - total_excess(model.as_ref(), 330.0 - step, &x))
+                / (2.0 * step);
+            let enthalpy = model.excess_enthalpy(temperature, &x);
+            assert!(
+                (enthalpy.get::<joule_per_mole>() + GAS_CONSTANT * 330.0 * 330.0 * derivative)
+                    .abs()
+                    < 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn infinite_dilution() {
+        let temperature = ThermodynamicTemperature::new::<kelvin>(300.0);
+        let dilute = [0.0, 1.0];
+
+        let mut nrtl = NRTL::new(names(&["A", "B"]));
+        let (tau_12, tau_21, alpha) = (0.8, 1.3, 0.3);
+        nrtl.set_binary_parameters(
+            "A",
+            "B",
+            BinaryParameter::constant(tau_12),
+            BinaryParameter::constant(tau_21),
+            alpha,
+        )
+        .unwrap();
+        let expected = tau_21 + tau_12 * (-alpha * tau_12).exp();
+        let ln_gamma = nrtl.ln_activity_coefficients(temperature, &dilute);
+        assert!((ln_gamma[0] - expected).abs() < 1e-12 && ln_gamma[1].abs() < 1e-12);
+
+        let mut wilson = Wilson::new(names(&["A", "B"]));
+        let (lambda_12, lambda_21): (f64, f64) = (0.4, 0.7);
+        wilson
+            .set_binary_parameters(
+                "A",
+                "B",
+                BinaryParameter::constant(lambda_12.ln()),
+                BinaryParameter::constant(lambda_21.ln()),
+            )
+            .unwrap();
+        let expected = 1.0 - lambda_12.ln() - lambda_21;
+        let ln_gamma = wilson.ln_activity_coefficients(temperature, &dilute);
+        assert!((ln_gamma[0] - expected).abs() < 1e-12);
+
+        // Without binary parameters, UNIQUAC only differs from an ideal
+        // solution through the size and shape of the molecules.
+        let uniquac = UNIQUAC::new(names(&["A", "B"]), vec![2.0, 2.0], vec![1.5, 1.5]).unwrap();
+        let ln_gamma = uniquac.ln_activity_coefficients(temperature, &[0.3, 0.7]);
+        assert!(ln_gamma.iter().all(|ln_gamma| ln_gamma.abs() < 1e-12));
+    }
+
+    #[test]
+    fn vapor_liquid_equilibrium() {
+        // Ethanol and water with illustrative parameters
+        let components = names(&["ethanol", "water"]);
+        let mut nrtl = NRTL::new(components.clone());
+        nrtl.set_binary_parameters(
+            "ethanol",
+            "water",
+            BinaryParameter::inverse_temperature(-50.0),
+            BinaryParameter::inverse_temperature(600.0),
+            0.3,
+        )
+        .unwrap();
+        let liquid: Arc<dyn ActivityModel> = Arc::new(nrtl);
+        let parameters = vec![
+            LiquidParameters {
+                antoine: AntoineCoefficients {
+                    a: 23.8047,
+                    b: 3803.98,
+                    c: -41.68,
+                },
+                molar_mass: MolarMass::new::<gram_per_mole>(46.07),
+                heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(65.6).into(),
+            },
+            LiquidParameters {
+                antoine: AntoineCoefficients {
+                    a: 23.1964,
+                    b: 3816.44,
+                    c: -46.13,
+                },
+                molar_mass: MolarMass::new::<gram_per_mole>(18.015),
+                heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(33.6).into(),
+            },
+        ];
+        let ideal =
+            GammaPhi::new(liquid.clone(), VaporModel::IdealGas, parameters.clone()).unwrap();
+
+        let temperature = ThermodynamicTemperature::new::<kelvin>(360.0);
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let z = [0.3, 0.7];
+        let flash = ideal.flash_tp(temperature, pressure, &z).unwrap();
+        let beta = flash.vapor_fraction.get::<ratio>();
+        assert!(beta > 0.0 && beta < 1.0);
+        // Modified Raoult's law: y_i P = x_i gamma_i P_sat,i
+        let (x, y) = (&flash.liquid_composition, &flash.vapor_composition);
+        let ln_gamma = liquid.ln_activity_coefficients(temperature, x);
+        for i in 0..2 {
+            let vapor_pressure = parameters[i].antoine.vapor_pressure(temperature);
+            let raoult = x[i] * ln_gamma[i].exp() * vapor_pressure.get::<pascal>();
+            assert!((y[i] * pressure.get::<pascal>() - raoult).abs() < 1e-6 * raoult);
+        }
+        assert!(y[0] > x[0]);
+        let latent = flash.vapor_enthalpy - flash.liquid_enthalpy;
+        assert!(latent.get::<joule_per_mole>() > 30_000.0);
+
+        // A cubic vapor barely changes the result at atmospheric pressure.
+        let equation = CubicEquation::new(
+            CubicModel::PengRobinson,
+            components,
+            [(513.9, 6.148e6, 0.645), (647.1, 22.064e6, 0.345)]
+                .iter()
+                .zip(&parameters)
+                .map(|((tc, pc, w), parameters)| EOSParameters {
+                    critical_temperature: ThermodynamicTemperature::new::<kelvin>(*tc),
+                    critical_pressure: Pressure::new::<pascal>(*pc),
+                    acentric_factor: *w,
+                    molar_mass: parameters.molar_mass,
+                    heat_capacity: parameters.heat_capacity.clone(),
+                })
+                .collect(),
+        )
+        .unwrap();
+        let cubic =
+            GammaPhi::new(liquid, VaporModel::Cubic(Arc::new(equation)), parameters).unwrap();
+        let corrected = cubic.flash_tp(temperature, pressure, &z).unwrap();
+        let difference = corrected.vapor_fraction.get::<ratio>() - beta;
+        assert!(difference != 0.0 && difference.abs() < 0.05);
+    }
+
+    #[test]
+    fn liquid_split_keeps_material_balance() {
+        // Strongly non-ideal parameters that split the liquid in two. A two
+        // phase flash only splits off a vapor, so the feed stays one liquid
+        // and no material is lost.
+        let mut nrtl = NRTL::new(names(&["A", "B"]));
+        nrtl.set_binary_parameters(
+            "A",
+            "B",
+            BinaryParameter::constant(3.0),
+            BinaryParameter::constant(3.0),
+            0.2,
+        )
+        .unwrap();
+        let parameters = [(23.8047, 3803.98, -41.68), (23.1964, 3816.44, -46.13)]
+            .iter()
+            .map(|(a, b, c)| LiquidParameters {
+                antoine: AntoineCoefficients {
+                    a: *a,
+                    b: *b,
+                    c: *c,
+                },
+                molar_mass: MolarMass::new::<gram_per_mole>(30.0),
+                heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(50.0).into(),
+            })
+            .collect();
+        let model = GammaPhi::new(Arc::new(nrtl), VaporModel::IdealGas, parameters).unwrap();
+        let z = [0.5, 0.5];
+        let flash = model
+            .flash_tp(
+                ThermodynamicTemperature::new::<kelvin>(300.0),
+                Pressure::new::<pascal>(1e6),
+                &z,
+            )
+            .unwrap();
+        let beta = flash.vapor_fraction.get::<ratio>();
+        for (i, z) in z.iter().enumerate() {
+            let total =
+                beta * flash.vapor_composition[i] + (1.0 - beta) * flash.liquid_composition[i];
+            assert!((total - z).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! # UNIFAC
+//!
+//! Group contribution activity coefficient models, for mixtures without
+//! measured binary parameters. Each component is split into functional
+//! subgroups, and the activity coefficients follow from the sizes of the
+//! subgroups and the interactions between their main groups.
+//!
+//! The original model (Fredenslund et al., 1975, with the parameters of
+//! Hansen et al., 1991) and the modified Dortmund model (Weidlich and
+//! Gmehling, 1987, with the parameters of Gmehling et al., 1993) are
+//! available. Their subgroup and interaction tables are bundled with the
+//! crate as CSV files under 'data/unifac', so no network access is needed.
+
+use super::ActivityModel;
+use autodiff::{Float, F1};
+use once_cell::sync::Lazy;
+use oscps_db::csv::{number, records};
+use std::collections::HashMap;
+
+/// Tables of the original UNIFAC model
+static ORIGINAL_TABLES: Lazy<UNIFACTables> = Lazy::new(|| {
+    UNIFACTables::parse(
+        include_str!("../../../data/unifac/original_groups.csv"),
+        include_str!("../../../data/unifac/original_interactions.csv"),
+    )
+    .expect("the bundled original UNIFAC tables are valid")
+});
+
+/// Tables of the modified (Dortmund) UNIFAC model
+static DORTMUND_TABLES: Lazy<UNIFACTables> = Lazy::new(|| {
+    UNIFACTables::parse(
+        include_str!("../../../data/unifac/dortmund_groups.csv"),
+        include_str!("../../../data/unifac/dortmund_interactions.csv"),
+    )
+    .expect("the bundled Dortmund UNIFAC tables are valid")
+});
+
+/// The variants of the UNIFAC model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UNIFACVariant {
+    /// The original model, with temperature independent interactions
+    Original,
+    /// The modified Dortmund model, with a modified combinatorial part and
+    /// temperature dependent interactions
+    Dortmund,
+}
+
+impl UNIFACVariant {
+    /// The subgroup and interaction tables bundled for this variant.
+    pub fn tables(&self) -> &'static UNIFACTables {
+        match self {
+            UNIFACVariant::Original => &ORIGINAL_TABLES,
+            UNIFACVariant::Dortmund => &DORTMUND_TABLES,
+        }
+    }
+}
+
+/// # Subgroup
+///
+/// A functional group of a UNIFAC table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subgroup {
+    /// Subgroup number
+    pub id: usize,
+    /// Name of the subgroup, such as 'CH3'
+    pub name: String,
+    /// Main group the subgroup belongs to, which sets its interactions
+    pub main_group: usize,
+    /// Relative van der Waals volume 'R_k'
+    pub volume: f64,
+    /// Relative van der Waals surface area 'Q_k'
+    pub area: f64,
+}
+
+/// # UNIFACTables
+///
+/// Subgroups and main group interaction parameters of a UNIFAC variant. The
+/// interaction between main groups 'n' and 'm' is
+/// 'psi_nm = exp(-(a_nm + b_nm T + c_nm T^2) / T)', where the original model
+/// only has 'a_nm'.
+#[derive(Debug, Clone, Default)]
+pub struct UNIFACTables {
+    subgroups: HashMap<usize, Subgroup>,
+    interactions: HashMap<(usize, usize), [f64; 3]>,
+}
+
+impl UNIFACTables {
+    /// Reads tables from CSV text. Subgroup records are
+    /// 'subgroup,name,main_group,R,Q', and interaction records are
+    /// 'main_group_n,main_group_m,a_nm' optionally followed by 'b_nm,c_nm'.
+    pub fn parse(subgroups: &str, interactions: &str) -> Result<Self, &'static str> {
+        let mut tables = UNIFACTables::default();
+        for record in records(subgroups) {
+            if record.len() != 5 {
+                return Err("a UNIFAC subgroup record needs five fields");
+            }
+            let subgroup = Subgroup {
+                id: number(record[0])?,
+                name: String::from(record[1]),
+                main_group: number(record[2])?,
+                volume: number(record[3])?,
+                area: number(record[4])?,
+            };
+            tables.subgroups.insert(subgroup.id, subgroup);
+        }
+        for record in records(interactions) {
+            if record.len() != 3 && record.len() != 5 {
+                return Err("a UNIFAC interaction record needs three or five fields");
+            }
+            let mut parameters = [0.0; 3];
+            for (parameter, field) in parameters.iter_mut().zip(&record[2..]) {
+                *parameter = number(field)?;
+            }
+            tables
+                .interactions
+                .insert((number(record[0])?, number(record[1])?), parameters);
+        }
+        Ok(tables)
+    }
+
+    /// Looks up a subgroup by number.
+    pub fn subgroup(&self, id: usize) -> Option<&Subgroup> {
+        self.subgroups.get(&id)
+    }
+
+    /// Looks up a subgroup by name.
+    pub fn subgroup_named(&self, name: &str) -> Option<&Subgroup> {
+        self.subgroups
+            .values()
+            .find(|subgroup| subgroup.name == name)
+    }
+
+    /// Parameters 'a_nm', 'b_nm' and 'c_nm' of the interaction between two
+    /// main groups, which are zero within a main group.
+    pub fn interaction(&self, n: usize, m: usize) -> Option<[f64; 3]> {
+        if n == m {
+            return Some([0.0; 3]);
+        }
+        self.interactions.get(&(n, m)).copied()
+    }
+}
+
+/// The groups making up a molecule, as pairs of a subgroup number and the
+/// number of times it occurs.
+pub type GroupDecomposition = Vec<(usize, usize)>;
+
+/// # UNIFACGroups
+///
+/// Group decompositions of a species for both UNIFAC variants, which number
+/// some subgroups differently.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UNIFACGroups {
+    /// Subgroups of the original model
+    pub original: GroupDecomposition,
+    /// Subgroups of the Dortmund model
+    pub dortmund: GroupDecomposition,
+}
+
+impl UNIFACGroups {
+    /// The decomposition for a given variant.
+    pub fn decomposition(&self, variant: UNIFACVariant) -> &GroupDecomposition {
+        match variant {
+            UNIFACVariant::Original => &self.original,
+            UNIFACVariant::Dortmund => &self.dortmund,
+        }
+    }
+}
+
+/// # UNIFAC
+///
+/// A UNIFAC activity model for a set of components. The activity
+/// coefficient of each component is the product of a combinatorial part,
+/// from the size and shape of the molecules, and a residual part, from the
+/// interactions between their groups.
+#[derive(Debug, Clone)]
+pub struct UNIFAC {
+    components: Vec<String>,
+    variant: UNIFACVariant,
+    /// Number of each subgroup in each component
+    counts: Vec<Vec<f64>>,
+    /// Surface area 'Q_k' of each subgroup
+    group_areas: Vec<f64>,
+    /// Volume 'r_i' of each component
+    volumes: Vec<f64>,
+    /// Surface area 'q_i' of each component
+    areas: Vec<f64>,
+    /// Interaction parameters between the main groups of each pair of
+    /// subgroups
+    interactions: Vec<Vec<[f64; 3]>>,
+}
+
+impl UNIFAC {
+    /// Creates a model from the group decomposition of each component,
+    /// using the tables bundled for the variant.
+    pub fn new(
+        variant: UNIFACVariant,
+        components: Vec<String>,
+        groups: Vec<GroupDecomposition>,
+    ) -> Result<Self, &'static str> {
+        UNIFAC::with_tables(variant, variant.tables(), components, groups)
+    }
+
+    /// Creates a model from the group decomposition of each component,
+    /// using the given tables.
+    pub fn with_tables(
+        variant: UNIFACVariant,
+        tables: &UNIFACTables,
+        components: Vec<String>,
+        groups: Vec<GroupDecomposition>,
+    ) -> Result<Self, &'static str> {
+        if groups.len() != components.len() {
+            return Err("every component needs a group decomposition");
+        }
+        let mut subgroups: Vec<&Subgroup> = Vec::new();
+        for &(id, _) in groups.iter().flatten() {
+            let subgroup = tables
+                .subgroup(id)
+                .ok_or("a subgroup is missing from the UNIFAC tables")?;
+            if !subgroups.contains(&subgroup) {
+                subgroups.push(subgroup);
+            }
+        }
+        let counts: Vec<Vec<f64>> = groups
+            .iter()
+            .map(|decomposition| {
+                subgroups
+                    .iter()
+                    .map(|subgroup| {
+                        decomposition
+                            .iter()
+                            .filter(|(id, _)| *id == subgroup.id)
+                            .map(|(_, count)| *count as f64)
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect();
+        if counts
+            .iter()
+            .any(|counts| counts.iter().sum::<f64>() == 0.0)
+        {
+            return Err("every component needs at least one group");
+        }
+        let interactions = subgroups
+            .iter()
+            .map(|n| {
+                subgroups
+                    .iter()
+                    .map(|m| {
+                        tables
+                            .interaction(n.main_group, m.main_group)
+                            .ok_or("no UNIFAC interaction parameters between two main groups")
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        let sizes = |property: fn(&Subgroup) -> f64| -> Vec<f64> {
+            counts
+                .iter()
+                .map(|counts| {
+                    counts
+                        .iter()
+                        .zip(&subgroups)
+                        .map(|(count, subgroup)| count * property(subgroup))
+                        .sum()
+                })
+                .collect()
+        };
+        let volumes = sizes(|subgroup| subgroup.volume);
+        let areas = sizes(|subgroup| subgroup.area);
+        Ok(UNIFAC {
+            group_areas: subgroups.iter().map(|subgroup| subgroup.area).collect(),
+            components,
+            variant,
+            counts,
+            volumes,
+            areas,
+            interactions,
+        })
+    }
+
+    /// The variant of the model
+    pub fn variant(&self) -> UNIFACVariant {
+        self.variant
+    }
+
+    /// Natural logarithm of the activity coefficient 'Gamma_k' of each
+    /// subgroup in a mixture with the given amount of each subgroup.
+    fn ln_group_activities(&self, psi: &[Vec<F1>], amounts: &[f64]) -> Vec<F1> {
+        let total: f64 = amounts
+            .iter()
+            .zip(&self.group_areas)
+            .map(|(amount, area)| amount * area)
+            .sum();
+        let theta: Vec<f64> = amounts
+            .iter()
+            .zip(&self.group_areas)
+            .map(|(amount, area)| amount * area / total)
+            .collect();
+        let groups = theta.len();
+        // 'sum_n theta_n psi_nm' for each subgroup
+        let sums: Vec<F1> = (0..groups)
+            .map(|m| (0..groups).map(|n| theta[n] * psi[n][m]).sum())
+            .collect();
+        (0..groups)
+            .map(|k| {
+                self.group_areas[k]
+                    * (1.0
+                        - sums[k].ln()
+                        - (0..groups)
+                            .map(|m| theta[m] * psi[k][m] / sums[m])
+                            .sum::<F1>())
+            })
+            .collect()
+    }
+}
+
+impl ActivityModel for UNIFAC {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn ln_activity_coefficients_dual(&self, temperature: F1, x: &[f64]) -> Vec<F1> {
+        let psi: Vec<Vec<F1>> = self
+            .interactions
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|[a, b, c]| {
+                        (-(*a + *b * temperature + *c * temperature * temperature) / temperature)
+                            .exp()
+                    })
+                    .collect()
+            })
+            .collect();
+        let groups = self.group_areas.len();
+        let amounts: Vec<f64> = (0..groups)
+            .map(|k| x.iter().zip(&self.counts).map(|(x, n)| x * n[k]).sum())
+            .collect();
+        let mixture = self.ln_group_activities(&psi, &amounts);
+
+        let mean_volume: f64 = x.iter().zip(&self.volumes).map(|(x, r)| x * r).sum();
+        let mean_area: f64 = x.iter().zip(&self.areas).map(|(x, q)| x * q).sum();
+        let mean_modified: f64 = x
+            .iter()
+            .zip(&self.volumes)
+            .map(|(x, r)| x * r.powf(0.75))
+            .sum();
+        (0..x.len())
+            .map(|i| {
+                let (r, q) = (self.volumes[i], self.areas[i]);
+                let volume = r / mean_volume;
+                let area = q / mean_area;
+                let modified = match self.variant {
+                    UNIFACVariant::Original => volume,
+                    UNIFACVariant::Dortmund => r.powf(0.75) / mean_modified,
+                };
+                let combinatorial = 1.0 - modified + modified.ln()
+                    - 5.0 * q * (1.0 - volume / area + (volume / area).ln());
+                let pure = self.ln_group_activities(&psi, &self.counts[i]);
+                let residual: F1 = (0..groups)
+                    .filter(|&k| self.counts[i][k] > 0.0)
+                    .map(|k| self.counts[i][k] * (mixture[k] - pure[k]))
+                    .sum();
+                residual + combinatorial
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod unifac_tests {
+    use super::*;
+    use crate::thermodynamics::eos_models::GAS_CONSTANT;
+    use std::collections::HashSet;
+    use uom::si::f64::ThermodynamicTemperature;
+    use uom::si::molar_energy::joule_per_mole;
+    use uom::si::thermodynamic_temperature::kelvin;
+
+    /// Ethanol, water and n-hexane in both variants
+    fn mixture(variant: UNIFACVariant) -> UNIFAC {
+        let ethanol = UNIFACGroups {
+            original: vec![(1, 1), (2, 1), (14, 1)],
+            dortmund: vec![(1, 1), (2, 1), (14, 1)],
+        };
+        let water = UNIFACGroups {
+            original: vec![(16, 1)],
+            dortmund: vec![(16, 1)],
+        };
+        let hexane = UNIFACGroups {
+            original: vec![(1, 2), (2, 4)],
+            dortmund: vec![(1, 2), (2, 4)],
+        };
+        UNIFAC::new(
+            variant,
+            vec![
+                String::from("ethanol"),
+                String::from("water"),
+                String::from("n-hexane"),
+            ],
+            [ethanol, water, hexane]
+                .iter()
+                .map(|groups| groups.decomposition(variant).clone())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn bundled_tables() {
+        for variant in [UNIFACVariant::Original, UNIFACVariant::Dortmund] {
+            let tables = variant.tables();
+            let methyl = tables.subgroup_named("CH3").unwrap();
+            assert_eq!((methyl.id, methyl.main_group), (1, 1));
+            assert!(tables.subgroup(16).is_some());
+            assert!(tables.interaction(1, 7).is_some() && tables.interaction(7, 1).is_some());
+            // Subgroups are looked up by name, so no two share one
+            let names: HashSet<&str> = tables
+                .subgroups
+                .values()
+                .map(|subgroup| subgroup.name.as_str())
+                .collect();
+            assert_eq!(names.len(), tables.subgroups.len());
+        }
+        assert!(UNIFACTables::parse("1,CH3,1,0.9011", "").is_err());
+        let ether = UNIFACVariant::Original
+            .tables()
+            .subgroup_named("CH-O")
+            .unwrap();
+        assert_eq!((ether.id, ether.main_group), (26, 13));
+        let groups = vec![vec![(15, 1)], vec![(16, 1)]];
+        let names = vec![String::from("methanol"), String::from("water")];
+        assert!(UNIFAC::new(UNIFACVariant::Original, names, groups).is_ok());
+
+        // Infinite dilution activity coefficients of ethanol and water at
+        // their boiling points, against the Van Laar fit of the VLE at
+        // 1 atm (Perry's Chemical Engineers' Handbook): ln(gamma) of 1.6798
+        // for ethanol in water and 0.9227 for water in ethanol.
+        let model = UNIFAC::new(
+            UNIFACVariant::Dortmund,
+            vec![String::from("ethanol"), String::from("water")],
+            vec![vec![(1, 1), (2, 1), (14, 1)], vec![(16, 1)]],
+        )
+        .unwrap();
+        let dilute = |temperature: f64, x: [f64; 2], i: usize| {
+            let temperature = ThermodynamicTemperature::new::<kelvin>(temperature);
+            model.ln_activity_coefficients(temperature, &x)[i].exp()
+        };
+        let ethanol = dilute(373.15, [0.0, 1.0], 0);
+        let water = dilute(351.44, [1.0, 0.0], 1);
+        assert!((ethanol / 1.6798f64.exp() - 1.0).abs() < 0.15, "{ethanol}");
+        assert!((water / 0.9227f64.exp() - 1.0).abs() < 0.05, "{water}");
+    }
+
+    #[test]
+    fn activity_coefficients_are_partial_molar() {
+        let t = 340.0;
+        let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+        let x = [0.3, 0.6, 0.1];
+        for variant in [UNIFACVariant::Original, UNIFACVariant::Dortmund] {
+            let model = mixture(variant);
+            let total_excess = |n: &[f64]| {
+                let total: f64 = n.iter().sum();
+                let x: Vec<f64> = n.iter().map(|n| n / total).collect();
+                let excess = model.excess_gibbs_energy(temperature, &x);
+                total * excess.get::<joule_per_mole>() / (GAS_CONSTANT * t)
+            };
+            let ln_gamma = model.ln_activity_coefficients(temperature, &x);
+            for i in 0..3 {
+                let step = 1e-6;
+                let mut more = x.to_vec();
+                let mut less = x.to_vec();
+                more[i] += step;
+                less[i] -= step;
+                let derivative = (total_excess(&more) - total_excess(&less)) / (2.0 * step);
+                assert!((derivative - ln_gamma[i]).abs() < 1e-6);
+            }
+            // A pure component is ideal
+            let pure = model.ln_activity_coefficients(temperature, &[0.0, 1.0, 0.0]);
+            assert!(pure[1].abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn ethanol_and_water() {
+        // Both variants predict positive deviations from Raoult's law, with
+        // ethanol more non-ideal when dilute in water than water in ethanol.
+        let temperature = ThermodynamicTemperature::new::<kelvin>(298.15);
+        for variant in [UNIFACVariant::Original, UNIFACVariant::Dortmund] {
+            let model = mixture(variant);
+            let in_water = model.ln_activity_coefficients(temperature, &[0.0, 1.0, 0.0]);
+            let in_ethanol = model.ln_activity_coefficients(temperature, &[1.0, 0.0, 0.0]);
+            let (ethanol, water) = (in_water[0].exp(), in_ethanol[1].exp());
+            assert!(ethanol > 2.0 && ethanol < 10.0);
+            assert!(water > 1.5 && water < ethanol);
+            // Hexane is nearly immiscible with water
+            assert!(in_water[2].exp() > 100.0);
+        }
+    }
+
+    #[test]
+    fn similar_molecules_are_nearly_ideal() {
+        // n-Hexane and n-heptane share their groups, so only the
+        // combinatorial part remains.
+        let model = UNIFAC::new(
+            UNIFACVariant::Original,
+            vec![String::from("n-hexane"), String::from("n-heptane")],
+            vec![vec![(1, 2), (2, 4)], vec![(1, 2), (2, 5)]],
+        )
+        .unwrap();
+        let temperature = ThermodynamicTemperature::new::<kelvin>(320.0);
+        let ln_gamma = model.ln_activity_coefficients(temperature, &[0.5, 0.5]);
+        assert!(ln_gamma.iter().all(|ln_gamma| ln_gamma.abs() < 0.01));
+        let enthalpy = model.excess_enthalpy(temperature, &[0.5, 0.5]);
+        assert!(enthalpy.get::<joule_per_mole>().abs() < 1e-9);
+    }
+}
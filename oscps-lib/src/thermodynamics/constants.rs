@@ -0,0 +1,123 @@
+//! # Constants
+//!
+//! Physical constants as `uom` quantities, usable directly in calculations,
+//! and the reference states that enthalpies and entropies are measured from.
+//!
+//! Values of the constants are the exact values of the 2019 SI, except for
+//! the gas constant, which is rounded to the ten digits used throughout the
+//! thermodynamic models.
+
+use std::marker::PhantomData;
+use uom::si::f64::*;
+use uom::si::{Dimension, Quantity, Units, ISQ, SI};
+use uom::typenum::{N1, Z0};
+
+/// A quantity in reciprocal moles, the dimension of the Avogadro constant
+pub type ReciprocalAmountOfSubstance = Quantity<ISQ<Z0, Z0, Z0, Z0, Z0, N1, Z0>, SI<f64>, f64>;
+
+/// Creates a quantity from its value in SI base units. Unlike
+/// `Quantity::new`, this can be used in constants.
+const fn quantity<D, U>(value: f64) -> Quantity<D, U, f64>
+where
+    D: Dimension + ?Sized,
+    U: Units<f64> + ?Sized,
+{
+    Quantity {
+        dimension: PhantomData,
+        units: PhantomData,
+        value,
+    }
+}
+
+/// Universal gas constant, 8.314462618 J/(mol*K)
+pub const UNIVERSAL_GAS_CONSTANT: MolarHeatCapacity = quantity(8.314462618);
+
+/// Avogadro constant, 6.02214076e23 mol^-1
+pub const AVOGADRO_CONSTANT: ReciprocalAmountOfSubstance = quantity(6.02214076e23);
+
+/// Boltzmann constant, 1.380649e-23 J/K
+pub const BOLTZMANN_CONSTANT: HeatCapacity = quantity(1.380649e-23);
+
+/// Standard pressure, 1 bar, as recommended by IUPAC since 1982
+pub const STANDARD_PRESSURE: Pressure = quantity(100_000.0);
+
+/// Standard temperature of thermochemical tables, 298.15 K
+pub const STANDARD_TEMPERATURE: ThermodynamicTemperature = quantity(298.15);
+
+/// One standard atmosphere, 101325 Pa
+pub const STANDARD_ATMOSPHERE: Pressure = quantity(101_325.0);
+
+/// Normal temperature, 273.15 K, the melting point of ice at one atmosphere
+pub const NORMAL_TEMPERATURE: ThermodynamicTemperature = quantity(273.15);
+
+/// # ReferenceState
+///
+/// The temperature and pressure at which the enthalpy and entropy of every
+/// pure component as an ideal gas are zero. Changing the reference state
+/// shifts enthalpies and entropies by a constant for each component, so
+/// energy balances and phase equilibria are unchanged and only the reported
+/// values move. Other conventions can be built from the public fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceState {
+    /// Temperature of the reference state
+    pub temperature: ThermodynamicTemperature,
+    /// Pressure of the reference state
+    pub pressure: Pressure,
+}
+
+impl ReferenceState {
+    /// The IUPAC standard state of 298.15 K and 1 bar, used by current
+    /// thermochemical tables
+    pub const STANDARD: ReferenceState = ReferenceState {
+        temperature: STANDARD_TEMPERATURE,
+        pressure: STANDARD_PRESSURE,
+    };
+
+    /// Normal conditions of 273.15 K and one atmosphere, which normal gas
+    /// volumes are measured at
+    pub const NORMAL: ReferenceState = ReferenceState {
+        temperature: NORMAL_TEMPERATURE,
+        pressure: STANDARD_ATMOSPHERE,
+    };
+
+    /// 298.15 K and one atmosphere, the standard state of older tables and
+    /// the default of the models in this crate
+    pub const ATMOSPHERIC: ReferenceState = ReferenceState {
+        temperature: STANDARD_TEMPERATURE,
+        pressure: STANDARD_ATMOSPHERE,
+    };
+}
+
+impl Default for ReferenceState {
+    fn default() -> Self {
+        ReferenceState::ATMOSPHERIC
+    }
+}
+
+#[cfg(test)]
+mod constants_tests {
+    use super::*;
+    use uom::si::amount_of_substance::mole;
+    use uom::si::energy::joule;
+    use uom::si::pressure::{atmosphere, bar};
+    use uom::si::thermodynamic_temperature::{degree_celsius, kelvin};
+
+    #[test]
+    fn constants_have_their_units() {
+        // R = N_A k_B
+        let product: MolarHeatCapacity = AVOGADRO_CONSTANT * BOLTZMANN_CONSTANT;
+        assert!(((product / UNIVERSAL_GAS_CONSTANT).value - 1.0).abs() < 1e-9);
+
+        // RT is an energy per mole
+        let energy = UNIVERSAL_GAS_CONSTANT
+            * ThermodynamicTemperature::new::<kelvin>(300.0)
+            * AmountOfSubstance::new::<mole>(2.0);
+        assert!((energy.get::<joule>() - 2.0 * 300.0 * 8.314462618).abs() < 1e-9);
+
+        assert!((STANDARD_PRESSURE.get::<bar>() - 1.0).abs() < 1e-12);
+        assert!((STANDARD_ATMOSPHERE.get::<atmosphere>() - 1.0).abs() < 1e-12);
+        assert!(NORMAL_TEMPERATURE.get::<degree_celsius>().abs() < 1e-9);
+        assert!((STANDARD_TEMPERATURE.get::<degree_celsius>() - 25.0).abs() < 1e-9);
+        assert_eq!(ReferenceState::default(), ReferenceState::ATMOSPHERIC);
+    }
+}
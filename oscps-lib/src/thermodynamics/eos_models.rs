@@ -1,23 +1,1072 @@
-///#EOSModel
-///
-///This struct will represent the different thermodynamic equations of state 
+//! # EOS Models
+//!
+//! Equations of state for mixtures. Each implements [`EquationOfState`], so
+//! [`EOSModel`] can describe a mixture with any of them.
+//!
+//! The Redlich-Kwong,
+//! Soave-Redlich-Kwong and Peng-Robinson equations are all of the form
+//!
+//! ```text
+//! P = RT / (v - b) - a(T) / ((v + d1 b) (v + d2 b))
+//! ```
+//!
+//! and only differ in the constants 'd1' and 'd2', the critical point
+//! constants of 'a' and 'b', and the temperature dependence of 'a'. They
+//! share a single implementation, [`CubicEquation`], with the variant chosen
+//! by [`CubicModel`].
+//!
+//! Each equation is combined with the van der Waals one-fluid mixing rules,
+//! `a = sum_i sum_j x_i x_j sqrt(a_i a_j) (1 - k_ij)` and `b = sum_i x_i b_i`,
+//! where `k_ij` are binary interaction parameters. Liquid volumes can be
+//! improved with the Peneloux volume translation, which shifts the volume of
+//! every phase without changing the phase equilibrium.
+//!
+//! Enthalpies and entropies are the ideal gas values plus the departure
+//! functions of the equation. The ideal gas reference state is the pure
+//! component at 298.15 K and 101325 Pa unless another [`ReferenceState`] is
+//! chosen.
+//!
+//! Inspired by: https://github.com/ClapeyronThermo/Clapeyron.jl
 
+///The PC-SAFT equation of state
+pub mod pc_saft;
 
-use crate::thermodynamics::*;
+use crate::thermodynamics::constants::{ReferenceState, UNIVERSAL_GAS_CONSTANT};
+use crate::thermodynamics::heat_capacity::HeatCapacityCoefficients;
+use crate::thermodynamics::phase_stability;
+use crate::thermodynamics::{FlashResult, MaxwellRelations, Phase, PhaseEquilibrium};
+use std::f64::consts::{PI, SQRT_2};
+use std::fmt;
 use std::sync::Arc;
+use uom::si::amount_of_substance::mole;
 use uom::si::f64::*;
-use uom::si::molar_energy;
-use uom::si::molar_heat_capacity;
-use uom::si::pressure;
-use uom::si::thermodynamic_temperature;
-use uom::si::energy;
-use uom::si::amount_of_substance;
-use uom::si::volume;
-use uom::si::ratio;
-
-
-/// This struct will hold the chemical potential equation for each type of equation of state
-/// Inspired by: https://github.com/ClapeyronThermo/Clapeyron.jl
+use uom::si::molar_energy::joule_per_mole;
+use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+use uom::si::molar_volume::cubic_meter_per_mole;
+use uom::si::pressure::pascal;
+use uom::si::ratio::ratio;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// Universal gas constant in J/(mol*K)
+pub(crate) const GAS_CONSTANT: f64 = UNIVERSAL_GAS_CONSTANT.value;
+
+/// # EquationOfState
+///
+/// Volumetric and caloric properties of a single phase, on top of the phase
+/// equilibrium every equation of state provides.
+pub trait EquationOfState: PhaseEquilibrium {
+    ///Molar volume of a phase
+    fn molar_volume(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarVolume, &'static str>;
+
+    ///Molar entropy of a phase, including the entropy of mixing
+    fn phase_entropy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarHeatCapacity, &'static str>;
+}
+
+impl fmt::Debug for dyn EquationOfState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EquationOfState")
+            .field("components", &self.components())
+            .finish()
+    }
+}
+
+/// Molar enthalpy of an ideal gas mixture in J/mol, relative to the
+/// reference state.
+pub(crate) fn ideal_gas_enthalpy(
+    heat_capacities: &[&HeatCapacityCoefficients],
+    t: f64,
+    x: &[f64],
+    reference: &ReferenceState,
+) -> f64 {
+    let t = ThermodynamicTemperature::new::<kelvin>(t);
+    x.iter()
+        .zip(heat_capacities)
+        .map(|(x, cp)| {
+            x * cp
+                .enthalpy_change(reference.temperature, t)
+                .get::<joule_per_mole>()
+        })
+        .sum()
+}
+
+/// Molar entropy of an ideal gas mixture in J/(mol*K), relative to the
+/// reference state and including the entropy of mixing.
+pub(crate) fn ideal_gas_entropy(
+    heat_capacities: &[&HeatCapacityCoefficients],
+    t: f64,
+    p: f64,
+    x: &[f64],
+    reference: &ReferenceState,
+) -> f64 {
+    let t = ThermodynamicTemperature::new::<kelvin>(t);
+    x.iter()
+        .zip(heat_capacities)
+        .filter(|(x, _)| **x > 0.0)
+        .map(|(x, cp)| {
+            let change = cp.entropy_change(reference.temperature, t);
+            x * (change.get::<joule_per_kelvin_mole>() - GAS_CONSTANT * x.ln())
+        })
+        .sum::<f64>()
+        - GAS_CONSTANT * (p / reference.pressure.get::<pascal>()).ln()
+}
+
+/// # EOSParameters
+///
+/// Pure component parameters of a cubic equation of state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EOSParameters {
+    /// Critical temperature
+    pub critical_temperature: ThermodynamicTemperature,
+    /// Critical pressure
+    pub critical_pressure: Pressure,
+    /// Acentric factor
+    pub acentric_factor: f64,
+    /// Molar mass
+    pub molar_mass: MolarMass,
+    /// Ideal gas heat capacity correlation
+    pub heat_capacity: HeatCapacityCoefficients,
+}
+
+/// # CubicModel
+///
+/// The cubic equations of state available in [`CubicEquation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubicModel {
+    /// Redlich-Kwong (1949), with 'a' proportional to '1 / sqrt(T)'
+    RedlichKwong,
+    /// Soave-Redlich-Kwong (1972)
+    SoaveRedlichKwong,
+    /// Peng-Robinson, with the 1978 correlation for the temperature
+    /// dependence of 'a'
+    PengRobinson,
+}
+
+impl CubicModel {
+    /// The constants 'd1' and 'd2' of the denominator of the attraction term.
+    fn deltas(&self) -> (f64, f64) {
+        match self {
+            CubicModel::RedlichKwong | CubicModel::SoaveRedlichKwong => (1.0, 0.0),
+            CubicModel::PengRobinson => (1.0 + SQRT_2, 1.0 - SQRT_2),
+        }
+    }
+
+    /// The constants of 'a' and 'b' at the critical point, 'a_c = omega_a
+    /// (R T_c)^2 / P_c' and 'b = omega_b R T_c / P_c'.
+    fn omegas(&self) -> (f64, f64) {
+        match self {
+            CubicModel::RedlichKwong | CubicModel::SoaveRedlichKwong => (0.42748, 0.08664),
+            CubicModel::PengRobinson => (0.45724, 0.07780),
+        }
+    }
+
+    /// The Peneloux volume shift, '(R T_c / P_c) (k1 - k2 Z_RA)', returned as
+    /// '(k1, k2)'. The Redlich-Kwong equation uses the constants of
+    /// Soave-Redlich-Kwong.
+    fn peneloux_constants(&self) -> (f64, f64) {
+        match self {
+            CubicModel::RedlichKwong | CubicModel::SoaveRedlichKwong => {
+                (0.40768 * 0.29441, 0.40768)
+            }
+            CubicModel::PengRobinson => (0.50033 * 0.25969, 0.50033),
+        }
+    }
+
+    /// The attraction parameter relative to its critical value, 'alpha', and
+    /// its temperature derivative.
+    fn alpha(&self, t: f64, tc: f64, w: f64) -> (f64, f64) {
+        let m = match self {
+            CubicModel::RedlichKwong => {
+                let alpha = (tc / t).sqrt();
+                return (alpha, -0.5 * alpha / t);
+            }
+            CubicModel::SoaveRedlichKwong => 0.480 + 1.574 * w - 0.176 * w * w,
+            CubicModel::PengRobinson if w <= 0.491 => 0.37464 + 1.54226 * w - 0.26992 * w * w,
+            CubicModel::PengRobinson => {
+                0.379642 + 1.48503 * w - 0.164423 * w * w + 0.016666 * w * w * w
+            }
+        };
+        let sqrt_alpha = 1.0 + m * (1.0 - (t / tc).sqrt());
+        (sqrt_alpha * sqrt_alpha, -m * sqrt_alpha / (t * tc).sqrt())
+    }
+}
+
+/// # CubicEquation
+///
+/// A cubic equation of state for a mixture. Changing 'model' switches
+/// between the Redlich-Kwong, Soave-Redlich-Kwong and Peng-Robinson
+/// equations with the same component parameters.
+#[derive(Debug, Clone)]
+pub struct CubicEquation {
+    /// Which cubic equation of state is used
+    pub model: CubicModel,
+    /// Whether the Peneloux volume translation is applied
+    pub volume_translation: bool,
+    /// State where the enthalpy and entropy of each ideal gas are zero
+    pub reference_state: ReferenceState,
+    components: Vec<String>,
+    parameters: Vec<EOSParameters>,
+    interaction_parameters: Vec<Vec<f64>>,
+}
+
+/// Mixture parameters of the equation at a given temperature and
+/// composition, in SI units.
+struct MixtureParameters {
+    /// Attraction parameter of the mixture
+    a: f64,
+    /// Temperature derivative of the attraction parameter of the mixture
+    da_dt: f64,
+    /// Covolume of the mixture
+    b: f64,
+    /// 'sum_j x_j a_ij' for each component
+    partial_a: Vec<f64>,
+    /// Covolume of each component
+    partial_b: Vec<f64>,
+}
+
+impl CubicEquation {
+    /// Creates the equation for a set of components, with every binary
+    /// interaction parameter set to zero and no volume translation.
+    pub fn new(
+        model: CubicModel,
+        components: Vec<String>,
+        parameters: Vec<EOSParameters>,
+    ) -> Result<Self, &'static str> {
+        if components.len() != parameters.len() {
+            return Err("every component needs one set of parameters");
+        }
+        let n = components.len();
+        Ok(CubicEquation {
+            model,
+            volume_translation: false,
+            reference_state: ReferenceState::default(),
+            components,
+            parameters,
+            interaction_parameters: vec![vec![0.0; n]; n],
+        })
+    }
+
+    /// Sets the binary interaction parameter 'k_ij' between two components.
+    /// The parameter is symmetric.
+    pub fn set_interaction_parameter(
+        &mut self,
+        first: &str,
+        second: &str,
+        value: f64,
+    ) -> Result<(), &'static str> {
+        let i = self.index(first)?;
+        let j = self.index(second)?;
+        if i == j {
+            return Err("a component has no interaction parameter with itself");
+        }
+        self.interaction_parameters[i][j] = value;
+        self.interaction_parameters[j][i] = value;
+        Ok(())
+    }
+
+    /// Returns the binary interaction parameter 'k_ij' between two components.
+    pub fn interaction_parameter(&self, first: &str, second: &str) -> Result<f64, &'static str> {
+        Ok(self.interaction_parameters[self.index(first)?][self.index(second)?])
+    }
+
+    /// Pure component parameters, in the component order of the model
+    pub fn parameters(&self) -> &[EOSParameters] {
+        &self.parameters
+    }
+
+    /// Enables or disables the Peneloux volume translation.
+    pub fn with_volume_translation(mut self, enabled: bool) -> Self {
+        self.volume_translation = enabled;
+        self
+    }
+
+    /// Measures enthalpies and entropies from another reference state.
+    pub fn with_reference_state(mut self, reference_state: ReferenceState) -> Self {
+        self.reference_state = reference_state;
+        self
+    }
+
+    /// Peneloux volume shift of each component, estimated from the Rackett
+    /// compressibility 'Z_RA = 0.29056 - 0.08775 w'. The shifts are zero
+    /// when the translation is disabled.
+    pub fn volume_shifts(&self) -> Vec<MolarVolume> {
+        let (k1, k2) = self.model.peneloux_constants();
+        self.parameters
+            .iter()
+            .map(|parameters| {
+                let shift = if self.volume_translation {
+                    let rackett = 0.29056 - 0.08775 * parameters.acentric_factor;
+                    GAS_CONSTANT * parameters.critical_temperature.get::<kelvin>()
+                        / parameters.critical_pressure.get::<pascal>()
+                        * (k1 - k2 * rackett)
+                } else {
+                    0.0
+                };
+                MolarVolume::new::<cubic_meter_per_mole>(shift)
+            })
+            .collect()
+    }
+
+    /// Compressibility factor of a phase. Vapor phases take the largest root
+    /// of the cubic equation and liquid phases the smallest. Where the
+    /// equation has a single root, both phases share it. The volume
+    /// translation is included.
+    pub fn compressibility(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        let mixture = self.mixture(t, composition);
+        Ok(self.root(t, p, &mixture, phase)? - self.shift(composition) * p / (GAS_CONSTANT * t))
+    }
+
+    /// Departure of the molar enthalpy of a phase from that of an ideal gas
+    /// at the same temperature.
+    pub fn departure_enthalpy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarEnergy, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        Ok(MolarEnergy::new::<joule_per_mole>(
+            self.enthalpy_departure(t, p, composition, phase)?,
+        ))
+    }
+
+    /// Departure of the molar entropy of a phase from that of an ideal gas at
+    /// the same temperature and pressure.
+    pub fn departure_entropy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarHeatCapacity, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        Ok(MolarHeatCapacity::new::<joule_per_kelvin_mole>(
+            self.entropy_departure(t, p, composition, phase)?,
+        ))
+    }
+
+    fn heat_capacities(&self) -> Vec<&HeatCapacityCoefficients> {
+        self.parameters
+            .iter()
+            .map(|parameters| &parameters.heat_capacity)
+            .collect()
+    }
+
+    /// Volume shift of a mixture in m^3/mol
+    fn shift(&self, x: &[f64]) -> f64 {
+        x.iter()
+            .zip(self.volume_shifts())
+            .map(|(x, c)| x * c.get::<cubic_meter_per_mole>())
+            .sum()
+    }
+
+    fn index(&self, component: &str) -> Result<usize, &'static str> {
+        self.components
+            .iter()
+            .position(|name| name == component)
+            .ok_or("component is unknown to the equation of state")
+    }
+
+    /// Attraction parameter of each pure component and its temperature
+    /// derivative.
+    fn pure_attraction(&self, t: f64) -> Vec<(f64, f64)> {
+        self.parameters
+            .iter()
+            .map(|parameters| {
+                let tc = parameters.critical_temperature.get::<kelvin>();
+                let pc = parameters.critical_pressure.get::<pascal>();
+                let (omega_a, _) = self.model.omegas();
+                let ac = omega_a * (GAS_CONSTANT * tc).powi(2) / pc;
+                let (alpha, dalpha_dt) = self.model.alpha(t, tc, parameters.acentric_factor);
+                (ac * alpha, ac * dalpha_dt)
+            })
+            .collect()
+    }
+
+    fn mixture(&self, t: f64, x: &[f64]) -> MixtureParameters {
+        let pure = self.pure_attraction(t);
+        let (_, omega_b) = self.model.omegas();
+        let partial_b: Vec<f64> = self
+            .parameters
+            .iter()
+            .map(|parameters| {
+                omega_b * GAS_CONSTANT * parameters.critical_temperature.get::<kelvin>()
+                    / parameters.critical_pressure.get::<pascal>()
+            })
+            .collect();
+        let mut partial_a = vec![0.0; x.len()];
+        let (mut a, mut da_dt) = (0.0, 0.0);
+        for (i, (a_i, da_i)) in pure.iter().enumerate() {
+            for (j, (a_j, da_j)) in pure.iter().enumerate() {
+                let binary = 1.0 - self.interaction_parameters[i][j];
+                let root = (a_i * a_j).sqrt();
+                let a_ij = binary * root;
+                let da_ij = if root > 0.0 {
+                    binary * (da_i * a_j + a_i * da_j) / (2.0 * root)
+                } else {
+                    0.0
+                };
+                partial_a[i] += x[j] * a_ij;
+                a += x[i] * x[j] * a_ij;
+                da_dt += x[i] * x[j] * da_ij;
+            }
+        }
+        let b = x.iter().zip(&partial_b).map(|(x, b)| x * b).sum();
+        MixtureParameters {
+            a,
+            da_dt,
+            b,
+            partial_a,
+            partial_b,
+        }
+    }
+
+    /// Real roots of the equation in the compressibility factor that are
+    /// larger than the dimensionless covolume, in increasing order.
+    fn roots(&self, t: f64, p: f64, mixture: &MixtureParameters) -> Vec<f64> {
+        let a = mixture.a * p / (GAS_CONSTANT * t).powi(2);
+        let b = mixture.b * p / (GAS_CONSTANT * t);
+        let (d1, d2) = self.model.deltas();
+        let coefficients = [
+            (d1 + d2 - 1.0) * b - 1.0,
+            a + d1 * d2 * b * b - (d1 + d2) * (b + b * b),
+            -(a * b + d1 * d2 * b * b * (b + 1.0)),
+        ];
+        let mut roots: Vec<f64> = cubic_roots(coefficients)
+            .into_iter()
+            .filter(|z| *z > b)
+            .collect();
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+
+    /// The root of a phase, or an error when no root is physical. A
+    /// composition without any material has no covolume and so no physical
+    /// root either.
+    fn root(
+        &self,
+        t: f64,
+        p: f64,
+        mixture: &MixtureParameters,
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let roots = if mixture.b > 0.0 {
+            self.roots(t, p, mixture)
+        } else {
+            Vec::new()
+        };
+        let root = match phase {
+            Phase::Vapor => roots.last(),
+            Phase::Liquid => roots.first(),
+        };
+        root.copied()
+            .ok_or("the cubic equation has no root above the covolume")
+    }
+
+    /// Returns 'A', 'B' and 'ln((Z + d1 B) / (Z + d2 B)) / (d1 - d2)'.
+    fn attraction_terms(&self, t: f64, p: f64, z: f64, mixture: &MixtureParameters) -> [f64; 3] {
+        let a = mixture.a * p / (GAS_CONSTANT * t).powi(2);
+        let b = mixture.b * p / (GAS_CONSTANT * t);
+        let (d1, d2) = self.model.deltas();
+        let log = ((z + d1 * b) / (z + d2 * b)).ln() / (d1 - d2);
+        [a, b, log]
+    }
+
+    fn fugacity_coefficients(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        let mixture = self.mixture(t, x);
+        let z = self.root(t, p, &mixture, phase)?;
+        let [a, b, log] = self.attraction_terms(t, p, z, &mixture);
+        // The volume translation adds '-c_i P / RT' to each coefficient.
+        let shifts = self.volume_shifts();
+        Ok(mixture
+            .partial_a
+            .iter()
+            .zip(&mixture.partial_b)
+            .zip(&shifts)
+            .map(|((a_i, b_i), c_i)| {
+                let relative = b_i / mixture.b;
+                relative * (z - 1.0)
+                    - (z - b).ln()
+                    - a / b * (2.0 * a_i / mixture.a - relative) * log
+                    - c_i.get::<cubic_meter_per_mole>() * p / (GAS_CONSTANT * t)
+            })
+            .collect())
+    }
+
+    fn enthalpy_departure(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let mixture = self.mixture(t, x);
+        let z = self.root(t, p, &mixture, phase)?;
+        let [_, _, log] = self.attraction_terms(t, p, z, &mixture);
+        Ok(
+            GAS_CONSTANT * t * (z - 1.0) + (t * mixture.da_dt - mixture.a) / mixture.b * log
+                - p * self.shift(x),
+        )
+    }
+
+    fn entropy_departure(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let mixture = self.mixture(t, x);
+        let z = self.root(t, p, &mixture, phase)?;
+        let [_, b, log] = self.attraction_terms(t, p, z, &mixture);
+        Ok(GAS_CONSTANT * (z - b).ln() + mixture.da_dt / mixture.b * log)
+    }
+
+    fn enthalpy(&self, t: f64, p: f64, x: &[f64], phase: Phase) -> Result<f64, &'static str> {
+        Ok(
+            ideal_gas_enthalpy(&self.heat_capacities(), t, x, &self.reference_state)
+                + self.enthalpy_departure(t, p, x, phase)?,
+        )
+    }
+}
+
+/// Real roots of the monic cubic 'z^3 + c2 z^2 + c1 z + c0', found with the
+/// trigonometric method and polished with Newton's method.
+fn cubic_roots([c2, c1, c0]: [f64; 3]) -> Vec<f64> {
+    let shift = -c2 / 3.0;
+    let p = c1 - c2 * c2 / 3.0;
+    let q = 2.0 * c2.powi(3) / 27.0 - c2 * c1 / 3.0 + c0;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+    let roots: Vec<f64> = if discriminant > 0.0 {
+        let sqrt = discriminant.sqrt();
+        vec![(-q / 2.0 + sqrt).cbrt() + (-q / 2.0 - sqrt).cbrt() + shift]
+    } else if p == 0.0 {
+        vec![shift]
+    } else {
+        let r = (-p / 3.0).sqrt();
+        let angle = (-q / (2.0 * r.powi(3))).clamp(-1.0, 1.0).acos();
+        (0..3)
+            .map(|k| 2.0 * r * ((angle - 2.0 * PI * k as f64) / 3.0).cos() + shift)
+            .collect()
+    };
+    roots
+        .into_iter()
+        .map(|mut z| {
+            for _ in 0..3 {
+                let f = ((z + c2) * z + c1) * z + c0;
+                let df = (3.0 * z + 2.0 * c2) * z + c1;
+                if df == 0.0 {
+                    break;
+                }
+                z -= f / df;
+            }
+            z
+        })
+        .collect()
+}
+
+impl PhaseEquilibrium for CubicEquation {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn molar_masses(&self) -> Vec<MolarMass> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.molar_mass)
+            .collect()
+    }
+
+    fn flash_tp(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+    ) -> Result<FlashResult, &'static str> {
+        phase_stability::flash_vapor_liquid(self, temperature, pressure, composition)
+    }
+
+    fn ln_fugacity_coefficients(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        self.fugacity_coefficients(
+            temperature.get::<kelvin>(),
+            pressure.get::<pascal>(),
+            composition,
+            phase,
+        )
+    }
+
+    fn phase_enthalpy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarEnergy, &'static str> {
+        Ok(MolarEnergy::new::<joule_per_mole>(self.enthalpy(
+            temperature.get::<kelvin>(),
+            pressure.get::<pascal>(),
+            composition,
+            phase,
+        )?))
+    }
+}
+
+impl EquationOfState for CubicEquation {
+    fn molar_volume(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarVolume, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        let z = self.compressibility(temperature, pressure, composition, phase)?;
+        Ok(MolarVolume::new::<cubic_meter_per_mole>(
+            z * GAS_CONSTANT * t / p,
+        ))
+    }
+
+    fn phase_entropy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarHeatCapacity, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        Ok(MolarHeatCapacity::new::<joule_per_kelvin_mole>(
+            ideal_gas_entropy(
+                &self.heat_capacities(),
+                t,
+                p,
+                composition,
+                &self.reference_state,
+            ) + self.entropy_departure(t, p, composition, phase)?,
+        ))
+    }
+}
+
+/// # EOSModel
+///
+/// The state of a mixture described by an equation of state. The mixture is
+/// flashed when the model is created, and the properties returned through
+/// [`MaxwellRelations`] are those of the mixture at equilibrium.
+#[derive(Debug, Clone)]
 pub struct EOSModel {
+    /// Equation of state describing the mixture
+    pub equation: Arc<dyn EquationOfState>,
+    /// Overall mole fractions of the mixture
+    pub composition: Vec<f64>,
+    /// Equilibrium state of the mixture
+    pub state: FlashResult,
+    entropy: MolarHeatCapacity,
+    molar_volume: MolarVolume,
+    heat_capacity: MolarHeatCapacity,
+}
+
+impl EOSModel {
+    /// Flashes a mixture at a given temperature and pressure, and evaluates
+    /// the properties of the phases found.
+    pub fn new(
+        equation: Arc<dyn EquationOfState>,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: Vec<f64>,
+    ) -> Result<Self, &'static str> {
+        let state = equation.flash_tp(temperature, pressure, &composition)?;
+        let vapor_fraction = state.vapor_fraction.get::<ratio>();
+        let phases = [
+            (vapor_fraction, &state.vapor_composition, Phase::Vapor),
+            (
+                1.0 - vapor_fraction,
+                &state.liquid_composition,
+                Phase::Liquid,
+            ),
+        ];
+
+        let mut entropy = MolarHeatCapacity::default();
+        let mut molar_volume = MolarVolume::default();
+        // The heat capacity is found with the phase compositions held fixed,
+        // by a central difference of the phase enthalpies.
+        let (t, dt) = (
+            temperature.get::<kelvin>(),
+            1e-4 * temperature.get::<kelvin>(),
+        );
+        let mut heat_capacity = MolarHeatCapacity::default();
+        for (fraction, x, phase) in phases.iter().filter(|(fraction, _, _)| *fraction > 0.0) {
+            entropy += equation.phase_entropy(temperature, pressure, x, *phase)? * *fraction;
+            molar_volume += equation.molar_volume(temperature, pressure, x, *phase)? * *fraction;
+            let enthalpy = |t: f64| -> Result<f64, &'static str> {
+                Ok(equation
+                    .phase_enthalpy(
+                        ThermodynamicTemperature::new::<kelvin>(t),
+                        pressure,
+                        x,
+                        *phase,
+                    )?
+                    .get::<joule_per_mole>())
+            };
+            heat_capacity += MolarHeatCapacity::new::<joule_per_kelvin_mole>(
+                fraction * (enthalpy(t + dt)? - enthalpy(t - dt)?) / (2.0 * dt),
+            );
+        }
+        Ok(EOSModel {
+            equation,
+            composition,
+            state,
+            entropy,
+            molar_volume,
+            heat_capacity,
+        })
+    }
+
+    /// Molar volume of the mixture
+    pub fn molar_volume(&self) -> MolarVolume {
+        self.molar_volume
+    }
+}
+
+impl MaxwellRelations for EOSModel {
+    fn enthalpy(&self) -> MolarEnergy {
+        self.state.enthalpy
+    }
+
+    fn entropy(&self) -> MolarHeatCapacity {
+        self.entropy
+    }
+
+    fn pressure(&self) -> Pressure {
+        self.state.pressure
+    }
+
+    /// Volume of one mole of the mixture
+    fn volume(&self) -> Volume {
+        self.molar_volume * AmountOfSubstance::new::<mole>(1.0)
+    }
+
+    fn temperature(&self) -> ThermodynamicTemperature {
+        self.state.temperature
+    }
+
+    fn vapor_fraction(&self) -> Ratio {
+        self.state.vapor_fraction
+    }
+
+    /// Heat capacity of the mixture with the phase compositions held fixed
+    fn heat_capacity_const_pressure(&self) -> MolarHeatCapacity {
+        self.heat_capacity
+    }
+
+    fn internal_energy(&self) -> MolarEnergy {
+        self.state.enthalpy - self.state.pressure * self.molar_volume
+    }
+
+    /// Gibbs free energy of one mole of the mixture
+    fn gibbs_free_energy(&self) -> Energy {
+        (self.enthalpy() - self.state.temperature * self.entropy())
+            * AmountOfSubstance::new::<mole>(1.0)
+    }
+}
+
+#[cfg(test)]
+mod eos_tests {
+    use super::*;
+    use crate::thermodynamics::find_root;
+    use uom::si::molar_mass::gram_per_mole;
+
+    fn parameters(tc: f64, pc: f64, w: f64, mw: f64, cp: f64) -> EOSParameters {
+        EOSParameters {
+            critical_temperature: ThermodynamicTemperature::new::<kelvin>(tc),
+            critical_pressure: Pressure::new::<pascal>(pc),
+            acentric_factor: w,
+            molar_mass: MolarMass::new::<gram_per_mole>(mw),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(cp).into(),
+        }
+    }
+
+    const MODELS: [CubicModel; 3] = [
+        CubicModel::RedlichKwong,
+        CubicModel::SoaveRedlichKwong,
+        CubicModel::PengRobinson,
+    ];
+
+    fn methane_propane(model: CubicModel) -> CubicEquation {
+        let mut equation = CubicEquation::new(
+            model,
+            vec![String::from("methane"), String::from("propane")],
+            vec![
+                parameters(190.56, 4.599e6, 0.0115, 16.04, 35.7),
+                parameters(369.83, 4.248e6, 0.1523, 44.10, 73.6),
+            ],
+        )
+        .unwrap();
+        equation
+            .set_interaction_parameter("methane", "propane", 0.014)
+            .unwrap();
+        equation
+    }
+
+    /// The pressure at which both roots of a pure component have the same
+    /// fugacity.
+    fn vapor_pressure(equation: &CubicEquation, temperature: ThermodynamicTemperature) -> Pressure {
+        let p = find_root(
+            |p| {
+                let pressure = Pressure::new::<pascal>(p);
+                let coefficient = |phase| {
+                    equation
+                        .ln_fugacity_coefficients(temperature, pressure, &[1.0], phase)
+                        .map(|ln_phi| ln_phi[0])
+                };
+                Ok(coefficient(Phase::Liquid)? - coefficient(Phase::Vapor)?)
+            },
+            8.0e5,
+            1.0e4,
+            (5.0e5, 1.5e6),
+        )
+        .unwrap();
+        Pressure::new::<pascal>(p)
+    }
+
+    #[test]
+    fn saturated_propane() {
+        let temperature = ThermodynamicTemperature::new::<kelvin>(300.0);
+        for model in [CubicModel::SoaveRedlichKwong, CubicModel::PengRobinson] {
+            let equation = CubicEquation::new(
+                model,
+                vec![String::from("propane")],
+                vec![parameters(369.83, 4.248e6, 0.1523, 44.10, 73.6)],
+            )
+            .unwrap();
+            // Measured: 0.998 MPa
+            let pressure = vapor_pressure(&equation, temperature);
+            assert!((pressure.get::<pascal>() / 0.998e6 - 1.0).abs() < 0.02);
+            let liquid = equation
+                .compressibility(temperature, pressure, &[1.0], Phase::Liquid)
+                .unwrap();
+            let vapor = equation
+                .compressibility(temperature, pressure, &[1.0], Phase::Vapor)
+                .unwrap();
+            assert!(liquid < 0.05 && vapor > 0.8);
+
+            // The translation leaves the vapor pressure unchanged and brings
+            // the liquid volume closer to the measured 90.2 cm^3/mol.
+            let translated = equation.clone().with_volume_translation(true);
+            let shifted = vapor_pressure(&translated, temperature);
+            assert!((shifted.get::<pascal>() / pressure.get::<pascal>() - 1.0).abs() < 1e-8);
+            let error = |equation: &CubicEquation| {
+                let volume = equation
+                    .molar_volume(temperature, pressure, &[1.0], Phase::Liquid)
+                    .unwrap()
+                    .get::<cubic_meter_per_mole>();
+                (volume / 90.2e-6 - 1.0).abs()
+            };
+            assert!(error(&translated) < error(&equation));
+            assert!(error(&translated) < 0.05);
+        }
+    }
+
+    #[test]
+    fn departure_functions_are_consistent() {
+        for model in MODELS {
+            for translation in [false, true] {
+                let equation = methane_propane(model).with_volume_translation(translation);
+                departures_match_fugacities(&equation);
+            }
+        }
+    }
+
+    fn departures_match_fugacities(equation: &CubicEquation) {
+        let x = [0.3, 0.7];
+        let pressure = Pressure::new::<pascal>(3.0e6);
+        let (t, step) = (280.0, 1e-3);
+        let gibbs = |t: f64, phase| -> f64 {
+            let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+            let ln_phi = equation
+                .ln_fugacity_coefficients(temperature, pressure, &x, phase)
+                .unwrap();
+            x.iter().zip(ln_phi).map(|(x, ln_phi)| x * ln_phi).sum()
+        };
+        for phase in [Phase::Vapor, Phase::Liquid] {
+            let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+            let enthalpy = equation
+                .departure_enthalpy(temperature, pressure, &x, phase)
+                .unwrap()
+                .get::<joule_per_mole>();
+            let entropy = equation
+                .departure_entropy(temperature, pressure, &x, phase)
+                .unwrap()
+                .get::<joule_per_kelvin_mole>();
+            // G_dep / RT = sum(x ln phi) = (H_dep - T S_dep) / RT
+            let residual = (enthalpy - t * entropy) / (GAS_CONSTANT * t);
+            assert!((residual - gibbs(t, phase)).abs() < 1e-8);
+            // H_dep = -R T^2 d(G_dep / RT)/dT
+            let derivative = (gibbs(t + step, phase) - gibbs(t - step, phase)) / (2.0 * step);
+            assert!((enthalpy + GAS_CONSTANT * t * t * derivative).abs() < 1e-3);
+        }
+
+        // An ideal gas at low pressure
+        let pressure = Pressure::new::<pascal>(1.0);
+        let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+        let z = equation
+            .compressibility(temperature, pressure, &x, Phase::Vapor)
+            .unwrap();
+        assert!((z - 1.0).abs() < 1e-5);
+        assert!(
+            equation
+                .departure_enthalpy(temperature, pressure, &x, Phase::Vapor)
+                .unwrap()
+                .get::<joule_per_mole>()
+                .abs()
+                < 1e-3
+        );
+    }
+
+    #[test]
+    fn mixtures_without_a_physical_root_are_errors() {
+        let equation = methane_propane(CubicModel::PengRobinson);
+        let temperature = ThermodynamicTemperature::new::<kelvin>(280.0);
+        let pressure = Pressure::new::<pascal>(3.0e6);
+        for x in [[0.0, 0.0], [f64::NAN, 0.5]] {
+            for phase in [Phase::Vapor, Phase::Liquid] {
+                assert!(equation
+                    .compressibility(temperature, pressure, &x, phase)
+                    .is_err());
+                assert!(equation
+                    .ln_fugacity_coefficients(temperature, pressure, &x, phase)
+                    .is_err());
+                assert!(equation
+                    .phase_enthalpy(temperature, pressure, &x, phase)
+                    .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn flash_of_methane_and_propane() {
+        let vapor_fractions: Vec<f64> = MODELS
+            .iter()
+            .map(|model| {
+                let equation = methane_propane(*model);
+                let vapor_fraction = equilibrium(equation.clone());
+                let translated = equilibrium(equation.with_volume_translation(true));
+                assert!((translated - vapor_fraction).abs() < 1e-8);
+                vapor_fraction
+            })
+            .collect();
+        // The variants give similar, but different, results.
+        assert!((vapor_fractions[1] - vapor_fractions[2]).abs() > 1e-4);
+        assert!((vapor_fractions[1] - vapor_fractions[2]).abs() < 0.05);
+    }
+
+    #[test]
+    fn reference_state_shifts_enthalpy_and_entropy() {
+        use crate::thermodynamics::constants::ReferenceState;
+        let atmospheric = methane_propane(CubicModel::PengRobinson);
+        let standard = atmospheric
+            .clone()
+            .with_reference_state(ReferenceState::STANDARD);
+        let normal = atmospheric
+            .clone()
+            .with_reference_state(ReferenceState::NORMAL);
+        let temperature = ThermodynamicTemperature::new::<kelvin>(250.0);
+        let pressure = Pressure::new::<pascal>(2.0e6);
+        let x = [0.3, 0.7];
+        for phase in [Phase::Vapor, Phase::Liquid] {
+            let enthalpy = |equation: &CubicEquation| {
+                equation
+                    .phase_enthalpy(temperature, pressure, &x, phase)
+                    .unwrap()
+                    .get::<joule_per_mole>()
+            };
+            let entropy = |equation: &CubicEquation| {
+                equation
+                    .phase_entropy(temperature, pressure, &x, phase)
+                    .unwrap()
+                    .get::<joule_per_kelvin_mole>()
+            };
+            // 1 bar against 1 atm at the same temperature
+            assert!((enthalpy(&standard) - enthalpy(&atmospheric)).abs() < 1e-9);
+            let shift = GAS_CONSTANT * (100_000.0f64 / 101_325.0).ln();
+            assert!((entropy(&standard) - entropy(&atmospheric) - shift).abs() < 1e-9);
+            // 273.15 K against 298.15 K at the same pressure
+            let cp = 0.3 * 35.7 + 0.7 * 73.6;
+            assert!((enthalpy(&normal) - enthalpy(&atmospheric) - cp * 25.0).abs() < 1e-9);
+        }
+        // Phase equilibrium does not depend on the reference state
+        assert!((equilibrium(normal) - equilibrium(atmospheric)).abs() < 1e-12);
+    }
+
+    /// Flashes an equimolar mixture and checks the state at equilibrium,
+    /// returning the vapor fraction.
+    fn equilibrium(equation: CubicEquation) -> f64 {
+        let equation = Arc::new(equation);
+        let temperature = ThermodynamicTemperature::new::<kelvin>(250.0);
+        let pressure = Pressure::new::<pascal>(2.0e6);
+        let z = vec![0.5, 0.5];
+        let model = EOSModel::new(equation.clone(), temperature, pressure, z.clone()).unwrap();
+        let beta = model.vapor_fraction().get::<ratio>();
+        assert!(beta > 0.0 && beta < 1.0);
+
+        let (x, y) = (
+            &model.state.liquid_composition,
+            &model.state.vapor_composition,
+        );
+        let liquid = equation
+            .ln_fugacity_coefficients(temperature, pressure, x, Phase::Liquid)
+            .unwrap();
+        let vapor = equation
+            .ln_fugacity_coefficients(temperature, pressure, y, Phase::Vapor)
+            .unwrap();
+        for i in 0..2 {
+            assert!((beta * y[i] + (1.0 - beta) * x[i] - z[i]).abs() < 1e-9);
+            assert!((x[i] * liquid[i].exp() - y[i] * vapor[i].exp()).abs() < 1e-8);
+        }
+        assert!(y[0] > x[0]);
 
+        let enthalpy = model.enthalpy().get::<joule_per_mole>();
+        let mixed = beta * model.state.vapor_enthalpy.get::<joule_per_mole>()
+            + (1.0 - beta) * model.state.liquid_enthalpy.get::<joule_per_mole>();
+        assert!((enthalpy - mixed).abs() < 1e-6);
+        let internal = model.internal_energy().get::<joule_per_mole>();
+        let pv = pressure.get::<pascal>() * model.molar_volume().get::<cubic_meter_per_mole>();
+        assert!((enthalpy - pv - internal).abs() < 1e-6);
+        assert!(
+            model
+                .heat_capacity_const_pressure()
+                .get::<joule_per_kelvin_mole>()
+                > 0.0
+        );
+        beta
+    }
 }
@@ -0,0 +1,786 @@
+//! # PC-SAFT
+//!
+//! The Perturbed-Chain SAFT equation of state of Gross and Sadowski (2001).
+//! Molecules are chains of spherical segments, and the residual Helmholtz
+//! energy is the sum of a hard-chain reference, a dispersion term for the
+//! attraction between segments, and an association term for hydrogen
+//! bonding between sites on the molecules (Chapman et al., 1990, as used by
+//! Gross and Sadowski, 2002).
+//!
+//! Every property follows from the reduced residual Helmholtz energy,
+//! 'a_res = A_res / (N k T)', as a function of temperature, number density
+//! and composition. Its derivatives are found exactly with forward automatic
+//! differentiation.
+
+use super::{ideal_gas_enthalpy, ideal_gas_entropy, EquationOfState, GAS_CONSTANT};
+use crate::properties::pure_species_properties::PureSpeciesProperties;
+use crate::thermodynamics::constants::{ReferenceState, AVOGADRO_CONSTANT};
+use crate::thermodynamics::heat_capacity::HeatCapacityCoefficients;
+use crate::thermodynamics::phase_stability;
+use crate::thermodynamics::{FlashResult, Phase, PhaseEquilibrium};
+use autodiff::{Float, F1};
+use std::f64::consts::PI;
+use uom::si::f64::*;
+use uom::si::length::angstrom;
+use uom::si::molar_energy::joule_per_mole;
+use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+use uom::si::molar_volume::cubic_meter_per_mole;
+use uom::si::pressure::pascal;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// Avogadro's number in 1/mol
+const AVOGADRO_NUMBER: f64 = AVOGADRO_CONSTANT.value;
+
+/// Cubic meters per cubic angstrom
+const CUBIC_ANGSTROM: f64 = 1e-30;
+
+/// Packing fraction of closest packed spheres, the densest a phase can be.
+const MAX_PACKING_FRACTION: f64 = 0.7405;
+
+/// Universal constants 'a_0i', 'a_1i' and 'a_2i' of the dispersion term.
+const DISPERSION_A: [[f64; 3]; 7] = [
+    [0.9105631445, -0.3084016918, -0.0906148351],
+    [0.6361281449, 0.1860531159, 0.4527842806],
+    [2.6861347891, -2.5030047259, 0.5962700728],
+    [-26.547362491, 21.419793629, -1.7241829131],
+    [97.759208784, -65.255885330, -4.1302112531],
+    [-159.59154087, 83.318680481, 13.776631870],
+    [91.297774084, -33.746922930, -8.6728470368],
+];
+
+/// Universal constants 'b_0i', 'b_1i' and 'b_2i' of the dispersion term.
+const DISPERSION_B: [[f64; 3]; 7] = [
+    [0.7240946941, -0.5755498075, 0.0976883116],
+    [2.2382791861, 0.6995095521, -0.2557574982],
+    [-4.0025849485, 3.8925673390, -9.1558561530],
+    [-21.003576815, -17.215471648, 20.642075974],
+    [26.855641363, 192.67226447, -38.804430052],
+    [206.55133841, -161.82646165, 93.626774077],
+    [-355.60235612, -165.20769346, -29.666905585],
+];
+
+/// # PCSAFTParameters
+///
+/// Pure component parameters of the PC-SAFT equation of state. Components
+/// that do not associate have no association sites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PCSAFTParameters {
+    /// Number of segments in a chain
+    pub segment_number: f64,
+    /// Diameter of a segment
+    pub segment_diameter: Length,
+    /// Depth of the dispersion potential divided by Boltzmann's constant
+    pub dispersion_energy: ThermodynamicTemperature,
+    /// Association energy divided by Boltzmann's constant
+    pub association_energy: ThermodynamicTemperature,
+    /// Effective association volume
+    pub association_volume: f64,
+    /// Number of sites that donate and that accept a hydrogen bond. Water
+    /// and alcohols are usually given one of each (the 2B scheme).
+    pub association_sites: (usize, usize),
+    /// Molar mass
+    pub molar_mass: MolarMass,
+    /// Ideal gas heat capacity correlation
+    pub heat_capacity: HeatCapacityCoefficients,
+}
+
+/// # PCSAFT
+///
+/// The PC-SAFT equation of state for a mixture. Segment diameters are
+/// combined with the Lorentz rule, and dispersion energies with the
+/// Berthelot rule corrected by binary interaction parameters 'k_ij'.
+#[derive(Debug, Clone)]
+pub struct PCSAFT {
+    /// State where the enthalpy and entropy of each ideal gas are zero
+    pub reference_state: ReferenceState,
+    components: Vec<String>,
+    parameters: Vec<PCSAFTParameters>,
+    interaction_parameters: Vec<Vec<f64>>,
+}
+
+/// The variable the residual Helmholtz energy is differentiated by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Seed {
+    Temperature,
+    Density,
+    Fraction(usize),
+}
+
+impl PCSAFT {
+    /// Creates the equation for a set of components, with every binary
+    /// interaction parameter set to zero.
+    pub fn new(
+        components: Vec<String>,
+        parameters: Vec<PCSAFTParameters>,
+    ) -> Result<Self, &'static str> {
+        if components.len() != parameters.len() {
+            return Err("every component needs one set of parameters");
+        }
+        let n = components.len();
+        Ok(PCSAFT {
+            reference_state: ReferenceState::default(),
+            components,
+            parameters,
+            interaction_parameters: vec![vec![0.0; n]; n],
+        })
+    }
+
+    /// Creates the equation from the properties of pure species fetched
+    /// from a property store. Every species needs PC-SAFT parameters.
+    pub fn from_properties(species: &[PureSpeciesProperties]) -> Result<Self, &'static str> {
+        let parameters = species
+            .iter()
+            .map(|species| species.pc_saft_parameters.clone())
+            .collect::<Option<Vec<PCSAFTParameters>>>()
+            .ok_or("every species needs PC-SAFT parameters")?;
+        let components = species.iter().map(|species| species.name.clone()).collect();
+        PCSAFT::new(components, parameters)
+    }
+
+    /// Measures enthalpies and entropies from another reference state.
+    pub fn with_reference_state(mut self, reference_state: ReferenceState) -> Self {
+        self.reference_state = reference_state;
+        self
+    }
+
+    /// Sets the binary interaction parameter 'k_ij' between two components.
+    /// The parameter is symmetric.
+    pub fn set_interaction_parameter(
+        &mut self,
+        first: &str,
+        second: &str,
+        value: f64,
+    ) -> Result<(), &'static str> {
+        let i = self.index(first)?;
+        let j = self.index(second)?;
+        if i == j {
+            return Err("a component has no interaction parameter with itself");
+        }
+        self.interaction_parameters[i][j] = value;
+        self.interaction_parameters[j][i] = value;
+        Ok(())
+    }
+
+    /// Returns the binary interaction parameter 'k_ij' between two components.
+    pub fn interaction_parameter(&self, first: &str, second: &str) -> Result<f64, &'static str> {
+        Ok(self.interaction_parameters[self.index(first)?][self.index(second)?])
+    }
+
+    /// Pure component parameters, in the component order of the model
+    pub fn parameters(&self) -> &[PCSAFTParameters] {
+        &self.parameters
+    }
+
+    /// Compressibility factor of a phase. Vapor phases take the lowest
+    /// density that matches the pressure and liquid phases the highest.
+    /// Where only one density matches, both phases share it.
+    pub fn compressibility(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        let density = self.density(t, p, composition, phase)?;
+        self.compressibility_at(t, density, composition)
+    }
+
+    fn index(&self, component: &str) -> Result<usize, &'static str> {
+        self.components
+            .iter()
+            .position(|name| name == component)
+            .ok_or("component is unknown to the equation of state")
+    }
+
+    fn heat_capacities(&self) -> Vec<&HeatCapacityCoefficients> {
+        self.parameters
+            .iter()
+            .map(|parameters| &parameters.heat_capacity)
+            .collect()
+    }
+
+    /// Temperature dependent segment diameter of each component in angstrom.
+    fn segment_diameters(&self, t: F1) -> Vec<F1> {
+        self.parameters
+            .iter()
+            .map(|parameters| {
+                let epsilon = parameters.dispersion_energy.get::<kelvin>();
+                (1.0 - 0.12 * (-3.0 * epsilon / t).exp())
+                    * parameters.segment_diameter.get::<angstrom>()
+            })
+            .collect()
+    }
+
+    /// Reduced residual Helmholtz energy at a temperature in K and a number
+    /// density in molecules per cubic angstrom, differentiated by 'seed'.
+    fn helmholtz(&self, t: f64, density: f64, x: &[f64], seed: Seed) -> Result<F1, &'static str> {
+        let variable = |value: f64, variable: Seed| {
+            if seed == variable {
+                F1::var(value)
+            } else {
+                F1::cst(value)
+            }
+        };
+        let t = variable(t, Seed::Temperature);
+        let density = variable(density, Seed::Density);
+        let x: Vec<F1> = x
+            .iter()
+            .enumerate()
+            .map(|(i, x)| variable(*x, Seed::Fraction(i)))
+            .collect();
+        self.residual_helmholtz(t, density, &x)
+    }
+
+    fn residual_helmholtz(&self, t: F1, density: F1, x: &[F1]) -> Result<F1, &'static str> {
+        let n = x.len();
+        let m: Vec<f64> = self
+            .parameters
+            .iter()
+            .map(|parameters| parameters.segment_number)
+            .collect();
+        let sigma: Vec<f64> = self
+            .parameters
+            .iter()
+            .map(|parameters| parameters.segment_diameter.get::<angstrom>())
+            .collect();
+        let epsilon: Vec<f64> = self
+            .parameters
+            .iter()
+            .map(|parameters| parameters.dispersion_energy.get::<kelvin>())
+            .collect();
+        let d = self.segment_diameters(t);
+
+        // Hard-chain reference
+        let zeta: Vec<F1> = (0..4)
+            .map(|k| density * (PI / 6.0) * (0..n).map(|i| x[i] * m[i] * d[i].powi(k)).sum::<F1>())
+            .collect();
+        let (z0, z1, z2, z3) = (zeta[0], zeta[1], zeta[2], zeta[3]);
+        let void: F1 = 1.0 - z3;
+        let mean: F1 = (0..n).map(|i| x[i] * m[i]).sum();
+        let hard_sphere = (3.0 * z1 * z2 / void
+            + z2.powi(3) / (z3 * void * void)
+            + (z2.powi(3) / (z3 * z3) - z0) * (-z3).ln_1p())
+            / z0;
+        let radial_distribution = |i: usize, j: usize| -> F1 {
+            let dij = d[i] * d[j] / (d[i] + d[j]);
+            1.0 / void + dij * 3.0 * z2 / (void * void) + dij * dij * 2.0 * z2 * z2 / void.powi(3)
+        };
+        let hard_chain = mean * hard_sphere
+            - (0..n)
+                .map(|i| x[i] * (m[i] - 1.0) * radial_distribution(i, i).ln())
+                .sum::<F1>();
+
+        // Dispersion
+        let eta = z3;
+        let (mut first, mut second) = (F1::cst(0.0), F1::cst(0.0));
+        for i in 0..n {
+            for j in 0..n {
+                let energy = (epsilon[i] * epsilon[j]).sqrt()
+                    * (1.0 - self.interaction_parameters[i][j])
+                    / t;
+                let volume = (0.5 * (sigma[i] + sigma[j])).powi(3);
+                first += x[i] * x[j] * m[i] * m[j] * energy * volume;
+                second += x[i] * x[j] * m[i] * m[j] * energy * energy * volume;
+            }
+        }
+        let chain = (mean - 1.0) / mean;
+        let coefficient = |constants: &[f64; 3]| {
+            constants[0] + chain * constants[1] + chain * (mean - 2.0) / mean * constants[2]
+        };
+        let integral = |constants: &[[f64; 3]; 7]| -> F1 {
+            constants
+                .iter()
+                .enumerate()
+                .map(|(k, constants)| coefficient(constants) * eta.powi(k as i32))
+                .sum()
+        };
+        let (chain_term, segment_term): (F1, F1) = (1.0 - mean, 2.0 - eta);
+        let compressibility = 1.0
+            / (1.0
+                + mean * (8.0 * eta - 2.0 * eta * eta) / void.powi(4)
+                + chain_term
+                    * (20.0 * eta - 27.0 * eta.powi(2) + 12.0 * eta.powi(3) - 2.0 * eta.powi(4))
+                    / (void * segment_term).powi(2));
+        let dispersion = -2.0 * PI * density * integral(&DISPERSION_A) * first
+            - PI * density * mean * compressibility * integral(&DISPERSION_B) * second;
+
+        Ok(hard_chain + dispersion + self.association(t, density, x, &radial_distribution)?)
+    }
+
+    /// Association term. The fraction of donor and acceptor sites of each
+    /// component that are not bonded is found by successive substitution,
+    /// which fails if the fractions do not settle.
+    fn association(
+        &self,
+        t: F1,
+        density: F1,
+        x: &[F1],
+        radial_distribution: &dyn Fn(usize, usize) -> F1,
+    ) -> Result<F1, &'static str> {
+        let associating: Vec<usize> = (0..x.len())
+            .filter(|i| {
+                let parameters = &self.parameters[*i];
+                parameters.association_volume > 0.0 && parameters.association_sites != (0, 0)
+            })
+            .collect();
+        if associating.is_empty() {
+            return Ok(F1::cst(0.0));
+        }
+
+        let strength: Vec<Vec<F1>> = associating
+            .iter()
+            .map(|i| {
+                associating
+                    .iter()
+                    .map(|j| {
+                        let (first, second) = (&self.parameters[*i], &self.parameters[*j]);
+                        let sigma_i = first.segment_diameter.get::<angstrom>();
+                        let sigma_j = second.segment_diameter.get::<angstrom>();
+                        let sigma = 0.5 * (sigma_i + sigma_j);
+                        let volume = (first.association_volume * second.association_volume).sqrt()
+                            * ((sigma_i * sigma_j).sqrt() / sigma).powi(3);
+                        let energy = 0.5
+                            * (first.association_energy.get::<kelvin>()
+                                + second.association_energy.get::<kelvin>());
+                        sigma.powi(3)
+                            * radial_distribution(*i, *j)
+                            * volume
+                            * ((energy / t).exp() - 1.0)
+                    })
+                    .collect()
+            })
+            .collect();
+        let sites: Vec<(f64, f64)> = associating
+            .iter()
+            .map(|i| {
+                let (donors, acceptors) = self.parameters[*i].association_sites;
+                (donors as f64, acceptors as f64)
+            })
+            .collect();
+
+        let k = associating.len();
+        let mut donors = vec![F1::cst(1.0); k];
+        let mut acceptors = vec![F1::cst(1.0); k];
+        let mut converged = false;
+        for _ in 0..1000 {
+            let mut change: f64 = 0.0;
+            let mut update = |current: &mut F1, bonded: F1| {
+                let next: F1 = 0.5 * (*current + 1.0 / (1.0 + density * bonded));
+                // A fraction that is not a number never settles
+                if !next.value().is_finite() {
+                    change = f64::INFINITY;
+                }
+                change = change
+                    .max((next.value() - current.value()).abs())
+                    .max((next.deriv() - current.deriv()).abs() / (1.0 + next.deriv().abs()));
+                *current = next;
+            };
+            let previous = (donors.clone(), acceptors.clone());
+            for a in 0..k {
+                let bonded: F1 = (0..k)
+                    .map(|b| x[associating[b]] * sites[b].1 * previous.1[b] * strength[a][b])
+                    .sum();
+                update(&mut donors[a], bonded);
+                let bonded: F1 = (0..k)
+                    .map(|b| x[associating[b]] * sites[b].0 * previous.0[b] * strength[a][b])
+                    .sum();
+                update(&mut acceptors[a], bonded);
+            }
+            if change < 1e-14 {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err("association site fractions did not converge");
+        }
+
+        Ok((0..k)
+            .map(|a| {
+                let site = |fraction: F1| fraction.ln() - 0.5 * fraction + 0.5;
+                x[associating[a]] * (sites[a].0 * site(donors[a]) + sites[a].1 * site(acceptors[a]))
+            })
+            .sum())
+    }
+
+    fn compressibility_at(&self, t: f64, density: f64, x: &[f64]) -> Result<f64, &'static str> {
+        Ok(1.0 + density * self.helmholtz(t, density, x, Seed::Density)?.deriv())
+    }
+
+    /// Pressure in Pa at a number density in molecules per cubic angstrom.
+    fn pressure_at(&self, t: f64, density: f64, x: &[f64]) -> Result<f64, &'static str> {
+        Ok(
+            self.compressibility_at(t, density, x)? * density / CUBIC_ANGSTROM * GAS_CONSTANT * t
+                / AVOGADRO_NUMBER,
+        )
+    }
+
+    /// Number density in molecules per cubic angstrom of a phase at a given
+    /// temperature and pressure, found with Newton's method in the packing
+    /// fraction. Vapor phases start from the ideal gas and liquid phases from
+    /// a dense liquid. If the search enters a region where the pressure
+    /// falls with density, that kind of phase does not exist and the other
+    /// one is returned.
+    fn density(&self, t: f64, p: f64, x: &[f64], phase: Phase) -> Result<f64, &'static str> {
+        let d = self.segment_diameters(F1::cst(t));
+        let packing = (PI / 6.0)
+            * x.iter()
+                .zip(&self.parameters)
+                .zip(&d)
+                .map(|((x, parameters), d)| x * parameters.segment_number * d.value().powi(3))
+                .sum::<f64>();
+        let newton = |initial: f64| -> Result<Option<f64>, &'static str> {
+            let mut eta = initial;
+            for _ in 0..100 {
+                let pressure = |eta: f64| self.pressure_at(t, eta / packing, x);
+                let step = 1e-6 * eta;
+                let slope = (pressure(eta + step)? - pressure(eta - step)?) / (2.0 * step);
+                if slope <= 0.0 || !slope.is_finite() {
+                    return Ok(None);
+                }
+                let mut next = eta - (pressure(eta)? - p) / slope;
+                if next <= 0.0 {
+                    next = 0.1 * eta;
+                } else if next >= MAX_PACKING_FRACTION {
+                    next = 0.5 * (eta + MAX_PACKING_FRACTION);
+                }
+                if (next - eta).abs() <= 1e-13 * eta {
+                    return Ok(Some(next / packing));
+                }
+                eta = next;
+            }
+            Ok(None)
+        };
+        let vapor = p / (GAS_CONSTANT * t / AVOGADRO_NUMBER) * CUBIC_ANGSTROM * packing;
+        let liquid = 0.5;
+        let (first, second) = match phase {
+            Phase::Vapor => (vapor, liquid),
+            Phase::Liquid => (liquid, vapor),
+        };
+        match newton(first)? {
+            Some(density) => Ok(density),
+            None => newton(second)?.ok_or("no density matches the pressure"),
+        }
+    }
+
+    /// Compressibility factor, reduced residual Helmholtz energy and its
+    /// temperature derivative of a phase.
+    fn residual_state(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<(f64, f64, f64), &'static str> {
+        let density = self.density(t, p, x, phase)?;
+        let by_temperature = self.helmholtz(t, density, x, Seed::Temperature)?;
+        Ok((
+            self.compressibility_at(t, density, x)?,
+            by_temperature.value(),
+            by_temperature.deriv(),
+        ))
+    }
+
+    fn fugacity_coefficients(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        let density = self.density(t, p, x, phase)?;
+        let by_density = self.helmholtz(t, density, x, Seed::Density)?;
+        let (a, z) = (by_density.value(), 1.0 + density * by_density.deriv());
+        let by_fraction = (0..x.len())
+            .map(|i| Ok(self.helmholtz(t, density, x, Seed::Fraction(i))?.deriv()))
+            .collect::<Result<Vec<f64>, &'static str>>()?;
+        let mean: f64 = x.iter().zip(&by_fraction).map(|(x, da)| x * da).sum();
+        Ok(by_fraction
+            .iter()
+            .map(|da| a + (z - 1.0) + da - mean - z.ln())
+            .collect())
+    }
+
+    fn enthalpy_departure(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let (z, _, da_dt) = self.residual_state(t, p, x, phase)?;
+        Ok(GAS_CONSTANT * t * (z - 1.0 - t * da_dt))
+    }
+
+    fn entropy_departure(
+        &self,
+        t: f64,
+        p: f64,
+        x: &[f64],
+        phase: Phase,
+    ) -> Result<f64, &'static str> {
+        let (z, a, da_dt) = self.residual_state(t, p, x, phase)?;
+        Ok(GAS_CONSTANT * (z.ln() - a - t * da_dt))
+    }
+}
+
+impl PhaseEquilibrium for PCSAFT {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn molar_masses(&self) -> Vec<MolarMass> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.molar_mass)
+            .collect()
+    }
+
+    fn flash_tp(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+    ) -> Result<FlashResult, &'static str> {
+        phase_stability::flash_vapor_liquid(self, temperature, pressure, composition)
+    }
+
+    fn ln_fugacity_coefficients(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        self.fugacity_coefficients(
+            temperature.get::<kelvin>(),
+            pressure.get::<pascal>(),
+            composition,
+            phase,
+        )
+    }
+
+    fn phase_enthalpy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarEnergy, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        Ok(MolarEnergy::new::<joule_per_mole>(
+            ideal_gas_enthalpy(
+                &self.heat_capacities(),
+                t,
+                composition,
+                &self.reference_state,
+            ) + self.enthalpy_departure(t, p, composition, phase)?,
+        ))
+    }
+}
+
+impl EquationOfState for PCSAFT {
+    fn molar_volume(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarVolume, &'static str> {
+        let t = temperature.get::<kelvin>();
+        let density = self.density(t, pressure.get::<pascal>(), composition, phase)?;
+        Ok(MolarVolume::new::<cubic_meter_per_mole>(
+            AVOGADRO_NUMBER * CUBIC_ANGSTROM / density,
+        ))
+    }
+
+    fn phase_entropy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarHeatCapacity, &'static str> {
+        let (t, p) = (temperature.get::<kelvin>(), pressure.get::<pascal>());
+        Ok(MolarHeatCapacity::new::<joule_per_kelvin_mole>(
+            ideal_gas_entropy(
+                &self.heat_capacities(),
+                t,
+                p,
+                composition,
+                &self.reference_state,
+            ) + self.entropy_departure(t, p, composition, phase)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod pc_saft_tests {
+    use super::*;
+    use crate::thermodynamics::eos_models::EOSModel;
+    use crate::thermodynamics::find_root;
+    use crate::thermodynamics::MaxwellRelations;
+    use std::sync::Arc;
+    use uom::si::molar_mass::gram_per_mole;
+    use uom::si::ratio::ratio;
+
+    fn parameters(m: f64, sigma: f64, epsilon: f64, mw: f64, cp: f64) -> PCSAFTParameters {
+        PCSAFTParameters {
+            segment_number: m,
+            segment_diameter: Length::new::<angstrom>(sigma),
+            dispersion_energy: ThermodynamicTemperature::new::<kelvin>(epsilon),
+            association_energy: ThermodynamicTemperature::new::<kelvin>(0.0),
+            association_volume: 0.0,
+            association_sites: (0, 0),
+            molar_mass: MolarMass::new::<gram_per_mole>(mw),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(cp).into(),
+        }
+    }
+
+    fn water() -> PCSAFTParameters {
+        PCSAFTParameters {
+            association_energy: ThermodynamicTemperature::new::<kelvin>(2500.7),
+            association_volume: 0.034868,
+            association_sites: (1, 1),
+            ..parameters(1.0656, 3.0007, 366.51, 18.015, 33.6)
+        }
+    }
+
+    fn propane() -> PCSAFTParameters {
+        parameters(2.0020, 3.6184, 208.11, 44.10, 73.6)
+    }
+
+    /// The pressure at which the vapor and liquid of a pure component have
+    /// the same fugacity.
+    fn vapor_pressure(equation: &PCSAFT, t: f64, initial: f64) -> f64 {
+        find_root(
+            |p| {
+                let ln_phi = |phase| {
+                    Ok::<_, &'static str>(equation.fugacity_coefficients(t, p, &[1.0], phase)?[0])
+                };
+                Ok(ln_phi(Phase::Liquid)? - ln_phi(Phase::Vapor)?)
+            },
+            initial,
+            0.01 * initial,
+            (0.2 * initial, 5.0 * initial),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn saturated_pure_components() {
+        // Measured: 0.998 MPa and 489 kg/m^3 at 300 K
+        let equation = PCSAFT::new(vec![String::from("propane")], vec![propane()]).unwrap();
+        let pressure = vapor_pressure(&equation, 300.0, 1.0e6);
+        assert!((pressure / 0.998e6 - 1.0).abs() < 0.02);
+        let density = |equation: &PCSAFT, t: f64, p: f64, molar_mass: f64| {
+            let volume = equation
+                .molar_volume(
+                    ThermodynamicTemperature::new::<kelvin>(t),
+                    Pressure::new::<pascal>(p),
+                    &[1.0],
+                    Phase::Liquid,
+                )
+                .unwrap();
+            molar_mass / volume.get::<cubic_meter_per_mole>()
+        };
+        assert!((density(&equation, 300.0, pressure, 44.10e-3) / 489.0 - 1.0).abs() < 0.02);
+
+        // Measured: 101325 Pa and 958.4 kg/m^3 at 373.15 K
+        let equation = PCSAFT::new(vec![String::from("water")], vec![water()]).unwrap();
+        let pressure = vapor_pressure(&equation, 373.15, 1.0e5);
+        assert!((pressure / 101_325.0 - 1.0).abs() < 0.05);
+        // The 2B parameters are fit mostly to vapor pressures and give liquid
+        // densities several percent low.
+        assert!((density(&equation, 373.15, pressure, 18.015e-3) / 958.4 - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn departure_functions_are_consistent() {
+        let equation = PCSAFT::new(
+            vec![String::from("water"), String::from("propane")],
+            vec![water(), propane()],
+        )
+        .unwrap();
+        let (t, step) = (350.0, 0.1);
+        for (x, p, phase) in [
+            ([0.02, 0.98], 1.0e5, Phase::Vapor),
+            ([0.99, 0.01], 1.0e6, Phase::Liquid),
+        ] {
+            let gibbs = |t: f64, p: f64| -> f64 {
+                let ln_phi = equation.fugacity_coefficients(t, p, &x, phase).unwrap();
+                x.iter().zip(ln_phi).map(|(x, ln_phi)| x * ln_phi).sum()
+            };
+            let enthalpy = equation.enthalpy_departure(t, p, &x, phase).unwrap();
+            let entropy = equation.entropy_departure(t, p, &x, phase).unwrap();
+            // G_dep / RT = sum(x ln phi) = (H_dep - T S_dep) / RT
+            let residual = (enthalpy - t * entropy) / (GAS_CONSTANT * t);
+            assert!((residual - gibbs(t, p)).abs() < 1e-8);
+            // H_dep = -R T^2 d(G_dep / RT)/dT
+            let derivative = (gibbs(t + step, p) - gibbs(t - step, p)) / (2.0 * step);
+            assert!((enthalpy + GAS_CONSTANT * t * t * derivative).abs() < 1e-2);
+            // Z - 1 = P d(G_dep / RT)/dP
+            let z = equation
+                .compressibility(
+                    ThermodynamicTemperature::new::<kelvin>(t),
+                    Pressure::new::<pascal>(p),
+                    &x,
+                    phase,
+                )
+                .unwrap();
+            let derivative = (gibbs(t, p * (1.0 + 1e-4)) - gibbs(t, p * (1.0 - 1e-4))) / 2e-4;
+            assert!((z - 1.0 - derivative).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn unsettled_site_fractions_are_errors() {
+        let equation = PCSAFT::new(vec![String::from("water")], vec![water()]).unwrap();
+        assert!(equation
+            .helmholtz(350.0, 0.03, &[1.0], Seed::Density)
+            .is_ok());
+        assert!(equation
+            .helmholtz(350.0, f64::NAN, &[1.0], Seed::Density)
+            .is_err());
+    }
+
+    #[test]
+    fn flash_of_methane_and_propane() {
+        let equation = Arc::new(
+            PCSAFT::new(
+                vec![String::from("methane"), String::from("propane")],
+                vec![parameters(1.0, 3.7039, 150.03, 16.04, 35.7), propane()],
+            )
+            .unwrap(),
+        );
+        let temperature = ThermodynamicTemperature::new::<kelvin>(250.0);
+        let pressure = Pressure::new::<pascal>(2.0e6);
+        let z = vec![0.5, 0.5];
+        let model = EOSModel::new(equation.clone(), temperature, pressure, z.clone()).unwrap();
+        let beta = model.vapor_fraction().get::<ratio>();
+        assert!(beta > 0.0 && beta < 1.0);
+
+        let (x, y) = (
+            &model.state.liquid_composition,
+            &model.state.vapor_composition,
+        );
+        let liquid = equation
+            .ln_fugacity_coefficients(temperature, pressure, x, Phase::Liquid)
+            .unwrap();
+        let vapor = equation
+            .ln_fugacity_coefficients(temperature, pressure, y, Phase::Vapor)
+            .unwrap();
+        for i in 0..2 {
+            assert!((beta * y[i] + (1.0 - beta) * x[i] - z[i]).abs() < 1e-9);
+            assert!((x[i] * liquid[i].exp() - y[i] * vapor[i].exp()).abs() < 1e-8);
+        }
+        assert!(y[0] > x[0]);
+        assert!(
+            model
+                .heat_capacity_const_pressure()
+                .get::<joule_per_kelvin_mole>()
+                > 0.0
+        );
+    }
+}
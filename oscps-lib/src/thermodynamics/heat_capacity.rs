@@ -0,0 +1,442 @@
+//! # Heat Capacity
+//!
+//! Correlations for the ideal gas heat capacity of pure components, and
+//! their integrals for the enthalpy and entropy of an ideal gas relative to
+//! a reference state.
+//!
+//! The polynomial, NASA and Shomate forms are sums of powers of temperature
+//! and are integrated term by term. The DIPPR 107 (Aly-Lee) form has closed
+//! integrals in hyperbolic functions. Correlations given over several
+//! temperature ranges are integrated range by range, so enthalpies and
+//! entropies follow the tabulated heat capacities across range boundaries.
+//! Outside of its ranges, a correlation is extrapolated from the nearest
+//! range.
+//!
+//! The integration constants of the NASA and Shomate forms, which give the
+//! absolute enthalpy and entropy, are kept with the other coefficients but
+//! not used: enthalpies and entropies are measured from the reference state
+//! of the model using them.
+
+use crate::thermodynamics::constants::UNIVERSAL_GAS_CONSTANT;
+use serde::{Deserialize, Serialize};
+use uom::si::f64::*;
+use uom::si::molar_energy::joule_per_mole;
+use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// # CorrelationRange
+///
+/// The coefficients of a correlation over the range of temperatures they
+/// were fitted to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationRange<const N: usize> {
+    /// Lowest temperature of the range
+    pub minimum: ThermodynamicTemperature,
+    /// Highest temperature of the range
+    pub maximum: ThermodynamicTemperature,
+    /// Coefficients of the correlation, in the order they are published
+    #[serde(with = "coefficient_array")]
+    pub coefficients: [f64; N],
+}
+
+/// Serializes the coefficients of a range as a sequence, since serde only
+/// implements arrays up to a fixed length.
+mod coefficient_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        coefficients: &[f64; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(coefficients)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[f64; N], D::Error> {
+        let coefficients = Vec::<f64>::deserialize(deserializer)?;
+        let length = coefficients.len();
+        coefficients
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(length, &"the coefficients of the correlation"))
+    }
+}
+
+/// # HeatCapacityCoefficients
+///
+/// The ideal gas heat capacity of a pure component as a function of
+/// temperature. Ranges of the NASA and Shomate forms are listed in order of
+/// increasing temperature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HeatCapacityCoefficients {
+    /// A heat capacity that does not change with temperature
+    Constant(MolarHeatCapacity),
+    /// 'Cp = c_0 + c_1 T + c_2 T^2 + ...' in J/(mol*K), with T in K
+    Polynomial(Vec<f64>),
+    /// DIPPR equation 107,
+    /// 'Cp = A + B ((C / T) / sinh(C / T))^2 + D ((E / T) / cosh(E / T))^2',
+    /// with coefficients '[A, B, C, D, E]' in J/(kmol*K) and K as published
+    /// in the DIPPR tables
+    Dippr107([f64; 5]),
+    /// NASA 7-coefficient polynomials,
+    /// 'Cp / R = a_1 + a_2 T + a_3 T^2 + a_4 T^3 + a_5 T^4', with
+    /// coefficients '[a_1, ..., a_7]' where 'a_6' and 'a_7' are the
+    /// enthalpy and entropy constants
+    Nasa7(Vec<CorrelationRange<7>>),
+    /// NASA 9-coefficient polynomials,
+    /// 'Cp / R = a_1 T^-2 + a_2 T^-1 + a_3 + a_4 T + a_5 T^2 + a_6 T^3 + a_7 T^4',
+    /// with coefficients '[a_1, ..., a_7, b_1, b_2]' where 'b_1' and 'b_2'
+    /// are the enthalpy and entropy constants
+    Nasa9(Vec<CorrelationRange<9>>),
+    /// The Shomate equation of the NIST Chemistry WebBook,
+    /// 'Cp = A + B t + C t^2 + D t^3 + E / t^2' in J/(mol*K) with
+    /// 't = T / 1000 K', and coefficients '[A, B, C, D, E, F, G, H]' where
+    /// 'F', 'G' and 'H' are the enthalpy and entropy constants
+    Shomate(Vec<CorrelationRange<8>>),
+}
+
+impl From<MolarHeatCapacity> for HeatCapacityCoefficients {
+    fn from(heat_capacity: MolarHeatCapacity) -> Self {
+        HeatCapacityCoefficients::Constant(heat_capacity)
+    }
+}
+
+/// A correlation over one range of temperatures, in J/(mol*K) and K.
+enum Piece {
+    /// 'Cp = sum_k c_k T^k' for pairs '(k, c_k)'
+    Powers(Vec<(i32, f64)>),
+    /// DIPPR 107 with coefficients in J/(mol*K) and K
+    Dippr107([f64; 5]),
+}
+
+/// Natural logarithm of 'sinh(u)' for 'u > 0', without overflow
+fn ln_sinh(u: f64) -> f64 {
+    u + (-(-2.0 * u).exp_m1()).ln() - std::f64::consts::LN_2
+}
+
+/// Natural logarithm of 'cosh(u)' for 'u > 0', without overflow
+fn ln_cosh(u: f64) -> f64 {
+    u + (-2.0 * u).exp().ln_1p() - std::f64::consts::LN_2
+}
+
+impl Piece {
+    fn heat_capacity(&self, t: f64) -> f64 {
+        match self {
+            Piece::Powers(terms) => terms.iter().map(|(k, c)| c * t.powi(*k)).sum(),
+            Piece::Dippr107([a, b, c, d, e]) => {
+                let (u, v) = (c / t, e / t);
+                a + b * (u / u.sinh()).powi(2) + d * (v / v.cosh()).powi(2)
+            }
+        }
+    }
+
+    /// An antiderivative of the heat capacity in temperature
+    fn enthalpy(&self, t: f64) -> f64 {
+        match self {
+            Piece::Powers(terms) => terms
+                .iter()
+                .map(|(k, c)| match k {
+                    -1 => c * t.ln(),
+                    k => c * t.powi(k + 1) / (k + 1) as f64,
+                })
+                .sum(),
+            Piece::Dippr107([a, b, c, d, e]) => {
+                a * t + b * c / (c / t).tanh() - d * e * (e / t).tanh()
+            }
+        }
+    }
+
+    /// An antiderivative of the heat capacity divided by temperature
+    fn entropy(&self, t: f64) -> f64 {
+        match self {
+            Piece::Powers(terms) => terms
+                .iter()
+                .map(|(k, c)| match k {
+                    0 => c * t.ln(),
+                    k => c * t.powi(*k) / *k as f64,
+                })
+                .sum(),
+            Piece::Dippr107([a, b, c, d, e]) => {
+                let (u, v) = (c / t, e / t);
+                a * t.ln() + b * (u / u.tanh() - ln_sinh(u)) - d * (v * v.tanh() - ln_cosh(v))
+            }
+        }
+    }
+}
+
+/// Pieces of a correlation given over several ranges, each with the
+/// temperature in K up to which it applies.
+fn ranged<const N: usize>(
+    ranges: &[CorrelationRange<N>],
+    powers: impl Fn(&[f64; N]) -> Vec<(i32, f64)>,
+) -> Vec<(f64, Piece)> {
+    ranges
+        .iter()
+        .enumerate()
+        .map(|(i, range)| {
+            let upper = if i + 1 == ranges.len() {
+                f64::INFINITY
+            } else {
+                range.maximum.get::<kelvin>()
+            };
+            (upper, Piece::Powers(powers(&range.coefficients)))
+        })
+        .collect()
+}
+
+impl HeatCapacityCoefficients {
+    /// The correlation as pieces in SI units, each with the temperature in K
+    /// up to which it applies
+    fn pieces(&self) -> Vec<(f64, Piece)> {
+        let r = UNIVERSAL_GAS_CONSTANT.get::<joule_per_kelvin_mole>();
+        match self {
+            HeatCapacityCoefficients::Constant(heat_capacity) => vec![(
+                f64::INFINITY,
+                Piece::Powers(vec![(0, heat_capacity.get::<joule_per_kelvin_mole>())]),
+            )],
+            HeatCapacityCoefficients::Polynomial(coefficients) => vec![(
+                f64::INFINITY,
+                Piece::Powers((0..).zip(coefficients.iter().copied()).collect()),
+            )],
+            HeatCapacityCoefficients::Dippr107([a, b, c, d, e]) => vec![(
+                f64::INFINITY,
+                Piece::Dippr107([a * 1e-3, b * 1e-3, *c, d * 1e-3, *e]),
+            )],
+            HeatCapacityCoefficients::Nasa7(ranges) => {
+                ranged(ranges, |a| (0..5).map(|k| (k, r * a[k as usize])).collect())
+            }
+            HeatCapacityCoefficients::Nasa9(ranges) => ranged(ranges, |a| {
+                (-2..5).map(|k| (k, r * a[(k + 2) as usize])).collect()
+            }),
+            HeatCapacityCoefficients::Shomate(ranges) => ranged(ranges, |c| {
+                vec![
+                    (0, c[0]),
+                    (1, c[1] * 1e-3),
+                    (2, c[2] * 1e-6),
+                    (3, c[3] * 1e-9),
+                    (-2, c[4] * 1e6),
+                ]
+            }),
+        }
+    }
+
+    /// Integrates an antiderivative of each piece from one temperature to
+    /// another.
+    fn integrate(&self, from: f64, to: f64, antiderivative: impl Fn(&Piece, f64) -> f64) -> f64 {
+        let (low, high, sign) = if from <= to {
+            (from, to, 1.0)
+        } else {
+            (to, from, -1.0)
+        };
+        let mut lower = f64::NEG_INFINITY;
+        let mut total = 0.0;
+        for (upper, piece) in self.pieces() {
+            let (a, b) = (low.max(lower), high.min(upper));
+            if a < b {
+                total += antiderivative(&piece, b) - antiderivative(&piece, a);
+            }
+            lower = upper;
+        }
+        sign * total
+    }
+
+    /// Ideal gas heat capacity at a given temperature
+    pub fn heat_capacity(&self, temperature: ThermodynamicTemperature) -> MolarHeatCapacity {
+        let t = temperature.get::<kelvin>();
+        let pieces = self.pieces();
+        let value = pieces
+            .iter()
+            .find(|(upper, _)| t <= *upper)
+            .or(pieces.last())
+            .map_or(0.0, |(_, piece)| piece.heat_capacity(t));
+        MolarHeatCapacity::new::<joule_per_kelvin_mole>(value)
+    }
+
+    /// Change in the ideal gas enthalpy from one temperature to another,
+    /// the integral of 'Cp dT'
+    pub fn enthalpy_change(
+        &self,
+        from: ThermodynamicTemperature,
+        to: ThermodynamicTemperature,
+    ) -> MolarEnergy {
+        MolarEnergy::new::<joule_per_mole>(self.integrate(
+            from.get::<kelvin>(),
+            to.get::<kelvin>(),
+            Piece::enthalpy,
+        ))
+    }
+
+    /// Change in the ideal gas entropy from one temperature to another at
+    /// constant pressure, the integral of 'Cp / T dT'
+    pub fn entropy_change(
+        &self,
+        from: ThermodynamicTemperature,
+        to: ThermodynamicTemperature,
+    ) -> MolarHeatCapacity {
+        MolarHeatCapacity::new::<joule_per_kelvin_mole>(self.integrate(
+            from.get::<kelvin>(),
+            to.get::<kelvin>(),
+            Piece::entropy,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod heat_capacity_tests {
+    use super::*;
+
+    fn range<const N: usize>(
+        minimum: f64,
+        maximum: f64,
+        coefficients: [f64; N],
+    ) -> CorrelationRange<N> {
+        CorrelationRange {
+            minimum: ThermodynamicTemperature::new::<kelvin>(minimum),
+            maximum: ThermodynamicTemperature::new::<kelvin>(maximum),
+            coefficients,
+        }
+    }
+
+    const NASA7_LOW: [f64; 7] = [
+        5.14987613,
+        -1.36709788e-2,
+        4.91800599e-5,
+        -4.84743026e-8,
+        1.66693956e-11,
+        -1.02466476e4,
+        -4.64130376,
+    ];
+    const NASA7_HIGH: [f64; 7] = [
+        7.48514950e-2,
+        1.33909467e-2,
+        -5.73285809e-6,
+        1.22292535e-9,
+        -1.01815230e-13,
+        -9.46834459e3,
+        1.84373180e1,
+    ];
+
+    /// Methane in each of the forms: DIPPR 107 from Perry's handbook, NASA 7
+    /// from GRI-Mech 3.0 and Shomate from the NIST Chemistry WebBook
+    fn methane() -> Vec<HeatCapacityCoefficients> {
+        vec![
+            HeatCapacityCoefficients::Dippr107([0.33298e5, 0.79933e5, 2.0869e3, 0.41602e5, 991.96]),
+            HeatCapacityCoefficients::Nasa7(vec![
+                range(200.0, 1000.0, NASA7_LOW),
+                range(1000.0, 3500.0, NASA7_HIGH),
+            ]),
+            HeatCapacityCoefficients::Shomate(vec![
+                range(
+                    298.0,
+                    1300.0,
+                    [
+                        -0.703029, 108.4773, -42.52157, 5.862788, 0.678565, -76.84376, 158.7163,
+                        -74.87310,
+                    ],
+                ),
+                range(
+                    1300.0,
+                    6000.0,
+                    [
+                        85.81217, 11.26467, -2.114146, 0.138190, -26.42221, -153.5327, 224.4143,
+                        -74.87310,
+                    ],
+                ),
+            ]),
+        ]
+    }
+
+    fn kelvin(t: f64) -> ThermodynamicTemperature {
+        ThermodynamicTemperature::new::<super::kelvin>(t)
+    }
+
+    #[test]
+    fn methane_correlations_agree() {
+        for correlation in methane() {
+            // Tabulated: 35.69 J/(mol*K) at 298.15 K, and an enthalpy 38.18
+            // kJ/mol above that at 1000 K, which the sources reproduce to
+            // within their scatter
+            let cp = correlation.heat_capacity(kelvin(298.15));
+            assert!((cp.get::<joule_per_kelvin_mole>() / 35.69 - 1.0).abs() < 0.01);
+            let dh = correlation.enthalpy_change(kelvin(298.15), kelvin(1000.0));
+            assert!((dh.get::<joule_per_mole>() / 38_180.0 - 1.0).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn integrals_match_quadrature() {
+        let mut correlations = methane();
+        correlations.push(HeatCapacityCoefficients::Polynomial(vec![
+            19.25, 5.213e-2, 1.197e-5, -1.132e-8,
+        ]));
+        correlations.push(HeatCapacityCoefficients::Constant(
+            MolarHeatCapacity::new::<joule_per_kelvin_mole>(29.1),
+        ));
+        let (from, to) = (250.0, 2500.0);
+        for correlation in correlations {
+            // Simpson's rule with a node at every range boundary
+            let steps = 22_500;
+            let h = (to - from) / steps as f64;
+            let (mut enthalpy, mut entropy) = (0.0, 0.0);
+            for i in 0..=steps {
+                let t = from + i as f64 * h;
+                let weight = match i {
+                    0 => 1.0,
+                    i if i == steps => 1.0,
+                    i if i % 2 == 1 => 4.0,
+                    _ => 2.0,
+                } * h
+                    / 3.0;
+                let cp = correlation
+                    .heat_capacity(kelvin(t))
+                    .get::<joule_per_kelvin_mole>();
+                enthalpy += weight * cp;
+                entropy += weight * cp / t;
+            }
+            let dh = correlation
+                .enthalpy_change(kelvin(from), kelvin(to))
+                .get::<joule_per_mole>();
+            let ds = correlation
+                .entropy_change(kelvin(from), kelvin(to))
+                .get::<joule_per_kelvin_mole>();
+            assert!((dh / enthalpy - 1.0).abs() < 1e-4);
+            assert!((ds / entropy - 1.0).abs() < 1e-4);
+            // Integrating backwards changes the sign
+            let back = correlation
+                .enthalpy_change(kelvin(to), kelvin(from))
+                .get::<joule_per_mole>();
+            assert!((back + dh).abs() < 1e-9 * dh.abs());
+        }
+    }
+
+    #[test]
+    fn nasa_forms_are_consistent() {
+        let nine = |a: [f64; 7]| [0.0, 0.0, a[0], a[1], a[2], a[3], a[4], a[5], a[6]];
+        let seven = HeatCapacityCoefficients::Nasa7(vec![
+            range(200.0, 1000.0, NASA7_LOW),
+            range(1000.0, 3500.0, NASA7_HIGH),
+        ]);
+        let nine = HeatCapacityCoefficients::Nasa9(vec![
+            range(200.0, 1000.0, nine(NASA7_LOW)),
+            range(1000.0, 3500.0, nine(NASA7_HIGH)),
+        ]);
+        for t in [150.0, 298.15, 1000.0, 1500.0, 4000.0] {
+            let (first, second) = (
+                seven.heat_capacity(kelvin(t)),
+                nine.heat_capacity(kelvin(t)),
+            );
+            assert!((first - second).get::<joule_per_kelvin_mole>().abs() < 1e-9);
+            let first = seven.entropy_change(kelvin(298.15), kelvin(t));
+            let second = nine.entropy_change(kelvin(298.15), kelvin(t));
+            assert!((first - second).get::<joule_per_kelvin_mole>().abs() < 1e-8);
+        }
+        // The low temperature range is used below 1000 K
+        let cp = seven
+            .heat_capacity(kelvin(500.0))
+            .get::<joule_per_kelvin_mole>();
+        let r = UNIVERSAL_GAS_CONSTANT.get::<joule_per_kelvin_mole>();
+        let expected: f64 = (0..5).map(|k| NASA7_LOW[k] * 500f64.powi(k as i32)).sum();
+        assert!((cp - r * expected).abs() < 1e-9);
+    }
+}
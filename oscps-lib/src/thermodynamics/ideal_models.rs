@@ -0,0 +1,567 @@
+//! # Ideal Models
+//!
+//! The simplest property package: an ideal gas vapor in equilibrium with an
+//! ideal liquid solution following Raoult's law, 'y_i P = x_i P_sat,i', with
+//! vapor pressures from the Antoine equation.
+//!
+//! Enthalpies integrate the ideal gas heat capacity from the reference
+//! temperature, and liquids subtract the heat of vaporization found from the
+//! Antoine equation with the Clausius-Clapeyron equation. The volume of the
+//! liquid is neglected, as Raoult's law itself does. The package is fast and
+//! always converges, which makes it a good baseline for quick studies and
+//! for checking the results of more detailed models.
+
+use crate::thermodynamics::activity_models::LiquidParameters;
+use crate::thermodynamics::constants::ReferenceState;
+use crate::thermodynamics::eos_models::{ideal_gas_enthalpy, ideal_gas_entropy, GAS_CONSTANT};
+use crate::thermodynamics::heat_capacity::HeatCapacityCoefficients;
+use crate::thermodynamics::{find_root, FlashResult, MaxwellRelations, Phase, PhaseEquilibrium};
+use std::sync::Arc;
+use uom::si::amount_of_substance::mole;
+use uom::si::f64::*;
+use uom::si::molar_energy::joule_per_mole;
+use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+use uom::si::molar_volume::cubic_meter_per_mole;
+use uom::si::pressure::pascal;
+use uom::si::ratio::ratio;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// # RaoultsLaw
+///
+/// Vapor-liquid equilibrium of an ideal gas and an ideal liquid solution.
+/// The equilibrium ratio of each component is 'K_i = P_sat,i / P'.
+#[derive(Debug, Clone)]
+pub struct RaoultsLaw {
+    /// State where the enthalpy and entropy of each ideal gas are zero
+    pub reference_state: ReferenceState,
+    components: Vec<String>,
+    parameters: Vec<LiquidParameters>,
+}
+
+impl RaoultsLaw {
+    /// Creates the model from the Antoine coefficients, molar mass and ideal
+    /// gas heat capacity of each component.
+    pub fn new(
+        components: Vec<String>,
+        parameters: Vec<LiquidParameters>,
+    ) -> Result<Self, &'static str> {
+        if components.len() != parameters.len() {
+            return Err("every component needs one set of parameters");
+        }
+        Ok(RaoultsLaw {
+            reference_state: ReferenceState::default(),
+            components,
+            parameters,
+        })
+    }
+
+    /// Measures enthalpies and entropies from another reference state.
+    pub fn with_reference_state(mut self, reference_state: ReferenceState) -> Self {
+        self.reference_state = reference_state;
+        self
+    }
+
+    /// Pure component parameters, in the component order of the model
+    pub fn parameters(&self) -> &[LiquidParameters] {
+        &self.parameters
+    }
+
+    /// Equilibrium ratio 'K_i = y_i / x_i' of each component
+    pub fn equilibrium_ratios(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+    ) -> Vec<f64> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.antoine.vapor_pressure(temperature) / pressure)
+            .map(|k: Ratio| k.get::<ratio>())
+            .collect()
+    }
+
+    fn heat_capacities(&self) -> Vec<&HeatCapacityCoefficients> {
+        self.parameters
+            .iter()
+            .map(|parameters| &parameters.heat_capacity)
+            .collect()
+    }
+
+    /// Molar entropy of a phase in J/(mol*K). The entropy of a pure liquid is
+    /// that of its saturated vapor less the entropy of vaporization, and does
+    /// not depend on pressure.
+    fn entropy(&self, t: f64, p: f64, composition: &[f64], phase: Phase) -> f64 {
+        let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+        let heat_capacities = self.heat_capacities();
+        let reference = &self.reference_state;
+        let p_ref = reference.pressure.get::<pascal>();
+        match phase {
+            Phase::Vapor => ideal_gas_entropy(&heat_capacities, t, p, composition, reference),
+            Phase::Liquid => {
+                ideal_gas_entropy(&heat_capacities, t, p_ref, composition, reference)
+                    - self
+                        .parameters
+                        .iter()
+                        .zip(composition)
+                        .map(|(parameters, x)| {
+                            let vapor_pressure = parameters.antoine.vapor_pressure(temperature);
+                            let heat = parameters.antoine.heat_of_vaporization(temperature);
+                            x * (GAS_CONSTANT * (vapor_pressure.get::<pascal>() / p_ref).ln()
+                                + heat.get::<joule_per_mole>() / t)
+                        })
+                        .sum::<f64>()
+            }
+        }
+    }
+}
+
+impl PhaseEquilibrium for RaoultsLaw {
+    fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    fn molar_masses(&self) -> Vec<MolarMass> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.molar_mass)
+            .collect()
+    }
+
+    fn flash_tp(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+    ) -> Result<FlashResult, &'static str> {
+        let k = self.equilibrium_ratios(temperature, pressure);
+        let rachford_rice = |v: f64| -> f64 {
+            composition
+                .iter()
+                .zip(&k)
+                .map(|(z, k)| z * (k - 1.0) / (1.0 + v * (k - 1.0)))
+                .sum()
+        };
+        let v = if rachford_rice(0.0) <= 0.0 {
+            0.0
+        } else if rachford_rice(1.0) >= 0.0 {
+            1.0
+        } else {
+            find_root(|v| Ok(rachford_rice(v)), 0.5, 0.1, (0.0, 1.0))?
+        };
+        // Outside the two-phase region the missing phase takes the
+        // composition of the first bubble or drop that would form
+        let normalized = |fractions: Vec<f64>| -> Vec<f64> {
+            let total: f64 = fractions.iter().sum();
+            if total > 0.0 {
+                fractions.iter().map(|fraction| fraction / total).collect()
+            } else {
+                fractions
+            }
+        };
+        let x: Vec<f64> = composition
+            .iter()
+            .zip(&k)
+            .map(|(z, k)| z / (1.0 + v * (k - 1.0)))
+            .collect();
+        let y = normalized(x.iter().zip(&k).map(|(x, k)| x * k).collect());
+        let x = normalized(x);
+        let liquid_enthalpy = self.phase_enthalpy(temperature, pressure, &x, Phase::Liquid)?;
+        let vapor_enthalpy = self.phase_enthalpy(temperature, pressure, &y, Phase::Vapor)?;
+        Ok(FlashResult {
+            temperature,
+            pressure,
+            vapor_fraction: Ratio::new::<ratio>(v),
+            liquid_composition: x,
+            vapor_composition: y,
+            enthalpy: liquid_enthalpy * (1.0 - v) + vapor_enthalpy * v,
+            liquid_enthalpy,
+            vapor_enthalpy,
+        })
+    }
+
+    /// Flashes at a given pressure and enthalpy. The temperature search of
+    /// the default implementation stops at the boiling point of a pure
+    /// component, where the enthalpy jumps, so the vapor fraction is then
+    /// found from the heat of vaporization.
+    fn flash_ph(
+        &self,
+        pressure: Pressure,
+        enthalpy: MolarEnergy,
+        composition: &[f64],
+        initial_temperature: ThermodynamicTemperature,
+    ) -> Result<FlashResult, &'static str> {
+        let target = enthalpy.get::<joule_per_mole>();
+        let temperature = find_root(
+            |t| {
+                let flash = self.flash_tp(
+                    ThermodynamicTemperature::new::<kelvin>(t),
+                    pressure,
+                    composition,
+                )?;
+                Ok(flash.enthalpy.get::<joule_per_mole>() - target)
+            },
+            initial_temperature.get::<kelvin>(),
+            10.0,
+            (1.0, 10_000.0),
+        )?;
+        let temperature = ThermodynamicTemperature::new::<kelvin>(temperature);
+        let mut flash = self.flash_tp(temperature, pressure, composition)?;
+        let pure = composition.iter().filter(|z| **z > 0.0).count() == 1;
+        let mismatch = (flash.enthalpy.get::<joule_per_mole>() - target).abs();
+        if pure && mismatch > 1e-6 * target.abs().max(1.0) {
+            let liquid = self.phase_enthalpy(temperature, pressure, composition, Phase::Liquid)?;
+            let vapor = self.phase_enthalpy(temperature, pressure, composition, Phase::Vapor)?;
+            let v: Ratio = (enthalpy - liquid) / (vapor - liquid);
+            flash.vapor_fraction = Ratio::new::<ratio>(v.get::<ratio>().clamp(0.0, 1.0));
+            flash.liquid_composition = composition.to_vec();
+            flash.vapor_composition = composition.to_vec();
+            flash.liquid_enthalpy = liquid;
+            flash.vapor_enthalpy = vapor;
+            flash.enthalpy = enthalpy;
+        }
+        Ok(flash)
+    }
+
+    fn ln_fugacity_coefficients(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<Vec<f64>, &'static str> {
+        Ok(match phase {
+            Phase::Vapor => vec![0.0; composition.len()],
+            Phase::Liquid => self
+                .equilibrium_ratios(temperature, pressure)
+                .iter()
+                .map(|k| k.ln())
+                .collect(),
+        })
+    }
+
+    fn phase_enthalpy(
+        &self,
+        temperature: ThermodynamicTemperature,
+        _pressure: Pressure,
+        composition: &[f64],
+        phase: Phase,
+    ) -> Result<MolarEnergy, &'static str> {
+        let ideal = MolarEnergy::new::<joule_per_mole>(ideal_gas_enthalpy(
+            &self.heat_capacities(),
+            temperature.get::<kelvin>(),
+            composition,
+            &self.reference_state,
+        ));
+        Ok(match phase {
+            Phase::Vapor => ideal,
+            Phase::Liquid => {
+                self.parameters
+                    .iter()
+                    .zip(composition)
+                    .fold(ideal, |total, (parameters, x)| {
+                        total - parameters.antoine.heat_of_vaporization(temperature) * *x
+                    })
+            }
+        })
+    }
+}
+
+/// # IdealModel
+///
+/// The state of a mixture described by [`RaoultsLaw`]. The mixture is
+/// flashed when the model is created, and the properties returned through
+/// [`MaxwellRelations`] are those of the mixture at equilibrium.
+#[derive(Debug, Clone)]
+pub struct IdealModel {
+    /// Vapor-liquid equilibrium model of the mixture
+    pub package: Arc<RaoultsLaw>,
+    /// Overall mole fractions of the mixture
+    pub composition: Vec<f64>,
+    /// Equilibrium state of the mixture
+    pub state: FlashResult,
+}
+
+impl IdealModel {
+    /// Flashes a mixture at a given temperature and pressure.
+    pub fn new(
+        package: Arc<RaoultsLaw>,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        composition: Vec<f64>,
+    ) -> Result<Self, &'static str> {
+        let state = package.flash_tp(temperature, pressure, &composition)?;
+        Ok(IdealModel {
+            package,
+            composition,
+            state,
+        })
+    }
+
+    /// Molar volume of the mixture, which is that of its vapor since the
+    /// liquid volume is neglected
+    pub fn molar_volume(&self) -> MolarVolume {
+        let t = self.state.temperature.get::<kelvin>();
+        let p = self.state.pressure.get::<pascal>();
+        MolarVolume::new::<cubic_meter_per_mole>(
+            self.state.vapor_fraction.get::<ratio>() * GAS_CONSTANT * t / p,
+        )
+    }
+}
+
+impl MaxwellRelations for IdealModel {
+    fn enthalpy(&self) -> MolarEnergy {
+        self.state.enthalpy
+    }
+
+    fn entropy(&self) -> MolarHeatCapacity {
+        let t = self.state.temperature.get::<kelvin>();
+        let p = self.state.pressure.get::<pascal>();
+        let v = self.state.vapor_fraction.get::<ratio>();
+        let mut entropy = 0.0;
+        if v > 0.0 {
+            entropy += v * self
+                .package
+                .entropy(t, p, &self.state.vapor_composition, Phase::Vapor);
+        }
+        if v < 1.0 {
+            entropy += (1.0 - v)
+                * self
+                    .package
+                    .entropy(t, p, &self.state.liquid_composition, Phase::Liquid);
+        }
+        MolarHeatCapacity::new::<joule_per_kelvin_mole>(entropy)
+    }
+
+    fn pressure(&self) -> Pressure {
+        self.state.pressure
+    }
+
+    /// Volume of one mole of the mixture
+    fn volume(&self) -> Volume {
+        self.molar_volume() * AmountOfSubstance::new::<mole>(1.0)
+    }
+
+    fn temperature(&self) -> ThermodynamicTemperature {
+        self.state.temperature
+    }
+
+    fn vapor_fraction(&self) -> Ratio {
+        self.state.vapor_fraction
+    }
+
+    /// Heat capacity of the mixture with the phase compositions held fixed.
+    /// The liquid heat capacity includes the change in the heat of
+    /// vaporization with temperature.
+    fn heat_capacity_const_pressure(&self) -> MolarHeatCapacity {
+        let t = self.state.temperature.get::<kelvin>();
+        let v = self.state.vapor_fraction.get::<ratio>();
+        let heat_capacity = |x: &[f64], liquid: bool| -> f64 {
+            self.package
+                .parameters
+                .iter()
+                .zip(x)
+                .map(|(parameters, x)| {
+                    let antoine = &parameters.antoine;
+                    // Temperature derivative of 'R T^2 B / (T + C)^2'
+                    let change = if liquid {
+                        2.0 * GAS_CONSTANT * antoine.b * antoine.c * t / (t + antoine.c).powi(3)
+                    } else {
+                        0.0
+                    };
+                    let cp = parameters
+                        .heat_capacity
+                        .heat_capacity(self.state.temperature);
+                    x * (cp.get::<joule_per_kelvin_mole>() - change)
+                })
+                .sum()
+        };
+        MolarHeatCapacity::new::<joule_per_kelvin_mole>(
+            v * heat_capacity(&self.state.vapor_composition, false)
+                + (1.0 - v) * heat_capacity(&self.state.liquid_composition, true),
+        )
+    }
+
+    fn internal_energy(&self) -> MolarEnergy {
+        self.state.enthalpy - self.state.pressure * self.molar_volume()
+    }
+
+    /// Gibbs free energy of one mole of the mixture
+    fn gibbs_free_energy(&self) -> Energy {
+        (self.enthalpy() - self.state.temperature * self.entropy())
+            * AmountOfSubstance::new::<mole>(1.0)
+    }
+}
+
+#[cfg(test)]
+mod ideal_tests {
+    use super::*;
+    use crate::thermodynamics::activity_models::AntoineCoefficients;
+    use uom::si::molar_mass::gram_per_mole;
+
+    /// Benzene and toluene, whose mixtures are nearly ideal
+    fn benzene_toluene() -> Arc<RaoultsLaw> {
+        let parameters = |a: f64, b: f64, c: f64, mw: f64, cp: f64| LiquidParameters {
+            antoine: AntoineCoefficients { a, b, c },
+            molar_mass: MolarMass::new::<gram_per_mole>(mw),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(cp).into(),
+        };
+        Arc::new(
+            RaoultsLaw::new(
+                vec![String::from("benzene"), String::from("toluene")],
+                vec![
+                    parameters(20.7936, 2788.51, -52.36, 78.11, 82.4),
+                    parameters(20.9065, 3096.52, -53.67, 92.14, 103.6),
+                ],
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn flash_follows_raoults_law() {
+        let package = benzene_toluene();
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let z = [0.5, 0.5];
+        // Benzene boils near 353 K and toluene near 384 K
+        for (t, phase) in [(340.0, 0.0), (400.0, 1.0)] {
+            let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+            let flash = package.flash_tp(temperature, pressure, &z).unwrap();
+            assert_eq!(flash.vapor_fraction.get::<ratio>(), phase);
+            // The missing phase is the incipient bubble or drop
+            let (x, y) = (&flash.liquid_composition, &flash.vapor_composition);
+            assert!((x.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            let k = package.equilibrium_ratios(temperature, pressure);
+            assert!((y[0] / x[0] / (y[1] / x[1]) - k[0] / k[1]).abs() < 1e-9);
+            let enthalpies = [(x, Phase::Liquid), (y, Phase::Vapor)].map(|(fractions, phase)| {
+                package.phase_enthalpy(temperature, pressure, fractions, phase)
+            });
+            assert_eq!(flash.liquid_enthalpy, enthalpies[0].unwrap());
+            assert_eq!(flash.vapor_enthalpy, enthalpies[1].unwrap());
+        }
+        let temperature = ThermodynamicTemperature::new::<kelvin>(368.0);
+        let flash = package.flash_tp(temperature, pressure, &z).unwrap();
+        let v = flash.vapor_fraction.get::<ratio>();
+        assert!(v > 0.0 && v < 1.0);
+        let k = package.equilibrium_ratios(temperature, pressure);
+        for i in 0..2 {
+            let (x, y) = (flash.liquid_composition[i], flash.vapor_composition[i]);
+            assert!((y - k[i] * x).abs() < 1e-9);
+            assert!((z[i] - (1.0 - v) * x - v * y).abs() < 1e-9);
+        }
+        assert!((flash.vapor_composition.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // Enthalpy flashes recover the temperature
+        let repeated = package
+            .flash_ph(
+                pressure,
+                flash.enthalpy,
+                &z,
+                ThermodynamicTemperature::new::<kelvin>(300.0),
+            )
+            .unwrap();
+        assert!((repeated.temperature.get::<kelvin>() - 368.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pure_component_enthalpy_flash() {
+        // Half of a pure component boils at its normal boiling point
+        let package = benzene_toluene();
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let z = [1.0, 0.0];
+        let antoine = &package.parameters()[0].antoine;
+        let boiling = antoine.b / (antoine.a - 101_325f64.ln()) - antoine.c;
+        let temperature = ThermodynamicTemperature::new::<kelvin>(boiling);
+        let liquid = package
+            .phase_enthalpy(temperature, pressure, &z, Phase::Liquid)
+            .unwrap();
+        let vapor = package
+            .phase_enthalpy(temperature, pressure, &z, Phase::Vapor)
+            .unwrap();
+        let flash = package
+            .flash_ph(
+                pressure,
+                (liquid + vapor) / 2.0,
+                &z,
+                ThermodynamicTemperature::new::<kelvin>(300.0),
+            )
+            .unwrap();
+        assert!((flash.temperature.get::<kelvin>() - boiling).abs() < 1e-6);
+        assert!((flash.vapor_fraction.get::<ratio>() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn maxwell_relations() {
+        let package = benzene_toluene();
+        let pressure = Pressure::new::<pascal>(101_325.0);
+        let state = |t: f64, p: Pressure| {
+            IdealModel::new(
+                package.clone(),
+                ThermodynamicTemperature::new::<kelvin>(t),
+                p,
+                vec![0.4, 0.6],
+            )
+            .unwrap()
+        };
+        for t in [320.0, 420.0] {
+            let model = state(t, pressure);
+            // 'C_p = dH/dT' and 'dS/dT = C_p / T' within a single phase
+            let step = 1e-3;
+            let (above, below) = (state(t + step, pressure), state(t - step, pressure));
+            let heat_capacity =
+                (above.enthalpy() - below.enthalpy()).get::<joule_per_mole>() / (2.0 * step);
+            let cp = model
+                .heat_capacity_const_pressure()
+                .get::<joule_per_kelvin_mole>();
+            assert!((heat_capacity - cp).abs() < 1e-6 * cp);
+            let entropy_change =
+                (above.entropy() - below.entropy()).get::<joule_per_kelvin_mole>() / (2.0 * step);
+            assert!((entropy_change - cp / t).abs() < 1e-6 * cp / t);
+        }
+        // An ideal gas: 'PV = RT' and 'dG/dP = V' for one mole
+        let vapor = state(420.0, pressure);
+        let volume = vapor.molar_volume().get::<cubic_meter_per_mole>();
+        assert!((volume * 101_325.0 - GAS_CONSTANT * 420.0).abs() < 1e-9);
+        let step = Pressure::new::<pascal>(10.0);
+        let (above, below) = (state(420.0, pressure + step), state(420.0, pressure - step));
+        let gibbs_change = (above.gibbs_free_energy() - below.gibbs_free_energy())
+            .get::<uom::si::energy::joule>()
+            / 20.0;
+        assert!((gibbs_change - volume).abs() < 1e-6 * volume);
+
+        // Liquid and vapor of an equilibrium mixture have the same chemical
+        // potentials, so the Gibbs energy changes smoothly through the
+        // two-phase region.
+        let two_phase = state(370.0, pressure);
+        let v = two_phase.vapor_fraction().get::<ratio>();
+        assert!(v > 0.0 && v < 1.0);
+        let t = 370.0;
+        let liquid = &two_phase.state.liquid_composition;
+        let vapor = &two_phase.state.vapor_composition;
+        let chemical_potentials = |x: &[f64], phase: Phase| -> Vec<f64> {
+            (0..2)
+                .map(|i| {
+                    let mut more = x.to_vec();
+                    more[i] += 1e-7;
+                    let g = |x: &[f64]| {
+                        let n: f64 = x.iter().sum();
+                        let fractions: Vec<f64> = x.iter().map(|x| x / n).collect();
+                        let temperature = ThermodynamicTemperature::new::<kelvin>(t);
+                        let h = package
+                            .phase_enthalpy(temperature, pressure, &fractions, phase)
+                            .unwrap()
+                            .get::<joule_per_mole>();
+                        n * (h - t * package.entropy(t, 101_325.0, &fractions, phase))
+                    };
+                    (g(&more) - g(x)) / 1e-7
+                })
+                .collect()
+        };
+        let (mu_liquid, mu_vapor) = (
+            chemical_potentials(liquid, Phase::Liquid),
+            chemical_potentials(vapor, Phase::Vapor),
+        );
+        for i in 0..2 {
+            assert!((mu_liquid[i] - mu_vapor[i]).abs() < 1e-2);
+        }
+    }
+}
@@ -0,0 +1,800 @@
+//! # Phase Envelope
+//!
+//! Bubble and dew point calculations, and the tracing of the pressure-
+//! temperature phase envelope of a mixture of fixed composition.
+//!
+//! Saturation points are found from the fugacity coefficients of a
+//! thermodynamic model: the mixture is the reference phase, and the
+//! incipient phase (the first bubble of vapor at a bubble point, or the first
+//! drop of liquid at a dew point) is in equilibrium with it while having no
+//! amount.
+//!
+//! The envelope is traced with the continuation method of Michelsen (1980).
+//! The unknowns are the logarithms of the equilibrium ratios, temperature and
+//! pressure. Each point fixes one of them, chosen as the one changing fastest
+//! along the curve, so the trace passes the cricondenbar, the cricondentherm
+//! and the critical point, where the bubble point curve turns into the dew
+//! point curve.
+
+use crate::numerics::SparseMatrix;
+use crate::thermodynamics::{find_root, Phase, PhaseEquilibrium};
+use uom::si::f64::*;
+use uom::si::pressure::pascal;
+use uom::si::thermodynamic_temperature::kelvin;
+
+/// Bounds on the natural logarithm of the pressure in Pa
+const LOG_PRESSURE_BOUNDS: (f64, f64) = (0.0, 23.0);
+
+/// Bounds on the temperature in K
+const TEMPERATURE_BOUNDS: (f64, f64) = (1.0, 10_000.0);
+
+/// Largest number of points traced on an envelope
+const MAX_POINTS: usize = 2000;
+
+/// Largest change of the logarithm of an equilibrium ratio, temperature and
+/// pressure from one point of the envelope to the next
+const MAX_CHANGES: [f64; 3] = [0.1, 0.02, 0.1];
+
+/// The kinds of saturation points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaturationKind {
+    /// The mixture is a liquid forming its first bubble of vapor
+    Bubble,
+    /// The mixture is a vapor forming its first drop of liquid
+    Dew,
+}
+
+impl SaturationKind {
+    /// Phases of the mixture and of the incipient phase
+    fn phases(&self) -> (Phase, Phase) {
+        match self {
+            SaturationKind::Bubble => (Phase::Liquid, Phase::Vapor),
+            SaturationKind::Dew => (Phase::Vapor, Phase::Liquid),
+        }
+    }
+
+    fn opposite(&self) -> SaturationKind {
+        match self {
+            SaturationKind::Bubble => SaturationKind::Dew,
+            SaturationKind::Dew => SaturationKind::Bubble,
+        }
+    }
+}
+
+/// # SaturationPoint
+///
+/// A bubble or dew point of a mixture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaturationPoint {
+    /// Whether the point is a bubble or a dew point
+    pub kind: SaturationKind,
+    /// Temperature of the point
+    pub temperature: ThermodynamicTemperature,
+    /// Pressure of the point
+    pub pressure: Pressure,
+    /// Mole fractions of the incipient phase, in the component order of the
+    /// model
+    pub incipient_composition: Vec<f64>,
+}
+
+/// Converges the composition of the incipient phase at a given temperature
+/// and pressure by successive substitution. Returns the logarithm of the sum
+/// of the unnormalized incipient mole fractions, which is zero at a
+/// saturation point, along with the normalized mole fractions. Like the
+/// stability test, the iteration starts from the mixture and from close to
+/// each pure component, and keeps the nontrivial solution with the largest
+/// sum.
+fn incipient_phase(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    composition: &[f64],
+    kind: SaturationKind,
+) -> Result<(f64, Vec<f64>), &'static str> {
+    let n = composition.len();
+    let (reference, incipient) = kind.phases();
+    let ln_phi = model.ln_fugacity_coefficients(temperature, pressure, composition, reference)?;
+    let amounts = |trial: &[f64]| -> Result<Vec<f64>, &'static str> {
+        let ln_phi_trial =
+            model.ln_fugacity_coefficients(temperature, pressure, trial, incipient)?;
+        Ok(composition
+            .iter()
+            .zip(ln_phi.iter().zip(ln_phi_trial))
+            .map(|(z, (ln_phi, ln_phi_trial))| z * (ln_phi - ln_phi_trial).exp())
+            .collect())
+    };
+
+    let mut starts = vec![composition.to_vec()];
+    if n > 1 {
+        starts.extend((0..n).map(|dominant| {
+            (0..n)
+                .map(|i| if i == dominant { 0.99 } else { 0.01 / n as f64 })
+                .collect()
+        }));
+    }
+    let mut best: Option<(f64, Vec<f64>)> = None;
+    for start in starts {
+        let mut w = amounts(&start)?;
+        for _ in 0..500 {
+            let total: f64 = w.iter().sum();
+            let trial: Vec<f64> = w.iter().map(|w| w / total).collect();
+            let next = amounts(&trial)?;
+            let change = next
+                .iter()
+                .zip(&w)
+                .fold(0.0, |change: f64, (next, w)| change.max((next - w).abs()));
+            w = next;
+            if change <= 1e-12 * total.max(1.0) {
+                let total: f64 = w.iter().sum();
+                let trial: Vec<f64> = w.iter().map(|w| w / total).collect();
+                let trivial = is_trivial(model, temperature, pressure, composition, &trial, kind);
+                if !trivial && best.as_ref().is_none_or(|(best, _)| total.ln() > *best) {
+                    best = Some((total.ln(), trial));
+                }
+                break;
+            }
+        }
+    }
+    best.ok_or("the incipient phase is identical to the mixture")
+}
+
+/// True when the incipient phase has converged to the mixture itself, which
+/// happens near critical points and where the model only has a single phase.
+/// A pure component at saturation also has an incipient phase of its own
+/// composition, but the phases differ in enthalpy.
+fn is_trivial(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    composition: &[f64],
+    incipient: &[f64],
+    kind: SaturationKind,
+) -> bool {
+    let same_composition = composition
+        .iter()
+        .zip(incipient)
+        .all(|(z, w)| (z - w).abs() < 1e-7);
+    if !same_composition {
+        return false;
+    }
+    let (reference, trial) = kind.phases();
+    match (
+        model.phase_enthalpy(temperature, pressure, composition, reference),
+        model.phase_enthalpy(temperature, pressure, composition, trial),
+    ) {
+        (Ok(first), Ok(second)) => (first - second).abs() <= first.abs().max(second.abs()) * 1e-9,
+        _ => true,
+    }
+}
+
+/// Finds the saturation pressure of a mixture at a given temperature.
+fn saturation_pressure(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    composition: &[f64],
+    initial_pressure: Pressure,
+    kind: SaturationKind,
+) -> Result<SaturationPoint, &'static str> {
+    let pressure = |ln_p: f64| Pressure::new::<pascal>(ln_p.exp());
+    let ln_p = find_root(
+        |ln_p| Ok(incipient_phase(model, temperature, pressure(ln_p), composition, kind)?.0),
+        initial_pressure.get::<pascal>().ln(),
+        0.1,
+        LOG_PRESSURE_BOUNDS,
+    )?;
+    let (_, incipient_composition) =
+        incipient_phase(model, temperature, pressure(ln_p), composition, kind)?;
+    Ok(SaturationPoint {
+        kind,
+        temperature,
+        pressure: pressure(ln_p),
+        incipient_composition,
+    })
+}
+
+/// Finds the saturation temperature of a mixture at a given pressure.
+fn saturation_temperature(
+    model: &dyn PhaseEquilibrium,
+    pressure: Pressure,
+    composition: &[f64],
+    initial_temperature: ThermodynamicTemperature,
+    kind: SaturationKind,
+) -> Result<SaturationPoint, &'static str> {
+    let temperature = ThermodynamicTemperature::new::<kelvin>;
+    let t = find_root(
+        |t| Ok(incipient_phase(model, temperature(t), pressure, composition, kind)?.0),
+        initial_temperature.get::<kelvin>(),
+        2.0,
+        TEMPERATURE_BOUNDS,
+    )?;
+    let (_, incipient_composition) =
+        incipient_phase(model, temperature(t), pressure, composition, kind)?;
+    Ok(SaturationPoint {
+        kind,
+        temperature: temperature(t),
+        pressure,
+        incipient_composition,
+    })
+}
+
+/// Finds the bubble point pressure of a mixture at a given temperature,
+/// searching from 'initial_pressure'.
+pub fn bubble_point_pressure(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    composition: &[f64],
+    initial_pressure: Pressure,
+) -> Result<SaturationPoint, &'static str> {
+    saturation_pressure(
+        model,
+        temperature,
+        composition,
+        initial_pressure,
+        SaturationKind::Bubble,
+    )
+}
+
+/// Finds the dew point pressure of a mixture at a given temperature,
+/// searching from 'initial_pressure'. Inside the retrograde region there are
+/// two dew point pressures, and the one closer to the initial pressure is
+/// usually found.
+pub fn dew_point_pressure(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    composition: &[f64],
+    initial_pressure: Pressure,
+) -> Result<SaturationPoint, &'static str> {
+    saturation_pressure(
+        model,
+        temperature,
+        composition,
+        initial_pressure,
+        SaturationKind::Dew,
+    )
+}
+
+/// Finds the bubble point temperature of a mixture at a given pressure,
+/// searching from 'initial_temperature'.
+pub fn bubble_point_temperature(
+    model: &dyn PhaseEquilibrium,
+    pressure: Pressure,
+    composition: &[f64],
+    initial_temperature: ThermodynamicTemperature,
+) -> Result<SaturationPoint, &'static str> {
+    saturation_temperature(
+        model,
+        pressure,
+        composition,
+        initial_temperature,
+        SaturationKind::Bubble,
+    )
+}
+
+/// Finds the dew point temperature of a mixture at a given pressure,
+/// searching from 'initial_temperature'.
+pub fn dew_point_temperature(
+    model: &dyn PhaseEquilibrium,
+    pressure: Pressure,
+    composition: &[f64],
+    initial_temperature: ThermodynamicTemperature,
+) -> Result<SaturationPoint, &'static str> {
+    saturation_temperature(
+        model,
+        pressure,
+        composition,
+        initial_temperature,
+        SaturationKind::Dew,
+    )
+}
+
+/// # PhaseEnvelope
+///
+/// The bubble and dew point curves of a mixture, with the points that
+/// characterize them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseEnvelope {
+    /// Saturation points in the order they were traced: up the bubble point
+    /// curve from the starting pressure, then down the dew point curve
+    pub points: Vec<SaturationPoint>,
+    /// Temperature and pressure where the bubble and dew point curves meet,
+    /// interpolated between the traced points
+    pub critical_point: Option<(ThermodynamicTemperature, Pressure)>,
+    /// Temperature and pressure of the highest pressure on the envelope
+    pub cricondenbar: Option<(ThermodynamicTemperature, Pressure)>,
+    /// Temperature and pressure of the highest temperature on the envelope
+    pub cricondentherm: Option<(ThermodynamicTemperature, Pressure)>,
+}
+
+impl PhaseEnvelope {
+    /// Temperatures in K and pressures in Pa of the points of one kind, in
+    /// tracing order, ready to be plotted.
+    pub fn series(&self, kind: SaturationKind) -> Vec<(f64, f64)> {
+        self.points
+            .iter()
+            .filter(|point| point.kind == kind)
+            .map(|point| {
+                (
+                    point.temperature.get::<kelvin>(),
+                    point.pressure.get::<pascal>(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Traces the phase envelope of a mixture. Tracing starts at the bubble
+/// point at 'start_pressure', searched for from 'initial_temperature', and
+/// continues up the bubble point curve. If the curve reaches a critical
+/// point, the trace continues down the dew point curve until it returns to
+/// the starting pressure. Otherwise, as for models without a critical point,
+/// the dew point curve is traced separately from the starting pressure.
+/// Each curve ends where the model stops converging or at the limits of
+/// temperature and pressure.
+pub fn phase_envelope(
+    model: &dyn PhaseEquilibrium,
+    composition: &[f64],
+    start_pressure: Pressure,
+    initial_temperature: ThermodynamicTemperature,
+) -> Result<PhaseEnvelope, &'static str> {
+    if composition.iter().any(|z| *z <= 0.0) {
+        return Err("every component needs a positive mole fraction");
+    }
+    let bubble = bubble_point_temperature(model, start_pressure, composition, initial_temperature)?;
+    let tracer = Tracer {
+        model,
+        composition,
+        minimum_pressure: start_pressure.get::<pascal>(),
+    };
+    let (mut points, critical_point) = tracer.trace(&bubble)?;
+    if critical_point.is_none() {
+        let dew = dew_point_temperature(model, start_pressure, composition, bubble.temperature)?;
+        points.extend(tracer.trace(&dew)?.0);
+    }
+
+    let conditions = |point: &SaturationPoint| {
+        (
+            point.temperature.get::<kelvin>(),
+            point.pressure.get::<pascal>(),
+        )
+    };
+    let values: Vec<(f64, f64)> = points.iter().map(conditions).collect();
+    let cricondenbar = maximum(&values, |(t, p)| (*p, *t)).map(|(p, t)| (t, p));
+    let cricondentherm = maximum(&values, |(t, p)| (*t, *p));
+    let state = |(t, p): (f64, f64)| {
+        (
+            ThermodynamicTemperature::new::<kelvin>(t),
+            Pressure::new::<pascal>(p),
+        )
+    };
+    Ok(PhaseEnvelope {
+        points,
+        critical_point: critical_point.map(state),
+        cricondenbar: cricondenbar.map(state),
+        cricondentherm: cricondentherm.map(state),
+    })
+}
+
+/// Interior maximum of the first of a pair of values over a curve, refined
+/// by a parabola through the largest value and its neighbours. The second
+/// value of the pair is taken from the parabola through the same points.
+/// Returns 'None' if the maximum is at an end of the curve.
+fn maximum(values: &[(f64, f64)], pick: impl Fn(&(f64, f64)) -> (f64, f64)) -> Option<(f64, f64)> {
+    let picked: Vec<(f64, f64)> = values.iter().map(pick).collect();
+    let index = (0..picked.len()).max_by(|a, b| picked[*a].0.total_cmp(&picked[*b].0))?;
+    if index == 0 || index + 1 == picked.len() {
+        return None;
+    }
+    let [(y0, x0), (y1, x1), (y2, x2)] = [picked[index - 1], picked[index], picked[index + 1]];
+    // The maximum of 'y' along the curve, where 'dy/dx' vanishes, so 'y' is
+    // a parabola in 'x' near it.
+    let denominator = (x0 - x1) * (x0 - x2) * (x1 - x2);
+    if denominator == 0.0 {
+        return Some((y1, x1));
+    }
+    let a = (x2 * (y1 - y0) + x1 * (y0 - y2) + x0 * (y2 - y1)) / denominator;
+    let b = (x2 * x2 * (y0 - y1) + x1 * x1 * (y2 - y0) + x0 * x0 * (y1 - y2)) / denominator;
+    let c = y1 - a * x1 * x1 - b * x1;
+    if a >= 0.0 {
+        return Some((y1, x1));
+    }
+    let x = -b / (2.0 * a);
+    Some((a * x * x + b * x + c, x))
+}
+
+/// Points of a traced curve, with the temperature in K and pressure in Pa of
+/// the critical point if the curve passes one
+type Branch = (Vec<SaturationPoint>, Option<(f64, f64)>);
+
+/// Continuation along a saturation curve of a fixed composition.
+struct Tracer<'a> {
+    model: &'a dyn PhaseEquilibrium,
+    composition: &'a [f64],
+    /// The trace ends when it returns below this pressure in Pa
+    minimum_pressure: f64,
+}
+
+impl Tracer<'_> {
+    /// The saturation equations of the unknowns 'ln(K_i)', 'ln(T)' and
+    /// 'ln(P)', where 'K_i' is the ratio of the mole fraction of a component
+    /// in the incipient phase to that in the mixture.
+    fn residuals(&self, x: &[f64], kind: SaturationKind) -> Result<Vec<f64>, &'static str> {
+        let n = self.composition.len();
+        let (reference, incipient) = kind.phases();
+        let temperature = ThermodynamicTemperature::new::<kelvin>(x[n].exp());
+        let pressure = Pressure::new::<pascal>(x[n + 1].exp());
+        let w: Vec<f64> = self
+            .composition
+            .iter()
+            .zip(x)
+            .map(|(z, ln_k)| z * ln_k.exp())
+            .collect();
+        let total: f64 = w.iter().sum();
+        let trial: Vec<f64> = w.iter().map(|w| w / total).collect();
+        let ln_phi = self.model.ln_fugacity_coefficients(
+            temperature,
+            pressure,
+            self.composition,
+            reference,
+        )?;
+        let ln_phi_trial =
+            self.model
+                .ln_fugacity_coefficients(temperature, pressure, &trial, incipient)?;
+        let mut residuals: Vec<f64> = (0..n).map(|i| x[i] + ln_phi_trial[i] - ln_phi[i]).collect();
+        residuals.push(total - 1.0);
+        Ok(residuals)
+    }
+
+    /// Jacobian of the saturation equations, extended with the row fixing
+    /// the unknown 'spec', found by central differences.
+    fn jacobian(
+        &self,
+        x: &[f64],
+        kind: SaturationKind,
+        spec: usize,
+    ) -> Result<SparseMatrix, &'static str> {
+        let size = x.len();
+        let mut jacobian = SparseMatrix::new(size);
+        for column in 0..size {
+            let step = 1e-6;
+            let mut above = x.to_vec();
+            let mut below = x.to_vec();
+            above[column] += step;
+            below[column] -= step;
+            let (above, below) = (self.residuals(&above, kind)?, self.residuals(&below, kind)?);
+            for (row, (above, below)) in above.iter().zip(&below).enumerate() {
+                jacobian.add(row, column, (above - below) / (2.0 * step));
+            }
+        }
+        jacobian.add(size - 1, spec, 1.0);
+        Ok(jacobian)
+    }
+
+    /// Solves the saturation equations with the unknown 'spec' held at its
+    /// value in 'initial'. Returns the solution and the number of
+    /// iterations taken.
+    fn solve(
+        &self,
+        initial: &[f64],
+        kind: SaturationKind,
+        spec: usize,
+    ) -> Result<(Vec<f64>, usize), &'static str> {
+        let mut x = initial.to_vec();
+        for iteration in 1..=20 {
+            let mut residuals = self.residuals(&x, kind)?;
+            residuals.push(0.0);
+            let rhs: Vec<f64> = residuals.iter().map(|r| -r).collect();
+            let step = self
+                .jacobian(&x, kind, spec)?
+                .solve(&rhs)
+                .ok_or("saturation equations are singular")?;
+            // Damp steps that would move far from the prediction
+            let largest = step
+                .iter()
+                .fold(0.0, |largest: f64, s| largest.max(s.abs()));
+            let scale = (0.5 / largest).min(1.0);
+            for (x, step) in x.iter_mut().zip(&step) {
+                *x += scale * step;
+            }
+            if largest < 1e-9 {
+                return Ok((x, iteration));
+            }
+        }
+        Err("saturation equations did not converge")
+    }
+
+    /// Traces a saturation curve from a starting point, with increasing
+    /// pressure at first. Returns the points and, if the curve passes one,
+    /// the critical point.
+    fn trace(&self, start: &SaturationPoint) -> Result<Branch, &'static str> {
+        let n = self.composition.len();
+        let mut kind = start.kind;
+        let mut x: Vec<f64> = self
+            .composition
+            .iter()
+            .zip(&start.incipient_composition)
+            .map(|(z, w)| (w / z).ln())
+            .collect();
+        x.push(start.temperature.get::<kelvin>().ln());
+        x.push(start.pressure.get::<pascal>().ln());
+        let mut spec = n + 1;
+        let (x0, _) = self.solve(&x, kind, spec)?;
+        x = x0;
+
+        let mut points = vec![self.point(&x, kind)];
+        let mut critical_point = None;
+        let mut direction = 1.0;
+        let mut factor: f64 = 0.25;
+        while points.len() < MAX_POINTS {
+            // Sensitivity of the unknowns to the specified one
+            let mut rhs = vec![0.0; n + 2];
+            rhs[n + 1] = 1.0;
+            let sensitivity = match self.jacobian(&x, kind, spec)?.solve(&rhs) {
+                Some(sensitivity) => sensitivity,
+                None => break,
+            };
+            // Specify the unknown changing fastest along the curve, keeping
+            // the direction of travel
+            let next_spec = (0..n + 2)
+                .max_by(|a, b| sensitivity[*a].abs().total_cmp(&sensitivity[*b].abs()))
+                .unwrap_or(spec);
+            direction *= sensitivity[next_spec].signum();
+            let sensitivity: Vec<f64> = sensitivity
+                .iter()
+                .map(|s| s / sensitivity[next_spec])
+                .collect();
+            spec = next_spec;
+            let limit = (0..n + 2)
+                .map(|i| MAX_CHANGES[if i < n { 0 } else { i - n + 1 }] / sensitivity[i].abs())
+                .fold(f64::INFINITY, f64::min);
+
+            let mut solved = None;
+            while factor > 1e-4 {
+                let step = direction * factor * limit;
+                let predicted: Vec<f64> = x
+                    .iter()
+                    .zip(&sensitivity)
+                    .map(|(x, s)| x + step * s)
+                    .collect();
+                // The incipient phase becomes the heavier one past the
+                // critical point, where every 'ln(K_i)' changes sign.
+                let largest = (0..n)
+                    .max_by(|a, b| x[*a].abs().total_cmp(&x[*b].abs()))
+                    .unwrap_or(0);
+                let crossing = n > 1 && predicted[largest].signum() != x[largest].signum();
+                let next_kind = if crossing { kind.opposite() } else { kind };
+                match self.solve(&predicted, next_kind, spec) {
+                    Ok((next, iterations))
+                        if (n == 1
+                            || (next[largest].signum() != x[largest].signum()) == crossing) =>
+                    {
+                        if iterations <= 3 {
+                            factor = (factor * 1.5).min(1.0);
+                        }
+                        solved = Some((next, next_kind, crossing, largest));
+                        break;
+                    }
+                    _ => factor /= 2.0,
+                }
+            }
+            let Some((next, next_kind, crossing, largest)) = solved else {
+                break;
+            };
+            if crossing {
+                let fraction = x[largest] / (x[largest] - next[largest]);
+                let interpolate = |i: usize| (x[i] + fraction * (next[i] - x[i])).exp();
+                critical_point = Some((interpolate(n), interpolate(n + 1)));
+            }
+            let (t, ln_p) = (next[n].exp(), next[n + 1]);
+            let in_bounds = (TEMPERATURE_BOUNDS.0..=TEMPERATURE_BOUNDS.1).contains(&t)
+                && (LOG_PRESSURE_BOUNDS.0..=LOG_PRESSURE_BOUNDS.1).contains(&ln_p);
+            if !in_bounds {
+                break;
+            }
+            x = next;
+            kind = next_kind;
+            points.push(self.point(&x, kind));
+            if x[n + 1].exp() < self.minimum_pressure {
+                break;
+            }
+        }
+        Ok((points, critical_point))
+    }
+
+    fn point(&self, x: &[f64], kind: SaturationKind) -> SaturationPoint {
+        let n = self.composition.len();
+        let w: Vec<f64> = self
+            .composition
+            .iter()
+            .zip(x)
+            .map(|(z, ln_k)| z * ln_k.exp())
+            .collect();
+        let total: f64 = w.iter().sum();
+        SaturationPoint {
+            kind,
+            temperature: ThermodynamicTemperature::new::<kelvin>(x[n].exp()),
+            pressure: Pressure::new::<pascal>(x[n + 1].exp()),
+            incipient_composition: w.iter().map(|w| w / total).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod phase_envelope_tests {
+    use super::*;
+    use crate::thermodynamics::activity_models::{AntoineCoefficients, LiquidParameters};
+    use crate::thermodynamics::eos_models::{CubicEquation, CubicModel, EOSParameters};
+    use crate::thermodynamics::ideal_models::RaoultsLaw;
+    use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+    use uom::si::molar_mass::gram_per_mole;
+
+    fn benzene_toluene() -> RaoultsLaw {
+        let parameters = |a: f64, b: f64, c: f64, mw: f64, cp: f64| LiquidParameters {
+            antoine: AntoineCoefficients { a, b, c },
+            molar_mass: MolarMass::new::<gram_per_mole>(mw),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(cp).into(),
+        };
+        RaoultsLaw::new(
+            vec![String::from("benzene"), String::from("toluene")],
+            vec![
+                parameters(20.7936, 2788.51, -52.36, 78.11, 82.4),
+                parameters(20.9065, 3096.52, -53.67, 92.14, 103.6),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn methane_propane() -> CubicEquation {
+        let parameters = |tc: f64, pc: f64, w: f64, mw: f64, cp: f64| EOSParameters {
+            critical_temperature: ThermodynamicTemperature::new::<kelvin>(tc),
+            critical_pressure: Pressure::new::<pascal>(pc),
+            acentric_factor: w,
+            molar_mass: MolarMass::new::<gram_per_mole>(mw),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(cp).into(),
+        };
+        let mut equation = CubicEquation::new(
+            CubicModel::PengRobinson,
+            vec![String::from("methane"), String::from("propane")],
+            vec![
+                parameters(190.56, 4.599e6, 0.0115, 16.04, 35.7),
+                parameters(369.83, 4.248e6, 0.1523, 44.10, 73.6),
+            ],
+        )
+        .unwrap();
+        equation
+            .set_interaction_parameter("methane", "propane", 0.014)
+            .unwrap();
+        equation
+    }
+
+    #[test]
+    fn raoults_law_saturation_points() {
+        let model = benzene_toluene();
+        let z = [0.4, 0.6];
+        let temperature = ThermodynamicTemperature::new::<kelvin>(370.0);
+        let psat: Vec<f64> = model
+            .parameters()
+            .iter()
+            .map(|p| p.antoine.vapor_pressure(temperature).get::<pascal>())
+            .collect();
+        let initial = Pressure::new::<pascal>(101_325.0);
+
+        let bubble = bubble_point_pressure(&model, temperature, &z, initial).unwrap();
+        let expected = z[0] * psat[0] + z[1] * psat[1];
+        assert!((bubble.pressure.get::<pascal>() / expected - 1.0).abs() < 1e-8);
+        assert!((bubble.incipient_composition[0] - z[0] * psat[0] / expected).abs() < 1e-8);
+
+        let dew = dew_point_pressure(&model, temperature, &z, initial).unwrap();
+        let expected = 1.0 / (z[0] / psat[0] + z[1] / psat[1]);
+        assert!((dew.pressure.get::<pascal>() / expected - 1.0).abs() < 1e-8);
+        assert!((dew.incipient_composition[0] - z[0] * expected / psat[0]).abs() < 1e-8);
+
+        // Saturation temperatures at those pressures return the temperature
+        let start = ThermodynamicTemperature::new::<kelvin>(350.0);
+        for (point, kind) in [(bubble, SaturationKind::Bubble), (dew, SaturationKind::Dew)] {
+            let found = match kind {
+                SaturationKind::Bubble => {
+                    bubble_point_temperature(&model, point.pressure, &z, start)
+                }
+                SaturationKind::Dew => dew_point_temperature(&model, point.pressure, &z, start),
+            }
+            .unwrap();
+            assert!((found.temperature.get::<kelvin>() - 370.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn raoults_law_envelope_has_no_critical_point() {
+        let model = benzene_toluene();
+        let z = [0.5, 0.5];
+        let envelope = phase_envelope(
+            &model,
+            &z,
+            Pressure::new::<pascal>(101_325.0),
+            ThermodynamicTemperature::new::<kelvin>(360.0),
+        )
+        .unwrap();
+        assert!(envelope.critical_point.is_none());
+        let bubble = envelope.series(SaturationKind::Bubble);
+        let dew = envelope.series(SaturationKind::Dew);
+        assert!(bubble.len() > 10 && dew.len() > 10);
+        // The dew point lies above the bubble point at the starting pressure
+        assert!(dew[0].0 > bubble[0].0);
+        for (t, p) in bubble.iter().step_by(5) {
+            let temperature = ThermodynamicTemperature::new::<kelvin>(*t);
+            let expected: f64 = model
+                .parameters()
+                .iter()
+                .zip(z)
+                .map(|(parameters, z)| {
+                    z * parameters
+                        .antoine
+                        .vapor_pressure(temperature)
+                        .get::<pascal>()
+                })
+                .sum();
+            assert!((p / expected - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn methane_propane_envelope() {
+        let model = methane_propane();
+        let z = [0.5, 0.5];
+        let start = Pressure::new::<pascal>(5.0e5);
+        let envelope = phase_envelope(
+            &model,
+            &z,
+            start,
+            ThermodynamicTemperature::new::<kelvin>(150.0),
+        )
+        .unwrap();
+        let (tc, pc) = envelope.critical_point.expect("no critical point found");
+        let (tc, pc) = (tc.get::<kelvin>(), pc.get::<pascal>());
+        // The mixture critical point lies between those of the components,
+        // at a higher pressure than either
+        assert!(tc > 190.56 && tc < 369.83);
+        assert!(pc > 4.599e6);
+
+        let (_, p_max) = envelope.cricondenbar.unwrap();
+        let (t_max, _) = envelope.cricondentherm.unwrap();
+        assert!(p_max.get::<pascal>() >= pc);
+        assert!(t_max.get::<kelvin>() >= tc);
+        for point in &envelope.points {
+            assert!(point.pressure.get::<pascal>() <= p_max.get::<pascal>() * (1.0 + 1e-6));
+            assert!(point.temperature.get::<kelvin>() <= t_max.get::<kelvin>() + 1e-6);
+        }
+
+        // The trace closes on the dew point at the starting pressure
+        let last = envelope.points.last().unwrap();
+        assert_eq!(last.kind, SaturationKind::Dew);
+        let dew = dew_point_temperature(&model, start, &z, last.temperature).unwrap();
+        assert!((last.temperature.get::<kelvin>() - dew.temperature.get::<kelvin>()).abs() < 5.0);
+
+        // Traced points are saturation points. Near the critical point the
+        // search for a saturation pressure steps into the single phase region
+        // above the envelope, so points at up to half the critical pressure
+        // are re-solved, and every one of them must be found again.
+        let checked: Vec<&SaturationPoint> = envelope
+            .points
+            .iter()
+            .filter(|point| point.pressure.get::<pascal>() < 0.5 * pc)
+            .collect();
+        assert!(checked.len() >= envelope.points.len() / 2);
+        for kind in [SaturationKind::Bubble, SaturationKind::Dew] {
+            assert!(checked.iter().any(|point| point.kind == kind));
+        }
+        for point in checked {
+            let found = match point.kind {
+                SaturationKind::Bubble => {
+                    bubble_point_pressure(&model, point.temperature, &z, point.pressure)
+                }
+                SaturationKind::Dew => {
+                    dew_point_pressure(&model, point.temperature, &z, point.pressure)
+                }
+            };
+            let found = found.unwrap_or_else(|error| panic!("{point:?}: {error}"));
+            let ratio = found.pressure.get::<pascal>() / point.pressure.get::<pascal>();
+            assert!((ratio - 1.0).abs() < 1e-4, "{point:?}: {ratio}");
+        }
+    }
+}
@@ -0,0 +1,634 @@
+//! # Phase Stability
+//!
+//! Tangent plane distance (TPD) stability testing and the multiphase flash
+//! built on it. A mixture is stable if no trial phase lowers its Gibbs
+//! energy, which is checked by searching for trial compositions with a
+//! negative tangent plane distance. Phases found this way are added to the
+//! flash one at a time until the mixture is stable.
+//!
+//! Both only rely on [`PhaseEquilibrium::ln_fugacity_coefficients`], so they
+//! work with any equation of state or activity model that provides it.
+
+use crate::numerics::SparseMatrix;
+use crate::thermodynamics::{FlashResult, Phase, PhaseEquilibrium};
+use uom::si::f64::{MolarEnergy, Pressure, Ratio, ThermodynamicTemperature};
+use uom::si::ratio::ratio;
+
+/// Smallest mole fraction used when taking logarithms.
+const MIN_FRACTION: f64 = 1e-300;
+
+/// Tangent plane distances above this value are treated as stable.
+const STABILITY_TOLERANCE: f64 = -1e-8;
+
+/// Trial phases closer than this to an existing phase of the same kind are
+/// treated as the trivial solution.
+const TRIVIAL_TOLERANCE: f64 = 1e-4;
+
+/// Phases with a smaller fraction of the feed are dropped from the flash.
+const MIN_PHASE_FRACTION: f64 = 1e-10;
+
+/// Largest number of times a flash adds a phase. A phase that vanishes while
+/// the phases are converged may be found again by the next stability test,
+/// so the additions are capped rather than the number of phases.
+const MAX_PHASE_ADDITIONS: usize = 20;
+
+/// One of the phases found by a multiphase flash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseSplit {
+    /// Kind of phase
+    pub phase: Phase,
+    /// Molar fraction of the feed in this phase
+    pub fraction: f64,
+    /// Mole fractions of the phase, in the component order of the model
+    pub composition: Vec<f64>,
+    /// Molar enthalpy of the phase
+    pub enthalpy: MolarEnergy,
+}
+
+/// # MultiphaseResult
+///
+/// The outcome of a multiphase flash: every phase present at equilibrium.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiphaseResult {
+    /// Temperature of the mixture
+    pub temperature: ThermodynamicTemperature,
+    /// Pressure of the mixture
+    pub pressure: Pressure,
+    /// Phases present at equilibrium
+    pub phases: Vec<PhaseSplit>,
+}
+
+impl MultiphaseResult {
+    /// Molar enthalpy of the mixture.
+    pub fn enthalpy(&self) -> MolarEnergy {
+        self.phases
+            .iter()
+            .fold(MolarEnergy::default(), |total, phase| {
+                total + phase.enthalpy * phase.fraction
+            })
+    }
+
+    /// Molar fraction of the mixture in the vapor phase.
+    pub fn vapor_fraction(&self) -> f64 {
+        self.phases
+            .iter()
+            .filter(|phase| phase.phase == Phase::Vapor)
+            .map(|phase| phase.fraction)
+            .sum()
+    }
+}
+
+/// Natural logarithm of each mole fraction, bounded away from zero.
+fn ln_fractions(composition: &[f64]) -> Vec<f64> {
+    composition
+        .iter()
+        .map(|x| x.max(MIN_FRACTION).ln())
+        .collect()
+}
+
+/// Tangent plane distance of a trial phase from the tangent plane given by
+/// `d`, where `d_i = ln x_i + ln phi_i` of a phase at equilibrium.
+pub fn tangent_plane_distance(composition: &[f64], ln_fugacity: &[f64], d: &[f64]) -> f64 {
+    composition
+        .iter()
+        .zip(ln_fractions(composition).iter().zip(ln_fugacity))
+        .zip(d)
+        .map(|((w, (ln_w, ln_phi)), d)| w * (ln_w + ln_phi - d))
+        .sum()
+}
+
+/// Searches for a trial phase that would lower the Gibbs energy of the
+/// mixture. `reference` is a phase of the current solution; every phase at
+/// equilibrium gives the same tangent plane. Trial phases start close to each
+/// pure component, in each of the kinds of phase in `kinds`, and are
+/// converged by successive substitution. Returns the kind and composition of
+/// the most unstable trial phase, or `None` if the mixture is stable.
+pub fn stability_test(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    reference: &PhaseSplit,
+    existing: &[PhaseSplit],
+    kinds: &[Phase],
+) -> Result<Option<(Phase, Vec<f64>)>, &'static str> {
+    let n = reference.composition.len();
+    let d: Vec<f64> = ln_fractions(&reference.composition)
+        .iter()
+        .zip(model.ln_fugacity_coefficients(
+            temperature,
+            pressure,
+            &reference.composition,
+            reference.phase,
+        )?)
+        .map(|(ln_x, ln_phi)| ln_x + ln_phi)
+        .collect();
+    let present: Vec<bool> = reference.composition.iter().map(|x| *x > 0.0).collect();
+
+    let mut best: Option<(f64, Phase, Vec<f64>)> = None;
+    for kind in kinds {
+        for dominant in (0..n).filter(|j| present[*j]) {
+            let mut w: Vec<f64> = (0..n)
+                .map(|i| match (present[i], i == dominant) {
+                    (false, _) => 0.0,
+                    (true, true) => 0.99,
+                    (true, false) => 0.01 / n as f64,
+                })
+                .collect();
+            let mut tm = 0.0;
+            let mut converged = false;
+            for _ in 0..200 {
+                let total: f64 = w.iter().sum();
+                let trial: Vec<f64> = w.iter().map(|w| w / total).collect();
+                let ln_phi =
+                    model.ln_fugacity_coefficients(temperature, pressure, &trial, *kind)?;
+                let next: Vec<f64> = (0..n)
+                    .map(|i| {
+                        if present[i] {
+                            (d[i] - ln_phi[i]).exp()
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect();
+                let change = next
+                    .iter()
+                    .zip(&w)
+                    .map(|(a, b)| (a - b).abs() / a.max(1e-12))
+                    .fold(0.0, f64::max);
+                w = next;
+                tm = 1.0 - w.iter().sum::<f64>();
+                if change < 1e-10 {
+                    converged = true;
+                    break;
+                }
+            }
+            let total: f64 = w.iter().sum();
+            if !converged || !total.is_finite() || total <= 0.0 {
+                continue;
+            }
+            let trial: Vec<f64> = w.iter().map(|w| w / total).collect();
+            let trivial = existing.iter().any(|phase| {
+                phase.phase == *kind
+                    && phase
+                        .composition
+                        .iter()
+                        .zip(&trial)
+                        .all(|(a, b)| (a - b).abs() < TRIVIAL_TOLERANCE)
+            });
+            if !trivial
+                && tm < STABILITY_TOLERANCE
+                && best.as_ref().is_none_or(|(lowest, _, _)| tm < *lowest)
+            {
+                best = Some((tm, *kind, trial));
+            }
+        }
+    }
+    Ok(best.map(|(_, kind, trial)| (kind, trial)))
+}
+
+/// Finds the fraction of the feed in each phase, given the fugacity
+/// coefficient of each component in each phase, by minimizing Michelsen's
+/// convex function `Q = sum(beta) - sum(z ln(sum(beta / phi)))` with the
+/// fractions kept non-negative.
+pub fn phase_fractions(z: &[f64], phi: &[Vec<f64>], initial: &[f64]) -> Option<Vec<f64>> {
+    let phases = phi.len();
+    let mut beta = initial.to_vec();
+    if beta.iter().all(|b| *b <= 0.0) {
+        beta[0] = 1.0;
+    }
+    let e = |beta: &[f64]| -> Vec<f64> {
+        (0..z.len())
+            .map(|i| (0..phases).map(|k| beta[k] / phi[k][i]).sum())
+            .collect()
+    };
+    let q = |beta: &[f64]| -> f64 {
+        beta.iter().sum::<f64>()
+            - z.iter()
+                .zip(e(beta))
+                .filter(|(z, _)| **z > 0.0)
+                .map(|(z, e)| z * e.ln())
+                .sum::<f64>()
+    };
+
+    for _ in 0..100 {
+        let e_values = e(&beta);
+        let gradient: Vec<f64> = (0..phases)
+            .map(|k| {
+                1.0 - z
+                    .iter()
+                    .zip(&e_values)
+                    .enumerate()
+                    .filter(|(_, (z, _))| **z > 0.0)
+                    .map(|(i, (z, e))| z / (phi[k][i] * e))
+                    .sum::<f64>()
+            })
+            .collect();
+        let free: Vec<usize> = (0..phases)
+            .filter(|k| beta[*k] > 0.0 || gradient[*k] < 0.0)
+            .collect();
+        if free.iter().all(|k| gradient[*k].abs() < 1e-12) {
+            return Some(beta);
+        }
+
+        let mut hessian = SparseMatrix::new(free.len());
+        for (a, k) in free.iter().enumerate() {
+            for (b, l) in free.iter().enumerate() {
+                let value: f64 = z
+                    .iter()
+                    .zip(&e_values)
+                    .enumerate()
+                    .filter(|(_, (z, _))| **z > 0.0)
+                    .map(|(i, (z, e))| z / (phi[*k][i] * phi[*l][i] * e * e))
+                    .sum();
+                hessian.add(a, b, value);
+            }
+        }
+        let rhs: Vec<f64> = free.iter().map(|k| -gradient[*k]).collect();
+        let step = hessian.solve(&rhs)?;
+
+        // Keep every fraction non-negative, then backtrack until Q decreases.
+        let mut length: f64 = 1.0;
+        for (a, k) in free.iter().enumerate() {
+            if step[a] < 0.0 && beta[*k] > 0.0 {
+                length = length.min(-beta[*k] / step[a]);
+            }
+        }
+        let current = q(&beta);
+        let mut next = beta.clone();
+        for _ in 0..30 {
+            next = beta.clone();
+            for (a, k) in free.iter().enumerate() {
+                next[*k] = (beta[*k] + length * step[a]).max(0.0);
+            }
+            if q(&next) <= current + 1e-14 * current.abs().max(1.0) {
+                break;
+            }
+            length /= 2.0;
+        }
+        beta = next;
+    }
+    None
+}
+
+/// Flashes a mixture at a given temperature and pressure, allowing up to
+/// `max_phases` phases of which at most one is vapor. Starting from the
+/// single phase with the lower Gibbs energy, phases are added while the
+/// stability test finds the mixture unstable. Phase compositions are then
+/// converged by successive substitution. Returns an error if phases are
+/// still being added after [`MAX_PHASE_ADDITIONS`] additions.
+pub fn flash_multiphase(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    z: &[f64],
+    max_phases: usize,
+) -> Result<MultiphaseResult, &'static str> {
+    flash_phases(model, temperature, pressure, z, max_phases, max_phases)
+}
+
+/// Flashes a mixture into at most `max_phases` phases, of which at most one
+/// is vapor and at most `max_liquids` are liquid.
+fn flash_phases(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    z: &[f64],
+    max_phases: usize,
+    max_liquids: usize,
+) -> Result<MultiphaseResult, &'static str> {
+    let gibbs = |phase: Phase| -> Result<f64, &'static str> {
+        let ln_phi = model.ln_fugacity_coefficients(temperature, pressure, z, phase)?;
+        Ok(tangent_plane_distance(z, &ln_phi, &vec![0.0; z.len()]))
+    };
+    let initial = if gibbs(Phase::Vapor)? < gibbs(Phase::Liquid)? {
+        Phase::Vapor
+    } else {
+        Phase::Liquid
+    };
+    let mut phases = vec![PhaseSplit {
+        phase: initial,
+        fraction: 1.0,
+        composition: z.to_vec(),
+        enthalpy: MolarEnergy::default(),
+    }];
+
+    let mut additions = 0;
+    while phases.len() < max_phases {
+        let liquids = phases
+            .iter()
+            .filter(|phase| phase.phase == Phase::Liquid)
+            .count();
+        let mut kinds = Vec::with_capacity(2);
+        if liquids == phases.len() {
+            kinds.push(Phase::Vapor);
+        }
+        if liquids < max_liquids {
+            kinds.push(Phase::Liquid);
+        }
+        if kinds.is_empty() {
+            break;
+        }
+        let Some((phase, composition)) =
+            stability_test(model, temperature, pressure, &phases[0], &phases, &kinds)?
+        else {
+            break;
+        };
+        if additions == MAX_PHASE_ADDITIONS {
+            return Err("the flash kept adding phases without converging");
+        }
+        additions += 1;
+        phases.push(PhaseSplit {
+            phase,
+            fraction: 0.0,
+            composition,
+            enthalpy: MolarEnergy::default(),
+        });
+        phases = converge_phases(model, temperature, pressure, z, phases)?;
+    }
+
+    for phase in phases.iter_mut() {
+        phase.enthalpy =
+            model.phase_enthalpy(temperature, pressure, &phase.composition, phase.phase)?;
+    }
+    Ok(MultiphaseResult {
+        temperature,
+        pressure,
+        phases,
+    })
+}
+
+/// Flashes a mixture into at most a vapor and a liquid phase, for models
+/// that implement [`PhaseEquilibrium::flash_tp`] on top of the stability
+/// test. Only a vapor may be split off a liquid, and a liquid off a vapor, so
+/// a feed that would split into two liquids stays a single liquid. The
+/// composition and enthalpy of a missing phase are those of the feed
+/// evaluated as that kind of phase.
+pub fn flash_vapor_liquid(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    z: &[f64],
+) -> Result<FlashResult, &'static str> {
+    let result = flash_phases(model, temperature, pressure, z, 2, 1)?;
+    let phase = |kind: Phase| result.phases.iter().find(|split| split.phase == kind);
+    let (vapor, liquid) = (phase(Phase::Vapor), phase(Phase::Liquid));
+    let composition_of =
+        |split: Option<&PhaseSplit>| split.map_or(z.to_vec(), |split| split.composition.clone());
+    let enthalpy_of = |split: Option<&PhaseSplit>, kind: Phase| match split {
+        Some(split) => Ok(split.enthalpy),
+        None => model.phase_enthalpy(temperature, pressure, z, kind),
+    };
+    Ok(FlashResult {
+        temperature,
+        pressure,
+        vapor_fraction: Ratio::new::<ratio>(result.vapor_fraction()),
+        liquid_composition: composition_of(liquid),
+        vapor_composition: composition_of(vapor),
+        enthalpy: result.enthalpy(),
+        liquid_enthalpy: enthalpy_of(liquid, Phase::Liquid)?,
+        vapor_enthalpy: enthalpy_of(vapor, Phase::Vapor)?,
+    })
+}
+
+/// Converges the compositions and fractions of a set of phases by successive
+/// substitution, dropping phases that vanish or merge with another phase.
+fn converge_phases(
+    model: &dyn PhaseEquilibrium,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    z: &[f64],
+    mut phases: Vec<PhaseSplit>,
+) -> Result<Vec<PhaseSplit>, &'static str> {
+    let mut converged = false;
+    for _ in 0..1000 {
+        let phi = phases
+            .iter()
+            .map(|phase| {
+                Ok(model
+                    .ln_fugacity_coefficients(
+                        temperature,
+                        pressure,
+                        &phase.composition,
+                        phase.phase,
+                    )?
+                    .iter()
+                    .map(|ln_phi| ln_phi.exp())
+                    .collect())
+            })
+            .collect::<Result<Vec<Vec<f64>>, &'static str>>()?;
+        let initial: Vec<f64> = phases.iter().map(|phase| phase.fraction).collect();
+        let beta = phase_fractions(z, &phi, &initial).ok_or("phase fractions did not converge")?;
+        let e: Vec<f64> = (0..z.len())
+            .map(|i| (0..phases.len()).map(|k| beta[k] / phi[k][i]).sum())
+            .collect();
+
+        let mut change: f64 = 0.0;
+        for (k, phase) in phases.iter_mut().enumerate() {
+            let unnormalized: Vec<f64> = (0..z.len())
+                .map(|i| {
+                    if z[i] > 0.0 {
+                        z[i] / (phi[k][i] * e[i])
+                    } else {
+                        0.0
+                    }
+                })
+                .collect();
+            let total: f64 = unnormalized.iter().sum();
+            for (x, new) in phase.composition.iter_mut().zip(&unnormalized) {
+                let new = new / total;
+                change = change.max((new - *x).abs());
+                *x = new;
+            }
+            phase.fraction = beta[k];
+        }
+        if change < 1e-10 {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return Err("phase compositions did not converge");
+    }
+
+    // Drop vanished phases, then merge phases that converged to the same
+    // composition.
+    phases.retain(|phase| phase.fraction > MIN_PHASE_FRACTION);
+    let mut merged: Vec<PhaseSplit> = Vec::with_capacity(phases.len());
+    for phase in phases {
+        match merged.iter_mut().find(|other| {
+            other.phase == phase.phase
+                && other
+                    .composition
+                    .iter()
+                    .zip(&phase.composition)
+                    .all(|(a, b)| (a - b).abs() < TRIVIAL_TOLERANCE)
+        }) {
+            Some(other) => other.fraction += phase.fraction,
+            None => merged.push(phase),
+        }
+    }
+    if merged.is_empty() {
+        return Err("every phase vanished during the flash");
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod phase_stability_tests {
+    use super::*;
+    use crate::thermodynamics::activity_models::{
+        AntoineCoefficients, BinaryParameter, GammaPhi, LiquidParameters, VaporModel, NRTL,
+    };
+    use std::sync::Arc;
+    use uom::si::f64::{MolarHeatCapacity, MolarMass};
+    use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+    use uom::si::molar_mass::gram_per_mole;
+    use uom::si::pressure::pascal;
+    use uom::si::thermodynamic_temperature::kelvin;
+
+    /// An NRTL liquid under an ideal gas, with 'tau' between the first two
+    /// components and the others ideal in the liquid. Each component is
+    /// given its Antoine coefficients for 'ln(P / Pa)'.
+    fn model(tau: f64, antoine: &[(f64, f64, f64)]) -> GammaPhi {
+        let components: Vec<String> = (0..antoine.len()).map(|i| format!("C{i}")).collect();
+        let mut nrtl = NRTL::new(components);
+        nrtl.set_binary_parameters(
+            "C0",
+            "C1",
+            BinaryParameter::constant(tau),
+            BinaryParameter::constant(tau),
+            0.2,
+        )
+        .unwrap();
+        let parameters = antoine
+            .iter()
+            .map(|(a, b, c)| LiquidParameters {
+                antoine: AntoineCoefficients {
+                    a: *a,
+                    b: *b,
+                    c: *c,
+                },
+                molar_mass: MolarMass::new::<gram_per_mole>(30.0),
+                heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(50.0).into(),
+            })
+            .collect();
+        GammaPhi::new(Arc::new(nrtl), VaporModel::IdealGas, parameters).unwrap()
+    }
+
+    /// Antoine coefficients close to those of ethanol, water and propane,
+    /// with vapor pressures of about 9 kPa, 3.5 kPa and 1 MPa at 300 K.
+    const ANTOINE: [(f64, f64, f64); 3] = [
+        (23.8047, 3803.98, -41.68),
+        (23.1964, 3816.44, -46.13),
+        (20.6188, 1872.46, -25.16),
+    ];
+
+    #[test]
+    fn phase_fractions_satisfy_material_balance() {
+        // Two phases with constant fugacity coefficients, equivalent to a
+        // Rachford-Rice problem with K = phi_liquid / phi_vapor = (2, 0.5).
+        let z = [0.5, 0.5];
+        let phi = vec![vec![1.0, 1.0], vec![2.0, 0.5]];
+        let beta = phase_fractions(&z, &phi, &[1.0, 0.0]).unwrap();
+        assert!((beta[0] - 0.5).abs() < 1e-9 && (beta[1] - 0.5).abs() < 1e-9);
+
+        // With every K above one the feed is entirely in the first phase.
+        let phi = vec![vec![1.0, 1.0], vec![2.0, 3.0]];
+        let beta = phase_fractions(&z, &phi, &[0.5, 0.5]).unwrap();
+        assert!((beta[0] - 1.0).abs() < 1e-9 && beta[1] == 0.0);
+    }
+
+    #[test]
+    fn stability_test_finds_liquid_splits() {
+        let temperature = ThermodynamicTemperature::new::<kelvin>(300.0);
+        let pressure = Pressure::new::<pascal>(1e6);
+        let feed = PhaseSplit {
+            phase: Phase::Liquid,
+            fraction: 1.0,
+            composition: vec![0.5, 0.5],
+            enthalpy: MolarEnergy::default(),
+        };
+        let existing = [feed.clone()];
+
+        // An ideal solution is stable.
+        let ideal = model(0.0, &ANTOINE[..2]);
+        let result = stability_test(
+            &ideal,
+            temperature,
+            pressure,
+            &feed,
+            &existing,
+            &[Phase::Liquid],
+        );
+        assert_eq!(result.unwrap(), None);
+
+        // Strongly non-ideal parameters split the liquid into one phase rich
+        // in each component.
+        let split = model(3.0, &ANTOINE[..2]);
+        let (phase, trial) = stability_test(
+            &split,
+            temperature,
+            pressure,
+            &feed,
+            &existing,
+            &[Phase::Liquid],
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(phase, Phase::Liquid);
+        assert!(trial[0] > 0.9 || trial[1] > 0.9);
+    }
+
+    #[test]
+    fn flash_splits_into_vapor_and_two_liquids() {
+        // Two immiscible liquids with a dissolved light gas
+        let model = model(3.0, &ANTOINE);
+        let temperature = ThermodynamicTemperature::new::<kelvin>(300.0);
+        let pressure = Pressure::new::<pascal>(5e4);
+        let z = [0.45, 0.45, 0.1];
+        let result = flash_multiphase(&model, temperature, pressure, &z, 3).unwrap();
+        assert_eq!(result.phases.len(), 3);
+        let count = |kind: Phase| {
+            result
+                .phases
+                .iter()
+                .filter(|split| split.phase == kind)
+                .count()
+        };
+        assert_eq!((count(Phase::Vapor), count(Phase::Liquid)), (1, 2));
+
+        for (i, z) in z.iter().enumerate() {
+            let total: f64 = result
+                .phases
+                .iter()
+                .map(|split| split.fraction * split.composition[i])
+                .sum();
+            assert!((total - z).abs() < 1e-8);
+        }
+
+        // Fugacities are equal in every phase
+        let ln_fugacities: Vec<Vec<f64>> = result
+            .phases
+            .iter()
+            .map(|split| {
+                model
+                    .ln_fugacity_coefficients(
+                        temperature,
+                        pressure,
+                        &split.composition,
+                        split.phase,
+                    )
+                    .unwrap()
+                    .iter()
+                    .zip(&split.composition)
+                    .map(|(ln_phi, x)| ln_phi + x.ln())
+                    .collect()
+            })
+            .collect();
+        for phase in &ln_fugacities[1..] {
+            for (a, b) in phase.iter().zip(&ln_fugacities[0]) {
+                assert!((a - b).abs() < 1e-6);
+            }
+        }
+    }
+}
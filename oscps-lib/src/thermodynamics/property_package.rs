@@ -0,0 +1,362 @@
+//! # Property Package
+//!
+//! A property package, or fluid package, bundles the components of a
+//! flowsheet with the thermodynamic model and parameters describing them.
+//! The simulation has a default package, set in its [`Settings`], that every
+//! block uses unless the block is given a package of its own. This is how
+//! the thermodynamic model of a flowsheet is switched, for example from an
+//! ideal model for a first estimate to an equation of state.
+//!
+//! The binary parameters of the models in a package are looked up with
+//! [`BinaryInteractions`], in a property store or in the defaults bundled
+//! with 'oscps-db'. Pairs without parameters are left at zero with a warning.
+//!
+//! [`Settings`]: crate::simulation::Settings
+
+use crate::thermodynamics::activity_models::{
+    ActivityModel, BinaryParameter, Wilson, NRTL, UNIQUAC,
+};
+use crate::thermodynamics::eos_models::{CubicEquation, CubicModel};
+use crate::thermodynamics::PhaseEquilibrium;
+use oscps_db::{BinaryModel, BinaryParameterSource, InteractionParameters};
+use std::fmt;
+use std::sync::Arc;
+
+/// # PropertyPackage
+///
+/// Common trait for property packages. Blocks do their calculations with the
+/// phase equilibrium model of the package.
+pub trait PropertyPackage: Send + Sync {
+    ///Name of the package shown to users, such as "Peng-Robinson"
+    fn name(&self) -> &str;
+    ///Phase equilibrium model holding the components and their parameters
+    fn model(&self) -> Arc<dyn PhaseEquilibrium>;
+    ///Names of the components the package has parameters for
+    fn components(&self) -> Vec<String> {
+        self.model().components().to_vec()
+    }
+}
+
+impl fmt::Debug for dyn PropertyPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropertyPackage")
+            .field("name", &self.name())
+            .field("components", &self.components())
+            .finish()
+    }
+}
+
+/// # FluidPackage
+///
+/// A property package made of a name and any phase equilibrium model, such
+/// as a cubic equation of state, PC-SAFT, a gamma-phi model or Raoult's law.
+#[derive(Clone)]
+pub struct FluidPackage {
+    name: String,
+    model: Arc<dyn PhaseEquilibrium>,
+}
+
+impl FluidPackage {
+    /// Creates a package from a model, under the name shown to users.
+    pub fn new(name: &str, model: Arc<dyn PhaseEquilibrium>) -> Self {
+        FluidPackage {
+            name: String::from(name),
+            model,
+        }
+    }
+}
+
+impl PropertyPackage for FluidPackage {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> Arc<dyn PhaseEquilibrium> {
+        self.model.clone()
+    }
+}
+
+/// # BinaryInteractions
+///
+/// Models whose binary parameters can be looked up in a
+/// [`BinaryParameterSource`], such as a property store or the defaults
+/// bundled with 'oscps-db'.
+pub trait BinaryInteractions {
+    ///Model the binary parameters are fitted for
+    fn binary_model(&self) -> Result<BinaryModel, &'static str>;
+    ///Names of the components, in the order of the model
+    fn component_names(&self) -> &[String];
+    ///Sets the parameters of a pair, from the first component to the second
+    fn set_pair_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        parameters: &InteractionParameters,
+    ) -> Result<(), &'static str>;
+
+    ///Looks the parameters of every pair of components up and sets them.
+    ///Pairs the source does not have keep parameters of zero, and a warning
+    ///names each of them. Returns the missing pairs.
+    fn load_binary_parameters(
+        &mut self,
+        source: &dyn BinaryParameterSource,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let model = self.binary_model().map_err(anyhow::Error::msg)?;
+        let components = self.component_names().to_vec();
+        let mut missing = Vec::new();
+        for (i, first) in components.iter().enumerate() {
+            for second in &components[i + 1..] {
+                match source.interaction_parameters(first, second, model)? {
+                    Some(parameters) => self
+                        .set_pair_parameters(first, second, &parameters)
+                        .map_err(anyhow::Error::msg)?,
+                    None => {
+                        log::warn!(
+                            "no {model} binary parameters for {first} and {second}, \
+                             which are set to zero"
+                        );
+                        missing.push((first.clone(), second.clone()));
+                    }
+                }
+            }
+        }
+        Ok(missing)
+    }
+}
+
+/// A binary parameter 'a + b / T'.
+fn binary_parameter(a: f64, b: f64) -> BinaryParameter {
+    BinaryParameter {
+        a,
+        b,
+        ..Default::default()
+    }
+}
+
+const OTHER_MODEL: &str = "the binary parameters are for another model";
+
+impl BinaryInteractions for CubicEquation {
+    fn binary_model(&self) -> Result<BinaryModel, &'static str> {
+        match self.model {
+            CubicModel::PengRobinson => Ok(BinaryModel::PengRobinson),
+            CubicModel::SoaveRedlichKwong => Ok(BinaryModel::SoaveRedlichKwong),
+            CubicModel::RedlichKwong => Err("no binary parameters are stored for Redlich-Kwong"),
+        }
+    }
+
+    fn component_names(&self) -> &[String] {
+        self.components()
+    }
+
+    fn set_pair_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        parameters: &InteractionParameters,
+    ) -> Result<(), &'static str> {
+        if parameters.model() != self.binary_model()? {
+            return Err(OTHER_MODEL);
+        }
+        match *parameters {
+            InteractionParameters::PengRobinson { kij }
+            | InteractionParameters::SoaveRedlichKwong { kij } => {
+                self.set_interaction_parameter(first, second, kij)
+            }
+            _ => Err(OTHER_MODEL),
+        }
+    }
+}
+
+impl BinaryInteractions for NRTL {
+    fn binary_model(&self) -> Result<BinaryModel, &'static str> {
+        Ok(BinaryModel::NRTL)
+    }
+
+    fn component_names(&self) -> &[String] {
+        self.components()
+    }
+
+    fn set_pair_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        parameters: &InteractionParameters,
+    ) -> Result<(), &'static str> {
+        match *parameters {
+            InteractionParameters::NRTL {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+                alpha,
+            } => self.set_binary_parameters(
+                first,
+                second,
+                binary_parameter(a_ij, b_ij),
+                binary_parameter(a_ji, b_ji),
+                alpha,
+            ),
+            _ => Err(OTHER_MODEL),
+        }
+    }
+}
+
+impl BinaryInteractions for Wilson {
+    fn binary_model(&self) -> Result<BinaryModel, &'static str> {
+        Ok(BinaryModel::Wilson)
+    }
+
+    fn component_names(&self) -> &[String] {
+        self.components()
+    }
+
+    fn set_pair_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        parameters: &InteractionParameters,
+    ) -> Result<(), &'static str> {
+        match *parameters {
+            InteractionParameters::Wilson {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+            } => self.set_binary_parameters(
+                first,
+                second,
+                binary_parameter(a_ij, b_ij),
+                binary_parameter(a_ji, b_ji),
+            ),
+            _ => Err(OTHER_MODEL),
+        }
+    }
+}
+
+impl BinaryInteractions for UNIQUAC {
+    fn binary_model(&self) -> Result<BinaryModel, &'static str> {
+        Ok(BinaryModel::UNIQUAC)
+    }
+
+    fn component_names(&self) -> &[String] {
+        self.components()
+    }
+
+    fn set_pair_parameters(
+        &mut self,
+        first: &str,
+        second: &str,
+        parameters: &InteractionParameters,
+    ) -> Result<(), &'static str> {
+        match *parameters {
+            InteractionParameters::UNIQUAC {
+                a_ij,
+                b_ij,
+                a_ji,
+                b_ji,
+            } => self.set_binary_parameters(
+                first,
+                second,
+                binary_parameter(a_ij, b_ij),
+                binary_parameter(a_ji, b_ji),
+            ),
+            _ => Err(OTHER_MODEL),
+        }
+    }
+}
+
+#[cfg(test)]
+mod property_package_tests {
+    use super::*;
+    use crate::thermodynamics::eos_models::EOSParameters;
+    use oscps_db::binary_parameters_db::BinaryParameterTable;
+    use uom::si::f64::*;
+    use uom::si::molar_heat_capacity::joule_per_kelvin_mole;
+    use uom::si::molar_mass::gram_per_mole;
+    use uom::si::pressure::pascal;
+    use uom::si::thermodynamic_temperature::kelvin;
+
+    fn parameters(tc: f64, pc: f64, w: f64, mw: f64) -> EOSParameters {
+        EOSParameters {
+            critical_temperature: ThermodynamicTemperature::new::<kelvin>(tc),
+            critical_pressure: Pressure::new::<pascal>(pc),
+            acentric_factor: w,
+            molar_mass: MolarMass::new::<gram_per_mole>(mw),
+            heat_capacity: MolarHeatCapacity::new::<joule_per_kelvin_mole>(35.0).into(),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    #[test]
+    fn cubic_parameters_are_loaded_and_missing_pairs_reported() {
+        let mut equation = CubicEquation::new(
+            CubicModel::PengRobinson,
+            names(&["methane", "carbon dioxide", "argon"]),
+            vec![
+                parameters(190.56, 4.599e6, 0.011, 16.04),
+                parameters(304.12, 7.374e6, 0.225, 44.01),
+                parameters(150.86, 4.898e6, -0.002, 39.95),
+            ],
+        )
+        .unwrap();
+        let missing = equation
+            .load_binary_parameters(BinaryParameterTable::bundled())
+            .unwrap();
+        assert_eq!(
+            equation.interaction_parameter("carbon dioxide", "methane"),
+            Ok(0.0919)
+        );
+        assert_eq!(equation.interaction_parameter("methane", "argon"), Ok(0.0));
+        assert_eq!(
+            missing,
+            [
+                (String::from("methane"), String::from("argon")),
+                (String::from("carbon dioxide"), String::from("argon"))
+            ]
+        );
+
+        equation.model = CubicModel::RedlichKwong;
+        assert!(equation
+            .load_binary_parameters(BinaryParameterTable::bundled())
+            .is_err());
+    }
+
+    #[test]
+    fn bundled_activity_parameters_give_ethanol_water_nonideality() {
+        // Infinite dilution activity coefficients near the boiling point of
+        // the azeotrope are about 6 for ethanol in water and 2.5 for water
+        // in ethanol.
+        let check = |model: &dyn ActivityModel| {
+            let t = ThermodynamicTemperature::new::<kelvin>(351.0);
+            let ethanol = model.ln_activity_coefficients(t, &[0.0, 1.0])[0].exp();
+            let water = model.ln_activity_coefficients(t, &[1.0, 0.0])[1].exp();
+            assert!(ethanol > 4.0 && ethanol < 9.0, "{ethanol}");
+            assert!(water > 2.0 && water < 3.5, "{water}");
+        };
+        let source = BinaryParameterTable::bundled();
+        let components = names(&["ethanol", "water"]);
+
+        let mut nrtl = NRTL::new(components.clone());
+        assert!(nrtl.load_binary_parameters(source).unwrap().is_empty());
+        check(&nrtl);
+
+        let mut wilson = Wilson::new(components.clone());
+        assert!(wilson.load_binary_parameters(source).unwrap().is_empty());
+        check(&wilson);
+
+        let mut uniquac = UNIQUAC::new(components, vec![2.1055, 0.92], vec![1.972, 1.4]).unwrap();
+        assert!(uniquac.load_binary_parameters(source).unwrap().is_empty());
+        check(&uniquac);
+
+        // Reversing the component order reverses the parameters
+        let mut reversed = NRTL::new(names(&["water", "ethanol"]));
+        reversed.load_binary_parameters(source).unwrap();
+        let t = ThermodynamicTemperature::new::<kelvin>(351.0);
+        let forward = nrtl.ln_activity_coefficients(t, &[0.3, 0.7]);
+        let backward = reversed.ln_activity_coefficients(t, &[0.7, 0.3]);
+        assert!((forward[0] - backward[1]).abs() < 1e-12);
+    }
+}